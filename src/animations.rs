@@ -1,13 +1,663 @@
 pub mod animations {
 	use crate::c;
+	use crate::sun;
+	use crate::n;
+	use crate::cl;
+	use crate::st;
+	use crate::ca;
+	use crate::b;
+	use crate::sp;
+	use crate::mx;
+	use crate::vw;
+
+	//the different things an animation can be doing. Each mode owns its own parameters and state.
+	pub enum Mode {
+		Solid(c::Color),
+		Sunrise(sun::Sunrise),
+		Noise(n::NoiseAnimation),
+		TheaterChase(cl::TheaterChase),
+		ColorWipe(cl::ColorWipe),
+		RunningLights(cl::RunningLights),
+		Storm(st::Storm),
+		Automaton(ca::Automaton),
+		BouncingBalls(b::BouncingBalls),
+		Spatial(sp::SpatialAnimation),
+		ScrollingText(mx::ScrollingText),
+		Rainbow(cl::RainbowCycle),
+	}
+
+	//the effects that can be picked by number, for saving settings and controlling the lights from
+	//outside. The numbers are stored in flash, so new effects go on the end.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Effect {
+		Solid,
+		Sunrise,
+		Aurora,
+		Ocean,
+		Lava,
+		TheaterChase,
+		ColorWipe,
+		RunningLights,
+		Storm,
+		Automaton,
+		Rainbow,
+		Sunset,
+		Balls,
+		Ripple,
+		Sweep,
+		Beam,
+		Text,
+	}
+
+	pub const EFFECTS: [Effect; 17] = [
+		Effect::Solid,
+		Effect::Sunrise,
+		Effect::Aurora,
+		Effect::Ocean,
+		Effect::Lava,
+		Effect::TheaterChase,
+		Effect::ColorWipe,
+		Effect::RunningLights,
+		Effect::Storm,
+		Effect::Automaton,
+		Effect::Rainbow,
+		Effect::Sunset,
+		Effect::Balls,
+		Effect::Ripple,
+		Effect::Sweep,
+		Effect::Beam,
+		Effect::Text,
+	];
+
+	impl Effect {
+		pub fn from_u8(value: u8) -> Option<Effect> {
+			EFFECTS.get(value as usize).copied()
+		}
+
+		pub fn to_u8(self) -> u8 {
+			self as u8
+		}
+
+		pub fn name(self) -> &'static str {
+			match self {
+				Effect::Solid => "solid",
+				Effect::Sunrise => "sunrise",
+				Effect::Aurora => "aurora",
+				Effect::Ocean => "ocean",
+				Effect::Lava => "lava",
+				Effect::TheaterChase => "chase",
+				Effect::ColorWipe => "wipe",
+				Effect::RunningLights => "running",
+				Effect::Storm => "storm",
+				Effect::Automaton => "automaton",
+				Effect::Rainbow => "rainbow",
+				Effect::Sunset => "sunset",
+				Effect::Balls => "balls",
+				Effect::Ripple => "ripple",
+				Effect::Sweep => "sweep",
+				Effect::Beam => "beam",
+				Effect::Text => "text",
+			}
+		}
+
+		pub fn from_name(name: &str) -> Option<Effect> {
+			EFFECTS.iter().copied().find(|effect| effect.name() == name)
+		}
+	}
+
+	//the speed effects run at when none is given. Speeds go from 1 for slow up to 255.
+	pub const DEFAULT_SPEED: u8 = 5;
+
+	//the color temperature sunrises end at and sunsets start from, unless the intensity picks one:
+	const SUNRISE_KELVIN: u16 = 4000;
+
+	//the intensity the storm strikes at when it is 0, once every 2 to 12 seconds. Strikes come
+	//more often at higher intensities, up to 8 times as often at 255:
+	const DEFAULT_STORM_INTENSITY: u32 = 32;
+
+	//the bouncing balls, when the intensity and custom don't pick otherwise:
+	const DEFAULT_BALL_COUNT: u8 = 3;
+	//LEDs per second per second at the default speed, 16.16 fixed point:
+	const DEFAULT_GRAVITY: i32 = 40 << 16;
+	//the first ball's elasticity, with each ball after it a little less bouncy so they spread out:
+	const BALL_ELASTICITY: u8 = 232;
+
+	//the effects that color LEDs by where they are in the room go once around the palette in this
+	//distance, and move that far in the default step time:
+	const SPATIAL_WAVELENGTH_MM: u32 = 2000;
+	const SPATIAL_PERIOD_MS: u32 = 4000;
+	//the angle the beam lights up to either side, out of 256 for a whole turn:
+	const DEFAULT_BEAM_WIDTH: u8 = 24;
+
+	pub const EFFECT_PARAMS_LENGTH: usize = 9;
+
+	//the bits of the flags byte effect params are saved with:
+	pub const FLAG_PER_STRIP: u8 = 0x01;
+	pub const FLAG_REVERSED: u8 = 0x02;
+
+	//everything needed to start one of the effects, small enough to save:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct EffectParams {
+		pub effect: Effect,
+		//used by solid, chase, running lights and as the storm's lightning:
+		pub color: c::Color,
+		//index into RAINBOW_ARRAY, used by chase, wipe, automaton and rainbow. When this is 0,
+		//the chase uses color and the others use all the colors of the rainbow:
+		pub palette: u8,
+		pub speed: u8,
+		//a number each effect uses its own way, like WLED's intensity. 0 is the effect's default.
+		//the automaton uses it as the Wolfram rule, with 0 for 1D life, sunrises and sunsets as
+		//their color temperature in hundreds of kelvin, the storm as how often it strikes, the
+		//balls as how many there are, the sweep as its direction and the beam as its width, both
+		//out of 256 for a whole turn.
+		pub intensity: u8,
+		//a second number for effects that need one, like WLED's custom sliders. The balls use it
+		//as the drag on them, out of 256 a second.
+		pub custom: u8,
+		//keeps the effect to one physical strip at a time, for the effects that can, like the
+		//storm striking a single strip or each ball bouncing on its own strip:
+		pub per_strip: bool,
+		//runs the chase, wipe, running lights and rainbow the other way along the strip:
+		pub reversed: bool,
+	}
+
+	//what an effect can know about where the LEDs it draws are, for the effects that work by position:
+	#[derive(Copy, Clone)]
+	pub struct Placement {
+		//where each physical strip is in the frame:
+		pub strips: vw::StripRanges,
+		//where each LED is in the room, or empty if the LEDs haven't been mapped:
+		pub map: &'static [sp::Point],
+		//the first LED of a matrix panel in the frame and how the panel is laid out, for effects
+		//that draw on a grid:
+		pub matrix: Option<(usize, mx::Layout)>,
+		//what the text effect scrolls across the matrix:
+		pub text: &'static str,
+	}
+
+	impl Placement {
+		pub const fn new() -> Self {
+			Placement {
+				strips: vw::StripRanges::new(),
+				map: &[],
+				matrix: None,
+				text: "",
+			}
+		}
+	}
+
+	impl Default for Placement {
+		fn default() -> Self {
+			Placement::new()
+		}
+	}
+
+	impl EffectParams {
+		pub const fn solid(color: c::Color) -> Self {
+			EffectParams {
+				effect: Effect::Solid,
+				color,
+				palette: 0,
+				speed: DEFAULT_SPEED,
+				intensity: 0,
+				custom: 0,
+				per_strip: false,
+				reversed: false,
+			}
+		}
+
+		//the flags byte the params are saved with:
+		pub fn flags(&self) -> u8 {
+			let per_strip = if self.per_strip { FLAG_PER_STRIP } else { 0 };
+			let reversed = if self.reversed { FLAG_REVERSED } else { 0 };
+			per_strip | reversed
+		}
+
+		//this packs the params into bytes for saving: effect, red, green, blue, palette, speed,
+		//intensity, flags, custom. Fields are only ever added to the end.
+		pub fn to_bytes(&self) -> [u8; EFFECT_PARAMS_LENGTH] {
+			[self.effect.to_u8(), self.color.r, self.color.g, self.color.b, self.palette, self.speed, self.intensity, self.flags(), self.custom]
+		}
+
+		//this unpacks params saved by to_bytes, or returns None if the effect doesn't exist:
+		pub fn from_bytes(bytes: &[u8; EFFECT_PARAMS_LENGTH]) -> Option<Self> {
+			Some(EffectParams {
+				effect: Effect::from_u8(bytes[0])?,
+				color: c::Color { r: bytes[1], g: bytes[2], b: bytes[3] },
+				palette: bytes[4],
+				speed: bytes[5],
+				intensity: bytes[6],
+				custom: bytes[8],
+				per_strip: bytes[7] & FLAG_PER_STRIP != 0,
+				reversed: bytes[7] & FLAG_REVERSED != 0,
+			})
+		}
+
+		//the palette, with palette 0 picking all the colors of the rainbow so the effects that
+		//draw with a palette don't come out dark:
+		pub fn palette(&self) -> c::Rainbow {
+			match self.palette {
+				0 => c::R_ROYGBIV,
+				palette => c::RAINBOW_ARRAY[palette as usize % c::NUM_RAINBOWS],
+			}
+		}
+
+		fn sunrise(&self, direction: sun::Direction) -> sun::Sunrise {
+			let kelvin = match self.intensity {
+				0 => SUNRISE_KELVIN,
+				hundreds => (hundreds as u16 * 100).clamp(c::KELVIN_MIN, c::KELVIN_MAX),
+			};
+			sun::Sunrise::new(direction, self.scaled_ms(sun::DEFAULT_DURATION_MS), kelvin)
+		}
+
+		fn automaton_rule(&self) -> ca::Rule {
+			match self.intensity {
+				0 => ca::Rule::Life,
+				rule => ca::Rule::Elementary(rule),
+			}
+		}
+
+		//this makes a storm that strikes as often as the intensity asks for. With per_strip set,
+		//each strike stays on one of the physical strips.
+		fn storm(&self, strips: &vw::StripRanges, seed: u32) -> st::Storm {
+			let defaults = st::StormSettings::default();
+			let scaled_ms = |interval_ms: u32| match self.intensity {
+				0 => interval_ms,
+				intensity => interval_ms * DEFAULT_STORM_INTENSITY / intensity as u32,
+			};
+			let settings = st::StormSettings {
+				flash_color: self.color,
+				strips: if self.per_strip { *strips } else { vw::StripRanges::new() },
+				min_strike_interval_ms: scaled_ms(defaults.min_strike_interval_ms),
+				max_strike_interval_ms: scaled_ms(defaults.max_strike_interval_ms),
+				..defaults
+			};
+			st::Storm::new(settings, seed)
+		}
+
+		//this drops a ball of each color of the palette, as many as the intensity asks for. With
+		//per_strip set, the balls take turns on the physical strips.
+		fn balls(&self, strips: &vw::StripRanges) -> b::BouncingBalls {
+			let gravity = (DEFAULT_GRAVITY as i64 * self.speed.max(1) as i64 / DEFAULT_SPEED as i64) as i32;
+			let mut balls = b::BouncingBalls::new(gravity, self.custom);
+			let ball_count = match self.intensity {
+				0 => DEFAULT_BALL_COUNT as usize,
+				count => (count as usize).min(b::MAX_BALLS),
+			};
+			let palette = self.palette();
+			for i in 0..ball_count {
+				let range = match self.per_strip && !strips.is_empty() {
+					true => strips.get(i % strips.len()),
+					false => None,
+				};
+				balls.add_ball(b::Ball::new(palette.color(i), BALL_ELASTICITY - i as u8 * 4, range));
+			}
+			balls
+		}
+
+		//this colors the LEDs by where they are in the room. Without a map there is nothing to go
+		//on, so the palette cycles along the strip instead.
+		fn spatial(&self, pattern: sp::Pattern, map: &'static [sp::Point]) -> Mode {
+			match map.is_empty() {
+				true => Mode::Rainbow(cl::RainbowCycle::new(self.palette(), 60, self.scaled_ms(50), self.reversed)),
+				false => Mode::Spatial(sp::SpatialAnimation::new(map, pattern, self.palette(), SPATIAL_WAVELENGTH_MM, self.scaled_ms(SPATIAL_PERIOD_MS))),
+			}
+		}
+
+		//this scrolls the placement's text across the matrix in the effect's color. Without a
+		//matrix the text can't be drawn, so lights run along the strip instead.
+		fn text(&self, placement: &Placement) -> Mode {
+			match placement.matrix {
+				Some((first_led, layout)) => {
+					let mut text = mx::ScrollingText::new(layout, placement.text, self.color, self.scaled_ms(100));
+					text.first_led = first_led;
+					Mode::ScrollingText(text)
+				}
+				None => Mode::RunningLights(cl::RunningLights::new(self.color, 16, self.scaled_ms(80), self.reversed)),
+			}
+		}
+
+		//this scales a time for the default speed, like a step time or a duration, by the speed:
+		fn scaled_ms(&self, default_ms: u32) -> u32 {
+			(default_ms * DEFAULT_SPEED as u32 / self.speed.max(1) as u32).max(1)
+		}
+
+		//this scales a noise preset's motion by the speed:
+		fn noise(&self, preset: n::NoiseAnimation) -> n::NoiseAnimation {
+			let speed = self.speed as i64;
+			n::NoiseAnimation {
+				speed: (preset.speed as i64 * speed / DEFAULT_SPEED as i64) as u32,
+				drift: (preset.drift as i64 * speed / DEFAULT_SPEED as i64) as i32,
+				..preset
+			}
+		}
+
+		//this makes a new mode running the effect. seed is used by the effects with randomness, and
+		//placement by the effects that work by where the LEDs are.
+		pub fn mode(&self, seed: u32, placement: &Placement) -> Mode {
+			let strips = &placement.strips;
+			match self.effect {
+				Effect::Solid => Mode::Solid(self.color),
+				Effect::Sunrise => Mode::Sunrise(self.sunrise(sun::Direction::Sunrise)),
+				Effect::Sunset => Mode::Sunrise(self.sunrise(sun::Direction::Sunset)),
+				Effect::Aurora => Mode::Noise(self.noise(n::NoiseAnimation::aurora())),
+				Effect::Ocean => Mode::Noise(self.noise(n::NoiseAnimation::ocean())),
+				Effect::Lava => Mode::Noise(self.noise(n::NoiseAnimation::lava())),
+				Effect::TheaterChase => Mode::TheaterChase(cl::TheaterChase::new(
+					self.color,
+					Some(self.palette()).filter(|_| self.palette != 0),
+					3,
+					self.scaled_ms(120),
+					self.reversed,
+				)),
+				Effect::ColorWipe => Mode::ColorWipe(cl::ColorWipe::new(self.palette(), self.scaled_ms(40), self.reversed)),
+				Effect::RunningLights => Mode::RunningLights(cl::RunningLights::new(self.color, 16, self.scaled_ms(80), self.reversed)),
+				Effect::Storm => Mode::Storm(self.storm(strips, seed)),
+				Effect::Automaton => Mode::Automaton(ca::Automaton::new(self.automaton_rule(), self.palette(), self.scaled_ms(150), seed)),
+				Effect::Rainbow => Mode::Rainbow(cl::RainbowCycle::new(self.palette(), 60, self.scaled_ms(50), self.reversed)),
+				Effect::Balls => Mode::BouncingBalls(self.balls(strips)),
+				Effect::Ripple => self.spatial(sp::Pattern::RadialWave { center: sp::center(placement.map) }, placement.map),
+				Effect::Sweep => self.spatial(sp::Pattern::LinearSweep { angle: self.intensity }, placement.map),
+				Effect::Beam => {
+					let beam_width = match self.intensity {
+						0 => DEFAULT_BEAM_WIDTH,
+						width => width,
+					};
+					self.spatial(sp::Pattern::RotatingBeam { center: sp::center(placement.map), beam_width }, placement.map)
+				}
+				Effect::Text => self.text(placement),
+			}
+		}
+	}
+
 	pub struct Animation {
 		buffer_size: usize,
+		pub mode: Mode,
+		//the effect the mode was made from, so it can be saved. Modes set up by hand don't have one.
+		effect: Option<EffectParams>,
+		//where the LEDs are, and the seed the mode was made with, so the mode can be made again
+		//when the placement is set:
+		placement: Placement,
+		seed: u32,
 	}
+
 	impl Animation {
-		pub fn new(buffer_size: usize) -> Self {
+		pub fn new(buffer_size: usize, mode: Mode) -> Self {
 			Animation {
 				buffer_size,
+				mode,
+				effect: None,
+				placement: Placement::new(),
+				seed: 0,
+			}
+		}
+
+		pub fn from_effect(buffer_size: usize, effect: EffectParams, seed: u32) -> Self {
+			let placement = Placement::new();
+			Animation {
+				buffer_size,
+				mode: effect.mode(seed, &placement),
+				effect: Some(effect),
+				placement,
+				seed,
+			}
+		}
+
+		//switching modes starts the new mode over from the beginning:
+		pub fn set_mode(&mut self, mode: Mode) {
+			self.mode = mode;
+			self.effect = None;
+		}
+
+		pub fn set_effect(&mut self, effect: EffectParams, seed: u32) {
+			self.mode = effect.mode(seed, &self.placement);
+			self.effect = Some(effect);
+			self.seed = seed;
+		}
+
+		pub fn placement(&self) -> Placement {
+			self.placement
+		}
+
+		//this tells the animation where its LEDs are. An effect that is running is started over
+		//so it can use them.
+		pub fn set_placement(&mut self, placement: Placement) {
+			self.placement = placement;
+			if let Some(effect) = self.effect {
+				self.set_effect(effect, self.seed);
+			}
+		}
+
+		pub fn effect(&self) -> Option<EffectParams> {
+			self.effect
+		}
+
+		//this renders the animation at the current time into the frame. Colors written to the
+		//frame are not gamma corrected, that happens when they are put on the strip.
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			let frame_len = frame.len().min(self.buffer_size);
+			let frame = &mut frame[..frame_len];
+			match &mut self.mode {
+				Mode::Solid(color) => {
+					for led in frame.iter_mut() {
+						*led = *color;
+					}
+				}
+				Mode::Sunrise(sunrise) => sunrise.update(now_ms, frame),
+				Mode::Noise(noise) => noise.update(now_ms, frame),
+				Mode::TheaterChase(chase) => chase.update(now_ms, frame),
+				Mode::ColorWipe(wipe) => wipe.update(now_ms, frame),
+				Mode::RunningLights(lights) => lights.update(now_ms, frame),
+				Mode::Storm(storm) => storm.update(now_ms, frame),
+				Mode::Automaton(automaton) => automaton.update(now_ms, frame),
+				Mode::BouncingBalls(balls) => balls.update(now_ms, frame),
+				Mode::Spatial(spatial) => spatial.update(now_ms, frame),
+				Mode::ScrollingText(text) => text.update(now_ms, frame),
+				Mode::Rainbow(rainbow) => rainbow.update(now_ms, frame),
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		fn params(effect: Effect) -> EffectParams {
+			EffectParams { effect, ..EffectParams::solid(c::C_OFF) }
+		}
+
+		//whether any frame of the first two seconds lights anything up:
+		fn lights_up(effect: EffectParams) -> bool {
+			let mut animation = Animation::from_effect(60, effect, 1234);
+			let mut frame = [c::C_OFF; 60];
+			(0..2000).step_by(50).any(|now_ms| {
+				animation.update(now_ms, &mut frame);
+				frame.iter().any(|&led| led != c::C_OFF)
+			})
+		}
+
+		#[test]
+		fn palette_0_is_a_rainbow() {
+			for &effect in [Effect::ColorWipe, Effect::Automaton, Effect::Rainbow].iter() {
+				assert!(lights_up(params(effect)), "{:?}", effect);
+			}
+		}
+
+		#[test]
+		fn automaton_rule_is_the_intensity() {
+			let rule = |intensity: u8| match (EffectParams { intensity, ..params(Effect::Automaton) }).mode(1, &Placement::new()) {
+				Mode::Automaton(automaton) => automaton.rule,
+				_ => panic!("not an automaton"),
+			};
+			assert!(rule(0) == ca::Rule::Life);
+			assert!(rule(30) == ca::Rule::Elementary(30));
+			assert!(lights_up(EffectParams { intensity: 110, ..params(Effect::Automaton) }));
+		}
+
+		#[test]
+		fn sunrise_and_sunset() {
+			let sunrise = |effect: Effect, intensity: u8| match (EffectParams { intensity, ..params(effect) }).mode(1, &Placement::new()) {
+				Mode::Sunrise(sunrise) => sunrise,
+				_ => panic!("not a sunrise"),
+			};
+			let default = sunrise(Effect::Sunrise, 0);
+			assert!(default.direction == sun::Direction::Sunrise);
+			assert_eq!(default.target_kelvin, SUNRISE_KELVIN);
+			assert_eq!(default.duration_ms, sun::DEFAULT_DURATION_MS);
+			let sunset = sunrise(Effect::Sunset, 27);
+			assert!(sunset.direction == sun::Direction::Sunset);
+			assert_eq!(sunset.target_kelvin, 2700);
+			assert_eq!(sunrise(Effect::Sunset, 255).target_kelvin, c::KELVIN_MAX);
+			assert_eq!(sunrise(Effect::Sunrise, 1).target_kelvin, c::KELVIN_MIN);
+			//a sunset starts at the color temperature and ends dark:
+			let mut animation = Animation::from_effect(4, EffectParams { intensity: 27, speed: 255, ..params(Effect::Sunset) }, 1);
+			let mut frame = [c::C_OFF; 4];
+			animation.update(0, &mut frame);
+			assert_eq!(frame[0], c::kelvin_to_color(2700));
+			animation.update(sun::DEFAULT_DURATION_MS, &mut frame);
+			assert_eq!(frame[0], c::C_OFF);
+		}
+
+		#[test]
+		fn balls() {
+			let balls = |effect: EffectParams, strips: &vw::StripRanges| match effect.mode(1, &Placement { strips: *strips, ..Placement::new() }) {
+				Mode::BouncingBalls(balls) => balls,
+				_ => panic!("not balls"),
+			};
+			let mut strips = vw::StripRanges::new();
+			strips.push(0..20);
+			strips.push(20..30);
+			let default = balls(params(Effect::Balls), &strips);
+			assert_eq!(default.ball_count, DEFAULT_BALL_COUNT as usize);
+			assert_eq!(default.gravity, DEFAULT_GRAVITY);
+			assert_eq!(default.damping, 0);
+			let effect = EffectParams { intensity: 200, speed: 10, custom: 40, per_strip: true, ..params(Effect::Balls) };
+			let mut many = balls(effect, &strips);
+			assert_eq!(many.ball_count, b::MAX_BALLS);
+			assert_eq!(many.gravity, DEFAULT_GRAVITY * 2);
+			assert_eq!(many.damping, 40);
+			for i in 0..b::MAX_BALLS {
+				let ball = many.ball_mut(i).unwrap();
+				assert_eq!(ball.range, strips.get(i % 2));
+				assert_eq!(ball.color, c::R_ROYGBIV.color(i));
+			}
+			assert_eq!(balls(EffectParams { per_strip: false, ..effect }, &strips).ball_mut(1).unwrap().range, None);
+			assert!(lights_up(params(Effect::Balls)));
+		}
+
+		#[test]
+		fn spatial_effects_use_the_map() {
+			static MAP: [sp::Point; 3] = [sp::Point::new(0, 0, 0), sp::Point::new(500, 0, 0), sp::Point::new(1000, 0, 0)];
+			let placement = Placement { map: &MAP, ..Placement::new() };
+			for &effect in [Effect::Ripple, Effect::Sweep, Effect::Beam].iter() {
+				match params(effect).mode(1, &placement) {
+					Mode::Spatial(spatial) => assert_eq!(spatial.map.len(), 3),
+					_ => panic!("{:?} isn't spatial", effect),
+				}
+				//without a map they still light up:
+				assert!(lights_up(params(effect)), "{:?}", effect);
 			}
+			match (EffectParams { intensity: 64, ..params(Effect::Sweep) }).mode(1, &placement) {
+				Mode::Spatial(spatial) => assert!(spatial.pattern == sp::Pattern::LinearSweep { angle: 64 }),
+				_ => panic!("not spatial"),
+			}
+			match params(Effect::Ripple).mode(1, &placement) {
+				Mode::Spatial(spatial) => assert!(spatial.pattern == sp::Pattern::RadialWave { center: sp::Point::new(500, 0, 0) }),
+				_ => panic!("not spatial"),
+			}
+		}
+
+		#[test]
+		fn text_scrolls_on_the_matrix() {
+			let layout = mx::Layout::new(8, 5, mx::Wiring::Progressive, mx::Corner::TopLeft, mx::Rotation::R0);
+			let placement = Placement { matrix: Some((10, layout)), text: "I", ..Placement::new() };
+			let effect = EffectParams { effect: Effect::Text, speed: DEFAULT_SPEED, ..EffectParams::solid(c::C_RED) };
+			let mut animation = Animation::from_effect(60, effect, 1);
+			animation.set_placement(placement);
+			let mut frame = [c::C_OFF; 60];
+			//the text starts off the right edge, and one step in the I's left column is on the panel's last column:
+			animation.update(0, &mut frame);
+			assert!(frame.iter().all(|&led| led == c::C_OFF));
+			animation.update(100, &mut frame);
+			let lit: Vec<usize> = (0..60).filter(|&i| frame[i] != c::C_OFF).collect();
+			assert_eq!(lit, [10 + 7, 10 + 4 * 8 + 7]);
+			assert_eq!(frame[17], c::C_RED);
+			//without a matrix it still lights up:
+			assert!(lights_up(effect));
+		}
+
+		#[test]
+		fn storm_intensity() {
+			let intervals = |intensity: u8| match (EffectParams { intensity, ..params(Effect::Storm) }).mode(1, &Placement::new()) {
+				Mode::Storm(storm) => (storm.settings.min_strike_interval_ms, storm.settings.max_strike_interval_ms),
+				_ => panic!("the storm effect makes a storm"),
+			};
+			assert_eq!(intervals(0), (2_000, 12_000));
+			assert_eq!(intervals(DEFAULT_STORM_INTENSITY as u8), intervals(0));
+			assert_eq!(intervals(64), (1_000, 6_000));
+			assert_eq!(intervals(255), (250, 1_505));
+			assert_eq!(intervals(1), (64_000, 384_000));
+			//a more intense storm flashes more often:
+			let flashes = |intensity: u8| {
+				let storm = EffectParams { effect: Effect::Storm, intensity, ..EffectParams::solid(c::C_WHITE) };
+				let mut animation = Animation::from_effect(20, storm, 5);
+				let mut frame = [c::C_OFF; 20];
+				let mut flashes = 0;
+				let mut was_lit = false;
+				for now_ms in (0..120_000).step_by(10) {
+					animation.update(now_ms, &mut frame);
+					let lit = frame.iter().any(|&led| led.r > 0);
+					flashes += (lit && !was_lit) as u32;
+					was_lit = lit;
+				}
+				flashes
+			};
+			let (calm, wild) = (flashes(0), flashes(255));
+			assert!(calm > 0 && wild > calm * 4, "{} {}", calm, wild);
+		}
+
+		#[test]
+		fn direction() {
+			let reversed = |effect: Effect, reversed: bool| match (EffectParams { reversed, ..params(effect) }).mode(1, &Placement::new()) {
+				Mode::TheaterChase(chase) => chase.reversed,
+				Mode::ColorWipe(wipe) => wipe.reversed,
+				Mode::RunningLights(lights) => lights.reversed,
+				Mode::Rainbow(rainbow) => rainbow.reversed,
+				_ => panic!("{} isn't a classic effect", effect.name()),
+			};
+			for effect in [Effect::TheaterChase, Effect::ColorWipe, Effect::RunningLights, Effect::Rainbow, Effect::Text, Effect::Ripple] {
+				assert!(!reversed(effect, false));
+				assert!(reversed(effect, true));
+			}
+			//the wipe fills from the other end:
+			let mut frames = [[c::C_OFF; 10]; 2];
+			for (frame, reversed) in frames.iter_mut().zip([false, true]) {
+				let mut animation = Animation::from_effect(10, EffectParams { reversed, ..params(Effect::ColorWipe) }, 1);
+				animation.update(0, frame);
+				animation.update(120, frame);
+			}
+			let lit = |frame: &[c::Color; 10]| (0..10).filter(|&i| frame[i] != c::C_OFF).collect::<Vec<usize>>();
+			assert_eq!(lit(&frames[0]), lit(&frames[1]).iter().map(|&i| 9 - i).rev().collect::<Vec<usize>>());
+			assert!(!lit(&frames[0]).is_empty() && lit(&frames[0]).len() < 10);
+		}
+
+		#[test]
+		fn bytes_round_trip() {
+			let effect = EffectParams {
+				effect: Effect::Automaton,
+				color: c::Color { r: 1, g: 2, b: 3 },
+				palette: 14,
+				speed: 9,
+				intensity: 90,
+				custom: 12,
+				per_strip: true,
+				reversed: true,
+			};
+			assert_eq!(effect.to_bytes()[7], FLAG_PER_STRIP | FLAG_REVERSED);
+			assert_eq!(EffectParams::from_bytes(&effect.to_bytes()), Some(effect));
+			let forwards = EffectParams { reversed: false, ..effect };
+			assert_eq!(EffectParams::from_bytes(&forwards.to_bytes()), Some(forwards));
+			assert_eq!(EffectParams::from_bytes(&[EFFECTS.len() as u8, 0, 0, 0, 0, 0, 0, 0, 0]), None);
 		}
 	}
 }
@@ -0,0 +1,414 @@
+pub mod artnet {
+	use crate::c;
+	use crate::dx;
+	use core::fmt::{self, Write};
+
+	//Art-Net sends DMX universes over UDP. The network side hands each packet that comes in on
+	//ARTNET_PORT to a Node. Every packet starts:
+	//  8 bytes  ID "Art-Net\0"
+	//  2 bytes  opcode, little endian
+	//  2 bytes  protocol version, big endian
+	//an ArtPoll, which asks every node to say what it is, carries on:
+	//  1 byte   flags
+	//  1 byte   diagnostics priority
+	//an ArtDmx carries on:
+	//  1 byte   sequence number, 0 if the sender doesn't number its packets
+	//  1 byte   physical input port
+	//  1 byte   low byte of the port address, the subnet and universe
+	//  1 byte   net, the high 7 bits of the port address
+	//  2 bytes  number of channels, big endian
+	//  channels up to 512
+	//the ArtPollReply layout is in Node::poll_reply.
+	pub const ARTNET_PORT: u16 = 6454;
+	const ARTNET_ID: [u8; 8] = *b"Art-Net\0";
+	const OPCODE_POLL: u16 = 0x2000;
+	const OPCODE_POLL_REPLY: u16 = 0x2100;
+	const OPCODE_DMX: u16 = 0x5000;
+	const PROTOCOL_VERSION: u16 = 14;
+	const POLL_LENGTH: usize = 14;
+	const DMX_HEADER_LENGTH: usize = 18;
+	pub const POLL_REPLY_LENGTH: usize = 239;
+	//a reply can only describe this many ports, so a strip on more universes needs more replies:
+	pub const PORTS_PER_REPLY: usize = 4;
+
+	//what goes in the reply fields that say what the node is:
+	const FIRMWARE_VERSION: u16 = 1;
+	const OEM_UNKNOWN: u16 = 0x00FF;
+	const ESTA_PROTOTYPE: u16 = 0x7FF0;
+	//indicators normal, port addresses set by this end:
+	const STATUS1: u8 = 0xD0;
+	//15 bit port addresses:
+	const STATUS2: u8 = 0x08;
+	//the port outputs DMX512 from Art-Net:
+	const PORT_TYPE_OUTPUT: u8 = 0x80;
+	//the port has output data:
+	const GOOD_OUTPUT_DATA: u8 = 0x80;
+	//the node report code for a node that is working:
+	const REPORT_POWER_OK: u16 = 0x0001;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum ArtNetError {
+		//the packet ends before its header or channels do
+		Truncated,
+		//this isn't an Art-Net packet
+		NotArtNet,
+		//the sender speaks an older version of Art-Net than 14
+		UnsupportedVersion(u16),
+		//this is an Art-Net packet the node doesn't handle
+		UnsupportedOpcode(u16),
+		Universe(dx::UniverseError),
+	}
+
+	//the packets a node handles:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Packet<'a> {
+		Poll,
+		Dmx { sequence: u8, port_address: u16, channels: &'a [u8] },
+	}
+
+	//a 15 bit port address, which is what Art-Net calls a universe:
+	pub const fn port_address(net: u8, subnet: u8, universe: u8) -> u16 {
+		(net as u16 & 0x7F) << 8 | (subnet as u16 & 0x0F) << 4 | (universe as u16 & 0x0F)
+	}
+
+	impl<'a> Packet<'a> {
+		pub fn parse(packet: &'a [u8]) -> Result<Self, ArtNetError> {
+			if packet.len() < 10 || packet[..8] != ARTNET_ID {
+				return Err(ArtNetError::NotArtNet);
+			}
+			let opcode = u16::from_le_bytes([packet[8], packet[9]]);
+			let minimum_length = match opcode {
+				OPCODE_POLL => POLL_LENGTH,
+				OPCODE_DMX => DMX_HEADER_LENGTH,
+				opcode => return Err(ArtNetError::UnsupportedOpcode(opcode)),
+			};
+			if packet.len() < minimum_length {
+				return Err(ArtNetError::Truncated);
+			}
+			let version = u16::from_be_bytes([packet[10], packet[11]]);
+			if version < PROTOCOL_VERSION {
+				return Err(ArtNetError::UnsupportedVersion(version));
+			}
+			match opcode {
+				OPCODE_POLL => Ok(Packet::Poll),
+				_ => {
+					let channel_count = (u16::from_be_bytes([packet[16], packet[17]]) as usize).min(dx::CHANNELS_PER_UNIVERSE);
+					Ok(Packet::Dmx {
+						sequence: packet[12],
+						port_address: u16::from_le_bytes([packet[14], packet[15]]) & 0x7FFF,
+						channels: packet.get(DMX_HEADER_LENGTH..DMX_HEADER_LENGTH + channel_count).ok_or(ArtNetError::Truncated)?,
+					})
+				}
+			}
+		}
+	}
+
+	//what a packet turned out to need:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Event {
+		Nothing,
+		//a frame is done, with this many LEDs
+		Frame(usize),
+		//a controller is looking for nodes, so the poll replies need sending back
+		Poll,
+	}
+
+	//what the node tells controllers about itself:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct NodeInfo {
+		pub ip_address: [u8; 4],
+		pub mac_address: [u8; 6],
+		//up to 17 characters
+		pub short_name: &'static str,
+		//up to 63 characters
+		pub long_name: &'static str,
+		//the number of physical strips, for the node report
+		pub strip_count: usize,
+	}
+
+	//writes text into a nul terminated field, cutting it short if it doesn't fit:
+	struct TextField<'a> {
+		bytes: &'a mut [u8],
+		length: usize,
+	}
+
+	impl<'a> fmt::Write for TextField<'a> {
+		fn write_str(&mut self, text: &str) -> fmt::Result {
+			for &byte in text.as_bytes() {
+				if self.length + 1 < self.bytes.len() {
+					self.bytes[self.length] = byte;
+					self.length += 1;
+				}
+			}
+			Ok(())
+		}
+	}
+
+	fn text_field(bytes: &mut [u8]) -> TextField<'_> {
+		TextField { bytes, length: 0 }
+	}
+
+	//an Art-Net node that puts ArtDmx packets for a strip's universes together into frames, and
+	//answers ArtPolls so it shows up in controllers. Universes here are 15 bit port addresses.
+	pub struct Node {
+		pub info: NodeInfo,
+		pub frames: dx::FrameAssembler,
+		//counts ArtPolls for the node report, from 0 to 9999:
+		poll_count: u16,
+		//whether any ArtDmx for the strip has come in:
+		has_output: bool,
+	}
+
+	impl Node {
+		pub fn new(info: NodeInfo, map: dx::UniverseMap) -> Self {
+			Node {
+				info,
+				frames: dx::FrameAssembler::new(map),
+				poll_count: 0,
+				has_output: false,
+			}
+		}
+
+		//this takes a packet, writing ArtDmx channels straight into pixels.
+		pub fn receive(&mut self, packet: &[u8], pixels: &mut [c::Color]) -> Result<Event, ArtNetError> {
+			match Packet::parse(packet)? {
+				Packet::Poll => {
+					self.poll_count = (self.poll_count + 1) % 10_000;
+					Ok(Event::Poll)
+				}
+				Packet::Dmx { sequence, port_address, channels } => {
+					let sequence = if sequence == 0 { None } else { Some(sequence) };
+					let frame = self.frames
+						.write(port_address, sequence, channels, pixels)
+						.map_err(ArtNetError::Universe)?;
+					self.has_output = true;
+					Ok(frame.map_or(Event::Nothing, Event::Frame))
+				}
+			}
+		}
+
+		//this writes the index'th ArtPollReply, returning false once there are no more to send.
+		//each reply describes up to PORTS_PER_REPLY of the strip's universes that share a net
+		//and subnet, one port each. A reply is laid out as:
+		//  8 bytes   ID "Art-Net\0"
+		//  2 bytes   opcode, little endian
+		//  4 bytes   IP address
+		//  2 bytes   UDP port, little endian
+		//  2 bytes   firmware version
+		//  1 byte    net
+		//  1 byte    subnet
+		//  2 bytes   OEM code
+		//  1 byte    UBEA version
+		//  1 byte    status 1
+		//  2 bytes   ESTA manufacturer code, little endian
+		//  18 bytes  short name
+		//  64 bytes  long name
+		//  64 bytes  node report
+		//  2 bytes   number of ports
+		//  4 bytes   port types
+		//  4 bytes   input status
+		//  4 bytes   output status
+		//  4 bytes   input universes, the low 4 bits of each port address
+		//  4 bytes   output universes
+		//  6 bytes   priority, macro and remote switches, spare
+		//  1 byte    style, 0 for a node
+		//  6 bytes   MAC address
+		//  4 bytes   bind IP address
+		//  1 byte    bind index, which reply of the node's this is, from 1
+		//  1 byte    status 2
+		//  the rest is left 0
+		//numbers are big endian unless they say otherwise.
+		pub fn poll_reply(&self, index: usize, reply: &mut [u8; POLL_REPLY_LENGTH]) -> bool {
+			let map = self.frames.map;
+			let universe_count = map.universe_count();
+			let (mut first, mut group) = (0, 0);
+			let (start, port_count) = loop {
+				if first >= universe_count {
+					//a node without universes still needs to say it is there:
+					if index == 0 && universe_count == 0 {
+						break (map.start_universe, 0);
+					}
+					return false;
+				}
+				let start = map.start_universe.wrapping_add(first as u16);
+				let mut port_count = 1;
+				while port_count < PORTS_PER_REPLY
+					&& first + port_count < universe_count
+					&& start.wrapping_add(port_count as u16) >> 4 == start >> 4
+				{
+					port_count += 1;
+				}
+				if group == index {
+					break (start, port_count);
+				}
+				group += 1;
+				first += port_count;
+			};
+
+			*reply = [0; POLL_REPLY_LENGTH];
+			reply[..8].copy_from_slice(&ARTNET_ID);
+			reply[8..10].copy_from_slice(&OPCODE_POLL_REPLY.to_le_bytes());
+			reply[10..14].copy_from_slice(&self.info.ip_address);
+			reply[14..16].copy_from_slice(&ARTNET_PORT.to_le_bytes());
+			reply[16..18].copy_from_slice(&FIRMWARE_VERSION.to_be_bytes());
+			reply[18] = (start >> 8) as u8 & 0x7F;
+			reply[19] = (start >> 4) as u8 & 0x0F;
+			reply[20..22].copy_from_slice(&OEM_UNKNOWN.to_be_bytes());
+			reply[23] = STATUS1;
+			reply[24..26].copy_from_slice(&ESTA_PROTOTYPE.to_le_bytes());
+			let _ = text_field(&mut reply[26..44]).write_str(self.info.short_name);
+			let _ = text_field(&mut reply[44..108]).write_str(self.info.long_name);
+			let _ = write!(
+				text_field(&mut reply[108..172]),
+				"#{:04x} [{:04}] {} strips, {} LEDs",
+				REPORT_POWER_OK, self.poll_count, self.info.strip_count, map.led_count
+			);
+			reply[172..174].copy_from_slice(&(port_count as u16).to_be_bytes());
+			for port in 0..port_count {
+				reply[174 + port] = PORT_TYPE_OUTPUT;
+				reply[182 + port] = if self.has_output { GOOD_OUTPUT_DATA } else { 0 };
+				reply[190 + port] = start.wrapping_add(port as u16) as u8 & 0x0F;
+			}
+			reply[201..207].copy_from_slice(&self.info.mac_address);
+			reply[207..211].copy_from_slice(&self.info.ip_address);
+			reply[211] = index as u8 + 1;
+			reply[212] = STATUS2;
+			true
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const INFO: NodeInfo = NodeInfo {
+			ip_address: [192, 168, 1, 40],
+			mac_address: [0x24, 0x0A, 0xC4, 0x01, 0x02, 0x03],
+			short_name: "office",
+			long_name: "office lights, the longest name that fits in the sixty three bytes",
+			strip_count: 3,
+		};
+
+		fn poll(version: u16) -> Vec<u8> {
+			let mut packet = ARTNET_ID.to_vec();
+			packet.extend(&OPCODE_POLL.to_le_bytes());
+			packet.extend(&version.to_be_bytes());
+			packet.extend(&[0x02, 0x10]);
+			packet
+		}
+
+		fn dmx(sequence: u8, port_address: u16, channels: &[u8]) -> Vec<u8> {
+			let mut packet = ARTNET_ID.to_vec();
+			packet.extend(&OPCODE_DMX.to_le_bytes());
+			packet.extend(&PROTOCOL_VERSION.to_be_bytes());
+			packet.extend(&[sequence, 0]);
+			packet.extend(&port_address.to_le_bytes());
+			packet.extend(&(channels.len() as u16).to_be_bytes());
+			packet.extend(channels);
+			packet
+		}
+
+		fn text(field: &[u8]) -> &str {
+			let length = field.iter().position(|&byte| byte == 0).unwrap();
+			core::str::from_utf8(&field[..length]).unwrap()
+		}
+
+		#[test]
+		fn parse() {
+			assert_eq!(port_address(1, 2, 3), 0x0123);
+			assert_eq!(port_address(0xFF, 0x1F, 0x1F), 0x7FFF);
+			assert_eq!(Packet::parse(&poll(14)), Ok(Packet::Poll));
+			assert_eq!(Packet::parse(&poll(15)), Ok(Packet::Poll));
+			let packet = dmx(7, 0x0123, &[1, 2, 3]);
+			assert_eq!(Packet::parse(&packet), Ok(Packet::Dmx { sequence: 7, port_address: 0x0123, channels: &[1, 2, 3] }));
+			//the top bit of the port address isn't part of it:
+			let packet = dmx(0, 0x8123, &[1, 2, 3]);
+			assert_eq!(Packet::parse(&packet), Ok(Packet::Dmx { sequence: 0, port_address: 0x0123, channels: &[1, 2, 3] }));
+			//only 512 channels are used, even if the length says there are more:
+			let mut packet = dmx(0, 0, &[9; 600]);
+			packet[16..18].copy_from_slice(&600_u16.to_be_bytes());
+			match Packet::parse(&packet) {
+				Ok(Packet::Dmx { channels, .. }) => assert_eq!(channels.len(), dx::CHANNELS_PER_UNIVERSE),
+				other => panic!("{:?}", other),
+			}
+		}
+
+		#[test]
+		fn bad_packets() {
+			assert_eq!(Packet::parse(b"Art-Net"), Err(ArtNetError::NotArtNet));
+			assert_eq!(Packet::parse(b"Art-Nat\0\0\x20\0\x0e\0\0"), Err(ArtNetError::NotArtNet));
+			assert_eq!(Packet::parse(&poll(13)), Err(ArtNetError::UnsupportedVersion(13)));
+			assert_eq!(Packet::parse(&poll(14)[..POLL_LENGTH - 1]), Err(ArtNetError::Truncated));
+			let mut reply = poll(14);
+			reply[8..10].copy_from_slice(&OPCODE_POLL_REPLY.to_le_bytes());
+			assert_eq!(Packet::parse(&reply), Err(ArtNetError::UnsupportedOpcode(OPCODE_POLL_REPLY)));
+			let packet = dmx(0, 0, &[1, 2, 3]);
+			assert_eq!(Packet::parse(&packet[..DMX_HEADER_LENGTH - 1]), Err(ArtNetError::Truncated));
+			//the length says there are more channels than came:
+			assert_eq!(Packet::parse(&packet[..DMX_HEADER_LENGTH + 2]), Err(ArtNetError::Truncated));
+		}
+
+		#[test]
+		fn receive() {
+			//two universes, the second with one LED in it:
+			let mut node = Node::new(INFO, dx::UniverseMap::new(port_address(0, 1, 15), 1, dx::PIXELS_PER_UNIVERSE + 1));
+			let mut pixels = [c::C_OFF; dx::PIXELS_PER_UNIVERSE + 1];
+			assert_eq!(node.receive(&dmx(1, 0x1F, &[1, 2, 3, 4, 5, 6]), &mut pixels), Ok(Event::Nothing));
+			assert_eq!(node.receive(&dmx(1, 0x20, &[7, 8, 9]), &mut pixels), Ok(Event::Frame(dx::PIXELS_PER_UNIVERSE + 1)));
+			assert_eq!(pixels[..2], [c::Color { r: 1, g: 2, b: 3 }, c::Color { r: 4, g: 5, b: 6 }]);
+			assert_eq!(pixels[dx::PIXELS_PER_UNIVERSE], c::Color { r: 7, g: 8, b: 9 });
+			assert_eq!(node.receive(&dmx(1, 0x21, &[1, 2, 3]), &mut pixels), Err(ArtNetError::Universe(dx::UniverseError::NotMapped(0x21))));
+			assert_eq!(node.receive(&dmx(1, 0x1F, &[1, 2, 3]), &mut pixels), Err(ArtNetError::Universe(dx::UniverseError::OutOfOrder)));
+			assert_eq!(node.receive(&poll(14), &mut pixels), Ok(Event::Poll));
+		}
+
+		#[test]
+		fn poll_replies_split_at_subnets() {
+			//universes 14 to 20 cross from subnet 0 to subnet 1:
+			let map = dx::UniverseMap::new(port_address(2, 0, 14), 1, 6 * dx::PIXELS_PER_UNIVERSE + 1);
+			assert_eq!(map.universe_count(), 7);
+			let mut node = Node::new(INFO, map);
+			let mut reply = [0_u8; POLL_REPLY_LENGTH];
+			let mut groups = Vec::new();
+			let mut index = 0;
+			while node.poll_reply(index, &mut reply) {
+				assert_eq!(reply[..8], ARTNET_ID);
+				assert_eq!(reply[8..10], OPCODE_POLL_REPLY.to_le_bytes());
+				assert_eq!(reply[18], 2);
+				assert_eq!(reply[211] as usize, index + 1);
+				let port_count = u16::from_be_bytes([reply[172], reply[173]]) as usize;
+				assert!(reply[174..174 + port_count].iter().all(|&port_type| port_type == PORT_TYPE_OUTPUT));
+				assert!(reply[174 + port_count..178].iter().all(|&port_type| port_type == 0));
+				groups.push((reply[19], reply[190..190 + port_count].to_vec()));
+				index += 1;
+			}
+			assert_eq!(groups, [(0, vec![14, 15]), (1, vec![0, 1, 2, 3]), (1, vec![4])]);
+
+			//what the node says about itself:
+			node.receive(&poll(14), &mut []).unwrap();
+			node.poll_reply(0, &mut reply);
+			assert_eq!(reply[10..14], INFO.ip_address);
+			assert_eq!(reply[14..16], ARTNET_PORT.to_le_bytes());
+			assert_eq!(reply[201..207], INFO.mac_address);
+			assert_eq!(reply[207..211], INFO.ip_address);
+			assert_eq!(text(&reply[26..44]), "office");
+			//the long name is cut short to leave room for the nul:
+			assert_eq!(text(&reply[44..108]), &INFO.long_name[..63]);
+			assert_eq!(text(&reply[108..172]), "#0001 [0001] 3 strips, 1021 LEDs");
+			//no output yet:
+			assert_eq!(reply[182], 0);
+			node.receive(&dmx(0, port_address(2, 0, 14), &[1, 2, 3]), &mut [c::C_OFF; 1]).unwrap();
+			node.poll_reply(0, &mut reply);
+			assert_eq!(reply[182..184], [GOOD_OUTPUT_DATA; 2]);
+		}
+
+		#[test]
+		fn a_node_without_universes_still_replies() {
+			let node = Node::new(INFO, dx::UniverseMap::new(port_address(0, 3, 2), 1, 0));
+			let mut reply = [0_u8; POLL_REPLY_LENGTH];
+			assert!(node.poll_reply(0, &mut reply));
+			assert_eq!((reply[19], reply[172..174].to_vec()), (3, vec![0, 0]));
+			assert!(!node.poll_reply(1, &mut reply));
+		}
+	}
+}
@@ -0,0 +1,232 @@
+pub mod astro {
+	use crate::ck;
+
+	//this works out when the sun rises and sets using NOAA's approximate solar position equations,
+	//which are good to a minute or two away from the poles. It isn't run every frame, so it uses
+	//floating point with small series for the trig functions core doesn't have.
+	const PI: f64 = core::f64::consts::PI;
+
+	//the sun counts as up while its top edge is above the horizon, allowing for refraction:
+	const SUN_ZENITH_DEGREES: f64 = 90.833;
+
+	//where the lights are, in thousandths of a degree. North and east are positive.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Location {
+		pub latitude_mdeg: i32,
+		pub longitude_mdeg: i32,
+	}
+
+	impl Location {
+		pub const fn new(latitude_mdeg: i32, longitude_mdeg: i32) -> Self {
+			Location {
+				latitude_mdeg,
+				longitude_mdeg,
+			}
+		}
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum SunTime {
+		//minutes after midnight UTC on the day. This can be below 0 or past a day's worth of
+		//minutes when the event lands on the day before or after in UTC.
+		At(i32),
+		//the sun doesn't set all day
+		AlwaysUp,
+		//the sun doesn't rise all day
+		AlwaysDown,
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct SunTimes {
+		pub sunrise: SunTime,
+		pub sunset: SunTime,
+	}
+
+	fn radians(degrees: f64) -> f64 {
+		degrees * PI / 180.0
+	}
+
+	fn degrees(radians: f64) -> f64 {
+		radians * 180.0 / PI
+	}
+
+	//this moves an angle into -pi to pi, where the series below are accurate:
+	fn wrap_angle(angle: f64) -> f64 {
+		let turns = angle / (2.0 * PI) + 0.5;
+		let whole_turns = turns as i64 as f64;
+		let whole_turns = if whole_turns > turns { whole_turns - 1.0 } else { whole_turns };
+		angle - whole_turns * 2.0 * PI
+	}
+
+	fn sin(angle: f64) -> f64 {
+		let x = wrap_angle(angle);
+		let mut term = x;
+		let mut sum = x;
+		for n in 1..12 {
+			term *= -x * x / ((2 * n) as f64 * (2 * n + 1) as f64);
+			sum += term;
+		}
+		sum
+	}
+
+	fn cos(angle: f64) -> f64 {
+		sin(angle + PI / 2.0)
+	}
+
+	fn sqrt(value: f64) -> f64 {
+		if value <= 0.0 {
+			return 0.0;
+		}
+		let mut root = if value > 1.0 { value } else { 1.0 };
+		for _ in 0..60 {
+			root = (root + value / root) / 2.0;
+		}
+		root
+	}
+
+	//arctangent, halving the angle until the series converges quickly:
+	fn atan(value: f64) -> f64 {
+		if value < 0.0 {
+			return -atan(-value);
+		}
+		if value > 1.0 {
+			return PI / 2.0 - atan(1.0 / value);
+		}
+		//tan(x / 2) = tan(x) / (1 + sqrt(1 + tan(x)^2)), twice:
+		let mut x = value;
+		for _ in 0..2 {
+			x /= 1.0 + sqrt(1.0 + x * x);
+		}
+		let mut term = x;
+		let mut sum = x;
+		for n in 1..12 {
+			term *= -x * x;
+			sum += term / (2 * n + 1) as f64;
+		}
+		sum * 4.0
+	}
+
+	fn acos(value: f64) -> f64 {
+		if value >= 1.0 {
+			return 0.0;
+		}
+		if value <= -1.0 {
+			return PI;
+		}
+		let angle = atan(sqrt(1.0 - value * value) / value);
+		if value < 0.0 { angle + PI } else { angle }
+	}
+
+	//this works out sunrise and sunset on a date at a location:
+	pub fn sun_times(date: ck::Date, location: Location) -> SunTimes {
+		let latitude = radians(location.latitude_mdeg as f64 / 1000.0);
+		let longitude = location.longitude_mdeg as f64 / 1000.0;
+		//the fraction of the way through the year at noon, as an angle:
+		let year_length = if ck::day_of_year(ck::Date { year: date.year, month: 12, day: 31 }) == 366 { 366.0 } else { 365.0 };
+		let year_angle = 2.0 * PI / year_length * (ck::day_of_year(date) as f64 - 1.0);
+		let (sin1, cos1) = (sin(year_angle), cos(year_angle));
+		let (sin2, cos2) = (sin(2.0 * year_angle), cos(2.0 * year_angle));
+		let (sin3, cos3) = (sin(3.0 * year_angle), cos(3.0 * year_angle));
+		//how far the sun is ahead of or behind clock time, in minutes:
+		let equation_of_time =
+			229.18 * (0.000075 + 0.001868 * cos1 - 0.032077 * sin1 - 0.014615 * cos2 - 0.040849 * sin2);
+		//how far north of the equator the sun is:
+		let declination = 0.006918 - 0.399912 * cos1 + 0.070257 * sin1 - 0.006758 * cos2 + 0.000907 * sin2
+			- 0.002697 * cos3 + 0.00148 * sin3;
+		let cos_hour_angle = cos(radians(SUN_ZENITH_DEGREES)) / (cos(latitude) * cos(declination))
+			- sin(latitude) / cos(latitude) * sin(declination) / cos(declination);
+		if cos_hour_angle >= 1.0 {
+			return SunTimes { sunrise: SunTime::AlwaysDown, sunset: SunTime::AlwaysDown };
+		}
+		if cos_hour_angle <= -1.0 {
+			return SunTimes { sunrise: SunTime::AlwaysUp, sunset: SunTime::AlwaysUp };
+		}
+		let hour_angle = degrees(acos(cos_hour_angle));
+		let noon = 720.0 - 4.0 * longitude - equation_of_time;
+		let round = |minutes: f64| (if minutes < 0.0 { minutes - 0.5 } else { minutes + 0.5 }) as i32;
+		SunTimes {
+			sunrise: SunTime::At(round(noon - 4.0 * hour_angle)),
+			sunset: SunTime::At(round(noon + 4.0 * hour_angle)),
+		}
+	}
+
+	//this gets sunrise or sunset on a local date as local seconds after midnight, which can be
+	//outside the day for far off time zones. Returns None if the sun doesn't rise or set that day.
+	pub fn local_seconds_of_day(time: SunTime, utc_offset_minutes: i32) -> Option<i32> {
+		match time {
+			SunTime::At(minutes) => Some((minutes + utc_offset_minutes) * 60),
+			_ => None,
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		//published sunrise and sunset times, in minutes after midnight UTC. The equations are
+		//good to a minute or two, and published times are rounded to the minute:
+		fn assert_near(time: SunTime, expected_minutes: i32) {
+			match time {
+				SunTime::At(minutes) => assert!((minutes - expected_minutes).abs() <= 3, "{} is not near {}", minutes, expected_minutes),
+				time => panic!("{:?} is not near {}", time, expected_minutes),
+			}
+		}
+
+		fn times(year: i32, month: u8, day: u8, latitude_mdeg: i32, longitude_mdeg: i32) -> SunTimes {
+			sun_times(ck::Date { year, month, day }, Location::new(latitude_mdeg, longitude_mdeg))
+		}
+
+		#[test]
+		fn new_york_summer_solstice() {
+			//5:24 and 20:31 EDT:
+			let times = times(2024, 6, 20, 40_713, -74_006);
+			assert_near(times.sunrise, 564);
+			assert_near(times.sunset, 1471);
+		}
+
+		#[test]
+		fn london_winter_solstice() {
+			//8:04 and 15:53 GMT:
+			let times = times(2024, 12, 21, 51_507, -128);
+			assert_near(times.sunrise, 484);
+			assert_near(times.sunset, 953);
+		}
+
+		#[test]
+		fn sydney_sunrise_is_the_day_before_in_utc() {
+			//5:48 and 20:09 AEDT:
+			let times = times(2024, 1, 1, -33_869, 151_209);
+			assert_near(times.sunrise, -312);
+			assert_near(times.sunset, 549);
+		}
+
+		#[test]
+		fn quito_equinox() {
+			//6:17 and 18:25 local time, 5 hours behind UTC:
+			let times = times(2024, 3, 20, -181, -78_468);
+			assert_near(times.sunrise, 677);
+			assert_near(times.sunset, 1405);
+		}
+
+		#[test]
+		fn reykjavik_equinox() {
+			//7:27 and 19:44 GMT:
+			let times = times(2024, 3, 20, 64_147, -21_943);
+			assert_near(times.sunrise, 447);
+			assert_near(times.sunset, 1184);
+		}
+
+		#[test]
+		fn tromso_midnight_sun_and_polar_night() {
+			assert_eq!(times(2024, 6, 20, 69_649, 18_955), SunTimes { sunrise: SunTime::AlwaysUp, sunset: SunTime::AlwaysUp });
+			assert_eq!(times(2024, 12, 21, 69_649, 18_955), SunTimes { sunrise: SunTime::AlwaysDown, sunset: SunTime::AlwaysDown });
+		}
+
+		#[test]
+		fn local_seconds() {
+			assert_eq!(local_seconds_of_day(SunTime::At(564), -240), Some(324 * 60));
+			assert_eq!(local_seconds_of_day(SunTime::At(-312), 660), Some(348 * 60));
+			assert_eq!(local_seconds_of_day(SunTime::AlwaysUp, 0), None);
+		}
+	}
+}
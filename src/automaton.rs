@@ -0,0 +1,247 @@
+pub mod automaton {
+	use crate::c;
+	use crate::rng;
+
+	//the most cells the automaton keeps track of. LEDs past this are left dark.
+	pub const MAX_CELLS: usize = 512;
+
+	//ages are kept in 4 bits, two cells to a byte, which is more than there are colors in a rainbow:
+	const MAX_AGE: u8 = 0x0F;
+	type Ages = [u8; MAX_CELLS / 2];
+
+	fn age(ages: &Ages, index: usize) -> u8 {
+		(ages[index / 2] >> (index % 2 * 4)) & MAX_AGE
+	}
+
+	fn set_age(ages: &mut Ages, index: usize, age: u8) {
+		let shift = index % 2 * 4;
+		ages[index / 2] = ages[index / 2] & !(MAX_AGE << shift) | age.min(MAX_AGE) << shift;
+	}
+
+	//which cells are alive in one generation, a bit per cell:
+	type Generation = [u8; MAX_CELLS / 8];
+
+	#[derive(Copy, Clone, PartialEq)]
+	pub enum Rule {
+		//Wolfram's elementary rules, 0 to 255. Each cell looks at itself and one neighbor on each side.
+		Elementary(u8),
+		//a 1D version of life: each cell looks at two neighbors on each side. Dead cells with
+		//2 or 3 live neighbors are born, and live cells with 2 or 4 live neighbors survive.
+		Life,
+	}
+
+	//each generation of the automaton is one frame. Cells count up their age while they stay
+	//alive, up to MAX_AGE, and the age picks the color from the rainbow. The ends of the strip
+	//wrap around to each other since the strips go all the way around the room.
+	pub struct Automaton {
+		pub rule: Rule,
+		pub rainbow: c::Rainbow,
+		pub step_ms: u32,
+		//roughly how many cells out of 1000 start out alive when reseeding:
+		pub seed_density: u16,
+		rng: rng::Rng,
+		ages: Ages,
+		//the generation before the one showing, to catch patterns that flip back and forth:
+		previous: Generation,
+		cell_count: usize,
+		last_step_ms: Option<u32>,
+	}
+
+	impl Automaton {
+		pub fn new(rule: Rule, rainbow: c::Rainbow, step_ms: u32, seed: u32) -> Self {
+			Automaton {
+				rule,
+				rainbow,
+				step_ms,
+				seed_density: 300,
+				rng: rng::Rng::new(seed),
+				ages: [0; MAX_CELLS / 2],
+				previous: [0; MAX_CELLS / 8],
+				cell_count: 0,
+				last_step_ms: None,
+			}
+		}
+
+		//this fills the cells with a new random pattern:
+		pub fn reseed(&mut self) {
+			for i in 0..self.cell_count {
+				let age = if self.rng.chance(self.seed_density) { 1 } else { 0 };
+				set_age(&mut self.ages, i, age);
+			}
+			self.previous = [0; MAX_CELLS / 8];
+		}
+
+		fn is_alive(&self, index: isize) -> bool {
+			let count = self.cell_count as isize;
+			age(&self.ages, index.rem_euclid(count) as usize) > 0
+		}
+
+		fn next_state(&self, index: usize) -> bool {
+			let index = index as isize;
+			match self.rule {
+				Rule::Elementary(rule) => {
+					let pattern = (self.is_alive(index - 1) as u8) << 2
+						| (self.is_alive(index) as u8) << 1
+						| self.is_alive(index + 1) as u8;
+					(rule >> pattern) & 0x01 == 0x01
+				}
+				Rule::Life => {
+					let neighbors = [index - 2, index - 1, index + 1, index + 2]
+						.iter()
+						.filter(|&&neighbor| self.is_alive(neighbor))
+						.count();
+					match self.is_alive(index) {
+						true => neighbors == 2 || neighbors == 4,
+						false => neighbors == 2 || neighbors == 3,
+					}
+				}
+			}
+		}
+
+		//this moves to the next generation, and returns false if the pattern died out, stopped
+		//changing or went back to what it was two generations ago:
+		pub fn step(&mut self) -> bool {
+			let mut next_ages: Ages = [0; MAX_CELLS / 2];
+			let mut current: Generation = [0; MAX_CELLS / 8];
+			let mut next: Generation = [0; MAX_CELLS / 8];
+			for i in 0..self.cell_count {
+				let age = age(&self.ages, i);
+				if age > 0 {
+					current[i / 8] |= 1 << (i % 8);
+				}
+				if self.next_state(i) {
+					next[i / 8] |= 1 << (i % 8);
+					set_age(&mut next_ages, i, age + 1);
+				}
+			}
+			self.ages = next_ages;
+			let previous = core::mem::replace(&mut self.previous, current);
+			next != [0; MAX_CELLS / 8] && next != current && next != previous
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			let cell_count = frame.len().min(MAX_CELLS);
+			if cell_count == 0 {
+				return;
+			}
+			match self.last_step_ms {
+				Some(last_step_ms) if cell_count == self.cell_count => {
+					if now_ms.wrapping_sub(last_step_ms) >= self.step_ms {
+						self.last_step_ms = Some(now_ms);
+						if !self.step() {
+							self.reseed();
+						}
+					}
+				}
+				_ => {
+					self.cell_count = cell_count;
+					self.last_step_ms = Some(now_ms);
+					self.reseed();
+				}
+			}
+
+			let last_color = self.rainbow.num_colors().max(1) - 1;
+			for (i, led) in frame.iter_mut().enumerate() {
+				*led = match i < self.cell_count {
+					true => match age(&self.ages, i) {
+						0 => c::C_OFF,
+						age => self.rainbow.color((age as usize - 1).min(last_color)),
+					},
+					false => c::C_OFF,
+				};
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		//an automaton showing a pattern, with '#' for live cells:
+		fn automaton(rule: Rule, pattern: &str) -> Automaton {
+			let mut automaton = Automaton::new(rule, c::R_ROYGBIV, 100, 1);
+			automaton.cell_count = pattern.len();
+			for (i, cell) in pattern.bytes().enumerate() {
+				set_age(&mut automaton.ages, i, (cell == b'#') as u8);
+			}
+			automaton
+		}
+
+		fn pattern(automaton: &Automaton) -> String {
+			(0..automaton.cell_count).map(|i| if age(&automaton.ages, i) > 0 { '#' } else { '.' }).collect()
+		}
+
+		#[test]
+		fn ages_share_bytes() {
+			let mut ages: Ages = [0; MAX_CELLS / 2];
+			set_age(&mut ages, 4, 9);
+			set_age(&mut ages, 5, 40);
+			assert_eq!((age(&ages, 4), age(&ages, 5)), (9, MAX_AGE));
+			set_age(&mut ages, 4, 0);
+			assert_eq!((age(&ages, 4), age(&ages, 5)), (0, MAX_AGE));
+		}
+
+		#[test]
+		fn rules() {
+			//rule 90 is each cell being the XOR of its neighbors, wrapping around at the ends:
+			let mut rule_90 = automaton(Rule::Elementary(90), "#..#....");
+			assert!(rule_90.step());
+			assert_eq!(pattern(&rule_90), ".##.#..#");
+			let mut life = automaton(Rule::Life, "##.#.....##.");
+			assert!(life.step());
+			assert_eq!(pattern(&life), "###.....#.#.");
+		}
+
+		#[test]
+		fn dead_and_still_patterns_stop() {
+			assert!(!automaton(Rule::Elementary(0), "#.#..#").step());
+			//rule 204 keeps every cell as it is:
+			assert!(!automaton(Rule::Elementary(204), "#.#..#").step());
+		}
+
+		#[test]
+		fn blinking_patterns_stop() {
+			//rule 51 flips every cell, so the pattern goes back and forth:
+			let mut blinker = automaton(Rule::Elementary(51), "#.##..");
+			assert!(blinker.step());
+			assert_eq!(pattern(&blinker), ".#..##");
+			assert!(!blinker.step());
+			//a pattern moving around the strip takes longer to come back, and keeps going:
+			let mut glider = automaton(Rule::Elementary(170), "##......");
+			for _ in 0..20 {
+				assert!(glider.step());
+			}
+		}
+
+		#[test]
+		fn stuck_patterns_are_reseeded() {
+			let mut automaton = Automaton::new(Rule::Elementary(51), c::R_ROYGBIV, 100, 7);
+			let mut frame = [c::C_OFF; 60];
+			let mut patterns = Vec::new();
+			for now_ms in (0..=300).step_by(100) {
+				automaton.update(now_ms, &mut frame);
+				patterns.push(pattern(&automaton));
+			}
+			assert!(patterns[0].contains('#'));
+			assert_eq!(patterns[1], patterns[0].chars().map(|cell| if cell == '#' { '.' } else { '#' }).collect::<String>());
+			//the third generation would have been the first again:
+			assert_ne!(patterns[2], patterns[0]);
+			assert_ne!(patterns[2], patterns[1]);
+		}
+
+		#[test]
+		fn colors_follow_age() {
+			let mut automaton = Automaton::new(Rule::Elementary(204), c::R_ROYGBIV, 100, 3);
+			let mut frame = [c::C_WHITE; MAX_CELLS + 2];
+			automaton.update(0, &mut frame);
+			let alive: Vec<usize> = (0..MAX_CELLS).filter(|&i| frame[i] != c::C_OFF).collect();
+			assert!(!alive.is_empty());
+			assert!(alive.iter().all(|&i| frame[i] == c::R_ROYGBIV.color(0)));
+			assert_eq!(frame[MAX_CELLS..], [c::C_OFF; 2]);
+			//the same seed gives the same pattern:
+			let mut again = [c::C_OFF; MAX_CELLS + 2];
+			Automaton::new(Rule::Elementary(204), c::R_ROYGBIV, 100, 3).update(0, &mut again);
+			assert_eq!(frame, again);
+		}
+	}
+}
@@ -0,0 +1,240 @@
+pub mod balls {
+	use crate::c;
+	use crate::fx;
+	use core::ops::Range;
+
+	pub const MAX_BALLS: usize = 8;
+
+	//positions and speeds are in LEDs, 16.16 fixed point:
+	const ONE_LED: i32 = 0x1_0000;
+
+	//long frames are split up so balls can't fall through the floor in one step:
+	const MAX_STEP_MS: u32 = 20;
+
+	#[derive(Clone)]
+	pub struct Ball {
+		pub color: c::Color,
+		//the fraction of its speed a ball keeps after a bounce, out of 256:
+		pub elasticity: u8,
+		//the LEDs this ball bounces in, None to roam the whole frame. Pass the range of a
+		//physical strip to keep a ball on that strip. The floor is at the start of the range.
+		pub range: Option<Range<usize>>,
+		position: i32,
+		velocity: i32,
+	}
+
+	impl Ball {
+		pub fn new(color: c::Color, elasticity: u8, range: Option<Range<usize>>) -> Self {
+			Ball {
+				color,
+				elasticity,
+				range,
+				//balls start out dropped from the top of their range:
+				position: i32::MAX,
+				velocity: 0,
+			}
+		}
+	}
+
+	pub struct BouncingBalls {
+		balls: [Option<Ball>; MAX_BALLS],
+		//how many of the balls are in play, up to MAX_BALLS:
+		pub ball_count: usize,
+		//LEDs per second per second, 16.16 fixed point:
+		pub gravity: i32,
+		//the fraction of its speed a ball loses to drag every second, out of 256:
+		pub damping: u8,
+		last_update_ms: Option<u32>,
+	}
+
+	impl BouncingBalls {
+		pub fn new(gravity: i32, damping: u8) -> Self {
+			BouncingBalls {
+				balls: Default::default(),
+				ball_count: 0,
+				gravity,
+				damping,
+				last_update_ms: None,
+			}
+		}
+
+		//adds a ball to the next open slot and puts it in play. Returns false if all the slots are full.
+		pub fn add_ball(&mut self, ball: Ball) -> bool {
+			match self.balls.iter_mut().position(|slot| slot.is_none()) {
+				Some(index) => {
+					self.balls[index] = Some(ball);
+					self.ball_count = self.ball_count.max(index + 1);
+					true
+				}
+				None => false,
+			}
+		}
+
+		pub fn ball_mut(&mut self, index: usize) -> Option<&mut Ball> {
+			self.balls.get_mut(index).and_then(|slot| slot.as_mut())
+		}
+
+		fn ball_range(ball: &Ball, led_count: usize) -> Range<usize> {
+			match &ball.range {
+				Some(range) => range.start.min(led_count)..range.end.min(led_count),
+				None => 0..led_count,
+			}
+		}
+
+		//this moves a ball forward in time by step_ms, bouncing it off the floor and ceiling:
+		fn step_ball(ball: &mut Ball, height: i32, gravity: i32, damping: u8, step_ms: u32) {
+			let step_ms = step_ms as i64;
+			let mut velocity = ball.velocity as i64;
+			velocity -= gravity as i64 * step_ms / 1000;
+			velocity -= velocity * damping as i64 * step_ms / (256 * 1000);
+			let mut position = ball.position as i64 + velocity * step_ms / 1000;
+
+			if position > height as i64 {
+				position = height as i64;
+				velocity = -velocity.abs();
+			}
+			if position <= 0 {
+				position = -position;
+				velocity = velocity.abs() * ball.elasticity as i64 / 256;
+				//once a ball has stopped bouncing, throw it back up to the top:
+				//v = sqrt(2gh), both g and h are 16.16 so the root comes out 16.16 too:
+				let launch_velocity = fx::isqrt(2 * gravity.max(0) as u64 * height as u64) as i64;
+				if velocity < launch_velocity / 16 {
+					velocity = launch_velocity;
+				}
+			}
+			ball.position = position.min(height as i64) as i32;
+			ball.velocity = velocity as i32;
+		}
+
+		//this draws a ball, splitting it across the two LEDs it is between so it moves smoothly:
+		fn draw_ball(ball: &Ball, range: &Range<usize>, frame: &mut [c::Color]) {
+			let led = range.start + (ball.position / ONE_LED) as usize;
+			let fraction = ((ball.position % ONE_LED) >> 8) as u8;
+			let mut light = |index: usize, brightness: u8| {
+				if index < range.end {
+					let color = ball.color.scaled(brightness);
+					let led = &mut frame[index];
+					led.r = led.r.max(color.r);
+					led.g = led.g.max(color.g);
+					led.b = led.b.max(color.b);
+				}
+			};
+			light(led, 255 - fraction);
+			light(led + 1, fraction);
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			let elapsed_ms = match self.last_update_ms {
+				Some(last_update_ms) => now_ms.wrapping_sub(last_update_ms).min(MAX_STEP_MS * 10),
+				None => 0,
+			};
+			self.last_update_ms = Some(now_ms);
+
+			for led in frame.iter_mut() {
+				*led = c::C_OFF;
+			}
+			let ball_count = self.ball_count.min(MAX_BALLS);
+			for ball in self.balls.iter_mut().take(ball_count).filter_map(|slot| slot.as_mut()) {
+				let range = Self::ball_range(ball, frame.len());
+				if range.start >= range.end {
+					continue;
+				}
+				let height = (range.end - range.start - 1) as i32 * ONE_LED;
+				let mut remaining_ms = elapsed_ms;
+				while remaining_ms > 0 {
+					let step_ms = remaining_ms.min(MAX_STEP_MS);
+					Self::step_ball(ball, height, self.gravity, self.damping, step_ms);
+					remaining_ms -= step_ms;
+				}
+				ball.position = ball.position.min(height);
+				Self::draw_ball(ball, &range, frame);
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const GRAVITY: i32 = 40 * ONE_LED;
+
+		//runs the balls for a while at frame_ms a frame, returning the first ball's position after each frame.
+		//every frame has to stay inside the LEDs from range:
+		fn run(balls: &mut BouncingBalls, frame: &mut [c::Color], range: Range<usize>, frame_ms: u32, frames: u32) -> Vec<i32> {
+			(0..frames)
+				.map(|i| {
+					balls.update(i * frame_ms, frame);
+					let lit: Vec<usize> = (0..frame.len()).filter(|&led| frame[led] != c::C_OFF).collect();
+					assert!(!lit.is_empty());
+					assert!(lit.iter().all(|led| range.contains(led)), "{:?}", lit);
+					balls.ball_mut(0).unwrap().position
+				})
+				.collect()
+		}
+
+		//the highest points the ball reached between bounces:
+		fn peaks(positions: &[i32]) -> Vec<i32> {
+			positions.windows(3).filter(|window| window[1] >= window[0] && window[1] > window[2]).map(|window| window[1]).collect()
+		}
+
+		#[test]
+		fn dropped_balls_come_to_rest() {
+			let mut balls = BouncingBalls::new(GRAVITY, 0);
+			balls.add_ball(Ball::new(c::C_RED, 200, Some(10..30)));
+			let mut frame = [c::C_OFF; 40];
+			let positions = run(&mut balls, &mut frame, 10..30, 20, 3000);
+			let height = 19 * ONE_LED;
+			assert_eq!(positions[0], height);
+			assert!(positions.iter().all(|&position| (0..=height).contains(&position)));
+			//each bounce is lower than the last until the ball is lying on the floor, and then it
+			//is thrown back up to the top:
+			let peaks = peaks(&positions);
+			let settled = peaks.windows(2).position(|pair| pair[1] > pair[0]).unwrap();
+			assert!(peaks[..=settled].windows(2).all(|pair| pair[1] < pair[0]), "{:?}", peaks);
+			assert!(settled >= 4);
+			assert!(peaks[settled] < ONE_LED / 4);
+			assert!(peaks[settled + 1] > height - ONE_LED);
+		}
+
+		#[test]
+		fn drag_slows_the_fall() {
+			let fall_ms = |damping| {
+				let mut balls = BouncingBalls::new(GRAVITY, damping);
+				balls.add_ball(Ball::new(c::C_RED, 200, None));
+				let mut frame = [c::C_OFF; 20];
+				let positions = run(&mut balls, &mut frame, 0..20, 10, 200);
+				positions.iter().position(|&position| position < ONE_LED).unwrap()
+			};
+			assert!(fall_ms(200) > fall_ms(0));
+		}
+
+		#[test]
+		fn balls_stay_on_the_strip() {
+			//long frames, and a range running off the end of the frame:
+			let mut balls = BouncingBalls::new(GRAVITY * 4, 0);
+			balls.add_ball(Ball::new(c::C_RED, 250, Some(10..30)));
+			let mut frame = [c::C_OFF; 20];
+			run(&mut balls, &mut frame, 10..20, 500, 200);
+			//a ball with no room to bounce is left out:
+			let mut balls = BouncingBalls::new(GRAVITY, 0);
+			balls.add_ball(Ball::new(c::C_RED, 200, Some(25..30)));
+			balls.add_ball(Ball::new(c::C_WHITE, 200, Some(0..1)));
+			balls.update(0, &mut frame);
+			balls.update(20, &mut frame);
+			assert_eq!(frame[0], c::C_WHITE);
+			assert!(frame[1..].iter().all(|&led| led == c::C_OFF));
+		}
+
+		#[test]
+		fn slots() {
+			let mut balls = BouncingBalls::new(GRAVITY, 0);
+			for _ in 0..MAX_BALLS {
+				assert!(balls.add_ball(Ball::new(c::C_RED, 200, None)));
+			}
+			assert!(!balls.add_ball(Ball::new(c::C_RED, 200, None)));
+			assert_eq!(balls.ball_count, MAX_BALLS);
+			assert!(balls.ball_mut(MAX_BALLS).is_none());
+		}
+	}
+}
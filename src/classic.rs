@@ -0,0 +1,176 @@
+pub mod classic {
+	use crate::c;
+	use crate::fx;
+
+	//these effects all work on logical LED indexes. send_all_sequential() takes care of flipping
+	//reversed physical strips, so motion stays continuous around the room as long as the strips
+	//are listed in the order they are mounted. The reversed flag here flips the direction of motion.
+
+	//this counts whole steps since the first update, starting the clock if it isn't running yet:
+	fn steps_since_start(start_ms: &mut Option<u32>, now_ms: u32, step_ms: u32) -> u32 {
+		let start = *start_ms.get_or_insert(now_ms);
+		now_ms.wrapping_sub(start) / step_ms.max(1)
+	}
+
+	//this gets the position of an LED along the direction of motion:
+	fn position(index: usize, led_count: usize, reversed: bool) -> i32 {
+		if reversed {
+			(led_count - 1 - index) as i32
+		} else {
+			index as i32
+		}
+	}
+
+	//every Nth LED is lit, and the lit LEDs march one step along the strip at a time.
+	pub struct TheaterChase {
+		pub color: c::Color,
+		//when set, each marching light takes the next color from the rainbow instead of using color:
+		pub palette: Option<c::Rainbow>,
+		pub spacing: u8,
+		pub step_ms: u32,
+		pub reversed: bool,
+		start_ms: Option<u32>,
+	}
+
+	impl TheaterChase {
+		pub fn new(color: c::Color, palette: Option<c::Rainbow>, spacing: u8, step_ms: u32, reversed: bool) -> Self {
+			TheaterChase {
+				color,
+				palette,
+				spacing,
+				step_ms,
+				reversed,
+				start_ms: None,
+			}
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			let step = steps_since_start(&mut self.start_ms, now_ms, self.step_ms) as i32;
+			let spacing = self.spacing.max(1) as i32;
+			let led_count = frame.len();
+			for (i, led) in frame.iter_mut().enumerate() {
+				let distance = position(i, led_count, self.reversed) - step;
+				*led = if distance.rem_euclid(spacing) != 0 {
+					c::C_OFF
+				} else {
+					match &self.palette {
+						//count lights along with the motion so each light keeps its color as it marches:
+						Some(palette) => {
+							let light = distance.div_euclid(spacing);
+							palette.color(light.rem_euclid(palette.num_colors().max(1) as i32) as usize)
+						}
+						None => self.color,
+					}
+				};
+			}
+		}
+	}
+
+	//fills the strip one LED at a time with the first color from the rainbow, then wipes the next
+	//color over it the same way, and so on around the rainbow.
+	pub struct ColorWipe {
+		pub rainbow: c::Rainbow,
+		pub step_ms: u32,
+		pub reversed: bool,
+		start_ms: Option<u32>,
+	}
+
+	impl ColorWipe {
+		pub fn new(rainbow: c::Rainbow, step_ms: u32, reversed: bool) -> Self {
+			ColorWipe {
+				rainbow,
+				step_ms,
+				reversed,
+				start_ms: None,
+			}
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			let led_count = frame.len();
+			if led_count == 0 {
+				return;
+			}
+			let step = steps_since_start(&mut self.start_ms, now_ms, self.step_ms) as usize;
+			let wipe = step / led_count;
+			let filled = (step % led_count) as i32;
+			let new_color = self.rainbow.color(wipe);
+			//the very first wipe starts from a dark strip:
+			let old_color = match wipe {
+				0 => c::C_OFF,
+				_ => self.rainbow.color(wipe - 1),
+			};
+			for (i, led) in frame.iter_mut().enumerate() {
+				*led = if position(i, led_count, self.reversed) <= filled {
+					new_color
+				} else {
+					old_color
+				};
+			}
+		}
+	}
+
+	//a sine wave of brightness running along the strip.
+	pub struct RunningLights {
+		pub color: c::Color,
+		//the number of LEDs from one bright spot to the next:
+		pub wavelength: u8,
+		pub step_ms: u32,
+		pub reversed: bool,
+		start_ms: Option<u32>,
+	}
+
+	impl RunningLights {
+		pub fn new(color: c::Color, wavelength: u8, step_ms: u32, reversed: bool) -> Self {
+			RunningLights {
+				color,
+				wavelength,
+				step_ms,
+				reversed,
+				start_ms: None,
+			}
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			let step = steps_since_start(&mut self.start_ms, now_ms, self.step_ms) as i32;
+			let wavelength = self.wavelength.max(1) as i32;
+			let led_count = frame.len();
+			for (i, led) in frame.iter_mut().enumerate() {
+				let phase = (position(i, led_count, self.reversed) - step).rem_euclid(wavelength);
+				let angle = (phase * 256 / wavelength) as u8;
+				*led = self.color.scaled(fx::sin8(angle));
+			}
+		}
+	}
+
+	//the rainbow spread along the strip, scrolling along one LED at a time.
+	pub struct RainbowCycle {
+		pub rainbow: c::Rainbow,
+		//the number of LEDs it takes to go all the way around the rainbow:
+		pub wavelength: u16,
+		pub step_ms: u32,
+		pub reversed: bool,
+		start_ms: Option<u32>,
+	}
+
+	impl RainbowCycle {
+		pub fn new(rainbow: c::Rainbow, wavelength: u16, step_ms: u32, reversed: bool) -> Self {
+			RainbowCycle {
+				rainbow,
+				wavelength,
+				step_ms,
+				reversed,
+				start_ms: None,
+			}
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			let step = steps_since_start(&mut self.start_ms, now_ms, self.step_ms) as i32;
+			let wavelength = self.wavelength.max(1) as i32;
+			let led_count = frame.len();
+			for (i, led) in frame.iter_mut().enumerate() {
+				let phase = (position(i, led_count, self.reversed) - step).rem_euclid(wavelength);
+				*led = self.rainbow.color_at_position((phase * 0x1_0000 / wavelength) as u16);
+			}
+		}
+	}
+}
@@ -0,0 +1,609 @@
+pub mod cli {
+	use crate::a;
+	use crate::c;
+	use crate::ctl;
+	use crate::ly;
+	use crate::pl;
+	use crate::sc;
+	use core::fmt;
+
+	//the longest command line that fits in the line buffer:
+	pub const MAX_LINE_LENGTH: usize = 96;
+
+	pub const HELP: &str = "\
+commands:
+  help                              show this list
+  on | off                          turn the lights on or off
+  color <r> <g> <b>                 solid color, 0 to 255 each
+  kelvin <k>                        solid white, 1000 to 6500 kelvin
+  brightness <0-255>                overall brightness
+  anim <effect> [speed=<1-255>] [palette=<0-31>] [color=<r>,<g>,<b>]
+       [intensity=<0-255>]          the automaton's rule, 0 for 1D life, the
+                                    sunrise or sunset's color temperature / 100,
+                                    how often the storm strikes or how many balls
+       [custom=<0-255>]             the drag on the balls
+       [perstrip=on|off]            keep the storm or each ball to one strip
+       [backward=on|off]            run the chase, wipe, running lights or
+                                    rainbow the other way
+  circadian                         follow the time of day, once the clock is set
+  scene save <slot> [name]          save what is showing to a slot
+  scene recall <slot or name>       show a saved scene
+  scene next | list | delete <slot>
+  playlist add <slot> <seconds> [fade=<ms> | wipe=<ms>]
+  playlist start | stop | pause | resume | next | clear | status
+  playlist shuffle on|off | repeat on|off
+  time [unix seconds]               show or set the clock
+  view [room | perimeter | closet] [on|off]
+                                    send on, off, color, kelvin, brightness,
+                                    anim, segment and layer to a view and turn
+                                    it on or off, or list the views
+  segment add <name> <start> <length> <effect> [the anim options]
+       [brightness=<0-255>] [reverse=on|off] [mirror=on|off]
+                                    run an effect on part of the view
+  segment remove <name> | list
+  layer <0-3> <effect> [the anim options] [blend=normal|add|multiply|
+       screen|max|mask] [opacity=<0-255>]
+                                    run an effect over the view
+  layer <0-3> off | layer list
+  strips                            list the physical strips
+  stats                             frame timing and uptime
+";
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum CliError<'a> {
+		UnknownCommand(&'a str),
+		MissingArgument(&'static str),
+		UnexpectedArgument(&'a str),
+		BadNumber(&'a str),
+		OutOfRange { name: &'static str, min: i64, max: i64 },
+		UnknownEffect(&'a str),
+		UnknownView(&'a str),
+		UnknownBlendMode(&'a str),
+		LineTooLong,
+	}
+
+	impl<'a> fmt::Display for CliError<'a> {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			match *self {
+				CliError::UnknownCommand(word) => write!(f, "unknown command '{}', try 'help'", word),
+				CliError::MissingArgument(name) => write!(f, "missing {}", name),
+				CliError::UnexpectedArgument(word) => write!(f, "unexpected '{}'", word),
+				CliError::BadNumber(word) => write!(f, "'{}' is not a number", word),
+				CliError::OutOfRange { name, min, max } => write!(f, "{} must be from {} to {}", name, min, max),
+				CliError::UnknownEffect(word) => write!(f, "unknown effect '{}'", word),
+				CliError::UnknownView(word) => write!(f, "unknown view '{}'", word),
+				CliError::UnknownBlendMode(word) => write!(f, "unknown blend mode '{}'", word),
+				CliError::LineTooLong => write!(f, "line is longer than {} characters", MAX_LINE_LENGTH),
+			}
+		}
+	}
+
+	//collects characters into lines as they come in, handling backspace. Control characters
+	//other than backspace and line endings are dropped.
+	pub struct LineBuffer {
+		bytes: [u8; MAX_LINE_LENGTH],
+		length: usize,
+		overflowed: bool,
+		//the last push finished a line, so the next one starts a new line:
+		complete: bool,
+	}
+
+	impl Default for LineBuffer {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	impl LineBuffer {
+		pub fn new() -> Self {
+			LineBuffer {
+				bytes: [0; MAX_LINE_LENGTH],
+				length: 0,
+				overflowed: false,
+				complete: false,
+			}
+		}
+
+		//this throws away the line typed so far:
+		pub fn clear(&mut self) {
+			self.length = 0;
+			self.overflowed = false;
+			self.complete = false;
+		}
+
+		//this adds a byte, returning the line when the byte ends it. Empty lines are skipped,
+		//so \r\n line endings only give one line.
+		pub fn push(&mut self, byte: u8) -> Option<Result<&str, CliError<'static>>> {
+			if self.complete {
+				self.clear();
+			}
+			match byte {
+				b'\r' | b'\n' => {
+					if self.overflowed {
+						self.complete = true;
+						return Some(Err(CliError::LineTooLong));
+					}
+					if self.length == 0 {
+						return None;
+					}
+					self.complete = true;
+					//only printable ASCII gets into the buffer, so it is always valid UTF-8:
+					core::str::from_utf8(&self.bytes[..self.length]).ok().map(Ok)
+				}
+				0x08 | 0x7F => {
+					self.length = self.length.saturating_sub(1);
+					None
+				}
+				b' '..=b'~' => {
+					match self.length < MAX_LINE_LENGTH {
+						true => {
+							self.bytes[self.length] = byte;
+							self.length += 1;
+						}
+						false => self.overflowed = true,
+					}
+					None
+				}
+				_ => None,
+			}
+		}
+	}
+
+	//this reads a whole number from a word and checks it is in range:
+	fn number<'a>(word: Option<&'a str>, name: &'static str, min: i64, max: i64) -> Result<i64, CliError<'a>> {
+		let word = word.ok_or(CliError::MissingArgument(name))?;
+		let value = word.parse::<i64>().map_err(|_| CliError::BadNumber(word))?;
+		if value < min || value > max {
+			return Err(CliError::OutOfRange { name, min, max });
+		}
+		Ok(value)
+	}
+
+	fn color<'a>(red: Option<&'a str>, green: Option<&'a str>, blue: Option<&'a str>) -> Result<c::Color, CliError<'a>> {
+		Ok(c::Color {
+			r: number(red, "red", 0, 255)? as u8,
+			g: number(green, "green", 0, 255)? as u8,
+			b: number(blue, "blue", 0, 255)? as u8,
+		})
+	}
+
+	fn on_off<'a>(word: Option<&'a str>) -> Result<bool, CliError<'a>> {
+		match word {
+			Some("on") => Ok(true),
+			Some("off") => Ok(false),
+			Some(word) => Err(CliError::UnexpectedArgument(word)),
+			None => Err(CliError::MissingArgument("on or off")),
+		}
+	}
+
+	fn scene_slot<'a>(word: Option<&'a str>) -> Result<u8, CliError<'a>> {
+		Ok(number(word, "slot", 0, sc::SCENE_SLOTS as i64 - 1)? as u8)
+	}
+
+	fn effect<'a>(word: Option<&'a str>) -> Result<a::Effect, CliError<'a>> {
+		let name = word.ok_or(CliError::MissingArgument("effect"))?;
+		a::Effect::from_name(name).ok_or(CliError::UnknownEffect(name))
+	}
+
+	//this reads one of the anim options into options, returning false if the word isn't one:
+	fn effect_option<'a>(word: &'a str, options: &mut ctl::EffectOptions) -> Result<bool, CliError<'a>> {
+		let mut parts = word.splitn(2, '=');
+		match (parts.next(), parts.next()) {
+			(Some("speed"), value) => options.speed = Some(number(value, "speed", 1, 255)? as u8),
+			(Some("palette"), value) => options.palette = Some(number(value, "palette", 0, c::NUM_RAINBOWS as i64 - 1)? as u8),
+			(Some("intensity"), value) => options.intensity = Some(number(value, "intensity", 0, 255)? as u8),
+			(Some("custom"), value) => options.custom = Some(number(value, "custom", 0, 255)? as u8),
+			(Some("perstrip"), value) => options.per_strip = Some(on_off(value)?),
+			(Some("backward"), value) => options.reversed = Some(on_off(value)?),
+			(Some("color"), Some(value)) => {
+				let mut channels = value.split(',');
+				options.color = Some(color(channels.next(), channels.next(), channels.next())?);
+				if let Some(extra) = channels.next() {
+					return Err(CliError::UnexpectedArgument(extra));
+				}
+			}
+			_ => return Ok(false),
+		}
+		Ok(true)
+	}
+
+	fn anim<'a>(words: &mut core::str::SplitAsciiWhitespace<'a>) -> Result<ctl::Command<'a>, CliError<'a>> {
+		let effect = effect(words.next())?;
+		let mut options = ctl::EffectOptions::default();
+		for word in words {
+			if !effect_option(word, &mut options)? {
+				return Err(CliError::UnexpectedArgument(word));
+			}
+		}
+		Ok(ctl::Command::Effect { effect, options })
+	}
+
+	fn segment<'a>(words: &mut core::str::SplitAsciiWhitespace<'a>) -> Result<ctl::Command<'a>, CliError<'a>> {
+		match words.next() {
+			Some("add") => {
+				let name = words.next().ok_or(CliError::MissingArgument("name"))?;
+				let mut segment = ctl::NewSegment {
+					name,
+					start: number(words.next(), "start", 0, u16::MAX as i64)? as u16,
+					length: number(words.next(), "length", 1, u16::MAX as i64)? as u16,
+					effect: effect(words.next())?,
+					options: ctl::EffectOptions::default(),
+					brightness: None,
+					reversed: false,
+					mirrored: false,
+				};
+				for word in words {
+					if effect_option(word, &mut segment.options)? {
+						continue;
+					}
+					let mut parts = word.splitn(2, '=');
+					match (parts.next(), parts.next()) {
+						(Some("brightness"), value) => segment.brightness = Some(number(value, "brightness", 0, 255)? as u8),
+						(Some("reverse"), value) => segment.reversed = on_off(value)?,
+						(Some("mirror"), value) => segment.mirrored = on_off(value)?,
+						_ => return Err(CliError::UnexpectedArgument(word)),
+					}
+				}
+				Ok(ctl::Command::SegmentAdd(segment))
+			}
+			Some("remove") => Ok(ctl::Command::SegmentRemove(words.next().ok_or(CliError::MissingArgument("name"))?)),
+			Some("list") => Ok(ctl::Command::SegmentList),
+			Some(word) => Err(CliError::UnexpectedArgument(word)),
+			None => Err(CliError::MissingArgument("add, remove or list")),
+		}
+	}
+
+	fn layer<'a>(words: &mut core::str::SplitAsciiWhitespace<'a>) -> Result<ctl::Command<'a>, CliError<'a>> {
+		let word = words.next().ok_or(CliError::MissingArgument("layer or list"))?;
+		if word == "list" {
+			return Ok(ctl::Command::LayerList);
+		}
+		let index = number(Some(word), "layer", 0, ly::MAX_LAYERS as i64 - 1)? as u8;
+		if words.clone().next() == Some("off") {
+			words.next();
+			return Ok(ctl::Command::LayerOff(index));
+		}
+		let effect = effect(words.next())?;
+		let mut options = ctl::EffectOptions::default();
+		let mut blend_mode = ly::BlendMode::Normal;
+		let mut opacity = 255;
+		for word in words {
+			if effect_option(word, &mut options)? {
+				continue;
+			}
+			let mut parts = word.splitn(2, '=');
+			match (parts.next(), parts.next()) {
+				(Some("blend"), Some(value)) => blend_mode = ly::BlendMode::from_name(value).ok_or(CliError::UnknownBlendMode(value))?,
+				(Some("blend"), None) => return Err(CliError::MissingArgument("blend")),
+				(Some("opacity"), value) => opacity = number(value, "opacity", 0, 255)? as u8,
+				_ => return Err(CliError::UnexpectedArgument(word)),
+			}
+		}
+		Ok(ctl::Command::Layer { index, effect, options, blend_mode, opacity })
+	}
+
+	fn scene<'a>(words: &mut core::str::SplitAsciiWhitespace<'a>) -> Result<ctl::Command<'a>, CliError<'a>> {
+		match words.next() {
+			Some("save") => {
+				let slot = scene_slot(words.next())?;
+				Ok(ctl::Command::SceneSave { slot, name: words.next() })
+			}
+			Some("recall") => {
+				let word = words.next().ok_or(CliError::MissingArgument("slot or name"))?;
+				match word.bytes().all(|byte| byte.is_ascii_digit()) {
+					true => Ok(ctl::Command::SceneRecall(ctl::SceneRef::Slot(scene_slot(Some(word))?))),
+					false => Ok(ctl::Command::SceneRecall(ctl::SceneRef::Name(word))),
+				}
+			}
+			Some("next") => Ok(ctl::Command::SceneNext),
+			Some("list") => Ok(ctl::Command::SceneList),
+			Some("delete") => Ok(ctl::Command::SceneDelete(scene_slot(words.next())?)),
+			Some(word) => Err(CliError::UnexpectedArgument(word)),
+			None => Err(CliError::MissingArgument("save, recall, next, list or delete")),
+		}
+	}
+
+	fn playlist<'a>(words: &mut core::str::SplitAsciiWhitespace<'a>) -> Result<ctl::Command<'a>, CliError<'a>> {
+		match words.next() {
+			Some("add") => {
+				let slot = scene_slot(words.next())?;
+				let seconds = number(words.next(), "seconds", 1, 24 * 60 * 60)? as u32;
+				let transition = match words.next() {
+					None => pl::Transition::Cut,
+					Some(word) => {
+						let mut parts = word.splitn(2, '=');
+						match (parts.next(), parts.next()) {
+							(Some("fade"), value) => pl::Transition::Fade(number(value, "fade", 0, 60_000)? as u32),
+							(Some("wipe"), value) => pl::Transition::Wipe(number(value, "wipe", 0, 60_000)? as u32),
+							_ => return Err(CliError::UnexpectedArgument(word)),
+						}
+					}
+				};
+				Ok(ctl::Command::PlaylistAdd(pl::Entry::new(slot, seconds * 1000, transition)))
+			}
+			Some("start") => Ok(ctl::Command::PlaylistStart),
+			Some("stop") => Ok(ctl::Command::PlaylistStop),
+			Some("pause") => Ok(ctl::Command::PlaylistPause),
+			Some("resume") => Ok(ctl::Command::PlaylistResume),
+			Some("next") => Ok(ctl::Command::PlaylistNext),
+			Some("clear") => Ok(ctl::Command::PlaylistClear),
+			Some("status") => Ok(ctl::Command::PlaylistStatus),
+			Some("shuffle") => Ok(ctl::Command::PlaylistShuffle(on_off(words.next())?)),
+			Some("repeat") => Ok(ctl::Command::PlaylistRepeat(on_off(words.next())?)),
+			Some(word) => Err(CliError::UnexpectedArgument(word)),
+			None => Err(CliError::MissingArgument("playlist command")),
+		}
+	}
+
+	//this turns a line into a command. Blank lines give None.
+	pub fn parse(line: &str) -> Result<Option<ctl::Command<'_>>, CliError<'_>> {
+		let mut words = line.split_ascii_whitespace();
+		let word = match words.next() {
+			Some(word) => word,
+			None => return Ok(None),
+		};
+		let command = match word {
+			"help" | "?" => ctl::Command::Help,
+			"on" => ctl::Command::On,
+			"off" => ctl::Command::Off,
+			"color" => ctl::Command::Color(color(words.next(), words.next(), words.next())?),
+			"kelvin" => ctl::Command::Kelvin(number(words.next(), "kelvin", c::KELVIN_MIN as i64, c::KELVIN_MAX as i64)? as u16),
+			"brightness" => ctl::Command::Brightness(number(words.next(), "brightness", 0, 255)? as u8),
+			"anim" => anim(&mut words)?,
+			"circadian" => ctl::Command::Circadian,
+			"scene" => scene(&mut words)?,
+			"playlist" => playlist(&mut words)?,
+			"time" => match words.next() {
+				Some(word) => ctl::Command::Time(Some(number(Some(word), "time", 0, i64::MAX)?)),
+				None => ctl::Command::Time(None),
+			},
+			"view" => match words.next() {
+				Some(word) => {
+					let view = ctl::View::from_name(word).ok_or(CliError::UnknownView(word))?;
+					let on = match words.next() {
+						Some(word) => Some(on_off(Some(word))?),
+						None => None,
+					};
+					ctl::Command::View { view: Some(view), on }
+				}
+				None => ctl::Command::View { view: None, on: None },
+			},
+			"segment" => segment(&mut words)?,
+			"layer" => layer(&mut words)?,
+			"strips" => ctl::Command::Strips,
+			"stats" => ctl::Command::Stats,
+			_ => return Err(CliError::UnknownCommand(word)),
+		};
+		match words.next() {
+			Some(extra) => Err(CliError::UnexpectedArgument(extra)),
+			None => Ok(Some(command)),
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		fn command(line: &str) -> ctl::Command<'_> {
+			parse(line).unwrap().unwrap()
+		}
+
+		fn effect(effect: a::Effect) -> ctl::Command<'static> {
+			ctl::Command::Effect { effect, options: ctl::EffectOptions::default() }
+		}
+
+		#[test]
+		fn every_command_in_the_help() {
+			let orange = c::Color { r: 255, g: 160, b: 0 };
+			assert_eq!(parse(""), Ok(None));
+			assert_eq!(parse("   "), Ok(None));
+			assert_eq!(command("help"), ctl::Command::Help);
+			assert_eq!(command("?"), ctl::Command::Help);
+			assert_eq!(command("on"), ctl::Command::On);
+			assert_eq!(command("off"), ctl::Command::Off);
+			assert_eq!(command("color 255 160 0"), ctl::Command::Color(orange));
+			assert_eq!(command("kelvin 2700"), ctl::Command::Kelvin(2700));
+			assert_eq!(command("brightness 0"), ctl::Command::Brightness(0));
+			assert_eq!(command("anim storm"), effect(a::Effect::Storm));
+			assert_eq!(
+				command("anim balls speed=9 palette=14 color=255,160,0 intensity=5 custom=20 perstrip=on backward=on"),
+				ctl::Command::Effect {
+					effect: a::Effect::Balls,
+					options: ctl::EffectOptions {
+						speed: Some(9),
+						palette: Some(14),
+						color: Some(orange),
+						intensity: Some(5),
+						custom: Some(20),
+						per_strip: Some(true),
+						reversed: Some(true),
+					},
+				}
+			);
+			assert_eq!(command("circadian"), ctl::Command::Circadian);
+			assert_eq!(command("scene save 2 evening"), ctl::Command::SceneSave { slot: 2, name: Some("evening") });
+			assert_eq!(command("scene save 2"), ctl::Command::SceneSave { slot: 2, name: None });
+			assert_eq!(command("scene recall 3"), ctl::Command::SceneRecall(ctl::SceneRef::Slot(3)));
+			assert_eq!(command("scene recall evening"), ctl::Command::SceneRecall(ctl::SceneRef::Name("evening")));
+			assert_eq!(command("scene next"), ctl::Command::SceneNext);
+			assert_eq!(command("scene list"), ctl::Command::SceneList);
+			assert_eq!(command("scene delete 7"), ctl::Command::SceneDelete(7));
+			assert_eq!(command("playlist add 1 60"), ctl::Command::PlaylistAdd(pl::Entry::new(1, 60_000, pl::Transition::Cut)));
+			assert_eq!(command("playlist add 1 60 fade=500"), ctl::Command::PlaylistAdd(pl::Entry::new(1, 60_000, pl::Transition::Fade(500))));
+			assert_eq!(command("playlist add 1 60 wipe=0"), ctl::Command::PlaylistAdd(pl::Entry::new(1, 60_000, pl::Transition::Wipe(0))));
+			for &(line, expected) in [
+				("playlist start", ctl::Command::PlaylistStart),
+				("playlist stop", ctl::Command::PlaylistStop),
+				("playlist pause", ctl::Command::PlaylistPause),
+				("playlist resume", ctl::Command::PlaylistResume),
+				("playlist next", ctl::Command::PlaylistNext),
+				("playlist clear", ctl::Command::PlaylistClear),
+				("playlist status", ctl::Command::PlaylistStatus),
+				("playlist shuffle on", ctl::Command::PlaylistShuffle(true)),
+				("playlist repeat off", ctl::Command::PlaylistRepeat(false)),
+			].iter() {
+				assert_eq!(command(line), expected, "{}", line);
+			}
+			assert_eq!(command("time"), ctl::Command::Time(None));
+			assert_eq!(command("time 1700000000"), ctl::Command::Time(Some(1_700_000_000)));
+			assert_eq!(command("view"), ctl::Command::View { view: None, on: None });
+			assert_eq!(command("view closet"), ctl::Command::View { view: Some(ctl::View::Closet), on: None });
+			assert_eq!(command("view perimeter on"), ctl::Command::View { view: Some(ctl::View::Perimeter), on: Some(true) });
+			assert_eq!(command("view room off"), ctl::Command::View { view: Some(ctl::View::Room), on: Some(false) });
+			let desk = ctl::NewSegment {
+				name: "desk",
+				start: 70,
+				length: 20,
+				effect: a::Effect::TheaterChase,
+				options: ctl::EffectOptions::default(),
+				brightness: None,
+				reversed: false,
+				mirrored: false,
+			};
+			assert_eq!(command("segment add desk 70 20 chase"), ctl::Command::SegmentAdd(desk));
+			assert_eq!(
+				command("segment add desk 70 20 chase speed=9 brightness=128 reverse=on mirror=on"),
+				ctl::Command::SegmentAdd(ctl::NewSegment {
+					options: ctl::EffectOptions { speed: Some(9), ..ctl::EffectOptions::default() },
+					brightness: Some(128),
+					reversed: true,
+					mirrored: true,
+					..desk
+				})
+			);
+			assert_eq!(command("segment remove desk"), ctl::Command::SegmentRemove("desk"));
+			assert_eq!(command("segment list"), ctl::Command::SegmentList);
+			assert_eq!(
+				command("layer 1 storm"),
+				ctl::Command::Layer {
+					index: 1,
+					effect: a::Effect::Storm,
+					options: ctl::EffectOptions::default(),
+					blend_mode: ly::BlendMode::Normal,
+					opacity: 255,
+				}
+			);
+			assert_eq!(
+				command("layer 3 ripple intensity=40 blend=screen opacity=128"),
+				ctl::Command::Layer {
+					index: 3,
+					effect: a::Effect::Ripple,
+					options: ctl::EffectOptions { intensity: Some(40), ..ctl::EffectOptions::default() },
+					blend_mode: ly::BlendMode::Screen,
+					opacity: 128,
+				}
+			);
+			assert_eq!(command("layer 0 off"), ctl::Command::LayerOff(0));
+			assert_eq!(command("layer list"), ctl::Command::LayerList);
+			assert_eq!(command("strips"), ctl::Command::Strips);
+			assert_eq!(command("stats"), ctl::Command::Stats);
+			//extra spaces don't matter:
+			assert_eq!(command("  kelvin   2700 "), ctl::Command::Kelvin(2700));
+			//every command listed in the help is one parse knows:
+			for line in HELP.lines().filter(|line| line.starts_with("  ") && !line.starts_with("   ")) {
+				let word = line.split_ascii_whitespace().next().unwrap();
+				assert!(!matches!(parse(word), Err(CliError::UnknownCommand(_))), "{}", word);
+			}
+			//every effect can be picked by name:
+			for &effect in a::EFFECTS.iter() {
+				assert_eq!(command(&format!("anim {}", effect.name())), self::effect(effect));
+			}
+		}
+
+		#[test]
+		fn errors() {
+			assert_eq!(parse("dance"), Err(CliError::UnknownCommand("dance")));
+			assert_eq!(parse("on now"), Err(CliError::UnexpectedArgument("now")));
+			assert_eq!(parse("color 255 160"), Err(CliError::MissingArgument("blue")));
+			assert_eq!(parse("color 255 160 0 0"), Err(CliError::UnexpectedArgument("0")));
+			assert_eq!(parse("brightness bright"), Err(CliError::BadNumber("bright")));
+			assert_eq!(parse("anim"), Err(CliError::MissingArgument("effect")));
+			assert_eq!(parse("anim disco"), Err(CliError::UnknownEffect("disco")));
+			assert_eq!(parse("anim storm fast"), Err(CliError::UnexpectedArgument("fast")));
+			assert_eq!(parse("anim storm speed="), Err(CliError::BadNumber("")));
+			assert_eq!(parse("anim storm color=1,2,3,4"), Err(CliError::UnexpectedArgument("4")));
+			assert_eq!(parse("anim storm perstrip=yes"), Err(CliError::UnexpectedArgument("yes")));
+			assert_eq!(parse("anim chase backward"), Err(CliError::MissingArgument("on or off")));
+			assert_eq!(parse("scene"), Err(CliError::MissingArgument("save, recall, next, list or delete")));
+			assert_eq!(parse("scene recall"), Err(CliError::MissingArgument("slot or name")));
+			assert_eq!(parse("playlist"), Err(CliError::MissingArgument("playlist command")));
+			assert_eq!(parse("playlist add 1 60 dissolve=5"), Err(CliError::UnexpectedArgument("dissolve=5")));
+			assert_eq!(parse("playlist shuffle"), Err(CliError::MissingArgument("on or off")));
+			assert_eq!(parse("view attic"), Err(CliError::UnknownView("attic")));
+			assert_eq!(parse("view room dim"), Err(CliError::UnexpectedArgument("dim")));
+			assert_eq!(parse("segment"), Err(CliError::MissingArgument("add, remove or list")));
+			assert_eq!(parse("segment add desk 70"), Err(CliError::MissingArgument("length")));
+			assert_eq!(parse("segment add desk 70 20"), Err(CliError::MissingArgument("effect")));
+			assert_eq!(parse("segment add desk 70 20 chase flip=on"), Err(CliError::UnexpectedArgument("flip=on")));
+			assert_eq!(parse("segment remove"), Err(CliError::MissingArgument("name")));
+			assert_eq!(parse("layer"), Err(CliError::MissingArgument("layer or list")));
+			assert_eq!(parse("layer 1"), Err(CliError::MissingArgument("effect")));
+			assert_eq!(parse("layer top storm"), Err(CliError::BadNumber("top")));
+			assert_eq!(parse("layer 1 storm blend=overlay"), Err(CliError::UnknownBlendMode("overlay")));
+			assert_eq!(parse("layer 1 storm blend"), Err(CliError::MissingArgument("blend")));
+			assert_eq!(parse("layer 1 off now"), Err(CliError::UnexpectedArgument("now")));
+		}
+
+		#[test]
+		fn ranges() {
+			let out_of_range = |name, min, max| Err(CliError::OutOfRange { name, min, max });
+			assert_eq!(parse("color 256 0 0"), out_of_range("red", 0, 255));
+			assert_eq!(parse("color 0 -1 0"), out_of_range("green", 0, 255));
+			assert_eq!(parse("kelvin 999"), out_of_range("kelvin", c::KELVIN_MIN as i64, c::KELVIN_MAX as i64));
+			assert_eq!(parse("kelvin 6501"), out_of_range("kelvin", c::KELVIN_MIN as i64, c::KELVIN_MAX as i64));
+			assert_eq!(command("kelvin 1000"), ctl::Command::Kelvin(c::KELVIN_MIN));
+			assert_eq!(command("kelvin 6500"), ctl::Command::Kelvin(c::KELVIN_MAX));
+			assert_eq!(parse("brightness 256"), out_of_range("brightness", 0, 255));
+			assert_eq!(parse("anim storm speed=0"), out_of_range("speed", 1, 255));
+			assert_eq!(parse("anim wipe palette=32"), out_of_range("palette", 0, c::NUM_RAINBOWS as i64 - 1));
+			assert_eq!(parse("anim automaton intensity=256"), out_of_range("intensity", 0, 255));
+			assert_eq!(parse("anim balls custom=256"), out_of_range("custom", 0, 255));
+			assert_eq!(parse("segment add desk 70 0 chase"), out_of_range("length", 1, u16::MAX as i64));
+			assert_eq!(parse("segment add desk 70 20 chase brightness=256"), out_of_range("brightness", 0, 255));
+			assert_eq!(parse("layer 4 storm"), out_of_range("layer", 0, ly::MAX_LAYERS as i64 - 1));
+			assert_eq!(parse("layer 0 storm opacity=256"), out_of_range("opacity", 0, 255));
+			assert_eq!(parse("scene save 8"), out_of_range("slot", 0, sc::SCENE_SLOTS as i64 - 1));
+			assert_eq!(parse("playlist add 1 0"), out_of_range("seconds", 1, 24 * 60 * 60));
+			assert_eq!(parse("playlist add 1 86401"), out_of_range("seconds", 1, 24 * 60 * 60));
+			assert_eq!(parse("playlist add 1 60 fade=60001"), out_of_range("fade", 0, 60_000));
+			assert_eq!(parse("time -1"), out_of_range("time", 0, i64::MAX));
+			assert_eq!(parse("time 99999999999999999999"), Err(CliError::BadNumber("99999999999999999999")));
+		}
+
+		fn push_all<'a>(buffer: &'a mut LineBuffer, bytes: &[u8]) -> Option<Result<&'a str, CliError<'static>>> {
+			let (last, rest) = bytes.split_last().unwrap();
+			for &byte in rest {
+				assert_eq!(buffer.push(byte), None);
+			}
+			buffer.push(*last)
+		}
+
+		#[test]
+		fn line_buffer() {
+			let mut buffer = LineBuffer::new();
+			assert_eq!(push_all(&mut buffer, b"kelvni\x08\x08in 2700\r"), Some(Ok("kelvin 2700")));
+			//the \n of a \r\n doesn't make an empty line, and control characters are dropped:
+			assert_eq!(buffer.push(b'\n'), None);
+			assert_eq!(push_all(&mut buffer, b"\x1b\x7fon\t\n"), Some(Ok("on")));
+			//a line that fills the buffer exactly is fine:
+			let longest = [b'x'; MAX_LINE_LENGTH];
+			for &byte in longest.iter() {
+				assert_eq!(buffer.push(byte), None);
+			}
+			assert_eq!(buffer.push(b'\n').map(|line| line.map(str::len)), Some(Ok(MAX_LINE_LENGTH)));
+			//one past that is thrown away when it ends, and the next line starts fresh:
+			for &byte in longest.iter().chain(b"yz") {
+				assert_eq!(buffer.push(byte), None);
+			}
+			assert_eq!(buffer.push(b'\r'), Some(Err(CliError::LineTooLong)));
+			assert_eq!(buffer.push(b'\n'), None);
+			assert_eq!(push_all(&mut buffer, b"off\r"), Some(Ok("off")));
+			//backspace past the start does nothing, and clear throws away what was typed:
+			assert_eq!(push_all(&mut buffer, b"\x08\x08on\n"), Some(Ok("on")));
+			assert_eq!(buffer.push(b'x'), None);
+			buffer.clear();
+			assert_eq!(push_all(&mut buffer, b"stats\n"), Some(Ok("stats")));
+			assert_eq!(format!("{}", CliError::LineTooLong), format!("line is longer than {} characters", MAX_LINE_LENGTH));
+		}
+	}
+}
@@ -0,0 +1,268 @@
+pub mod clock {
+	//wall clock time, kept as seconds since 1970-01-01 00:00 UTC.
+
+	pub const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+	//NTP counts seconds from 1900 instead of 1970:
+	const NTP_TO_UNIX_SECONDS: u64 = 2_208_988_800;
+	pub const SNTP_PACKET_LENGTH: usize = 48;
+	const SNTP_MODE_CLIENT: u8 = 3;
+	const SNTP_MODE_SERVER: u8 = 4;
+	const SNTP_VERSION: u8 = 4;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Date {
+		pub year: i32,
+		//1 to 12:
+		pub month: u8,
+		//1 to 31:
+		pub day: u8,
+	}
+
+	//this counts days from 1970-01-01 to a date, going negative before it. This is Howard Hinnant's
+	//days_from_civil, which treats March as the first month so leap days land at the end of the year.
+	pub fn days_from_date(date: Date) -> i32 {
+		let year = if date.month <= 2 { date.year - 1 } else { date.year };
+		let era = year.div_euclid(400);
+		let year_of_era = year - era * 400;
+		let month = date.month as i32;
+		let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + date.day as i32 - 1;
+		let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+		era * 146_097 + day_of_era - 719_468
+	}
+
+	//the other way around, from days since 1970-01-01 to a date:
+	pub fn date_from_days(days: i32) -> Date {
+		let days = days + 719_468;
+		let era = days.div_euclid(146_097);
+		let day_of_era = days - era * 146_097;
+		let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+		let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+		let shifted_month = (5 * day_of_year + 2) / 153;
+		let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+		let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u8;
+		let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+		Date { year, month, day }
+	}
+
+	//the day of the year, from 1 for January 1st:
+	pub fn day_of_year(date: Date) -> u16 {
+		let new_year = Date { year: date.year, month: 1, day: 1 };
+		(days_from_date(date) - days_from_date(new_year) + 1) as u16
+	}
+
+	//this splits a unix time into the date and the seconds since midnight:
+	pub fn split_unix_time(unix_seconds: i64) -> (Date, u32) {
+		let days = unix_seconds.div_euclid(SECONDS_PER_DAY as i64);
+		let seconds = unix_seconds.rem_euclid(SECONDS_PER_DAY as i64);
+		(date_from_days(days as i32), seconds as u32)
+	}
+
+	pub fn unix_time(date: Date, seconds_of_day: u32) -> i64 {
+		days_from_date(date) as i64 * SECONDS_PER_DAY as i64 + seconds_of_day as i64
+	}
+
+	//a software real time clock. It has no idea what time it is until it is set, from a command or
+	//SNTP, and after that it counts along with the frame clock.
+	pub struct Rtc {
+		unix_seconds: Option<i64>,
+		//milliseconds counted towards the next second:
+		leftover_ms: u32,
+		last_update_ms: u32,
+		//local time is this many minutes ahead of UTC:
+		pub utc_offset_minutes: i32,
+	}
+
+	impl Rtc {
+		pub fn new(utc_offset_minutes: i32) -> Self {
+			Rtc {
+				unix_seconds: None,
+				leftover_ms: 0,
+				last_update_ms: 0,
+				utc_offset_minutes,
+			}
+		}
+
+		//this sets the clock to a unix time, at the frame clock time now_ms:
+		pub fn set(&mut self, unix_seconds: i64, now_ms: u32) {
+			self.unix_seconds = Some(unix_seconds);
+			self.leftover_ms = 0;
+			self.last_update_ms = now_ms;
+		}
+
+		pub fn is_set(&self) -> bool {
+			self.unix_seconds.is_some()
+		}
+
+		//this moves the clock along to the frame clock time now_ms.
+		//the frame clock wraps every 49 days, so this needs to be called more often than that.
+		pub fn update(&mut self, now_ms: u32) {
+			let elapsed_ms = now_ms.wrapping_sub(self.last_update_ms) + self.leftover_ms;
+			self.last_update_ms = now_ms;
+			self.leftover_ms = elapsed_ms % 1000;
+			if let Some(unix_seconds) = self.unix_seconds.as_mut() {
+				*unix_seconds += (elapsed_ms / 1000) as i64;
+			}
+		}
+
+		pub fn unix_seconds(&self) -> Option<i64> {
+			self.unix_seconds
+		}
+
+		//the time in the local time zone, as if it were a unix time:
+		pub fn local_seconds(&self) -> Option<i64> {
+			self.unix_seconds
+				.map(|unix_seconds| unix_seconds + self.utc_offset_minutes as i64 * 60)
+		}
+	}
+
+	//this makes an SNTP request to send to a time server on UDP port 123:
+	pub fn sntp_request() -> [u8; SNTP_PACKET_LENGTH] {
+		let mut packet = [0_u8; SNTP_PACKET_LENGTH];
+		packet[0] = SNTP_VERSION << 3 | SNTP_MODE_CLIENT;
+		packet
+	}
+
+	//this reads the unix time out of a time server's reply, or None if the reply isn't usable.
+	//network delay is ignored, since a second or so doesn't matter for lighting schedules.
+	pub fn sntp_unix_seconds(packet: &[u8]) -> Option<i64> {
+		if packet.len() < SNTP_PACKET_LENGTH || packet[0] & 0x07 != SNTP_MODE_SERVER {
+			return None;
+		}
+		//a leap indicator of 3 means the server's clock isn't synchronized:
+		if packet[0] >> 6 == 3 {
+			return None;
+		}
+		let mut transmit_seconds = [0_u8; 4];
+		transmit_seconds.copy_from_slice(&packet[40..44]);
+		let ntp_seconds = u32::from_be_bytes(transmit_seconds) as u64;
+		if ntp_seconds == 0 {
+			return None;
+		}
+		//NTP seconds wrap in 2036, so small values are from after that:
+		let ntp_seconds = if ntp_seconds < NTP_TO_UNIX_SECONDS { ntp_seconds + (1 << 32) } else { ntp_seconds };
+		Some((ntp_seconds - NTP_TO_UNIX_SECONDS) as i64)
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const fn date(year: i32, month: u8, day: u8) -> Date {
+			Date { year, month, day }
+		}
+
+		fn is_leap_year(year: i32) -> bool {
+			year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+		}
+
+		//the day after a date, worked out the long way:
+		fn next_day(Date { year, month, day }: Date) -> Date {
+			let month_length = match month {
+				2 if is_leap_year(year) => 29,
+				2 => 28,
+				4 | 6 | 9 | 11 => 30,
+				_ => 31,
+			};
+			match (month, day) {
+				(12, 31) => date(year + 1, 1, 1),
+				(_, day) if day == month_length => date(year, month + 1, 1),
+				_ => date(year, month, day + 1),
+			}
+		}
+
+		#[test]
+		fn dates() {
+			assert_eq!(days_from_date(date(1970, 1, 1)), 0);
+			assert_eq!(days_from_date(date(1969, 12, 31)), -1);
+			assert_eq!(days_from_date(date(2000, 2, 29)), 11_016);
+			assert_eq!(days_from_date(date(1900, 3, 1)), -25_508);
+			assert_eq!(days_from_date(date(2036, 2, 7)), 24_143);
+			assert_eq!(day_of_year(date(2023, 12, 31)), 365);
+			assert_eq!(day_of_year(date(2024, 12, 31)), 366);
+			assert_eq!(day_of_year(date(2100, 3, 1)), 60);
+		}
+
+		#[test]
+		fn dates_round_trip() {
+			//four centuries either side of 2000, taking in leap years and the century years that aren't:
+			let mut expected = date(1600, 1, 1);
+			for days in -135_140..=157_419 {
+				assert_eq!(date_from_days(days), expected);
+				assert_eq!(days_from_date(expected), days);
+				expected = next_day(expected);
+			}
+			assert_eq!(expected, date(2401, 1, 1));
+			assert_eq!(next_day(date(1900, 2, 28)), date(1900, 3, 1));
+			assert_eq!(next_day(date(2000, 2, 28)), date(2000, 2, 29));
+		}
+
+		#[test]
+		fn unix_times() {
+			assert_eq!(split_unix_time(-1), (date(1969, 12, 31), SECONDS_PER_DAY - 1));
+			assert_eq!(split_unix_time(1_718_884_800), (date(2024, 6, 20), 12 * 3600));
+			assert_eq!(unix_time(date(2024, 6, 20), 12 * 3600), 1_718_884_800);
+		}
+
+		fn sntp_reply(first_byte: u8, transmit_seconds: u32) -> [u8; SNTP_PACKET_LENGTH] {
+			let mut packet = [0_u8; SNTP_PACKET_LENGTH];
+			packet[0] = first_byte;
+			packet[40..44].copy_from_slice(&transmit_seconds.to_be_bytes());
+			packet
+		}
+
+		#[test]
+		fn sntp() {
+			assert_eq!(sntp_request()[0], 0x23);
+			//version 4, server mode, no leap second coming:
+			let reply = |transmit_seconds| sntp_unix_seconds(&sntp_reply(0x24, transmit_seconds));
+			assert_eq!(reply(2_208_988_800), Some(0));
+			assert_eq!(reply(3_927_873_600), Some(1_718_884_800));
+			//either side of NTP seconds wrapping, at 2036-02-07 06:28:16:
+			assert_eq!(reply(u32::MAX), Some(2_085_978_495));
+			assert_eq!(reply(1), Some(2_085_978_497));
+			assert_eq!(split_unix_time(2_085_978_496), (date(2036, 2, 7), 6 * 3600 + 28 * 60 + 16));
+			//a server that hasn't got the time yet sends zero:
+			assert_eq!(reply(0), None);
+		}
+
+		#[test]
+		fn unusable_sntp_replies() {
+			let reply = sntp_reply(0x24, 3_927_873_600);
+			assert_eq!(sntp_unix_seconds(&reply[..SNTP_PACKET_LENGTH - 1]), None);
+			assert_eq!(sntp_unix_seconds(&sntp_reply(0x23, 3_927_873_600)), None);
+			assert_eq!(sntp_unix_seconds(&sntp_reply(0xE4, 3_927_873_600)), None);
+			//a leap second coming is fine:
+			assert_eq!(sntp_unix_seconds(&sntp_reply(0x64, 3_927_873_600)), Some(1_718_884_800));
+		}
+
+		#[test]
+		fn rtc_counts_along() {
+			let mut rtc = Rtc::new(60);
+			rtc.update(5000);
+			assert!(!rtc.is_set());
+			assert_eq!(rtc.local_seconds(), None);
+			rtc.set(1000, 5000);
+			rtc.update(5999);
+			assert_eq!(rtc.unix_seconds(), Some(1000));
+			rtc.update(6000);
+			assert_eq!(rtc.unix_seconds(), Some(1001));
+			assert_eq!(rtc.local_seconds(), Some(1001 + 3600));
+			//the leftover milliseconds aren't lost between updates:
+			for now_ms in (6300..=9000).step_by(300) {
+				rtc.update(now_ms);
+			}
+			assert_eq!(rtc.unix_seconds(), Some(1004));
+		}
+
+		#[test]
+		fn rtc_across_the_frame_clock_wrapping() {
+			let mut rtc = Rtc::new(0);
+			rtc.set(1000, u32::MAX - 499);
+			rtc.update(499);
+			assert_eq!(rtc.unix_seconds(), Some(1000));
+			rtc.update(500);
+			assert_eq!(rtc.unix_seconds(), Some(1001));
+		}
+	}
+}
@@ -1,7 +1,7 @@
 #[allow(dead_code)]
 
 pub mod colors {
-	#[derive(Default, Copy, Clone)]
+	#[derive(Default, Copy, Clone, PartialEq, Debug)]
 	pub struct Color {
 		pub r: u8,
 		pub g: u8,
@@ -25,13 +25,22 @@ pub mod colors {
 			self.b = GAMMA8[b as usize];
 		}
 
+		//this scales all three channels of the color by brightness / 255:
+		pub fn scaled(self, brightness: u8) -> Color {
+			Color {
+				r: ((self.r as u16 * brightness as u16) / 255) as u8,
+				g: ((self.g as u16 * brightness as u16) / 255) as u8,
+				b: ((self.b as u16 * brightness as u16) / 255) as u8,
+			}
+		}
+
 		//this maps a color to a fractional mid-color based on the position of the factor between the in_min and in_max values.
 		//It will automatically truncate any values below 0 or larger than 255 when it is cast back to a u8 at the end of the calculation.
 		pub fn color_lerp(factor: i32, in_min: i32, in_max: i32, start_color: Color, end_color: Color) -> Color {
 			let mut mid_color = C_OFF;
 			mid_color.r = ((factor - in_min) * (end_color.r as i32 - start_color.r as i32) / (in_max - in_min) + start_color.r as i32) as u8;
-			mid_color.g = ((factor - in_min) * (end_color.g as i32 - start_color.r as i32) / (in_max - in_min) + start_color.g as i32) as u8;
-			mid_color.b = ((factor - in_min) * (end_color.b as i32 - start_color.r as i32) / (in_max - in_min) + start_color.b as i32) as u8;
+			mid_color.g = ((factor - in_min) * (end_color.g as i32 - start_color.g as i32) / (in_max - in_min) + start_color.g as i32) as u8;
+			mid_color.b = ((factor - in_min) * (end_color.b as i32 - start_color.b as i32) / (in_max - in_min) + start_color.b as i32) as u8;
 			mid_color
 		}
 	}
@@ -43,6 +52,29 @@ pub mod colors {
 		num_colors: u8,
 	}
 
+	impl Rainbow {
+		pub fn num_colors(&self) -> usize {
+			self.num_colors as usize
+		}
+
+		//gets a color from the rainbow by index, wrapping around past the last color:
+		pub fn color(&self, index: usize) -> Color {
+			if self.num_colors == 0 {
+				return C_OFF;
+			}
+			self.colors[index % self.num_colors as usize].unwrap_or(C_OFF)
+		}
+
+		//this gets the color at a position around the rainbow, fading between the two colors on either side.
+		//a position of 0 is the first color, and the rainbow wraps back around to it at u16::MAX.
+		pub fn color_at_position(&self, position: u16) -> Color {
+			let scaled_position = position as u32 * self.num_colors.max(1) as u32;
+			let index = (scaled_position >> 16) as usize;
+			let fraction = (scaled_position & 0xFFFF) as i32;
+			Color::color_lerp(fraction, 0, 0x1_0000, self.color(index), self.color(index + 1))
+		}
+	}
+
 	//a color correction table for LEDs to make them look like the color you expect:
 	//shamelessly stolen from Adafruit somewhere a long time ago.
 	pub const GAMMA8: [u8; 256] = [
@@ -79,10 +111,44 @@ pub mod colors {
 	pub const C_DARK_PURPLE: Color = Color { r: 255, g: 0, b: 127 };
 	pub const C_WHITE: Color = Color { r: 255, g: 255, b: 127 };
 	pub const C_OFF: Color = Color { r: 0, g: 0, b: GAMMA8[0] };
+	pub const C_T_1000K: Color = Color { r: 255, g: 56, b: 0 };
+	pub const C_T_2000K: Color = Color { r: 255, g: 137, b: 14 };
+	pub const C_T_2500K: Color = Color { r: 255, g: 161, b: 72 };
 	pub const C_T_3000K: Color = Color { r: 255, g: 180, b: 107 };
 	pub const C_T_3500K: Color = Color { r: 255, g: 196, b: 137 };
 	pub const C_T_4000K: Color = Color { r: 255, g: 209, b: 163 };
 	pub const C_T_5000K: Color = Color { r: 255, g: 228, b: 206 };
+	pub const C_T_6500K: Color = Color { r: 255, g: 249, b: 253 };
+
+	//the color temperature whites above in order, used to generate in-between temperatures:
+	pub const KELVIN_WAYPOINTS: [(u16, Color); 8] = [
+		(1000, C_T_1000K),
+		(2000, C_T_2000K),
+		(2500, C_T_2500K),
+		(3000, C_T_3000K),
+		(3500, C_T_3500K),
+		(4000, C_T_4000K),
+		(5000, C_T_5000K),
+		(6500, C_T_6500K),
+	];
+	pub const KELVIN_MIN: u16 = KELVIN_WAYPOINTS[0].0;
+	pub const KELVIN_MAX: u16 = KELVIN_WAYPOINTS[KELVIN_WAYPOINTS.len() - 1].0;
+
+	//this generates a white for any color temperature by interpolating between the nearest waypoints.
+	//values outside of KELVIN_MIN and KELVIN_MAX are clamped to the end of the table.
+	pub fn kelvin_to_color(kelvin: u16) -> Color {
+		if kelvin <= KELVIN_MIN {
+			return KELVIN_WAYPOINTS[0].1;
+		}
+		for pair in KELVIN_WAYPOINTS.windows(2) {
+			let (low_kelvin, low_color) = pair[0];
+			let (high_kelvin, high_color) = pair[1];
+			if kelvin <= high_kelvin {
+				return Color::color_lerp(kelvin as i32, low_kelvin as i32, high_kelvin as i32, low_color, high_color);
+			}
+		}
+		KELVIN_WAYPOINTS[KELVIN_WAYPOINTS.len() - 1].1
+	}
 
 	//if you change this, you need to modify all the rainbows below to match the new size
 	pub const MAX_COLORS_IN_RAINBOW: usize = 10;
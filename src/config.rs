@@ -0,0 +1,481 @@
+pub mod config {
+	use crate::c;
+	use crate::mx;
+	use crate::vw;
+
+	//buffers are sized at compile time for the biggest setup the firmware supports.
+	//the number of strips is limited by the pins wired up in pins.rs.
+	pub const MAX_PHYSICAL_STRIPS: usize = 3;
+	pub const MAX_LEDS_PER_STRIP: usize = 150;
+	pub const MAX_LEDS: usize = MAX_PHYSICAL_STRIPS * MAX_LEDS_PER_STRIP;
+	//the views of the strips besides the whole room, in the order they come after the room in
+	//ctl::VIEWS: the ceiling perimeter and the closet.
+	pub const MAX_VIEWS: usize = 2;
+
+	//the strip configuration blob is laid out as:
+	//  4 bytes  magic "WSCF"
+	//  1 byte   version
+	//  1 byte   number of strips
+	//then for each strip, in the order they go around the room:
+	//  1 byte   gpio pin number
+	//  2 bytes  led count, little endian
+	//  1 byte   flags, bit 0 set if the strip is reversed
+	//  1 byte   color order, see ColorOrder
+	//version 2 added, for strips that are wired up as a grid:
+	//  1 byte   matrix width, 0 if the strip isn't a matrix
+	//  1 byte   matrix height
+	//  1 byte   matrix flags, bit 0 set for serpentine wiring, bits 1-2 the first LED's corner
+	//           and bits 3-4 the rotation, numbered in the order they are listed in matrix.rs
+	//and after the strips, for each of the MAX_VIEWS views:
+	//  1 byte   number of slices, then for each slice:
+	//  1 byte   strip index
+	//  2 bytes  first LED on the strip, little endian
+	//  2 bytes  number of LEDs, little endian
+	//  1 byte   flags, bit 0 set if the slice runs backwards
+	//version 1 blobs have no views, so the views are left empty.
+	pub const CONFIG_MAGIC: [u8; 4] = *b"WSCF";
+	pub const CONFIG_VERSION: u8 = 2;
+	const HEADER_LENGTH: usize = 6;
+	const STRIP_RECORD_LENGTH_V1: usize = 5;
+	const STRIP_RECORD_LENGTH: usize = 8;
+	const SLICE_RECORD_LENGTH: usize = 6;
+	const FLAG_REVERSED: u8 = 0x01;
+	const MATRIX_SERPENTINE: u8 = 0x01;
+	const MATRIX_CORNER_SHIFT: u8 = 1;
+	const MATRIX_ROTATION_SHIFT: u8 = 3;
+	const CORNERS: [mx::Corner; 4] = [mx::Corner::TopLeft, mx::Corner::TopRight, mx::Corner::BottomLeft, mx::Corner::BottomRight];
+	const ROTATIONS: [mx::Rotation; 4] = [mx::Rotation::R0, mx::Rotation::R90, mx::Rotation::R180, mx::Rotation::R270];
+	pub const MAX_CONFIG_LENGTH: usize =
+		HEADER_LENGTH + MAX_PHYSICAL_STRIPS * STRIP_RECORD_LENGTH + MAX_VIEWS * (1 + vw::MAX_SLICES * SLICE_RECORD_LENGTH);
+
+	//the order the strip wants the color channels sent in:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum ColorOrder {
+		RGB,
+		RBG,
+		GRB,
+		GBR,
+		BRG,
+		BGR,
+	}
+
+	impl ColorOrder {
+		pub fn from_u8(value: u8) -> Option<ColorOrder> {
+			match value {
+				0 => Some(ColorOrder::RGB),
+				1 => Some(ColorOrder::RBG),
+				2 => Some(ColorOrder::GRB),
+				3 => Some(ColorOrder::GBR),
+				4 => Some(ColorOrder::BRG),
+				5 => Some(ColorOrder::BGR),
+				_ => None,
+			}
+		}
+
+		pub fn to_u8(self) -> u8 {
+			self as u8
+		}
+
+		//this puts the channels of a color in the order they are sent:
+		pub fn bytes(self, color: c::Color) -> [u8; 3] {
+			match self {
+				ColorOrder::RGB => [color.r, color.g, color.b],
+				ColorOrder::RBG => [color.r, color.b, color.g],
+				ColorOrder::GRB => [color.g, color.r, color.b],
+				ColorOrder::GBR => [color.g, color.b, color.r],
+				ColorOrder::BRG => [color.b, color.r, color.g],
+				ColorOrder::BGR => [color.b, color.g, color.r],
+			}
+		}
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct StripConfig {
+		pub pin: u8,
+		pub led_count: usize,
+		pub reversed: bool,
+		pub color_order: ColorOrder,
+		//how the LEDs are laid out if the strip is a matrix panel, for effects that draw on a grid:
+		pub matrix: Option<mx::Layout>,
+	}
+
+	impl StripConfig {
+		pub const EMPTY: StripConfig = StripConfig {
+			pin: 0,
+			led_count: 0,
+			reversed: false,
+			color_order: ColorOrder::RGB,
+			matrix: None,
+		};
+	}
+
+	//the pieces of the physical strips one of the views is made of:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct ViewConfig {
+		slices: [vw::StripSlice; vw::MAX_SLICES],
+		slice_count: usize,
+	}
+
+	impl ViewConfig {
+		pub const EMPTY: ViewConfig = ViewConfig::new(&[]);
+
+		//slices past vw::MAX_SLICES are left off:
+		pub const fn new(slices: &[vw::StripSlice]) -> Self {
+			let mut view = ViewConfig {
+				slices: [vw::StripSlice::new(0, 0, 0, false); vw::MAX_SLICES],
+				slice_count: 0,
+			};
+			while view.slice_count < slices.len() && view.slice_count < vw::MAX_SLICES {
+				view.slices[view.slice_count] = slices[view.slice_count];
+				view.slice_count += 1;
+			}
+			view
+		}
+
+		pub fn slices(&self) -> &[vw::StripSlice] {
+			&self.slices[..self.slice_count]
+		}
+	}
+
+	//this packs a matrix layout into the three bytes at the end of a strip record:
+	fn encode_matrix(matrix: Option<mx::Layout>) -> [u8; 3] {
+		match matrix {
+			Some(layout) => {
+				let wiring = if layout.wiring == mx::Wiring::Serpentine { MATRIX_SERPENTINE } else { 0 };
+				let corner = CORNERS.iter().position(|&corner| corner == layout.origin).unwrap_or(0) as u8;
+				let rotation = ROTATIONS.iter().position(|&rotation| rotation == layout.rotation).unwrap_or(0) as u8;
+				[layout.width as u8, layout.height as u8, wiring | corner << MATRIX_CORNER_SHIFT | rotation << MATRIX_ROTATION_SHIFT]
+			}
+			None => [0; 3],
+		}
+	}
+
+	fn decode_matrix(bytes: &[u8]) -> Option<mx::Layout> {
+		match bytes {
+			&[width, height, flags] if width != 0 => Some(mx::Layout::new(
+				width as usize,
+				height as usize,
+				if flags & MATRIX_SERPENTINE != 0 { mx::Wiring::Serpentine } else { mx::Wiring::Progressive },
+				CORNERS[(flags >> MATRIX_CORNER_SHIFT) as usize & 0x03],
+				ROTATIONS[(flags >> MATRIX_ROTATION_SHIFT) as usize & 0x03],
+			)),
+			_ => None,
+		}
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum ConfigError {
+		//the blob couldn't be read out of flash
+		Unreadable,
+		//the blob doesn't start with CONFIG_MAGIC, so there probably isn't one in flash
+		BadMagic,
+		UnsupportedVersion(u8),
+		//the blob ends before all the strips it says it has
+		Truncated,
+		NoStrips,
+		//more strips than MAX_PHYSICAL_STRIPS
+		TooManyStrips(usize),
+		//this strip has more LEDs than MAX_LEDS_PER_STRIP
+		StripTooLong { strip: usize, led_count: usize },
+		//this pin isn't one of the pins the firmware can drive
+		UnsupportedPin(u8),
+		//more than one strip is on this pin
+		DuplicatePin(u8),
+		//this strip has a color order number that doesn't exist
+		BadColorOrder(usize),
+		//this strip's matrix doesn't have the same number of LEDs as the strip
+		MatrixSize(usize),
+		//this view doesn't fit the strips
+		BadView { view: usize, error: vw::ViewError },
+	}
+
+	//the physical strips, in the order they go around the room:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Topology {
+		strips: [StripConfig; MAX_PHYSICAL_STRIPS],
+		strip_count: usize,
+		views: [ViewConfig; MAX_VIEWS],
+	}
+
+	impl Topology {
+		pub fn strips(&self) -> &[StripConfig] {
+			&self.strips[..self.strip_count]
+		}
+
+		pub fn total_leds(&self) -> usize {
+			self.strips().iter().map(|strip| strip.led_count).sum()
+		}
+
+		pub fn views(&self) -> &[ViewConfig; MAX_VIEWS] {
+			&self.views
+		}
+
+		//this sets the slices of the views, checking they are on the strips:
+		pub fn set_views(&mut self, views: [ViewConfig; MAX_VIEWS]) -> Result<(), ConfigError> {
+			let mut strip_lengths = [0; MAX_PHYSICAL_STRIPS];
+			for (length, strip) in strip_lengths.iter_mut().zip(self.strips()) {
+				*length = strip.led_count;
+			}
+			for (index, view) in views.iter().enumerate() {
+				vw::ViewMap::new(view.slices(), &strip_lengths[..self.strip_count], MAX_LEDS)
+					.map_err(|error| ConfigError::BadView { view: index, error })?;
+			}
+			self.views = views;
+			Ok(())
+		}
+
+		//this makes a topology from a list of strips, checking it against the buffer sizes and the
+		//pins the firmware can drive. The views start out empty.
+		//MAX_LEDS has room for every strip at its longest, so the total doesn't need checking.
+		pub fn new(strips: &[StripConfig], supported_pins: &[u8]) -> Result<Self, ConfigError> {
+			if strips.is_empty() {
+				return Err(ConfigError::NoStrips);
+			}
+			if strips.len() > MAX_PHYSICAL_STRIPS {
+				return Err(ConfigError::TooManyStrips(strips.len()));
+			}
+			let mut topology = Topology {
+				strips: [StripConfig::EMPTY; MAX_PHYSICAL_STRIPS],
+				strip_count: strips.len(),
+				views: [ViewConfig::EMPTY; MAX_VIEWS],
+			};
+			for (i, strip) in strips.iter().enumerate() {
+				if strip.led_count > MAX_LEDS_PER_STRIP {
+					return Err(ConfigError::StripTooLong { strip: i, led_count: strip.led_count });
+				}
+				if !supported_pins.contains(&strip.pin) {
+					return Err(ConfigError::UnsupportedPin(strip.pin));
+				}
+				if strips[..i].iter().any(|other| other.pin == strip.pin) {
+					return Err(ConfigError::DuplicatePin(strip.pin));
+				}
+				if strip.matrix.map_or(false, |layout| layout.led_count() != strip.led_count) {
+					return Err(ConfigError::MatrixSize(i));
+				}
+				topology.strips[i] = *strip;
+			}
+			Ok(topology)
+		}
+
+		//this reads and checks a configuration blob, from any version up to CONFIG_VERSION:
+		pub fn parse(blob: &[u8], supported_pins: &[u8]) -> Result<Self, ConfigError> {
+			if blob.len() < HEADER_LENGTH || blob[..4] != CONFIG_MAGIC {
+				return Err(ConfigError::BadMagic);
+			}
+			let record_length = match blob[4] {
+				1 => STRIP_RECORD_LENGTH_V1,
+				CONFIG_VERSION => STRIP_RECORD_LENGTH,
+				version => return Err(ConfigError::UnsupportedVersion(version)),
+			};
+			let strip_count = blob[5] as usize;
+			if strip_count > MAX_PHYSICAL_STRIPS {
+				return Err(ConfigError::TooManyStrips(strip_count));
+			}
+			if blob.len() < HEADER_LENGTH + strip_count * record_length {
+				return Err(ConfigError::Truncated);
+			}
+			let mut strips = [StripConfig::EMPTY; MAX_PHYSICAL_STRIPS];
+			for (i, record) in blob[HEADER_LENGTH..].chunks(record_length).take(strip_count).enumerate() {
+				strips[i] = StripConfig {
+					pin: record[0],
+					led_count: u16::from_le_bytes([record[1], record[2]]) as usize,
+					reversed: record[3] & FLAG_REVERSED == FLAG_REVERSED,
+					color_order: ColorOrder::from_u8(record[4]).ok_or(ConfigError::BadColorOrder(i))?,
+					matrix: decode_matrix(&record[STRIP_RECORD_LENGTH_V1..]),
+				};
+			}
+			let mut topology = Topology::new(&strips[..strip_count], supported_pins)?;
+			if record_length == STRIP_RECORD_LENGTH {
+				let mut views = [ViewConfig::EMPTY; MAX_VIEWS];
+				let mut position = HEADER_LENGTH + strip_count * record_length;
+				for (index, view) in views.iter_mut().enumerate() {
+					let slice_count = *blob.get(position).ok_or(ConfigError::Truncated)? as usize;
+					if slice_count > vw::MAX_SLICES {
+						return Err(ConfigError::BadView { view: index, error: vw::ViewError::TooManySlices });
+					}
+					position += 1;
+					let records = blob.get(position..position + slice_count * SLICE_RECORD_LENGTH).ok_or(ConfigError::Truncated)?;
+					position += records.len();
+					let mut slices = [vw::StripSlice::new(0, 0, 0, false); vw::MAX_SLICES];
+					for (slice, record) in slices.iter_mut().zip(records.chunks(SLICE_RECORD_LENGTH)) {
+						*slice = vw::StripSlice::new(
+							record[0] as usize,
+							u16::from_le_bytes([record[1], record[2]]) as usize,
+							u16::from_le_bytes([record[3], record[4]]) as usize,
+							record[5] & FLAG_REVERSED == FLAG_REVERSED,
+						);
+					}
+					*view = ViewConfig::new(&slices[..slice_count]);
+				}
+				topology.set_views(views)?;
+			}
+			Ok(topology)
+		}
+
+		//this writes the topology out as a configuration blob, returning the number of bytes used:
+		pub fn encode(&self, blob: &mut [u8; MAX_CONFIG_LENGTH]) -> usize {
+			blob[..4].copy_from_slice(&CONFIG_MAGIC);
+			blob[4] = CONFIG_VERSION;
+			blob[5] = self.strip_count as u8;
+			for (i, strip) in self.strips().iter().enumerate() {
+				let base = HEADER_LENGTH + i * STRIP_RECORD_LENGTH;
+				let led_count = (strip.led_count as u16).to_le_bytes();
+				blob[base] = strip.pin;
+				blob[base + 1] = led_count[0];
+				blob[base + 2] = led_count[1];
+				blob[base + 3] = if strip.reversed { FLAG_REVERSED } else { 0 };
+				blob[base + 4] = strip.color_order.to_u8();
+				blob[base + 5..base + STRIP_RECORD_LENGTH].copy_from_slice(&encode_matrix(strip.matrix));
+			}
+			let mut position = HEADER_LENGTH + self.strip_count * STRIP_RECORD_LENGTH;
+			for view in self.views.iter() {
+				blob[position] = view.slice_count as u8;
+				position += 1;
+				for slice in view.slices() {
+					let start = (slice.start as u16).to_le_bytes();
+					let led_count = (slice.led_count as u16).to_le_bytes();
+					let flags = if slice.reversed { FLAG_REVERSED } else { 0 };
+					blob[position..position + SLICE_RECORD_LENGTH]
+						.copy_from_slice(&[slice.strip_index as u8, start[0], start[1], led_count[0], led_count[1], flags]);
+					position += SLICE_RECORD_LENGTH;
+				}
+			}
+			position
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const PINS: [u8; 3] = [33, 13, 25];
+		//the office strips, as written in the readme:
+		const OFFICE_V1: [u8; 21] = [
+			0x57, 0x53, 0x43, 0x46, 0x01, 0x03,
+			0x21, 0x22, 0x00, 0x00, 0x04,
+			0x0D, 0x4A, 0x00, 0x00, 0x04,
+			0x19, 0x3D, 0x00, 0x01, 0x04,
+		];
+
+		#[test]
+		fn version_1_blobs_load() {
+			let topology = Topology::parse(&OFFICE_V1, &PINS).unwrap();
+			assert_eq!(topology.strips().len(), 3);
+			assert_eq!(topology.total_leds(), 34 + 74 + 61);
+			assert_eq!(topology.strips()[2], StripConfig { pin: 25, led_count: 61, reversed: true, color_order: ColorOrder::BRG, matrix: None });
+			assert_eq!(topology.views(), &[ViewConfig::EMPTY; MAX_VIEWS]);
+			assert_eq!(Topology::parse(&OFFICE_V1[..20], &PINS), Err(ConfigError::Truncated));
+		}
+
+		#[test]
+		fn views_round_trip() {
+			//the office strips with the ceiling perimeter and closet views, as written in the readme:
+			let office = [
+				0x57, 0x53, 0x43, 0x46, 0x02, 0x03,
+				0x21, 0x22, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+				0x0D, 0x4A, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+				0x19, 0x3D, 0x00, 0x01, 0x04, 0x00, 0x00, 0x00,
+				0x02, 0x01, 0x00, 0x00, 0x4A, 0x00, 0x00, 0x02, 0x00, 0x00, 0x3D, 0x00, 0x00,
+				0x01, 0x00, 0x00, 0x00, 0x22, 0x00, 0x00,
+			];
+			let topology = Topology::parse(&office, &PINS).unwrap();
+			let perimeter = [vw::StripSlice::new(1, 0, 74, false), vw::StripSlice::new(2, 0, 61, false)];
+			assert_eq!(topology.views()[0].slices(), &perimeter[..]);
+			assert_eq!(topology.views()[1].slices(), &[vw::StripSlice::new(0, 0, 34, false)][..]);
+			let mut blob = [0_u8; MAX_CONFIG_LENGTH];
+			let length = topology.encode(&mut blob);
+			assert_eq!(blob[..length], office[..]);
+
+			//a slice part way along a strip, backwards:
+			let mut topology = Topology::new(topology.strips(), &PINS).unwrap();
+			let views = [ViewConfig::new(&[vw::StripSlice::new(2, 10, 51, true)]), ViewConfig::EMPTY];
+			topology.set_views(views).unwrap();
+			let length = topology.encode(&mut blob);
+			assert_eq!(Topology::parse(&blob[..length], &PINS), Ok(topology));
+			assert_eq!(Topology::parse(&blob[..length - 1], &PINS), Err(ConfigError::Truncated));
+			assert_eq!(Topology::parse(&blob[..length - 2], &PINS), Err(ConfigError::Truncated));
+		}
+
+		#[test]
+		fn views_have_to_fit() {
+			let mut topology = Topology::parse(&OFFICE_V1, &PINS).unwrap();
+			let past_the_end = ViewConfig::new(&[vw::StripSlice::new(0, 30, 5, false)]);
+			assert_eq!(
+				topology.set_views([ViewConfig::EMPTY, past_the_end]),
+				Err(ConfigError::BadView { view: 1, error: vw::ViewError::SliceOutOfRange(0) })
+			);
+			let no_strip = ViewConfig::new(&[vw::StripSlice::new(0, 0, 5, false), vw::StripSlice::new(3, 0, 5, false)]);
+			assert_eq!(
+				topology.set_views([no_strip, ViewConfig::EMPTY]),
+				Err(ConfigError::BadView { view: 0, error: vw::ViewError::UnknownStrip(1) })
+			);
+			assert_eq!(topology.views(), &[ViewConfig::EMPTY; MAX_VIEWS]);
+			//a blob with more slices than a view can have:
+			let mut blob = [0_u8; MAX_CONFIG_LENGTH];
+			let length = topology.encode(&mut blob);
+			blob[length - 2] = vw::MAX_SLICES as u8 + 1;
+			assert_eq!(Topology::parse(&blob, &PINS), Err(ConfigError::BadView { view: 0, error: vw::ViewError::TooManySlices }));
+		}
+
+		#[test]
+		fn strips_are_checked() {
+			let strip = |pin, led_count| StripConfig { pin, led_count, ..StripConfig::EMPTY };
+			assert_eq!(Topology::new(&[], &PINS), Err(ConfigError::NoStrips));
+			assert_eq!(Topology::new(&[strip(33, 1), strip(13, 1), strip(25, 1), strip(33, 1)], &PINS), Err(ConfigError::TooManyStrips(4)));
+			assert_eq!(
+				Topology::new(&[strip(33, 1), strip(13, MAX_LEDS_PER_STRIP + 1)], &PINS),
+				Err(ConfigError::StripTooLong { strip: 1, led_count: MAX_LEDS_PER_STRIP + 1 })
+			);
+			assert_eq!(Topology::new(&[strip(33, 1), strip(2, 1)], &PINS), Err(ConfigError::UnsupportedPin(2)));
+			assert_eq!(Topology::new(&[strip(33, 1), strip(13, 1), strip(33, 1)], &PINS), Err(ConfigError::DuplicatePin(33)));
+			//every strip at its longest fits:
+			let longest = [strip(33, MAX_LEDS_PER_STRIP), strip(13, MAX_LEDS_PER_STRIP), strip(25, MAX_LEDS_PER_STRIP)];
+			assert_eq!(Topology::new(&longest, &PINS).map(|topology| topology.total_leds()), Ok(MAX_LEDS));
+		}
+
+		#[test]
+		fn bad_blobs() {
+			assert_eq!(Topology::parse(&[], &PINS), Err(ConfigError::BadMagic));
+			assert_eq!(Topology::parse(&[0xFF; MAX_CONFIG_LENGTH], &PINS), Err(ConfigError::BadMagic));
+			let mut blob = OFFICE_V1;
+			blob[4] = 0;
+			assert_eq!(Topology::parse(&blob, &PINS), Err(ConfigError::UnsupportedVersion(0)));
+			let mut blob = OFFICE_V1;
+			blob[5] = MAX_PHYSICAL_STRIPS as u8 + 1;
+			assert_eq!(Topology::parse(&blob, &PINS), Err(ConfigError::TooManyStrips(MAX_PHYSICAL_STRIPS + 1)));
+			let mut blob = OFFICE_V1;
+			blob[5] = 0;
+			assert_eq!(Topology::parse(&blob, &PINS), Err(ConfigError::NoStrips));
+			//the second strip's color order:
+			let mut blob = OFFICE_V1;
+			blob[15] = 6;
+			assert_eq!(Topology::parse(&blob, &PINS), Err(ConfigError::BadColorOrder(1)));
+			//the third strip moved onto the first one's pin:
+			let mut blob = OFFICE_V1;
+			blob[16] = 0x21;
+			assert_eq!(Topology::parse(&blob, &PINS), Err(ConfigError::DuplicatePin(33)));
+		}
+
+		#[test]
+		fn matrix_round_trip() {
+			let panel = StripConfig {
+				pin: 13,
+				led_count: 128,
+				reversed: false,
+				color_order: ColorOrder::GRB,
+				matrix: Some(mx::Layout::new(16, 8, mx::Wiring::Serpentine, mx::Corner::BottomRight, mx::Rotation::R270)),
+			};
+			let strips = [StripConfig { pin: 33, led_count: 20, ..StripConfig::EMPTY }, panel];
+			let topology = Topology::new(&strips, &PINS).unwrap();
+			let mut blob = [0_u8; MAX_CONFIG_LENGTH];
+			let length = topology.encode(&mut blob);
+			assert_eq!(length, HEADER_LENGTH + 2 * STRIP_RECORD_LENGTH + MAX_VIEWS);
+			assert_eq!(blob[HEADER_LENGTH + STRIP_RECORD_LENGTH + 5..HEADER_LENGTH + 2 * STRIP_RECORD_LENGTH], [16, 8, 0x01 | 3 << 1 | 3 << 3]);
+			assert_eq!(Topology::parse(&blob[..length], &PINS), Ok(topology));
+			let wrong_size = StripConfig { led_count: 100, ..panel };
+			assert_eq!(Topology::new(&[wrong_size], &PINS), Err(ConfigError::MatrixSize(0)));
+			blob[4] = CONFIG_VERSION + 1;
+			assert_eq!(Topology::parse(&blob[..length], &PINS), Err(ConfigError::UnsupportedVersion(CONFIG_VERSION + 1)));
+		}
+	}
+}
@@ -0,0 +1,130 @@
+pub mod control {
+	use crate::a;
+	use crate::c;
+	use crate::ly;
+	use crate::pl;
+
+	//a scene slot can be picked by its number or by the name of the scene in it:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum SceneRef<'a> {
+		Slot(u8),
+		Name(&'a str),
+	}
+
+	//the views of the strips that can be controlled on their own, in the order of the logical
+	//strips in main. The room is all of the strips, and the others show over it when they are on.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum View {
+		Room,
+		Perimeter,
+		Closet,
+	}
+
+	pub const VIEWS: [View; 3] = [View::Room, View::Perimeter, View::Closet];
+
+	impl View {
+		pub const fn index(self) -> usize {
+			self as usize
+		}
+
+		pub fn name(self) -> &'static str {
+			match self {
+				View::Room => "room",
+				View::Perimeter => "perimeter",
+				View::Closet => "closet",
+			}
+		}
+
+		pub fn from_name(name: &str) -> Option<View> {
+			VIEWS.iter().copied().find(|view| view.name() == name)
+		}
+	}
+
+	//the parts of an effect a command can pick. Anything left out is kept from the effect that
+	//is running now.
+	#[derive(Copy, Clone, PartialEq, Debug, Default)]
+	pub struct EffectOptions {
+		pub speed: Option<u8>,
+		pub palette: Option<u8>,
+		pub color: Option<c::Color>,
+		pub intensity: Option<u8>,
+		pub custom: Option<u8>,
+		pub per_strip: Option<bool>,
+		pub reversed: Option<bool>,
+	}
+
+	impl EffectOptions {
+		//this makes params for the effect, taking what the options leave out from current:
+		pub fn params(&self, effect: a::Effect, current: a::EffectParams) -> a::EffectParams {
+			a::EffectParams {
+				effect,
+				color: self.color.unwrap_or(current.color),
+				palette: self.palette.unwrap_or(current.palette),
+				speed: self.speed.unwrap_or(current.speed),
+				intensity: self.intensity.unwrap_or(current.intensity),
+				custom: self.custom.unwrap_or(current.custom),
+				per_strip: self.per_strip.unwrap_or(current.per_strip),
+				reversed: self.reversed.unwrap_or(current.reversed),
+			}
+		}
+	}
+
+	//a segment to add to a view, covering length LEDs from start:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct NewSegment<'a> {
+		pub name: &'a str,
+		pub start: u16,
+		pub length: u16,
+		pub effect: a::Effect,
+		pub options: EffectOptions,
+		pub brightness: Option<u8>,
+		pub reversed: bool,
+		pub mirrored: bool,
+	}
+
+	//the things that can be asked of the lights, from the serial command line or anywhere else.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Command<'a> {
+		Help,
+		On,
+		Off,
+		Color(c::Color),
+		Kelvin(u16),
+		Brightness(u8),
+		//switch effects. Anything left out is kept from the effect that is running now.
+		Effect { effect: a::Effect, options: EffectOptions },
+		//follow the circadian curve, once the clock is set
+		Circadian,
+		SceneSave { slot: u8, name: Option<&'a str> },
+		SceneRecall(SceneRef<'a>),
+		SceneNext,
+		SceneDelete(u8),
+		SceneList,
+		PlaylistAdd(pl::Entry),
+		PlaylistClear,
+		PlaylistStart,
+		PlaylistStop,
+		PlaylistPause,
+		PlaylistResume,
+		PlaylistNext,
+		PlaylistShuffle(bool),
+		PlaylistRepeat(bool),
+		PlaylistStatus,
+		//set the clock to a unix time, or show the time with None
+		Time(Option<i64>),
+		//send on, off, color, kelvin, brightness and effect commands to a view from now on, and
+		//turn it on or off. With no view, the views are listed.
+		View { view: Option<View>, on: Option<bool> },
+		//segments of the view picked with View, each running its own effect over part of it
+		SegmentAdd(NewSegment<'a>),
+		SegmentRemove(&'a str),
+		SegmentList,
+		//put an effect in one of the layers over the view picked with View, or take it out with LayerOff
+		Layer { index: u8, effect: a::Effect, options: EffectOptions, blend_mode: ly::BlendMode, opacity: u8 },
+		LayerOff(u8),
+		LayerList,
+		//list the physical strips
+		Strips,
+		Stats,
+	}
+}
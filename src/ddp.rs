@@ -0,0 +1,242 @@
+pub mod ddp {
+	use crate::c;
+
+	//DDP sends LED data over UDP as runs of bytes at an offset into the display, so long strips
+	//don't need splitting into universes. The network side hands each packet that comes in on
+	//DDP_PORT to a Receiver. A packet is laid out as:
+	//  1 byte   flags: version in the top two bits, then see FLAG_*
+	//  1 byte   sequence number in the low 4 bits, 0 if the sender doesn't number its packets
+	//  1 byte   data type
+	//  1 byte   destination
+	//  4 bytes  offset of the data into the display, in bytes, big endian
+	//  2 bytes  length of the data, big endian
+	//  4 bytes  timecode, only if FLAG_TIMECODE is set
+	//  data     red, green, blue for each LED
+	pub const DDP_PORT: u16 = 4048;
+	const HEADER_LENGTH: usize = 10;
+	const TIMECODE_LENGTH: usize = 4;
+	const VERSION_MASK: u8 = 0xC0;
+	const VERSION_1: u8 = 0x40;
+	const FLAG_TIMECODE: u8 = 0x10;
+	const FLAG_QUERY: u8 = 0x02;
+	//the data so far makes up a whole frame, and should be shown:
+	const FLAG_PUSH: u8 = 0x01;
+	const SEQUENCE_MASK: u8 = 0x0F;
+	//senders that don't fill in the data type mean 8 bit RGB. Older ones send 1 for it:
+	const DATA_TYPE_UNDEFINED: u8 = 0x00;
+	const DATA_TYPE_RGB_LEGACY: u8 = 0x01;
+	const DATA_TYPE_RGB8: u8 = 0x0B;
+	const DESTINATION_DISPLAY: u8 = 0x01;
+	const DESTINATION_ALL: u8 = 0xFF;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum DdpError {
+		//the packet ends before its header or data do
+		Truncated,
+		UnsupportedVersion(u8),
+		//the data isn't 8 bit RGB
+		UnsupportedDataType(u8),
+		//the packet is for something other than the LEDs, like configuration or status
+		NotForDisplay(u8),
+		//queries for the sender's status or configuration aren't answered
+		Query,
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Packet<'a> {
+		pub push: bool,
+		pub sequence: u8,
+		//where the data goes, in bytes from the start of the display:
+		pub offset: usize,
+		pub data: &'a [u8],
+	}
+
+	impl<'a> Packet<'a> {
+		pub fn parse(packet: &'a [u8]) -> Result<Self, DdpError> {
+			if packet.len() < HEADER_LENGTH {
+				return Err(DdpError::Truncated);
+			}
+			let flags = packet[0];
+			if flags & VERSION_MASK != VERSION_1 {
+				return Err(DdpError::UnsupportedVersion(flags >> 6));
+			}
+			if flags & FLAG_QUERY != 0 {
+				return Err(DdpError::Query);
+			}
+			match packet[2] {
+				DATA_TYPE_UNDEFINED | DATA_TYPE_RGB_LEGACY | DATA_TYPE_RGB8 => {}
+				data_type => return Err(DdpError::UnsupportedDataType(data_type)),
+			}
+			match packet[3] {
+				DESTINATION_DISPLAY | DESTINATION_ALL => {}
+				destination => return Err(DdpError::NotForDisplay(destination)),
+			}
+			let offset = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]) as usize;
+			let length = u16::from_be_bytes([packet[8], packet[9]]) as usize;
+			let data_start = if flags & FLAG_TIMECODE != 0 { HEADER_LENGTH + TIMECODE_LENGTH } else { HEADER_LENGTH };
+			Ok(Packet {
+				push: flags & FLAG_PUSH != 0,
+				sequence: packet[1] & SEQUENCE_MASK,
+				offset,
+				data: packet.get(data_start..data_start + length).ok_or(DdpError::Truncated)?,
+			})
+		}
+	}
+
+	//this puts DDP packets together into frames. Data is written into the receiver's own buffer,
+	//which is only copied out when a packet with the push flag comes in, so a frame is only ever
+	//shown whole.
+	pub struct Receiver<const NUM_LEDS: usize> {
+		back_buffer: [c::Color; NUM_LEDS],
+		//how many bytes into the display the frame's data has been written, so far:
+		length: usize,
+	}
+
+	impl<const NUM_LEDS: usize> Default for Receiver<NUM_LEDS> {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	impl<const NUM_LEDS: usize> Receiver<NUM_LEDS> {
+		pub fn new() -> Self {
+			Receiver {
+				back_buffer: [c::C_OFF; NUM_LEDS],
+				length: 0,
+			}
+		}
+
+		//this takes a packet. When the packet pushes the frame, the frame is copied into pixels
+		//and the number of LEDs in it is returned. LEDs the frame's packets didn't reach keep the
+		//colors they had in earlier frames, and data past the end of the buffer is ignored.
+		pub fn receive(&mut self, packet: &[u8], pixels: &mut [c::Color]) -> Result<Option<usize>, DdpError> {
+			let packet = Packet::parse(packet)?;
+			let capacity = NUM_LEDS * 3;
+			let end = packet.offset.saturating_add(packet.data.len());
+			if packet.offset < capacity {
+				for (index, &byte) in (packet.offset..end.min(capacity)).zip(packet.data) {
+					let pixel = &mut self.back_buffer[index / 3];
+					match index % 3 {
+						0 => pixel.r = byte,
+						1 => pixel.g = byte,
+						_ => pixel.b = byte,
+					}
+				}
+				self.length = self.length.max(end.min(capacity));
+			}
+			if !packet.push {
+				return Ok(None);
+			}
+			let led_count = self.length.div_ceil(3).min(pixels.len());
+			pixels[..led_count].copy_from_slice(&self.back_buffer[..led_count]);
+			self.length = 0;
+			Ok(Some(led_count))
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		//whole packets, laid out the way xLights sends a 6 LED display: the first 4 LEDs, then
+		//the last 2 with the push flag, numbering the packets as it goes:
+		const FIRST: [u8; HEADER_LENGTH + 12] = [
+			0x40, 0x03, 0x0B, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0C,
+			0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x10, 0x20, 0x30,
+		];
+		const LAST: [u8; HEADER_LENGTH + 6] = [
+			0x41, 0x04, 0x0B, 0x01, 0x00, 0x00, 0x00, 0x0C, 0x00, 0x06,
+			0x40, 0x50, 0x60, 0x70, 0x80, 0x90,
+		];
+		//and the way WLED syncs 3 LEDs in one packet, with a timecode and the legacy data type:
+		const TIMECODE: [u8; HEADER_LENGTH + TIMECODE_LENGTH + 9] = [
+			0x51, 0x00, 0x01, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x01, 0x86, 0xA0,
+			0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09,
+		];
+		//a push with no data, to show what has come in so far:
+		const PUSH_ONLY: [u8; HEADER_LENGTH] = [0x41, 0x05, 0x0B, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+		//the display the packets above make up:
+		const FRAME: [c::Color; 6] = [
+			c::Color { r: 0xFF, g: 0x00, b: 0x00 },
+			c::Color { r: 0x00, g: 0xFF, b: 0x00 },
+			c::Color { r: 0x00, g: 0x00, b: 0xFF },
+			c::Color { r: 0x10, g: 0x20, b: 0x30 },
+			c::Color { r: 0x40, g: 0x50, b: 0x60 },
+			c::Color { r: 0x70, g: 0x80, b: 0x90 },
+		];
+
+		#[test]
+		fn parses_packets() {
+			let parsed = Packet::parse(&FIRST).unwrap();
+			assert_eq!((parsed.push, parsed.sequence, parsed.offset), (false, 3, 0));
+			assert_eq!(parsed.data, &FIRST[HEADER_LENGTH..]);
+			let parsed = Packet::parse(&LAST).unwrap();
+			assert_eq!((parsed.push, parsed.sequence, parsed.offset), (true, 4, 12));
+			assert_eq!(parsed.data, &LAST[HEADER_LENGTH..]);
+			let parsed = Packet::parse(&TIMECODE).unwrap();
+			assert_eq!((parsed.push, parsed.sequence, parsed.offset), (true, 0, 0));
+			assert_eq!(parsed.data, &TIMECODE[HEADER_LENGTH + TIMECODE_LENGTH..]);
+			assert_eq!(Packet::parse(&PUSH_ONLY).unwrap().data, &[]);
+		}
+
+		#[test]
+		fn rejects_what_isnt_for_the_leds() {
+			let with = |index: usize, byte: u8| {
+				let mut packet = FIRST;
+				packet[index] = byte;
+				packet
+			};
+			assert_eq!(Packet::parse(&FIRST[..HEADER_LENGTH - 1]), Err(DdpError::Truncated));
+			assert_eq!(Packet::parse(&FIRST[..FIRST.len() - 1]), Err(DdpError::Truncated));
+			assert_eq!(Packet::parse(&TIMECODE[..HEADER_LENGTH + TIMECODE_LENGTH + 8]), Err(DdpError::Truncated));
+			assert_eq!(Packet::parse(&with(0, 0x80)), Err(DdpError::UnsupportedVersion(2)));
+			assert_eq!(Packet::parse(&with(0, 0x42)), Err(DdpError::Query));
+			assert_eq!(Packet::parse(&with(2, 0x1B)), Err(DdpError::UnsupportedDataType(0x1B)));
+			assert_eq!(Packet::parse(&with(3, 0xFB)), Err(DdpError::NotForDisplay(0xFB)));
+		}
+
+		#[test]
+		fn frames_are_only_shown_on_push() {
+			let mut receiver = Receiver::<6>::default();
+			let mut pixels = [c::C_OFF; 6];
+			assert_eq!(receiver.receive(&FIRST, &mut pixels), Ok(None));
+			assert_eq!(pixels, [c::C_OFF; 6]);
+			assert_eq!(receiver.receive(&LAST, &mut pixels), Ok(Some(6)));
+			assert_eq!(pixels, FRAME);
+		}
+
+		#[test]
+		fn each_push_starts_a_new_frame() {
+			let mut receiver = Receiver::<6>::new();
+			let mut pixels = [c::C_OFF; 6];
+			receiver.receive(&FIRST, &mut pixels).unwrap();
+			receiver.receive(&LAST, &mut pixels).unwrap();
+			//a shorter frame only counts its own LEDs, and the ones past it keep their colors:
+			assert_eq!(receiver.receive(&TIMECODE, &mut pixels), Ok(Some(3)));
+			assert_eq!(pixels[..3], [c::Color { r: 1, g: 2, b: 3 }, c::Color { r: 4, g: 5, b: 6 }, c::Color { r: 7, g: 8, b: 9 }]);
+			assert_eq!(pixels[3..], FRAME[3..]);
+			//a push on its own shows nothing new:
+			assert_eq!(receiver.receive(&PUSH_ONLY, &mut pixels), Ok(Some(0)));
+			//and data that ends part way through an LED still counts it:
+			let mut partial = FIRST;
+			partial[0] |= FLAG_PUSH;
+			partial[9] = 0x04;
+			assert_eq!(receiver.receive(&partial[..HEADER_LENGTH + 4], &mut pixels), Ok(Some(2)));
+		}
+
+		#[test]
+		fn data_past_the_end_is_ignored() {
+			let mut receiver = Receiver::<5>::new();
+			let mut pixels = [c::C_OFF; 5];
+			assert_eq!(receiver.receive(&FIRST, &mut pixels), Ok(None));
+			assert_eq!(receiver.receive(&LAST, &mut pixels), Ok(Some(5)));
+			assert_eq!(pixels, FRAME[..5]);
+			//a smaller pixels than the buffer only gets what fits:
+			let mut pixels = [c::C_OFF; 2];
+			assert_eq!(receiver.receive(&FIRST, &mut pixels), Ok(None));
+			assert_eq!(receiver.receive(&LAST, &mut pixels), Ok(Some(2)));
+			assert_eq!(pixels, FRAME[..2]);
+		}
+	}
+}
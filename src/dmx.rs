@@ -0,0 +1,259 @@
+pub mod dmx {
+	use crate::c;
+
+	//lighting consoles send LED colors as DMX universes of 512 channels, three channels an LED.
+	//an LED is never split between universes, so each universe holds at most 170 of them.
+	pub const CHANNELS_PER_UNIVERSE: usize = 512;
+	pub const PIXELS_PER_UNIVERSE: usize = CHANNELS_PER_UNIVERSE / 3;
+	//a FrameAssembler keeps track of this many universes:
+	pub const MAX_UNIVERSES: usize = 32;
+	//a sequence number up to this far behind the last one is a packet that arrived out of order:
+	pub const SEQUENCE_WINDOW: i8 = -20;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum UniverseError {
+		//the universe isn't one of the strip's
+		NotMapped(u16),
+		//the packet came in after a newer one for the same universe
+		OutOfOrder,
+	}
+
+	//where a strip's LEDs are in a run of universes. The first LED is at start_channel, counting
+	//from 1, of start_universe, and the LEDs carry on from channel 1 of each universe after that.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct UniverseMap {
+		pub start_universe: u16,
+		pub start_channel: u16,
+		pub led_count: usize,
+	}
+
+	impl UniverseMap {
+		pub const fn new(start_universe: u16, start_channel: u16, led_count: usize) -> Self {
+			UniverseMap {
+				start_universe,
+				start_channel,
+				led_count,
+			}
+		}
+
+		//how many channels of the first universe come before the strip:
+		fn first_offset(&self) -> usize {
+			(self.start_channel.max(1) as usize - 1).min(CHANNELS_PER_UNIVERSE - 3)
+		}
+
+		fn first_universe_pixels(&self) -> usize {
+			((CHANNELS_PER_UNIVERSE - self.first_offset()) / 3).min(PIXELS_PER_UNIVERSE)
+		}
+
+		//the number of universes the strip's LEDs take up:
+		pub fn universe_count(&self) -> usize {
+			let first_pixels = self.first_universe_pixels();
+			match self.led_count {
+				0 => 0,
+				led_count if led_count <= first_pixels => 1,
+				led_count => 1 + (led_count - first_pixels).div_ceil(PIXELS_PER_UNIVERSE),
+			}
+		}
+
+		//which of the strip's universes this is, counting from 0, or None if it isn't one of them:
+		pub fn universe_index(&self, universe: u16) -> Option<usize> {
+			let index = universe.checked_sub(self.start_universe)? as usize;
+			if index < self.universe_count() { Some(index) } else { None }
+		}
+
+		//the first LED in one of the strip's universes, how many LEDs it holds and the channel
+		//offset they start at:
+		fn universe_start(&self, index: usize) -> (usize, usize, usize) {
+			match index {
+				0 => (0, self.first_universe_pixels(), self.first_offset()),
+				index => (self.first_universe_pixels() + (index - 1) * PIXELS_PER_UNIVERSE, PIXELS_PER_UNIVERSE, 0),
+			}
+		}
+
+		//this copies a universe's channels onto the LEDs they cover, returning false if the
+		//universe isn't one of the strip's. Consoles can send fewer than 512 channels, in which
+		//case only the LEDs they reach are set. LEDs past the end of pixels are ignored.
+		pub fn write(&self, universe: u16, channels: &[u8], pixels: &mut [c::Color]) -> bool {
+			let index = match self.universe_index(universe) {
+				Some(index) => index,
+				None => return false,
+			};
+			let (first_pixel, pixel_count, offset) = self.universe_start(index);
+			let last_pixel = (first_pixel + pixel_count).min(self.led_count).min(pixels.len());
+			let channels = channels.get(offset..).unwrap_or(&[]);
+			for (pixel, rgb) in pixels[first_pixel.min(last_pixel)..last_pixel].iter_mut().zip(channels.chunks_exact(3)) {
+				*pixel = c::Color { r: rgb[0], g: rgb[1], b: rgb[2] };
+			}
+			true
+		}
+	}
+
+	//this puts universes that come in one packet at a time together into whole frames.
+	pub struct FrameAssembler {
+		pub map: UniverseMap,
+		//the last sequence number from each of the strip's universes:
+		sequences: [Option<u8>; MAX_UNIVERSES],
+		//the universes that have come in since the last frame, a bit each:
+		received: u32,
+	}
+
+	impl FrameAssembler {
+		pub fn new(map: UniverseMap) -> Self {
+			FrameAssembler {
+				map,
+				sequences: [None; MAX_UNIVERSES],
+				received: 0,
+			}
+		}
+
+		//this forgets the sequence numbers and the part of a frame that has come in, for when
+		//the sender changes:
+		pub fn reset(&mut self) {
+			self.sequences = [None; MAX_UNIVERSES];
+			self.received = 0;
+		}
+
+		//this writes a universe's channels straight into pixels. Once every one of the strip's
+		//universes has come in, the frame is done and the number of LEDs in it is returned.
+		//sequence is None for senders that don't number their packets.
+		pub fn write(&mut self, universe: u16, sequence: Option<u8>, channels: &[u8], pixels: &mut [c::Color]) -> Result<Option<usize>, UniverseError> {
+			let index = self.map.universe_index(universe)
+				.filter(|&index| index < MAX_UNIVERSES)
+				.ok_or(UniverseError::NotMapped(universe))?;
+			if let (Some(last), Some(sequence)) = (self.sequences[index], sequence) {
+				let difference = sequence.wrapping_sub(last) as i8;
+				if difference <= 0 && difference > SEQUENCE_WINDOW {
+					return Err(UniverseError::OutOfOrder);
+				}
+			}
+			self.sequences[index] = sequence;
+			self.map.write(universe, channels, pixels);
+			//a universe coming in twice before the others means some went missing, so the
+			//frame starts over from this one:
+			let bit = 1 << index;
+			if self.received & bit != 0 {
+				self.received = 0;
+			}
+			self.received |= bit;
+			let universe_count = self.map.universe_count().min(MAX_UNIVERSES);
+			match self.received == u32::MAX >> (32 - universe_count) {
+				true => {
+					self.received = 0;
+					Ok(Some(self.map.led_count.min(pixels.len())))
+				}
+				false => Ok(None),
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		//a universe where each LED's channels count up from its number:
+		fn universe(first_led: u8, channel_count: usize) -> Vec<u8> {
+			(0..channel_count).map(|channel| first_led.wrapping_add((channel / 3) as u8)).collect()
+		}
+
+		fn gray(level: u8) -> c::Color {
+			c::Color { r: level, g: level, b: level }
+		}
+
+		#[test]
+		fn universe_counts() {
+			let count = |start_channel, led_count| UniverseMap::new(0, start_channel, led_count).universe_count();
+			assert_eq!(count(1, 0), 0);
+			assert_eq!(count(1, 170), 1);
+			assert_eq!(count(1, 171), 2);
+			assert_eq!(count(1, 340), 2);
+			assert_eq!(count(1, 341), 3);
+			//starting a few channels in leaves room for one less LED in the first universe:
+			assert_eq!(count(4, 169), 1);
+			assert_eq!(count(4, 170), 2);
+			assert_eq!(count(0, 170), 1);
+			assert_eq!(count(600, 2), 2);
+		}
+
+		#[test]
+		fn universe_indexes() {
+			let map = UniverseMap::new(5, 1, 400);
+			assert_eq!(map.universe_index(4), None);
+			assert_eq!(map.universe_index(5), Some(0));
+			assert_eq!(map.universe_index(7), Some(2));
+			assert_eq!(map.universe_index(8), None);
+		}
+
+		#[test]
+		fn leds_carry_on_into_the_next_universe() {
+			let map = UniverseMap::new(1, 1, 172);
+			let mut pixels = [c::C_OFF; 172];
+			assert!(map.write(1, &universe(0, CHANNELS_PER_UNIVERSE), &mut pixels));
+			assert!(map.write(2, &universe(200, CHANNELS_PER_UNIVERSE), &mut pixels));
+			assert!(!map.write(3, &universe(0, CHANNELS_PER_UNIVERSE), &mut pixels));
+			assert!(!map.write(0, &universe(0, CHANNELS_PER_UNIVERSE), &mut pixels));
+			//the last two channels of a universe can't hold a whole LED, so they are skipped:
+			assert_eq!(pixels[169], gray(169));
+			assert_eq!(pixels[170..], [gray(200), gray(201)]);
+		}
+
+		#[test]
+		fn start_channel_offsets() {
+			let map = UniverseMap::new(0, 10, 172);
+			let mut pixels = [c::C_OFF; 172];
+			let mut channels = universe(0, CHANNELS_PER_UNIVERSE);
+			channels[9..12].copy_from_slice(&[1, 2, 3]);
+			map.write(0, &channels, &mut pixels);
+			assert_eq!(pixels[0], c::Color { r: 1, g: 2, b: 3 });
+			//(512 - 9) / 3 = 167 LEDs fit after the offset:
+			assert_eq!(pixels[166], gray(169));
+			assert_eq!(pixels[167], c::C_OFF);
+			//later universes start from channel 1:
+			map.write(1, &universe(100, 9), &mut pixels);
+			assert_eq!(pixels[167..170], [gray(100), gray(101), gray(102)]);
+			assert_eq!(pixels[170], c::C_OFF);
+		}
+
+		#[test]
+		fn short_universes_and_frames() {
+			let map = UniverseMap::new(0, 1, 10);
+			let mut pixels = [c::C_OFF; 4];
+			//a partial LED at the end is left alone, and so are LEDs past the end of pixels:
+			map.write(0, &universe(1, 8), &mut pixels);
+			assert_eq!(pixels, [gray(1), gray(2), c::C_OFF, c::C_OFF]);
+			map.write(0, &universe(1, CHANNELS_PER_UNIVERSE), &mut pixels);
+			assert_eq!(pixels, [gray(1), gray(2), gray(3), gray(4)]);
+		}
+
+		#[test]
+		fn frames_are_assembled() {
+			let mut assembler = FrameAssembler::new(UniverseMap::new(3, 1, 200));
+			let mut pixels = [c::C_OFF; 200];
+			let full = universe(0, CHANNELS_PER_UNIVERSE);
+			assert_eq!(assembler.write(4, Some(1), &full, &mut pixels), Ok(None));
+			assert_eq!(assembler.write(3, Some(1), &full, &mut pixels), Ok(Some(200)));
+			assert_eq!(assembler.write(2, Some(1), &full, &mut pixels), Err(UniverseError::NotMapped(2)));
+			//a universe coming in twice starts the frame over:
+			assert_eq!(assembler.write(3, Some(2), &full, &mut pixels), Ok(None));
+			assert_eq!(assembler.write(3, Some(3), &full, &mut pixels), Ok(None));
+			assert_eq!(assembler.write(4, Some(3), &full, &mut pixels), Ok(Some(200)));
+		}
+
+		#[test]
+		fn sequence_numbers() {
+			let mut assembler = FrameAssembler::new(UniverseMap::new(0, 1, 10));
+			let mut pixels = [c::C_OFF; 10];
+			let full = universe(0, 30);
+			assert_eq!(assembler.write(0, Some(250), &full, &mut pixels), Ok(Some(10)));
+			assert_eq!(assembler.write(0, Some(250), &full, &mut pixels), Err(UniverseError::OutOfOrder));
+			assert_eq!(assembler.write(0, Some(240), &full, &mut pixels), Err(UniverseError::OutOfOrder));
+			//wrapping around is fine, and so is a jump back far enough to be a restarted sender:
+			assert_eq!(assembler.write(0, Some(2), &full, &mut pixels), Ok(Some(10)));
+			assert_eq!(assembler.write(0, Some(200), &full, &mut pixels), Ok(Some(10)));
+			//unnumbered packets are always taken:
+			assert_eq!(assembler.write(0, None, &full, &mut pixels), Ok(Some(10)));
+			assert_eq!(assembler.write(0, Some(100), &full, &mut pixels), Ok(Some(10)));
+			assembler.reset();
+			assert_eq!(assembler.write(0, Some(99), &full, &mut pixels), Ok(Some(10)));
+		}
+	}
+}
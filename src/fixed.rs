@@ -0,0 +1,124 @@
+pub mod fixed {
+	//integer math helpers shared by the animations, so none of them need floating point.
+	//angles are u8 values where 256 is a full circle.
+
+	//a quarter of a sine wave, 0 to 90 degrees as 0 to 64, scaled to +/-127:
+	const QUARTER_SINE: [u8; 65] = [
+		0, 3, 6, 9, 12, 16, 19, 22, 25, 28, 31, 34, 37, 40, 43, 46,
+		49, 51, 54, 57, 60, 63, 65, 68, 71, 73, 76, 78, 81, 83, 85, 88,
+		90, 92, 94, 96, 98, 100, 102, 104, 106, 107, 109, 111, 112, 113, 115, 116,
+		117, 118, 120, 121, 122, 122, 123, 124, 125, 125, 126, 126, 126, 127, 127, 127,
+		127,
+	];
+
+	//sine of an angle, from -127 to 127:
+	pub fn sin_i8(angle: u8) -> i32 {
+		let quarter_index = (angle & 0x3F) as usize;
+		match angle >> 6 {
+			0 => QUARTER_SINE[quarter_index] as i32,
+			1 => QUARTER_SINE[64 - quarter_index] as i32,
+			2 => -(QUARTER_SINE[quarter_index] as i32),
+			_ => -(QUARTER_SINE[64 - quarter_index] as i32),
+		}
+	}
+
+	//cosine of an angle, from -127 to 127:
+	pub fn cos_i8(angle: u8) -> i32 {
+		sin_i8(angle.wrapping_add(64))
+	}
+
+	//sine of an angle shifted up to 0 to 254, centered on 127, for use as a brightness:
+	pub fn sin8(angle: u8) -> u8 {
+		(127 + sin_i8(angle)) as u8
+	}
+
+	//the angle from the origin to (x, y), counterclockwise from the x axis.
+	//this uses a polynomial approximation, good to within about a degree.
+	pub fn atan2_8(y: i32, x: i32) -> u8 {
+		if x == 0 && y == 0 {
+			return 0;
+		}
+		let (abs_x, abs_y) = (x.abs() as i64, y.abs() as i64);
+		//work out the angle in the first octant from the ratio of the smaller side to the larger:
+		let (small, large) = if abs_x >= abs_y { (abs_y, abs_x) } else { (abs_x, abs_y) };
+		let ratio = small * 256 / large;
+		//atan(r) in 1/256ths of a circle is about 32r + 11r(1 - r) for r from 0 to 1, rounded to
+		//the nearest step:
+		let mut angle = ((32 * ratio + 11 * ratio * (256 - ratio) / 256 + 128) / 256) as i32;
+		if abs_y > abs_x {
+			angle = 64 - angle;
+		}
+		if x < 0 {
+			angle = 128 - angle;
+		}
+		if y < 0 {
+			angle = 256 - angle;
+		}
+		angle as u8
+	}
+
+	//integer square root, rounded down:
+	pub fn isqrt(value: u64) -> u64 {
+		if value < 2 {
+			return value;
+		}
+		let mut root = value;
+		let mut next = root.div_ceil(2);
+		while next < root {
+			root = next;
+			next = (root + value / root) / 2;
+		}
+		root
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		#[test]
+		fn sines() {
+			assert_eq!((sin_i8(0), sin_i8(64), sin_i8(128), sin_i8(192)), (0, 127, 0, -127));
+			assert_eq!((cos_i8(0), cos_i8(64), cos_i8(128), cos_i8(192)), (127, 0, -127, 0));
+			assert_eq!((sin8(0), sin8(64), sin8(192)), (127, 254, 0));
+			for angle in 0..=255_u8 {
+				//the second half of the wave is the first half upside down, and each quarter mirrors the one before:
+				assert_eq!(sin_i8(angle), -sin_i8(angle.wrapping_add(128)), "{}", angle);
+				assert_eq!(sin_i8(angle), sin_i8(128_u8.wrapping_sub(angle)), "{}", angle);
+				let exact = (angle as f64 / 256.0 * core::f64::consts::TAU).sin() * 127.0;
+				assert!((sin_i8(angle) as f64 - exact).abs() <= 2.0, "{}", angle);
+			}
+		}
+
+		#[test]
+		fn angles() {
+			assert_eq!(atan2_8(0, 0), 0);
+			assert_eq!(atan2_8(0, 100), 0);
+			assert_eq!(atan2_8(100, 0), 64);
+			assert_eq!(atan2_8(0, -100), 128);
+			assert_eq!(atan2_8(-100, 0), 192);
+			assert_eq!(atan2_8(100, 100), 32);
+			assert_eq!(atan2_8(-100, -100), 160);
+			for y in (-1000..=1000).step_by(37) {
+				for x in (-1000..=1000).step_by(41) {
+					if x == 0 && y == 0 {
+						continue;
+					}
+					let exact = (y as f64).atan2(x as f64) / core::f64::consts::TAU * 256.0;
+					let error = (atan2_8(y, x) as f64 - exact).rem_euclid(256.0);
+					assert!(error <= 1.0 || error >= 255.0, "({}, {}) gave {}", x, y, atan2_8(y, x));
+				}
+			}
+		}
+
+		#[test]
+		fn square_roots() {
+			assert_eq!((isqrt(0), isqrt(1), isqrt(2), isqrt(3), isqrt(4)), (0, 1, 1, 1, 2));
+			assert_eq!((isqrt(15), isqrt(16), isqrt(17)), (3, 4, 4));
+			assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+			for value in (0..1_000_000_u64).step_by(997) {
+				let root = isqrt(value);
+				assert!(root * root <= value && (root + 1) * (root + 1) > value, "{}", value);
+			}
+		}
+	}
+}
@@ -0,0 +1,238 @@
+pub mod flash {
+	//the ESP32 ROM has routines for working with the SPI flash chip. They live at fixed addresses
+	//in the ROM, so they are called through function pointers instead of being linked in.
+	const ROM_SPIFLASH_UNLOCK: usize = 0x4006_28B0;
+	const ROM_SPIFLASH_ERASE_SECTOR: usize = 0x4006_2CCC;
+	const ROM_SPIFLASH_WRITE: usize = 0x4006_2D50;
+	const ROM_SPIFLASH_READ: usize = 0x4006_2ED8;
+
+	//and for the cache the firmware runs out of flash through, which has to be off while they run:
+	const ROM_CACHE_FLUSH: usize = 0x4000_9A14;
+	const ROM_CACHE_READ_ENABLE: usize = 0x4000_9A84;
+	const ROM_CACHE_READ_DISABLE: usize = 0x4000_9AB8;
+
+	//flash is erased a whole sector at a time, which sets every bit in it back to 1.
+	//writes can only change bits from 1 to 0.
+	pub const SECTOR_SIZE: u32 = 4096;
+
+	//where the strip configuration blob lives. This is the start of the last 64KB of a 4MB flash
+	//chip, well past the end of the firmware image.
+	pub const CONFIG_FLASH_ADDRESS: u32 = 0x3F_0000;
+
+	//the saved settings take the two sectors after the strip configuration:
+	pub const SETTINGS_FLASH_ADDRESS: u32 = CONFIG_FLASH_ADDRESS + SECTOR_SIZE;
+
+	//and the saved scenes take the sector after those:
+	pub const SCENES_FLASH_ADDRESS: u32 = SETTINGS_FLASH_ADDRESS + 2 * SECTOR_SIZE;
+
+	//the ROM reads and writes whole 32 bit words at a time, so data goes through a small word buffer:
+	const CHUNK_WORDS: usize = 16;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum FlashError {
+		//flash addresses need to be a multiple of 4 bytes, and erases need to start on a sector
+		Unaligned,
+		//the address is past the end of the flash
+		OutOfRange,
+		//the ROM routine returned this error code
+		Rom(i32),
+	}
+
+	//something flash-like that settings can be stored in, so the storage code can run against
+	//MemoryFlash on a computer as well as the real chip.
+	pub trait Flash {
+		fn read(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), FlashError>;
+		//this clears bits in flash to match data. Bits can only be set again by erasing the sector.
+		fn write(&mut self, address: u32, data: &[u8]) -> Result<(), FlashError>;
+		//this erases the sector starting at address:
+		fn erase_sector(&mut self, address: u32) -> Result<(), FlashError>;
+	}
+
+	//the SPI flash routines take over the bus the cache reads the firmware through, so the cache is
+	//turned off around them, the way ESP-IDF does it. Everything that runs while it is off has to be
+	//in instruction RAM, which the runtime links .rwtext into, so these are kept small and only call
+	//into the ROM. Only the PRO CPU is running and no interrupts are enabled, so nothing else can be
+	//running from flash while the cache is off.
+	#[inline(always)]
+	fn cache_disable() {
+		let rom_cache_read_disable: extern "C" fn(u32) = unsafe { core::mem::transmute(ROM_CACHE_READ_DISABLE) };
+		rom_cache_read_disable(0);
+	}
+
+	//the flash may have changed under the cache, so it is flushed before it is turned back on:
+	#[inline(always)]
+	fn cache_enable() {
+		let rom_cache_flush: extern "C" fn(u32) = unsafe { core::mem::transmute(ROM_CACHE_FLUSH) };
+		let rom_cache_read_enable: extern "C" fn(u32) = unsafe { core::mem::transmute(ROM_CACHE_READ_ENABLE) };
+		rom_cache_flush(0);
+		rom_cache_read_enable(0);
+	}
+
+	#[inline(never)]
+	#[link_section = ".rwtext"]
+	fn rom_read(address: u32, words: *mut u32, length: i32) -> i32 {
+		let rom_read: extern "C" fn(u32, *mut u32, i32) -> i32 = unsafe { core::mem::transmute(ROM_SPIFLASH_READ) };
+		cache_disable();
+		let result = rom_read(address, words, length);
+		cache_enable();
+		result
+	}
+
+	#[inline(never)]
+	#[link_section = ".rwtext"]
+	fn rom_write(address: u32, words: *const u32, length: i32) -> i32 {
+		let rom_unlock: extern "C" fn() -> i32 = unsafe { core::mem::transmute(ROM_SPIFLASH_UNLOCK) };
+		let rom_write: extern "C" fn(u32, *const u32, i32) -> i32 = unsafe { core::mem::transmute(ROM_SPIFLASH_WRITE) };
+		cache_disable();
+		let mut result = rom_unlock();
+		if result == 0 {
+			result = rom_write(address, words, length);
+		}
+		cache_enable();
+		result
+	}
+
+	#[inline(never)]
+	#[link_section = ".rwtext"]
+	fn rom_erase_sector(sector: u32) -> i32 {
+		let rom_unlock: extern "C" fn() -> i32 = unsafe { core::mem::transmute(ROM_SPIFLASH_UNLOCK) };
+		let rom_erase_sector: extern "C" fn(u32) -> i32 = unsafe { core::mem::transmute(ROM_SPIFLASH_ERASE_SECTOR) };
+		cache_disable();
+		let mut result = rom_unlock();
+		if result == 0 {
+			result = rom_erase_sector(sector);
+		}
+		cache_enable();
+		result
+	}
+
+	//this reads buffer.len() bytes of flash starting at address:
+	pub fn read(address: u32, buffer: &mut [u8]) -> Result<(), FlashError> {
+		if !address.is_multiple_of(4) {
+			return Err(FlashError::Unaligned);
+		}
+		let mut words = [0_u32; CHUNK_WORDS];
+		for (i, chunk) in buffer.chunks_mut(CHUNK_WORDS * 4).enumerate() {
+			let chunk_address = address + (i * CHUNK_WORDS * 4) as u32;
+			let word_count = chunk.len().div_ceil(4);
+			let result = rom_read(chunk_address, words.as_mut_ptr(), (word_count * 4) as i32);
+			if result != 0 {
+				return Err(FlashError::Rom(result));
+			}
+			for (j, byte) in chunk.iter_mut().enumerate() {
+				*byte = words[j / 4].to_le_bytes()[j % 4];
+			}
+		}
+		Ok(())
+	}
+
+	//this writes data to flash starting at address. A partial word at the end is padded with
+	//0xFF, which leaves the flash under it as it was.
+	pub fn write(address: u32, data: &[u8]) -> Result<(), FlashError> {
+		if !address.is_multiple_of(4) {
+			return Err(FlashError::Unaligned);
+		}
+		let mut words = [0_u32; CHUNK_WORDS];
+		for (i, chunk) in data.chunks(CHUNK_WORDS * 4).enumerate() {
+			let chunk_address = address + (i * CHUNK_WORDS * 4) as u32;
+			let word_count = chunk.len().div_ceil(4);
+			for (j, word) in words.iter_mut().take(word_count).enumerate() {
+				let mut bytes = [0xFF_u8; 4];
+				for (k, byte) in bytes.iter_mut().enumerate() {
+					if let Some(&value) = chunk.get(j * 4 + k) {
+						*byte = value;
+					}
+				}
+				*word = u32::from_le_bytes(bytes);
+			}
+			let result = rom_write(chunk_address, words.as_ptr(), (word_count * 4) as i32);
+			if result != 0 {
+				return Err(FlashError::Rom(result));
+			}
+		}
+		Ok(())
+	}
+
+	//this erases the sector starting at address:
+	pub fn erase_sector(address: u32) -> Result<(), FlashError> {
+		if !address.is_multiple_of(SECTOR_SIZE) {
+			return Err(FlashError::Unaligned);
+		}
+		match rom_erase_sector(address / SECTOR_SIZE) {
+			0 => Ok(()),
+			result => Err(FlashError::Rom(result)),
+		}
+	}
+
+	//the flash chip on the ESP32, through the ROM routines:
+	pub struct RomFlash;
+
+	impl Flash for RomFlash {
+		fn read(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), FlashError> {
+			read(address, buffer)
+		}
+
+		fn write(&mut self, address: u32, data: &[u8]) -> Result<(), FlashError> {
+			write(address, data)
+		}
+
+		fn erase_sector(&mut self, address: u32) -> Result<(), FlashError> {
+			erase_sector(address)
+		}
+	}
+
+	//a stand-in for a piece of flash starting at base_address, kept in memory. It follows the same
+	//rules as the real chip, so writes can only clear bits, and it starts out erased.
+	pub struct MemoryFlash<const SIZE: usize> {
+		pub base_address: u32,
+		pub data: [u8; SIZE],
+	}
+
+	impl<const SIZE: usize> MemoryFlash<SIZE> {
+		pub fn new(base_address: u32) -> Self {
+			MemoryFlash {
+				base_address,
+				data: [0xFF; SIZE],
+			}
+		}
+
+		//this gets the range of data an access covers, checking it is inside the flash:
+		fn range(&self, address: u32, length: usize) -> Result<core::ops::Range<usize>, FlashError> {
+			if !address.is_multiple_of(4) {
+				return Err(FlashError::Unaligned);
+			}
+			let start = address.checked_sub(self.base_address).ok_or(FlashError::OutOfRange)? as usize;
+			if start + length > SIZE {
+				return Err(FlashError::OutOfRange);
+			}
+			Ok(start..start + length)
+		}
+	}
+
+	impl<const SIZE: usize> Flash for MemoryFlash<SIZE> {
+		fn read(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), FlashError> {
+			let range = self.range(address, buffer.len())?;
+			buffer.copy_from_slice(&self.data[range]);
+			Ok(())
+		}
+
+		fn write(&mut self, address: u32, data: &[u8]) -> Result<(), FlashError> {
+			let range = self.range(address, data.len())?;
+			for (byte, &value) in self.data[range].iter_mut().zip(data) {
+				*byte &= value;
+			}
+			Ok(())
+		}
+
+		fn erase_sector(&mut self, address: u32) -> Result<(), FlashError> {
+			if !address.is_multiple_of(SECTOR_SIZE) {
+				return Err(FlashError::Unaligned);
+			}
+			let range = self.range(address, SECTOR_SIZE as usize)?;
+			for byte in self.data[range].iter_mut() {
+				*byte = 0xFF;
+			}
+			Ok(())
+		}
+	}
+}
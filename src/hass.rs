@@ -0,0 +1,566 @@
+pub mod hass {
+	use crate::a;
+	use crate::c;
+	use crate::ctl;
+	use crate::js;
+	use crate::mq;
+	use core::fmt::{self, Write};
+
+	//the lights as a Home Assistant MQTT light, with the JSON schema. Home Assistant finds the
+	//light from the discovery config, sends commands as JSON on the command topic and shows
+	//what the state topic says. The topics for a light with the ID <id> are:
+	//  homeassistant/light/<id>/config  the discovery config, retained
+	//  ws2811/<id>/state                the state, retained
+	//  ws2811/<id>/set                  commands from Home Assistant
+	//  ws2811/<id>/status               "online", or "offline" from the will once the light is gone, retained
+	//  homeassistant/status             Home Assistant says "online" here when it starts, and
+	//                                   the config and state are sent again
+	//a command looks like:
+	//  {"state":"ON", "brightness":128, "color":{"r":255,"g":160,"b":0}, "color_temp":2700, "effect":"rainbow"}
+	//color temperatures are in Kelvin, not mireds.
+	pub const DISCOVERY_PREFIX: &str = "homeassistant";
+	const TOPIC_PREFIX: &str = "ws2811";
+	pub const HASS_STATUS_TOPIC: &str = "homeassistant/status";
+	const ONLINE: &str = "online";
+	const OFFLINE: &str = "offline";
+	//IDs are up to this long, so every topic fits in MAX_TOPIC_LENGTH:
+	pub const MAX_ID_LENGTH: usize = 32;
+	pub const MAX_TOPIC_LENGTH: usize = 64;
+	//the broker drops the light if it doesn't hear from it for this long, and the light gives up
+	//on the broker the same way:
+	pub const KEEP_ALIVE_S: u16 = 60;
+	//the most commands one message turns into:
+	pub const MAX_COMMANDS: usize = 3;
+
+	//what is waiting to be sent, a bit each, sent lowest bit first:
+	const SEND_DISCOVERY: u8 = 0x01;
+	const SEND_ONLINE: u8 = 0x02;
+	const SEND_SUBSCRIBE: u8 = 0x04;
+	const SEND_STATE: u8 = 0x08;
+	const SEND_PING: u8 = 0x10;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum HassError {
+		//IDs are 1 to MAX_ID_LENGTH letters, digits, underscores and dashes
+		BadId,
+		Mqtt(mq::MqttError),
+		//the broker refused the connection, with this return code
+		Refused(u8),
+		SubscriptionFailed,
+		//the broker didn't answer a connect or a ping in time, so the connection should be dropped
+		TimedOut,
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum CommandError {
+		NotUtf8,
+		Json(js::JsonError),
+		NotAnObject,
+		UnknownEffect,
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Topic {
+		Discovery,
+		State,
+		Command,
+		Availability,
+	}
+
+	//a topic, written out:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct TopicName {
+		bytes: [u8; MAX_TOPIC_LENGTH],
+		length: usize,
+	}
+
+	impl TopicName {
+		pub fn as_str(&self) -> &str {
+			core::str::from_utf8(&self.bytes[..self.length]).unwrap_or("")
+		}
+	}
+
+	impl fmt::Write for TopicName {
+		fn write_str(&mut self, text: &str) -> fmt::Result {
+			let end = self.length + text.len();
+			self.bytes.get_mut(self.length..end).ok_or(fmt::Error)?.copy_from_slice(text.as_bytes());
+			self.length = end;
+			Ok(())
+		}
+	}
+
+	//the light as Home Assistant sees it. The ID goes in the topics and picks out the light in
+	//Home Assistant, so it should stay the same, like one made from the MAC address.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Light<'a> {
+		id: &'a str,
+		pub name: &'a str,
+	}
+
+	impl<'a> Light<'a> {
+		pub fn new(id: &'a str, name: &'a str) -> Result<Self, HassError> {
+			let valid = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-';
+			match id.len() {
+				1..=MAX_ID_LENGTH if id.bytes().all(valid) => Ok(Light { id, name }),
+				_ => Err(HassError::BadId),
+			}
+		}
+
+		pub fn id(&self) -> &'a str {
+			self.id
+		}
+
+		pub fn topic(&self, topic: Topic) -> TopicName {
+			let mut name = TopicName { bytes: [0; MAX_TOPIC_LENGTH], length: 0 };
+			//the ID is short enough that these always fit:
+			let _ = match topic {
+				Topic::Discovery => write!(name, "{}/light/{}/config", DISCOVERY_PREFIX, self.id),
+				Topic::State => write!(name, "{}/{}/state", TOPIC_PREFIX, self.id),
+				Topic::Command => write!(name, "{}/{}/set", TOPIC_PREFIX, self.id),
+				Topic::Availability => write!(name, "{}/{}/status", TOPIC_PREFIX, self.id),
+			};
+			name
+		}
+	}
+
+	//what the lights are doing now, for the state topic:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct State {
+		pub on: bool,
+		pub brightness: u8,
+		pub effect: a::EffectParams,
+		//the color temperature a solid color was made from, if it was made from one
+		pub kelvin: Option<u16>,
+	}
+
+	//this writes the discovery config, which tells Home Assistant what the light can do and
+	//where its topics are:
+	pub fn write_discovery<W: fmt::Write>(out: &mut W, light: &Light) -> fmt::Result {
+		//the entity has no name of its own, so it is called by the device's name:
+		write!(out, "{{\"name\":null,\"unique_id\":\"{}\",\"schema\":\"json\",", light.id)?;
+		for &(key, topic) in [
+			("state_topic", Topic::State),
+			("command_topic", Topic::Command),
+			("availability_topic", Topic::Availability),
+		].iter() {
+			write!(out, "\"{}\":", key)?;
+			js::write_string(out, light.topic(topic).as_str())?;
+			out.write_char(',')?;
+		}
+		out.write_str("\"brightness\":true,\"supported_color_modes\":[\"rgb\",\"color_temp\"],")?;
+		write!(out, "\"color_temp_kelvin\":true,\"min_kelvin\":{},\"max_kelvin\":{},", c::KELVIN_MIN, c::KELVIN_MAX)?;
+		out.write_str("\"effect\":true,\"effect_list\":[")?;
+		for (index, effect) in a::EFFECTS.iter().enumerate() {
+			if index > 0 {
+				out.write_char(',')?;
+			}
+			js::write_string(out, effect.name())?;
+		}
+		write!(out, "],\"device\":{{\"identifiers\":[\"{}\"],\"name\":", light.id)?;
+		js::write_string(out, light.name)?;
+		out.write_str(",\"manufacturer\":\"ws2811 office lights\",\"model\":\"ESP32\"}}")
+	}
+
+	//this writes the state, for the state topic:
+	pub fn write_state<W: fmt::Write>(out: &mut W, state: &State) -> fmt::Result {
+		write!(out, "{{\"state\":\"{}\",\"brightness\":{},", if state.on { "ON" } else { "OFF" }, state.brightness)?;
+		match state.kelvin.filter(|_| state.effect.effect == a::Effect::Solid) {
+			Some(kelvin) => write!(out, "\"color_mode\":\"color_temp\",\"color_temp\":{},", kelvin)?,
+			None => {
+				let color = state.effect.color;
+				write!(out, "\"color_mode\":\"rgb\",\"color\":{{\"r\":{},\"g\":{},\"b\":{}}},", color.r, color.g, color.b)?;
+			}
+		}
+		out.write_str("\"effect\":")?;
+		js::write_string(out, state.effect.effect.name())?;
+		out.write_char('}')
+	}
+
+	//this turns a command from Home Assistant into the commands that carry it out, in the order
+	//to run them. Picking an effect turns the lights on, so on and off go last.
+	pub fn parse_command(payload: &[u8]) -> Result<[Option<ctl::Command<'static>>; MAX_COMMANDS], CommandError> {
+		let text = core::str::from_utf8(payload).map_err(|_| CommandError::NotUtf8)?;
+		let command = js::parse(text).map_err(CommandError::Json)?.as_object().ok_or(CommandError::NotAnObject)?;
+		let mut commands = [None; MAX_COMMANDS];
+
+		let effect = match command.get("effect") {
+			Some(effect) => Some(effect.as_str().and_then(a::Effect::from_name).ok_or(CommandError::UnknownEffect)?),
+			None => None,
+		};
+		let color = command.get("color").and_then(|color| color.as_object()).and_then(|rgb| {
+			Some(c::Color {
+				r: rgb.get("r")?.as_u8()?,
+				g: rgb.get("g")?.as_u8()?,
+				b: rgb.get("b")?.as_u8()?,
+			})
+		});
+		let kelvin = command.get("color_temp").and_then(|kelvin| kelvin.as_i64()).map(|kelvin| kelvin.max(0).min(u16::MAX as i64) as u16);
+		commands[0] = match (effect, color, kelvin) {
+			(Some(effect), color, _) => Some(ctl::Command::Effect { effect, options: ctl::EffectOptions { color, ..ctl::EffectOptions::default() } }),
+			(None, Some(color), _) => Some(ctl::Command::Color(color)),
+			(None, None, Some(kelvin)) => Some(ctl::Command::Kelvin(kelvin)),
+			(None, None, None) => None,
+		};
+		commands[1] = command.get("brightness").and_then(|brightness| brightness.as_u8()).map(ctl::Command::Brightness);
+		commands[2] = match command.get("state").and_then(|state| state.as_str()) {
+			Some("ON") => Some(ctl::Command::On),
+			Some("OFF") => Some(ctl::Command::Off),
+			_ => None,
+		};
+		Ok(commands)
+	}
+
+	//what a packet from the broker turned out to need:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Event {
+		Nothing,
+		//the broker accepted the connection, and the light is being announced
+		Connected,
+		Commands([Option<ctl::Command<'static>>; MAX_COMMANDS]),
+		//a command came in that couldn't be understood
+		BadCommand(CommandError),
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	enum Connection {
+		Disconnected,
+		Connecting { since_ms: u32 },
+		Connected,
+	}
+
+	//keeps a light's connection to the broker going. The network side opens the TCP connection
+	//and sends what connect writes, hands whatever comes back to receive, and sends what poll
+	//writes every frame until it has nothing more. Once the broker is connected, the light
+	//announces itself, says it is online, subscribes to its commands and publishes its state.
+	pub struct Client<'a> {
+		pub light: Light<'a>,
+		connection: Connection,
+		pending: u8,
+		last_sent_ms: u32,
+		//when the ping being waited on was sent:
+		ping_sent_ms: Option<u32>,
+		packet_id: u16,
+	}
+
+	impl<'a> Client<'a> {
+		pub fn new(light: Light<'a>) -> Self {
+			Client {
+				light,
+				connection: Connection::Disconnected,
+				pending: 0,
+				last_sent_ms: 0,
+				ping_sent_ms: None,
+				packet_id: 0,
+			}
+		}
+
+		pub fn is_connected(&self) -> bool {
+			self.connection == Connection::Connected
+		}
+
+		//this writes the CONNECT for a new connection to the broker, with a will that marks the
+		//light offline if the connection drops:
+		pub fn connect(&mut self, username: Option<&str>, password: Option<&[u8]>, now_ms: u32, packet: &mut [u8]) -> Result<usize, HassError> {
+			let availability = self.light.topic(Topic::Availability);
+			let connect = mq::Connect {
+				client_id: self.light.id,
+				keep_alive_s: KEEP_ALIVE_S,
+				username,
+				password,
+				will: Some(mq::Message { topic: availability.as_str(), payload: OFFLINE.as_bytes(), retain: true }),
+			};
+			let length = mq::write_connect(packet, &connect).map_err(HassError::Mqtt)?;
+			self.connection = Connection::Connecting { since_ms: now_ms };
+			self.pending = 0;
+			self.last_sent_ms = now_ms;
+			self.ping_sent_ms = None;
+			Ok(length)
+		}
+
+		//this queues the state to be published again, for when the lights change:
+		pub fn state_changed(&mut self) {
+			self.pending |= SEND_STATE;
+		}
+
+		//this reads the packet at the start of bytes, returning what it needs and its length, or
+		//None if it hasn't all come in yet.
+		pub fn receive(&mut self, bytes: &[u8]) -> Result<Option<(Event, usize)>, HassError> {
+			let (packet, length) = match mq::read_packet(bytes).map_err(HassError::Mqtt)? {
+				Some(packet) => packet,
+				None => return Ok(None),
+			};
+			let event = match packet {
+				mq::Packet::ConnAck { return_code: 0, .. } => {
+					self.connection = Connection::Connected;
+					self.pending = SEND_DISCOVERY | SEND_ONLINE | SEND_SUBSCRIBE | SEND_STATE;
+					Event::Connected
+				}
+				mq::Packet::ConnAck { return_code, .. } => {
+					self.connection = Connection::Disconnected;
+					return Err(HassError::Refused(return_code));
+				}
+				mq::Packet::Publish(message) if message.topic == self.light.topic(Topic::Command).as_str() => {
+					match parse_command(message.payload) {
+						Ok(commands) => Event::Commands(commands),
+						Err(error) => Event::BadCommand(error),
+					}
+				}
+				//Home Assistant forgets lights it doesn't have retained configs for when it restarts:
+				mq::Packet::Publish(message) if message.topic == HASS_STATUS_TOPIC && message.payload == ONLINE.as_bytes() => {
+					self.pending |= SEND_DISCOVERY | SEND_STATE;
+					Event::Nothing
+				}
+				mq::Packet::SubAck { return_codes, .. } if return_codes.contains(&mq::SUBSCRIPTION_FAILED) => {
+					return Err(HassError::SubscriptionFailed);
+				}
+				mq::Packet::PingResp => {
+					self.ping_sent_ms = None;
+					Event::Nothing
+				}
+				_ => Event::Nothing,
+			};
+			Ok(Some((event, length)))
+		}
+
+		//this writes the next packet that needs sending, if there is one. An error means the
+		//broker has stopped answering, and the connection should be dropped and opened again.
+		pub fn poll(&mut self, state: &State, now_ms: u32, packet: &mut [u8]) -> Result<Option<usize>, HassError> {
+			let timeout_ms = KEEP_ALIVE_S as u32 * 1000;
+			match self.connection {
+				Connection::Disconnected => return Ok(None),
+				Connection::Connecting { since_ms } => {
+					if now_ms.wrapping_sub(since_ms) > timeout_ms {
+						self.connection = Connection::Disconnected;
+						return Err(HassError::TimedOut);
+					}
+					return Ok(None);
+				}
+				Connection::Connected => {}
+			}
+			if self.ping_sent_ms.is_some_and(|sent_ms| now_ms.wrapping_sub(sent_ms) > timeout_ms) {
+				self.connection = Connection::Disconnected;
+				return Err(HassError::TimedOut);
+			}
+			//pinging at half the keep alive leaves time for the answer:
+			if self.ping_sent_ms.is_none() && now_ms.wrapping_sub(self.last_sent_ms) >= timeout_ms / 2 {
+				self.pending |= SEND_PING;
+			}
+			let next = self.pending & self.pending.wrapping_neg();
+			let length = match next {
+				0 => return Ok(None),
+				SEND_DISCOVERY => mq::write_publish_with(packet, self.light.topic(Topic::Discovery).as_str(), true, |out| {
+					write_discovery(out, &self.light)
+				}),
+				SEND_ONLINE => mq::write_publish(packet, &mq::Message {
+					topic: self.light.topic(Topic::Availability).as_str(),
+					payload: ONLINE.as_bytes(),
+					retain: true,
+				}),
+				SEND_SUBSCRIBE => {
+					self.packet_id = self.packet_id.wrapping_add(1).max(1);
+					mq::write_subscribe(packet, self.packet_id, &[self.light.topic(Topic::Command).as_str(), HASS_STATUS_TOPIC])
+				}
+				SEND_STATE => mq::write_publish_with(packet, self.light.topic(Topic::State).as_str(), true, |out| write_state(out, state)),
+				_ => {
+					self.ping_sent_ms = Some(now_ms);
+					mq::write_ping(packet)
+				}
+			}
+			.map_err(HassError::Mqtt)?;
+			self.pending &= !next;
+			self.last_sent_ms = now_ms;
+			Ok(Some(length))
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use std::io::{Read, Write as _};
+		use std::net::{TcpListener, TcpStream};
+
+		const STATE: State = State {
+			on: true,
+			brightness: 128,
+			effect: a::EffectParams::solid(c::Color { r: 255, g: 160, b: 0 }),
+			kelvin: None,
+		};
+
+		//a packet as the broker stand-in reads it: the first byte, and the fields after the fixed header
+		type BrokerPacket = (u8, Vec<u8>);
+
+		fn read_client_packet(stream: &mut TcpStream) -> BrokerPacket {
+			let mut byte = [0_u8; 1];
+			stream.read_exact(&mut byte).unwrap();
+			let first_byte = byte[0];
+			let (mut remaining_length, mut shift) = (0, 0);
+			loop {
+				stream.read_exact(&mut byte).unwrap();
+				remaining_length |= (byte[0] as usize & 0x7F) << shift;
+				shift += 7;
+				if byte[0] & 0x80 == 0 {
+					break;
+				}
+			}
+			let mut fields = vec![0_u8; remaining_length];
+			stream.read_exact(&mut fields).unwrap();
+			(first_byte, fields)
+		}
+
+		//the topic and payload of a PUBLISH the client sent:
+		fn published(packet: &BrokerPacket) -> (bool, &str, &str) {
+			let (first_byte, fields) = packet;
+			assert_eq!(first_byte >> 4, 3, "not a publish");
+			let topic_length = u16::from_be_bytes([fields[0], fields[1]]) as usize;
+			let topic = core::str::from_utf8(&fields[2..2 + topic_length]).unwrap();
+			let payload = core::str::from_utf8(&fields[2 + topic_length..]).unwrap();
+			(first_byte & 0x01 != 0, topic, payload)
+		}
+
+		#[test]
+		fn topics_and_ids() {
+			let light = Light::new("office_1", "Office").unwrap();
+			assert_eq!(light.topic(Topic::Discovery).as_str(), "homeassistant/light/office_1/config");
+			assert_eq!(light.topic(Topic::State).as_str(), "ws2811/office_1/state");
+			assert_eq!(light.topic(Topic::Command).as_str(), "ws2811/office_1/set");
+			assert_eq!(light.topic(Topic::Availability).as_str(), "ws2811/office_1/status");
+			assert_eq!(Light::new("", "Office"), Err(HassError::BadId));
+			assert_eq!(Light::new("office/1", "Office"), Err(HassError::BadId));
+			assert!(Light::new(&"x".repeat(MAX_ID_LENGTH), "Office").is_ok());
+			assert_eq!(Light::new(&"x".repeat(MAX_ID_LENGTH + 1), "Office"), Err(HassError::BadId));
+		}
+
+		#[test]
+		fn commands() {
+			let commands = parse_command(b"{\"state\":\"ON\",\"brightness\":40,\"color_temp\":2700}").unwrap();
+			assert_eq!(commands, [Some(ctl::Command::Kelvin(2700)), Some(ctl::Command::Brightness(40)), Some(ctl::Command::On)]);
+			let commands = parse_command(b"{\"effect\":\"rainbow\",\"color\":{\"r\":1,\"g\":2,\"b\":3}}").unwrap();
+			assert_eq!(commands[0], Some(ctl::Command::Effect {
+				effect: a::Effect::Rainbow,
+				options: ctl::EffectOptions { color: Some(c::Color { r: 1, g: 2, b: 3 }), ..ctl::EffectOptions::default() },
+			}));
+			assert_eq!(parse_command(b"{\"state\":\"OFF\"}").unwrap(), [None, None, Some(ctl::Command::Off)]);
+			assert_eq!(parse_command(b"{\"effect\":\"disco\"}"), Err(CommandError::UnknownEffect));
+			assert_eq!(parse_command(b"[]"), Err(CommandError::NotAnObject));
+			assert_eq!(parse_command(&[0xFF]), Err(CommandError::NotUtf8));
+		}
+
+		#[test]
+		fn state() {
+			let mut out = String::new();
+			write_state(&mut out, &STATE).unwrap();
+			assert_eq!(out, "{\"state\":\"ON\",\"brightness\":128,\"color_mode\":\"rgb\",\"color\":{\"r\":255,\"g\":160,\"b\":0},\"effect\":\"solid\"}");
+			out.clear();
+			write_state(&mut out, &State { on: false, kelvin: Some(2700), ..STATE }).unwrap();
+			assert_eq!(out, "{\"state\":\"OFF\",\"brightness\":128,\"color_mode\":\"color_temp\",\"color_temp\":2700,\"effect\":\"solid\"}");
+			out.clear();
+			write_discovery(&mut out, &Light::new("office", "Office").unwrap()).unwrap();
+			let discovery = js::parse(&out).unwrap().as_object().unwrap();
+			assert_eq!(discovery.get("command_topic").and_then(|topic| topic.as_str()), Some("ws2811/office/set"));
+			assert_eq!(discovery.get("effect_list").unwrap().as_array().unwrap().iter().count(), a::EFFECTS.len());
+		}
+
+		//the client connects to a broker stand-in over TCP on the loopback interface, and announces itself:
+		#[test]
+		fn connects_to_a_broker() {
+			let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+			let address = listener.local_addr().unwrap();
+			let broker = std::thread::spawn(move || {
+				let (mut stream, _) = listener.accept().unwrap();
+				let mut packets = vec![read_client_packet(&mut stream)];
+				//CONNACK, sent in two pieces to check the client waits for the rest:
+				stream.write_all(&[0x20, 0x02]).unwrap();
+				stream.flush().unwrap();
+				std::thread::sleep(std::time::Duration::from_millis(20));
+				stream.write_all(&[0x00, 0x00]).unwrap();
+				while packets.len() < 5 {
+					packets.push(read_client_packet(&mut stream));
+				}
+				//SUBACK for both topics, then a command from Home Assistant:
+				let (_, subscribe) = &packets[3];
+				stream.write_all(&[0x90, 0x04, subscribe[0], subscribe[1], 0x00, 0x00]).unwrap();
+				let mut command = [0_u8; 64];
+				let length = mq::write_publish(&mut command, &mq::Message { topic: "ws2811/office/set", payload: b"{\"state\":\"OFF\"}", retain: false }).unwrap();
+				stream.write_all(&command[..length]).unwrap();
+				packets
+			});
+
+			let mut client = Client::new(Light::new("office", "Office").unwrap());
+			let mut stream = TcpStream::connect(address).unwrap();
+			let mut packet = [0_u8; 1024];
+			let length = client.connect(None, None, 0, &mut packet).unwrap();
+			stream.write_all(&packet[..length]).unwrap();
+			assert!(!client.is_connected());
+			assert_eq!(client.poll(&STATE, 10, &mut packet), Ok(None));
+
+			//whatever comes in is handed to the client until the whole command has come in:
+			let mut received = Vec::new();
+			let mut events = Vec::new();
+			let mut now_ms = 10;
+			while !events.iter().any(|event| matches!(event, Event::Commands(_))) {
+				let mut bytes = [0_u8; 64];
+				let count = stream.read(&mut bytes).unwrap();
+				assert!(count > 0, "the broker hung up");
+				received.extend_from_slice(&bytes[..count]);
+				while let Some((event, length)) = client.receive(&received).unwrap() {
+					received.drain(..length);
+					events.push(event);
+				}
+				now_ms += 10;
+				while let Some(length) = client.poll(&STATE, now_ms, &mut packet).unwrap() {
+					stream.write_all(&packet[..length]).unwrap();
+				}
+			}
+			assert!(client.is_connected());
+			assert_eq!(events[0], Event::Connected);
+			assert_eq!(events.last(), Some(&Event::Commands([None, None, Some(ctl::Command::Off)])));
+
+			let packets = broker.join().unwrap();
+			let (connect_byte, connect) = &packets[0];
+			assert_eq!(*connect_byte, 0x10);
+			//the will marks the light offline, retained:
+			assert_eq!(connect[7], 0x02 | 0x04 | 0x20);
+			let will = b"\x00\x14ws2811/office/status\x00\x07offline";
+			assert!(connect.windows(will.len()).any(|window| window == will));
+			let (retain, topic, payload) = published(&packets[1]);
+			assert!(retain);
+			assert_eq!(topic, "homeassistant/light/office/config");
+			assert!(payload.starts_with("{\"name\":null,\"unique_id\":\"office\""));
+			assert_eq!(published(&packets[2]), (true, "ws2811/office/status", "online"));
+			let (subscribe_byte, subscribe) = &packets[3];
+			assert_eq!(*subscribe_byte, 0x82);
+			assert_eq!(subscribe[2..], b"\x00\x11ws2811/office/set\x00\x00\x14homeassistant/status\x00"[..]);
+			let (retain, topic, payload) = published(&packets[4]);
+			assert_eq!((retain, topic), (true, "ws2811/office/state"));
+			let mut state = String::new();
+			write_state(&mut state, &STATE).unwrap();
+			assert_eq!(payload, state);
+		}
+
+		#[test]
+		fn pings_and_timeouts() {
+			let mut client = Client::new(Light::new("office", "Office").unwrap());
+			let mut packet = [0_u8; 1024];
+			let timeout_ms = KEEP_ALIVE_S as u32 * 1000;
+			client.connect(None, None, 0, &mut packet).unwrap();
+			//a broker that never answers the connect:
+			assert_eq!(client.poll(&STATE, timeout_ms + 1, &mut packet), Err(HassError::TimedOut));
+			client.connect(None, None, 0, &mut packet).unwrap();
+			assert_eq!(client.receive(&[0x20, 0x02, 0x00, 0x05]), Err(HassError::Refused(5)));
+			assert!(!client.is_connected());
+
+			client.connect(None, None, 0, &mut packet).unwrap();
+			assert_eq!(client.receive(&[0x20, 0x02, 0x00, 0x00]), Ok(Some((Event::Connected, 4))));
+			while client.poll(&STATE, 0, &mut packet).unwrap().is_some() {}
+			//half way through the keep alive, a ping goes out:
+			assert_eq!(client.poll(&STATE, timeout_ms / 2 - 1, &mut packet), Ok(None));
+			assert_eq!(client.poll(&STATE, timeout_ms / 2, &mut packet), Ok(Some(2)));
+			assert_eq!(packet[..2], [0xC0, 0x00]);
+			assert_eq!(client.receive(&[0xD0, 0x00]), Ok(Some((Event::Nothing, 2))));
+			//one that isn't answered drops the connection:
+			assert_eq!(client.poll(&STATE, timeout_ms, &mut packet), Ok(Some(2)));
+			assert_eq!(client.poll(&STATE, timeout_ms * 2 + 1, &mut packet), Err(HassError::TimedOut));
+			assert!(!client.is_connected());
+		}
+	}
+}
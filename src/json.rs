@@ -0,0 +1,472 @@
+pub mod json {
+	use core::fmt;
+
+	//a small JSON reader for the network APIs that doesn't need an allocator. Parsing checks the
+	//whole document and hands back values that point into the text, and objects and arrays are
+	//read by walking over their text again when they are looked at. Writing is done with
+	//fmt::Write, with write_string for the strings.
+
+	//objects and arrays can't be nested deeper than this:
+	pub const MAX_DEPTH: usize = 16;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum JsonError {
+		//the text ends in the middle of a value
+		UnexpectedEnd,
+		//the byte at this offset into the text isn't what should be there
+		Unexpected(usize),
+		TooDeep,
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Value<'a> {
+		Null,
+		Bool(bool),
+		//the number as it was written
+		Number(&'a str),
+		//the string between the quotes, with any escapes left in
+		String(&'a str),
+		Object(Object<'a>),
+		Array(Array<'a>),
+	}
+
+	//the text of an object, from its opening brace to its closing one:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Object<'a> {
+		text: &'a str,
+	}
+
+	//the text of an array, from its opening bracket to its closing one:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Array<'a> {
+		text: &'a str,
+	}
+
+	//this parses a whole document, which is one value with nothing but whitespace around it:
+	pub fn parse(text: &str) -> Result<Value<'_>, JsonError> {
+		let mut scanner = Scanner { text, position: 0 };
+		let value = scanner.value(0)?;
+		scanner.skip_whitespace();
+		match scanner.position < text.len() {
+			true => Err(JsonError::Unexpected(scanner.position)),
+			false => Ok(value),
+		}
+	}
+
+	impl<'a> Value<'a> {
+		pub fn as_bool(&self) -> Option<bool> {
+			match *self {
+				Value::Bool(value) => Some(value),
+				_ => None,
+			}
+		}
+
+		//the whole number part of a number, or None if it doesn't fit in an i64. Exponents
+		//aren't worked out, so 1e3 is 1.
+		pub fn as_i64(&self) -> Option<i64> {
+			let text = match *self {
+				Value::Number(text) => text,
+				_ => return None,
+			};
+			let (negative, digits) = match text.strip_prefix('-') {
+				Some(digits) => (true, digits),
+				None => (false, text),
+			};
+			//negative numbers are counted down so i64::MIN fits:
+			let mut number: i64 = 0;
+			for byte in digits.bytes().take_while(u8::is_ascii_digit) {
+				let digit = (byte - b'0') as i64;
+				number = match negative {
+					true => number.checked_mul(10)?.checked_sub(digit)?,
+					false => number.checked_mul(10)?.checked_add(digit)?,
+				};
+			}
+			Some(number)
+		}
+
+		//a number, clamped to fit in a u8:
+		pub fn as_u8(&self) -> Option<u8> {
+			self.as_i64().map(|number| number.clamp(0, 255) as u8)
+		}
+
+		pub fn as_str(&self) -> Option<&'a str> {
+			match *self {
+				Value::String(text) => Some(text),
+				_ => None,
+			}
+		}
+
+		pub fn as_object(&self) -> Option<Object<'a>> {
+			match *self {
+				Value::Object(object) => Some(object),
+				_ => None,
+			}
+		}
+
+		pub fn as_array(&self) -> Option<Array<'a>> {
+			match *self {
+				Value::Array(array) => Some(array),
+				_ => None,
+			}
+		}
+	}
+
+	impl<'a> Object<'a> {
+		pub fn members(&self) -> Members<'a> {
+			Members { scanner: Scanner { text: self.text, position: 1 } }
+		}
+
+		//the value of the first member with this key. Keys are compared as they were written,
+		//so a key with escapes in it won't match the plain text.
+		pub fn get(&self, key: &str) -> Option<Value<'a>> {
+			self.members().find(|&(member_key, _)| member_key == key).map(|(_, value)| value)
+		}
+	}
+
+	impl<'a> Array<'a> {
+		pub fn iter(&self) -> Elements<'a> {
+			Elements { scanner: Scanner { text: self.text, position: 1 } }
+		}
+
+		pub fn get(&self, index: usize) -> Option<Value<'a>> {
+			self.iter().nth(index)
+		}
+	}
+
+	//the keys and values of an object, in the order they were written:
+	pub struct Members<'a> {
+		scanner: Scanner<'a>,
+	}
+
+	impl<'a> Iterator for Members<'a> {
+		type Item = (&'a str, Value<'a>);
+
+		fn next(&mut self) -> Option<Self::Item> {
+			let scanner = &mut self.scanner;
+			if !scanner.next_element(b'}') {
+				return None;
+			}
+			let key = scanner.string().ok()?;
+			scanner.skip_whitespace();
+			scanner.expect(b':').ok()?;
+			Some((key, scanner.value(0).ok()?))
+		}
+	}
+
+	pub struct Elements<'a> {
+		scanner: Scanner<'a>,
+	}
+
+	impl<'a> Iterator for Elements<'a> {
+		type Item = Value<'a>;
+
+		fn next(&mut self) -> Option<Self::Item> {
+			match self.scanner.next_element(b']') {
+				true => self.scanner.value(0).ok(),
+				false => None,
+			}
+		}
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	struct Scanner<'a> {
+		text: &'a str,
+		position: usize,
+	}
+
+	impl<'a> Scanner<'a> {
+		fn peek(&self) -> Option<u8> {
+			self.text.as_bytes().get(self.position).copied()
+		}
+
+		fn skip_whitespace(&mut self) {
+			while matches!(self.peek(), Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r')) {
+				self.position += 1;
+			}
+		}
+
+		fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+			match self.peek() {
+				Some(next) if next == byte => {
+					self.position += 1;
+					Ok(())
+				}
+				Some(_) => Err(JsonError::Unexpected(self.position)),
+				None => Err(JsonError::UnexpectedEnd),
+			}
+		}
+
+		//this moves past the separator before the next member or element of an object or array,
+		//returning false once the closing byte is reached:
+		fn next_element(&mut self, close: u8) -> bool {
+			self.skip_whitespace();
+			match self.peek() {
+				Some(byte) if byte == close => false,
+				Some(b',') => {
+					self.position += 1;
+					self.skip_whitespace();
+					true
+				}
+				Some(_) => true,
+				None => false,
+			}
+		}
+
+		fn value(&mut self, depth: usize) -> Result<Value<'a>, JsonError> {
+			self.skip_whitespace();
+			let start = self.position;
+			match self.peek() {
+				Some(b'{') => {
+					self.container(b'}', depth)?;
+					Ok(Value::Object(Object { text: &self.text[start..self.position] }))
+				}
+				Some(b'[') => {
+					self.container(b']', depth)?;
+					Ok(Value::Array(Array { text: &self.text[start..self.position] }))
+				}
+				Some(b'"') => self.string().map(Value::String),
+				Some(b'-') | Some(b'0'..=b'9') => self.number().map(Value::Number),
+				Some(b't') => self.literal("true", Value::Bool(true)),
+				Some(b'f') => self.literal("false", Value::Bool(false)),
+				Some(b'n') => self.literal("null", Value::Null),
+				Some(_) => Err(JsonError::Unexpected(start)),
+				None => Err(JsonError::UnexpectedEnd),
+			}
+		}
+
+		fn literal(&mut self, word: &str, value: Value<'a>) -> Result<Value<'a>, JsonError> {
+			for &byte in word.as_bytes() {
+				self.expect(byte)?;
+			}
+			Ok(value)
+		}
+
+		//this moves past an object or array, checking everything in it:
+		fn container(&mut self, close: u8, depth: usize) -> Result<(), JsonError> {
+			if depth >= MAX_DEPTH {
+				return Err(JsonError::TooDeep);
+			}
+			self.position += 1;
+			self.skip_whitespace();
+			if self.peek() == Some(close) {
+				self.position += 1;
+				return Ok(());
+			}
+			loop {
+				if close == b'}' {
+					self.skip_whitespace();
+					self.string()?;
+					self.skip_whitespace();
+					self.expect(b':')?;
+				}
+				self.value(depth + 1)?;
+				self.skip_whitespace();
+				match self.peek() {
+					Some(b',') => self.position += 1,
+					Some(byte) if byte == close => {
+						self.position += 1;
+						return Ok(());
+					}
+					Some(_) => return Err(JsonError::Unexpected(self.position)),
+					None => return Err(JsonError::UnexpectedEnd),
+				}
+			}
+		}
+
+		//this moves past a string, returning what is between the quotes:
+		fn string(&mut self) -> Result<&'a str, JsonError> {
+			self.expect(b'"')?;
+			let start = self.position;
+			loop {
+				match self.peek() {
+					Some(b'"') => {
+						self.position += 1;
+						return Ok(&self.text[start..self.position - 1]);
+					}
+					Some(b'\\') => {
+						self.position += 1;
+						match self.peek() {
+							Some(b'"') | Some(b'\\') | Some(b'/') | Some(b'b') | Some(b'f') | Some(b'n') | Some(b'r') | Some(b't') => self.position += 1,
+							Some(b'u') => {
+								self.position += 1;
+								for _ in 0..4 {
+									match self.peek() {
+										Some(byte) if byte.is_ascii_hexdigit() => self.position += 1,
+										Some(_) => return Err(JsonError::Unexpected(self.position)),
+										None => return Err(JsonError::UnexpectedEnd),
+									}
+								}
+							}
+							Some(_) => return Err(JsonError::Unexpected(self.position)),
+							None => return Err(JsonError::UnexpectedEnd),
+						}
+					}
+					Some(byte) if byte < 0x20 => return Err(JsonError::Unexpected(self.position)),
+					Some(_) => self.position += 1,
+					None => return Err(JsonError::UnexpectedEnd),
+				}
+			}
+		}
+
+		fn digits(&mut self) -> Result<(), JsonError> {
+			match self.peek() {
+				Some(b'0'..=b'9') => {}
+				Some(_) => return Err(JsonError::Unexpected(self.position)),
+				None => return Err(JsonError::UnexpectedEnd),
+			}
+			while let Some(b'0'..=b'9') = self.peek() {
+				self.position += 1;
+			}
+			Ok(())
+		}
+
+		//this moves past a number: a minus sign, the whole number part without leading zeros, then
+		//a fraction and exponent if there are any:
+		fn number(&mut self) -> Result<&'a str, JsonError> {
+			let start = self.position;
+			if self.peek() == Some(b'-') {
+				self.position += 1;
+			}
+			match self.peek() {
+				Some(b'0') => self.position += 1,
+				_ => self.digits()?,
+			}
+			if self.peek() == Some(b'.') {
+				self.position += 1;
+				self.digits()?;
+			}
+			if matches!(self.peek(), Some(b'e') | Some(b'E')) {
+				self.position += 1;
+				if matches!(self.peek(), Some(b'+') | Some(b'-')) {
+					self.position += 1;
+				}
+				self.digits()?;
+			}
+			Ok(&self.text[start..self.position])
+		}
+	}
+
+	//this writes text as a JSON string, quotes and all:
+	pub fn write_string<W: fmt::Write>(out: &mut W, text: &str) -> fmt::Result {
+		out.write_char('"')?;
+		for character in text.chars() {
+			match character {
+				'"' => out.write_str("\\\"")?,
+				'\\' => out.write_str("\\\\")?,
+				'\n' => out.write_str("\\n")?,
+				'\r' => out.write_str("\\r")?,
+				'\t' => out.write_str("\\t")?,
+				character if (character as u32) < 0x20 => write!(out, "\\u{:04x}", character as u32)?,
+				character => out.write_char(character)?,
+			}
+		}
+		out.write_char('"')
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		fn number(text: &str) -> Option<i64> {
+			parse(text).unwrap().as_i64()
+		}
+
+		#[test]
+		fn values() {
+			assert_eq!(parse(" null "), Ok(Value::Null));
+			assert_eq!(parse("true"), Ok(Value::Bool(true)));
+			assert_eq!(parse("false").unwrap().as_bool(), Some(false));
+			assert_eq!(parse("\"hi\"").unwrap().as_str(), Some("hi"));
+			let document = parse("{\"on\": true, \"seg\": [{\"fx\": 3}, 4, \"x\"], \"on\": false}").unwrap().as_object().unwrap();
+			assert_eq!(document.get("on"), Some(Value::Bool(true)));
+			assert_eq!(document.members().count(), 3);
+			let seg = document.get("seg").unwrap().as_array().unwrap();
+			assert_eq!(seg.get(0).unwrap().as_object().unwrap().get("fx").unwrap().as_i64(), Some(3));
+			assert_eq!(seg.get(2), Some(Value::String("x")));
+			assert_eq!(seg.get(3), None);
+			assert_eq!(parse("[ ]").unwrap().as_array().unwrap().iter().count(), 0);
+			assert_eq!(parse("{}").unwrap().as_object().unwrap().get("on"), None);
+		}
+
+		#[test]
+		fn escapes() {
+			//strings come back with their escapes as written:
+			let escaped = "\"a\\\"b\\\\c\\/d\\b\\f\\n\\r\\t\"";
+			assert_eq!(parse(escaped).unwrap().as_str(), Some("a\\\"b\\\\c\\/d\\b\\f\\n\\r\\t"));
+			assert_eq!(parse("\"\\u00e9\\uD83D\\ude00\"").unwrap().as_str(), Some("\\u00e9\\uD83D\\ude00"));
+			assert_eq!(parse("\"\\x\""), Err(JsonError::Unexpected(2)));
+			assert_eq!(parse("\"\\u12G4\""), Err(JsonError::Unexpected(5)));
+			assert_eq!(parse("\"\\u12"), Err(JsonError::UnexpectedEnd));
+			assert_eq!(parse("\"\\"), Err(JsonError::UnexpectedEnd));
+			assert_eq!(parse("\"tab\there\""), Err(JsonError::Unexpected(4)));
+			assert_eq!(parse("\"open"), Err(JsonError::UnexpectedEnd));
+			//keys with escapes only match as written:
+			let object = parse("{\"a\\u0062\": 1}").unwrap().as_object().unwrap();
+			assert_eq!(object.get("ab"), None);
+			assert_eq!(object.get("a\\u0062").unwrap().as_i64(), Some(1));
+		}
+
+		#[test]
+		fn writing_strings() {
+			let mut out = String::new();
+			write_string(&mut out, "say \"hi\"\\\n\r\t\u{1}é").unwrap();
+			assert_eq!(out, "\"say \\\"hi\\\"\\\\\\n\\r\\t\\u0001é\"");
+			assert_eq!(parse(&out).unwrap().as_str(), Some(&out[1..out.len() - 1]));
+		}
+
+		#[test]
+		fn depth() {
+			let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+			assert!(parse(&nested(MAX_DEPTH)).is_ok());
+			assert_eq!(parse(&nested(MAX_DEPTH + 1)), Err(JsonError::TooDeep));
+			let objects = "{\"a\":".repeat(MAX_DEPTH) + "{}" + &"}".repeat(MAX_DEPTH);
+			assert_eq!(parse(&objects), Err(JsonError::TooDeep));
+		}
+
+		#[test]
+		fn number_grammar() {
+			for text in ["0", "-0", "12", "-7", "0.5", "1.25e3", "1E+2", "2e-2"] {
+				assert_eq!(parse(text), Ok(Value::Number(text)));
+			}
+			assert_eq!(parse("01"), Err(JsonError::Unexpected(1)));
+			assert_eq!(parse("[-00]"), Err(JsonError::Unexpected(3)));
+			assert_eq!(parse("+1"), Err(JsonError::Unexpected(0)));
+			assert_eq!(parse(".5"), Err(JsonError::Unexpected(0)));
+			assert_eq!(parse("1.e5"), Err(JsonError::Unexpected(2)));
+			assert_eq!(parse("1."), Err(JsonError::UnexpectedEnd));
+			assert_eq!(parse("1e"), Err(JsonError::UnexpectedEnd));
+			assert_eq!(parse("-"), Err(JsonError::UnexpectedEnd));
+			assert_eq!(parse("-a"), Err(JsonError::Unexpected(1)));
+		}
+
+		#[test]
+		fn whole_numbers() {
+			assert_eq!(number("-0"), Some(0));
+			assert_eq!(number("42.9"), Some(42));
+			assert_eq!(number("-42.9"), Some(-42));
+			assert_eq!(number("1e3"), Some(1));
+			assert_eq!(number("9223372036854775807"), Some(i64::MAX));
+			assert_eq!(number("-9223372036854775808"), Some(i64::MIN));
+			assert_eq!(number("9223372036854775808"), None);
+			assert_eq!(number("-9223372036854775809"), None);
+			assert_eq!(number("300"), Some(300));
+			assert_eq!(parse("300").unwrap().as_u8(), Some(255));
+			assert_eq!(parse("-3").unwrap().as_u8(), Some(0));
+			assert_eq!(parse("\"3\"").unwrap().as_i64(), None);
+		}
+
+		#[test]
+		fn trailing_text() {
+			assert_eq!(parse("{} "), Ok(Value::Object(Object { text: "{}" })));
+			assert_eq!(parse("{} x"), Err(JsonError::Unexpected(3)));
+			assert_eq!(parse("true1"), Err(JsonError::Unexpected(4)));
+			assert_eq!(parse("[1] [2]"), Err(JsonError::Unexpected(4)));
+			assert_eq!(parse("nul"), Err(JsonError::UnexpectedEnd));
+			assert_eq!(parse("[1,]"), Err(JsonError::Unexpected(3)));
+			assert_eq!(parse("[1 2]"), Err(JsonError::Unexpected(3)));
+			assert_eq!(parse("{\"a\" 1}"), Err(JsonError::Unexpected(5)));
+			assert_eq!(parse("{\"a\":1"), Err(JsonError::UnexpectedEnd));
+			assert_eq!(parse(""), Err(JsonError::UnexpectedEnd));
+		}
+	}
+}
@@ -0,0 +1,223 @@
+pub mod layers {
+	use crate::a;
+	use crate::c;
+
+	pub const MAX_LAYERS: usize = 4;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum BlendMode {
+		//the layer covers everything below it, black included
+		Normal,
+		//channels are added together, clipping at full brightness
+		Add,
+		//channels are multiplied, so the layer darkens what is below it
+		Multiply,
+		//the inverse of multiply, so the layer brightens what is below it
+		Screen,
+		//the brighter of the two channels wins
+		Max,
+		//the layer's brightness is used as a stencil for what is below it, black hides everything
+		Mask,
+	}
+
+	pub const BLEND_MODES: [BlendMode; 6] = [
+		BlendMode::Normal,
+		BlendMode::Add,
+		BlendMode::Multiply,
+		BlendMode::Screen,
+		BlendMode::Max,
+		BlendMode::Mask,
+	];
+
+	impl BlendMode {
+		pub fn name(self) -> &'static str {
+			match self {
+				BlendMode::Normal => "normal",
+				BlendMode::Add => "add",
+				BlendMode::Multiply => "multiply",
+				BlendMode::Screen => "screen",
+				BlendMode::Max => "max",
+				BlendMode::Mask => "mask",
+			}
+		}
+
+		pub fn from_name(name: &str) -> Option<BlendMode> {
+			BLEND_MODES.iter().copied().find(|blend_mode| blend_mode.name() == name)
+		}
+	}
+
+	//this combines one channel of a layer with the channel below it:
+	fn blend_channel(bottom: u8, top: u8, blend_mode: BlendMode) -> u8 {
+		let (bottom, top) = (bottom as u16, top as u16);
+		let blended = match blend_mode {
+			BlendMode::Normal => top,
+			BlendMode::Add => (bottom + top).min(255),
+			BlendMode::Multiply => bottom * top / 255,
+			BlendMode::Screen => 255 - (255 - bottom) * (255 - top) / 255,
+			BlendMode::Max => bottom.max(top),
+			BlendMode::Mask => bottom,
+		};
+		blended as u8
+	}
+
+	//this puts the top color over the bottom color using the blend mode, then fades between the
+	//original bottom color and the blended color by opacity, where 255 is fully opaque.
+	pub fn blend(bottom: c::Color, top: c::Color, blend_mode: BlendMode, opacity: u8) -> c::Color {
+		let blended = match blend_mode {
+			BlendMode::Mask => bottom.scaled(top.r.max(top.g).max(top.b)),
+			_ => c::Color {
+				r: blend_channel(bottom.r, top.r, blend_mode),
+				g: blend_channel(bottom.g, top.g, blend_mode),
+				b: blend_channel(bottom.b, top.b, blend_mode),
+			},
+		};
+		c::Color::color_lerp(opacity as i32, 0, 255, bottom, blended)
+	}
+
+	pub struct Layer {
+		pub animation: a::Animation,
+		pub blend_mode: BlendMode,
+		pub opacity: u8,
+		pub enabled: bool,
+	}
+
+	impl Layer {
+		pub fn new(animation: a::Animation, blend_mode: BlendMode, opacity: u8) -> Self {
+			Layer {
+				animation,
+				blend_mode,
+				opacity,
+				enabled: true,
+			}
+		}
+	}
+
+	//a stack of animation layers that get composited, lowest index first, over a base frame.
+	#[derive(Default)]
+	pub struct LayerStack {
+		layers: [Option<Layer>; MAX_LAYERS],
+	}
+
+	impl LayerStack {
+		pub fn new() -> Self {
+			LayerStack {
+				layers: Default::default(),
+			}
+		}
+
+		//puts a layer in a slot, handing back whatever layer was there before.
+		//if the slot doesn't exist the new layer is handed straight back.
+		pub fn set_layer(&mut self, index: usize, layer: Layer) -> Option<Layer> {
+			match self.layers.get_mut(index) {
+				Some(slot) => slot.replace(layer),
+				None => Some(layer),
+			}
+		}
+
+		pub fn remove_layer(&mut self, index: usize) -> Option<Layer> {
+			self.layers.get_mut(index).and_then(|slot| slot.take())
+		}
+
+		pub fn layer(&self, index: usize) -> Option<&Layer> {
+			self.layers.get(index).and_then(|slot| slot.as_ref())
+		}
+
+		pub fn layer_mut(&mut self, index: usize) -> Option<&mut Layer> {
+			self.layers.get_mut(index).and_then(|slot| slot.as_mut())
+		}
+
+		//this renders each enabled layer into the scratch frame and blends it onto the frame:
+		pub fn render_over(&mut self, now_ms: u32, frame: &mut [c::Color], scratch: &mut [c::Color]) {
+			for layer in self.layers.iter_mut().filter_map(|slot| slot.as_mut()) {
+				if !layer.enabled {
+					continue;
+				}
+				for led in scratch.iter_mut() {
+					*led = c::C_OFF;
+				}
+				layer.animation.update(now_ms, scratch);
+				for (bottom, &top) in frame.iter_mut().zip(scratch.iter()) {
+					*bottom = blend(*bottom, top, layer.blend_mode, layer.opacity);
+				}
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const BOTTOM: c::Color = c::Color { r: 200, g: 100, b: 0 };
+		const TOP: c::Color = c::Color { r: 100, g: 200, b: 50 };
+		const RED: c::Color = c::Color { r: 200, g: 0, b: 0 };
+		const BLUE: c::Color = c::Color { r: 0, g: 0, b: 200 };
+
+		fn solid(color: c::Color, blend_mode: BlendMode, opacity: u8) -> Layer {
+			Layer::new(a::Animation::new(4, a::Mode::Solid(color)), blend_mode, opacity)
+		}
+
+		#[test]
+		fn blend_modes() {
+			let blended = |blend_mode| blend(BOTTOM, TOP, blend_mode, 255);
+			assert_eq!(blended(BlendMode::Normal), TOP);
+			assert_eq!(blended(BlendMode::Add), c::Color { r: 255, g: 255, b: 50 });
+			assert_eq!(blended(BlendMode::Multiply), c::Color { r: 78, g: 78, b: 0 });
+			assert_eq!(blended(BlendMode::Screen), c::Color { r: 222, g: 222, b: 50 });
+			assert_eq!(blended(BlendMode::Max), c::Color { r: 200, g: 200, b: 50 });
+			//the brightest channel of the top color is 200:
+			assert_eq!(blended(BlendMode::Mask), c::Color { r: 156, g: 78, b: 0 });
+			assert_eq!(blend(BOTTOM, c::C_OFF, BlendMode::Mask, 255), c::C_OFF);
+			assert_eq!(blend(BOTTOM, c::C_WHITE, BlendMode::Mask, 255), BOTTOM);
+			for &blend_mode in BLEND_MODES.iter() {
+				assert_eq!(BlendMode::from_name(blend_mode.name()), Some(blend_mode));
+			}
+			assert_eq!(BlendMode::from_name("overlay"), None);
+		}
+
+		#[test]
+		fn opacity() {
+			for &blend_mode in BLEND_MODES.iter() {
+				assert_eq!(blend(BOTTOM, TOP, blend_mode, 0), BOTTOM, "{:?}", blend_mode);
+			}
+			assert_eq!(blend(BOTTOM, TOP, BlendMode::Normal, 51), c::Color { r: 180, g: 120, b: 10 });
+			assert_eq!(blend(BOTTOM, TOP, BlendMode::Normal, 128), c::Color { r: 150, g: 150, b: 25 });
+			assert_eq!(blend(BOTTOM, TOP, BlendMode::Add, 128), c::Color { r: 227, g: 177, b: 25 });
+		}
+
+		#[test]
+		fn layers_stack_in_order() {
+			let mut layers = LayerStack::default();
+			let mut frame = [BOTTOM; 4];
+			let mut scratch = [c::C_OFF; 4];
+			layers.render_over(0, &mut frame, &mut scratch);
+			assert_eq!(frame, [BOTTOM; 4]);
+
+			assert!(layers.set_layer(1, solid(RED, BlendMode::Normal, 255)).is_none());
+			assert!(layers.set_layer(0, solid(BLUE, BlendMode::Normal, 255)).is_none());
+			layers.render_over(0, &mut frame, &mut scratch);
+			assert_eq!(frame, [RED; 4]);
+
+			//the blue layer underneath shows through when the red one is added to it:
+			layers.layer_mut(1).unwrap().blend_mode = BlendMode::Add;
+			let mut frame = [BOTTOM; 4];
+			layers.render_over(0, &mut frame, &mut scratch);
+			assert_eq!(frame, [c::Color { r: 200, g: 0, b: 200 }; 4]);
+
+			layers.layer_mut(1).unwrap().enabled = false;
+			let mut frame = [BOTTOM; 4];
+			layers.render_over(0, &mut frame, &mut scratch);
+			assert_eq!(frame, [BLUE; 4]);
+
+			assert!(layers.remove_layer(0).is_some());
+			assert!(layers.remove_layer(0).is_none());
+			assert!(layers.layer(0).is_none());
+			let mut frame = [BOTTOM; 4];
+			layers.render_over(0, &mut frame, &mut scratch);
+			assert_eq!(frame, [BOTTOM; 4]);
+
+			//slots past the end hand the layer straight back:
+			assert!(layers.set_layer(MAX_LAYERS, solid(RED, BlendMode::Normal, 255)).is_some());
+			assert!(layers.layer(MAX_LAYERS).is_none());
+		}
+	}
+}
@@ -0,0 +1,71 @@
+#![cfg_attr(not(test), no_std)]
+
+//everything that doesn't touch the pins, so it can be built and tested on a computer with
+//`cargo test-host`. The firmware itself is in main.rs.
+pub mod colors;
+pub mod animations;
+pub mod fixed;
+pub mod sunrise;
+pub mod noise;
+pub mod classic;
+pub mod random;
+pub mod storm;
+pub mod automaton;
+pub mod balls;
+pub mod layers;
+pub mod segments;
+pub mod views;
+pub mod spatial;
+pub mod matrix;
+pub mod config;
+pub mod flash;
+pub mod settings;
+pub mod scenes;
+pub mod playlist;
+pub mod clock;
+pub mod schedule;
+pub mod astro;
+pub mod control;
+pub mod cli;
+pub mod uart;
+pub mod stream;
+pub mod dmx;
+pub mod sacn;
+pub mod artnet;
+pub mod ddp;
+pub mod opc;
+pub mod json;
+pub mod wled;
+pub mod mqtt;
+pub mod hass;
+
+pub use crate::colors::colors as c;
+pub use crate::animations::animations as a;
+pub use crate::fixed::fixed as fx;
+pub use crate::sunrise::sunrise as sun;
+pub use crate::noise::noise as n;
+pub use crate::classic::classic as cl;
+pub use crate::random::random as rng;
+pub use crate::storm::storm as st;
+pub use crate::automaton::automaton as ca;
+pub use crate::balls::balls as b;
+pub use crate::layers::layers as ly;
+pub use crate::segments::segments as sg;
+pub use crate::views::views as vw;
+pub use crate::spatial::spatial as sp;
+pub use crate::matrix::matrix as mx;
+pub use crate::config::config as cf;
+pub use crate::flash::flash as fl;
+pub use crate::settings::settings as sv;
+pub use crate::scenes::scenes as sc;
+pub use crate::playlist::playlist as pl;
+pub use crate::clock::clock as ck;
+pub use crate::schedule::schedule as sch;
+pub use crate::astro::astro as ast;
+pub use crate::control::control as ctl;
+pub use crate::cli::cli as cmd;
+pub use crate::uart::uart as ua;
+pub use crate::stream::stream as sr;
+pub use crate::dmx::dmx as dx;
+pub use crate::json::json as js;
+pub use crate::mqtt::mqtt as mq;
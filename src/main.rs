@@ -3,17 +3,17 @@
 #[macro_use]
 
 #[allow(unused_imports)]
-pub mod colors;
 pub mod pins;
-pub mod animations;
-use crate::colors::colors as c;
 use crate::pins::pins as p;
-use crate::animations::animations as a;
+use esp32_ws2811::{c, a, ly, sg, vw, sp, mx, cf, fl, sv, sc, pl, ck, sch, ast, ctl, cmd, ua, sr};
 use esp32_hal::target;
 use esp32_hal::gpio::{OutputPin, PushPull, Output};
 use hal::prelude::*;
-use xtensa_lx::timer::{delay, get_cycle_count};
+use xtensa_lx::timer::get_cycle_count;
 use panic_halt as _;
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::fmt::{self, Write as _};
 use esp32_hal as hal;
 
 //macro to add Push trait to gpio pins:
@@ -46,6 +46,10 @@ const ZERO: bool = false;
 // This is equivalent to 12.5ns per clock cycle.
 const CORE_HZ: u32 = 80_000_000;
 const CORE_PERIOD_NS:f32 = 12.5;
+const CORE_CLOCKS_PER_MS: u32 = CORE_HZ / 1000;
+
+//how long to wait between animation frames, about 30 frames per second:
+const FRAME_DELAY_CLOCKS: u32 = CORE_HZ / 30;
 
 //Timing values for our 800kHz WS2811 Strips in nanoseconds:
 const WS2811_0H_TIME_NS: u32 = 350;
@@ -65,51 +69,132 @@ const SEND_START_OFFSET_DELAY_CLOCKS: u32 = 30000;
 const NUM_LEDS_WINDOW_STRIP: usize = 74;
 const NUM_LEDS_DOOR_STRIP: usize = 61;
 const NUM_LEDS_CLOSET_STRIP: usize = 34;
-const MAX_SINGLE_STRIP_BYTE_BUFFER_LENGTH: usize = get_single_strip_buffer_max_length(&ALL_STRIPS);
+//three bytes per led
+const MAX_SINGLE_STRIP_BYTE_BUFFER_LENGTH: usize = cf::MAX_LEDS_PER_STRIP * 3;
 const MAX_SINGLE_STRIP_BIT_BUFFER_LENGTH: usize = MAX_SINGLE_STRIP_BYTE_BUFFER_LENGTH * 8;
 
-//individual strips:
+//the buffers a strip's colors go through on the way out. With MAX_LEDS_PER_STRIP LEDs they come
+//to over 30KB, too much for the main task's stack, so there is one set of them in static memory
+//that main takes at start up and hands to send_physical_frame.
+struct SendBuffers {
+	bytes: [u8; MAX_SINGLE_STRIP_BYTE_BUFFER_LENGTH],
+	bits: [bool; MAX_SINGLE_STRIP_BIT_BUFFER_LENGTH],
+	timings: [(u32, u32); MAX_SINGLE_STRIP_BIT_BUFFER_LENGTH],
+}
+
+impl SendBuffers {
+	//this hands out the send buffers the first time it is called, and None after that, so there
+	//is only ever one reference to them:
+	fn take() -> Option<&'static mut SendBuffers> {
+		static mut BUFFERS: SendBuffers = SendBuffers {
+			bytes: [0; MAX_SINGLE_STRIP_BYTE_BUFFER_LENGTH],
+			bits: [ZERO; MAX_SINGLE_STRIP_BIT_BUFFER_LENGTH],
+			timings: [(0, 0); MAX_SINGLE_STRIP_BIT_BUFFER_LENGTH],
+		};
+		static TAKEN: AtomicBool = AtomicBool::new(false);
+		if TAKEN.swap(true, Ordering::Relaxed) {
+			return None;
+		}
+		//TAKEN was false, so no other reference to BUFFERS has been made:
+		Some(unsafe { &mut *core::ptr::addr_of_mut!(BUFFERS) })
+	}
+}
+
+//individual strips. These are the built in setup for tim's office, used when there is no
+//strip configuration in flash:
 const CLOSET_STRIP: WS2811PhysicalStrip =
 	WS2811PhysicalStrip {
 		pin: p::CLOSET_STRIP_PIN,
 		led_count: NUM_LEDS_CLOSET_STRIP,
 		reversed: false,
-		_color_order: ColorOrder::BRG,
+		color_order: cf::ColorOrder::BRG,
+		matrix: None,
 	};
 const WINDOW_STRIP: WS2811PhysicalStrip =
 	WS2811PhysicalStrip {
 		pin: p::WINDOW_STRIP_PIN,
 		led_count: NUM_LEDS_WINDOW_STRIP,
 		reversed: false,
-		_color_order: ColorOrder::BRG,
+		color_order: cf::ColorOrder::BRG,
+		matrix: None,
 	};
 const DOOR_STRIP: WS2811PhysicalStrip =
 	WS2811PhysicalStrip {
 		pin: p::DOOR_STRIP_PIN,
 		led_count: NUM_LEDS_DOOR_STRIP,
 		reversed: true,
-		_color_order: ColorOrder::BRG,
+		color_order: cf::ColorOrder::BRG,
+		matrix: None,
 	};
 
 //combined strip group:
-const ALL_STRIPS: [WS2811PhysicalStrip; 3] = [
+const DEFAULT_STRIPS: [WS2811PhysicalStrip; 3] = [
 	CLOSET_STRIP,
 	WINDOW_STRIP,
 	DOOR_STRIP,
 ];
 
 //calculate the total number of LEDs from the above values:
-const NUM_LEDS: usize = get_total_num_leds(&ALL_STRIPS);
-
-#[allow(dead_code)]
-enum ColorOrder {
-	RGB,
-	RBG,
-	GRB,
-	GBR,
-	BRG,
-	BGR,
-}
+const DEFAULT_NUM_LEDS: usize = get_total_num_leds(&DEFAULT_STRIPS);
+
+//where each strip is in DEFAULT_STRIPS:
+const CLOSET_STRIP_INDEX: usize = 0;
+const WINDOW_STRIP_INDEX: usize = 1;
+const DOOR_STRIP_INDEX: usize = 2;
+
+//other views of the default strips that can be controlled on their own. Strips loaded from the
+//configuration in flash bring their own views.
+const CEILING_PERIMETER_SLICES: [vw::StripSlice; 2] = [
+	vw::StripSlice::new(WINDOW_STRIP_INDEX, 0, NUM_LEDS_WINDOW_STRIP, false),
+	vw::StripSlice::new(DOOR_STRIP_INDEX, 0, NUM_LEDS_DOOR_STRIP, false),
+];
+const CLOSET_ONLY_SLICES: [vw::StripSlice; 1] = [
+	vw::StripSlice::new(CLOSET_STRIP_INDEX, 0, NUM_LEDS_CLOSET_STRIP, false),
+];
+//in the order they come after the room in ctl::VIEWS:
+const DEFAULT_VIEWS: [cf::ViewConfig; cf::MAX_VIEWS] = [
+	cf::ViewConfig::new(&CEILING_PERIMETER_SLICES),
+	cf::ViewConfig::new(&CLOSET_ONLY_SLICES),
+];
+
+//where each view is in the logical strips array in main. Scenes and playlists run on the whole room:
+const OFFICE_VIEW_INDEX: usize = ctl::View::Room.index();
+
+//the office's time zone, as minutes ahead of UTC:
+const UTC_OFFSET_MINUTES: i32 = 0;
+
+//where the office is, for schedule entries that follow sunrise and sunset. None turns those entries off.
+const OFFICE_LOCATION: Option<ast::Location> = None;
+
+//scheduled scene changes fade in over this long:
+const SCHEDULE_FADE_MS: u32 = 2_000;
+
+//scenes recalled from the command line fade in over this long:
+const COMMAND_FADE_MS: u32 = 500;
+
+//what scenes saved from the command line without a name are called:
+const DEFAULT_SCENE_NAMES: [&str; sc::SCENE_SLOTS] = ["scene0", "scene1", "scene2", "scene3", "scene4", "scene5", "scene6", "scene7"];
+
+//frames streamed in from outside are shown in place of the animations, which come back this long after the last one:
+const LIVE_TIMEOUT_MS: u32 = 2_500;
+
+//shown at the start of each line on the serial console:
+const PROMPT: &str = "> ";
+
+//what the text effect scrolls across a matrix panel, if one of the strips is set up as one:
+const MATRIX_TEXT: &str = "OFFICE";
+
+//where the LEDs of the whole room logical strip are, for animations that work by position.
+//measured in millimeters from the corner the closet strip starts in, with the strips running
+//around the top of the walls. The door strip turns the corner 34 LEDs in.
+const CEILING_HEIGHT_MM: i32 = 2400;
+const ROOM_MAP_LINES: [sp::MapLine; 4] = [
+	sp::MapLine::new(sp::Point::new(0, 0, CEILING_HEIGHT_MM), sp::Point::new(1122, 0, CEILING_HEIGHT_MM), NUM_LEDS_CLOSET_STRIP),
+	sp::MapLine::new(sp::Point::new(1122, 0, CEILING_HEIGHT_MM), sp::Point::new(1122, 2442, CEILING_HEIGHT_MM), NUM_LEDS_WINDOW_STRIP),
+	sp::MapLine::new(sp::Point::new(1122, 2442, CEILING_HEIGHT_MM), sp::Point::new(0, 2442, CEILING_HEIGHT_MM), 34),
+	sp::MapLine::new(sp::Point::new(0, 2442, CEILING_HEIGHT_MM), sp::Point::new(0, 1551, CEILING_HEIGHT_MM), NUM_LEDS_DOOR_STRIP - 34),
+];
+static ROOM_MAP: [sp::Point; DEFAULT_NUM_LEDS] = sp::map_lines::<DEFAULT_NUM_LEDS>(&ROOM_MAP_LINES);
 
 const fn get_total_num_leds(strips: &[WS2811PhysicalStrip]) -> usize {
 	let mut index = 0;
@@ -121,27 +206,37 @@ const fn get_total_num_leds(strips: &[WS2811PhysicalStrip]) -> usize {
 	total
 }
 
-const fn get_single_strip_buffer_max_length(strips: &[WS2811PhysicalStrip]) -> usize {
-	let mut max_len = 0;
+const fn get_strip_lengths(strips: &[WS2811PhysicalStrip]) -> [usize; vw::MAX_SLICES] {
+	let mut lengths = [0; vw::MAX_SLICES];
 	let mut index = 0;
-	while index < strips.len() {
-		if strips[index].led_count > max_len {
-			max_len = strips[index].led_count;
-		}
+	while index < strips.len() && index < vw::MAX_SLICES {
+		lengths[index] = strips[index].led_count;
 		index += 1;
 	}
-	// three bytes per led
-	max_len * 3
+	lengths
 }
 
+#[derive(Copy, Clone)]
 struct WS2811PhysicalStrip {
 	pin: u8,
 	led_count: usize,
 	reversed: bool,
-	_color_order: ColorOrder,
+	color_order: cf::ColorOrder,
+	//how the LEDs are laid out, if the strip is a matrix panel:
+	matrix: Option<mx::Layout>,
 }
 
 impl WS2811PhysicalStrip {
+	fn from_config(config: &cf::StripConfig) -> Self {
+		WS2811PhysicalStrip {
+			pin: config.pin,
+			led_count: config.led_count,
+			reversed: config.reversed,
+			color_order: config.color_order,
+			matrix: config.matrix,
+		}
+	}
+
 	fn send_bits<P1, P2, P3> (&self, pins: &mut p::PinControl<P1, P2, P3>, timings: &[(u32, u32)] )
 	where P1: OutputPin + p::Push,
 		  P2: OutputPin + p::Push,
@@ -159,16 +254,74 @@ impl WS2811PhysicalStrip {
 struct LogicalStrip<'a, const NUM_LEDS: usize> {
 	color_buffer: [c::Color; NUM_LEDS],
 	strips: &'a [WS2811PhysicalStrip],
+	//which LEDs of the physical strips this logical strip is made of, in order:
+	map: vw::ViewMap,
+	//where logical strips share physical LEDs, the highest priority one is shown:
+	priority: u8,
+	enabled: bool,
+	//the whole strip is scaled by this after everything else is drawn:
+	brightness: u8,
 	animation: a::Animation,
+	//zones of the strip that run their own animations in place of the main animation:
+	segments: sg::SegmentList,
+	//extra animations composited on top of the main animation and segments:
+	layers: ly::LayerStack,
+	//the last frame drawn before the current transition started, without gamma correction:
+	previous_frame: [c::Color; NUM_LEDS],
+	transition: Option<pl::Transition>,
+	transition_start_ms: Option<u32>,
+	//when the last frame streamed in from outside was shown:
+	live_ms: Option<u32>,
 }
 
 impl<'a, const NUM_LEDS: usize> LogicalStrip<'a, NUM_LEDS> {
+	//this makes a logical strip out of all of the physical strips, end to end in order:
 	fn new(strips: &'a [WS2811PhysicalStrip], animation: a::Animation ) -> Self {
-		LogicalStrip::<NUM_LEDS> {
+		let strip_lengths = get_strip_lengths(strips);
+		let map = vw::ViewMap::whole_strips(&strip_lengths[..strips.len()], NUM_LEDS)
+			.expect("Physical strips do not fit in the logical strip");
+		Self::from_map(strips, map, animation)
+	}
+
+	//this makes a logical strip out of pieces of the physical strips, in the order the slices are listed:
+	fn from_slices(strips: &'a [WS2811PhysicalStrip], slices: &[vw::StripSlice], animation: a::Animation) -> Result<Self, vw::ViewError> {
+		let strip_lengths = get_strip_lengths(strips);
+		let map = vw::ViewMap::new(slices, &strip_lengths[..strips.len()], NUM_LEDS)?;
+		Ok(Self::from_map(strips, map, animation))
+	}
+
+	fn from_map(strips: &'a [WS2811PhysicalStrip], map: vw::ViewMap, animation: a::Animation) -> Self {
+		let mut logical_strip = LogicalStrip::<NUM_LEDS> {
 			color_buffer: [c::Color::default(); NUM_LEDS],
 			strips,
+			map,
+			priority: 0,
+			enabled: true,
+			brightness: 255,
 			animation,
+			segments: sg::SegmentList::new(),
+			layers: ly::LayerStack::new(),
+			previous_frame: [c::C_OFF; NUM_LEDS],
+			transition: None,
+			transition_start_ms: None,
+			live_ms: None,
+		};
+		//effects that stay on one physical strip at a time pick from these, and effects that draw on
+		//a grid use the first matrix panel that is all in this logical strip:
+		let mut placement = logical_strip.animation.placement();
+		placement.strips = vw::StripRanges::new();
+		placement.matrix = None;
+		placement.text = MATRIX_TEXT;
+		for (strip_index, physical_strip) in strips.iter().enumerate() {
+			if let Some(range) = logical_strip.physical_strip_range(strip_index) {
+				placement.strips.push(range.clone());
+				if let Some(layout) = physical_strip.matrix.filter(|layout| range.len() == layout.led_count()) {
+					placement.matrix = placement.matrix.or(Some((range.start, layout)));
+				}
+			}
 		}
+		logical_strip.animation.set_placement(placement);
+		logical_strip
 	}
 
 	//this sets the color value in the color array at index:
@@ -185,75 +338,179 @@ impl<'a, const NUM_LEDS: usize> LogicalStrip<'a, NUM_LEDS> {
 		}
 	}
 
-	//this will iterate over all the strips and send the led data in series:
-	fn send_all_sequential<P1, P2, P3> ( &self, pins: &mut p::PinControl<P1, P2, P3>)
+	//this gets the range of logical LED indexes that one of the physical strips covers,
+	//for effects that should stay on a single strip:
+	fn physical_strip_range(&self, strip_index: usize) -> Option<Range<usize>> {
+		self.map.strip_range(strip_index)
+	}
+
+	//this renders the current animation frame, its segments and any layers above them into the color buffer:
+	fn update_animation(&mut self, now_ms: u32) {
+		if let Some(live_ms) = self.live_ms {
+			if now_ms.wrapping_sub(live_ms) < LIVE_TIMEOUT_MS {
+				return;
+			}
+			self.live_ms = None;
+		}
+		let led_count = self.map.led_count();
+		let mut frame = [c::C_OFF; NUM_LEDS];
+		let mut scratch_frame = [c::C_OFF; NUM_LEDS];
+		let frame = &mut frame[..led_count];
+		let scratch_frame = &mut scratch_frame[..led_count];
+		self.animation.update(now_ms, frame);
+		self.segments.render_over(now_ms, frame, scratch_frame);
+		self.layers.render_over(now_ms, frame, scratch_frame);
+		for led in frame.iter_mut() {
+			*led = led.scaled(self.brightness);
+		}
+		if let Some(transition) = self.transition {
+			let start_ms = *self.transition_start_ms.get_or_insert(now_ms);
+			if transition.apply(&self.previous_frame[..led_count], frame, now_ms.wrapping_sub(start_ms)) {
+				self.transition = None;
+			}
+		}
+		//the previous frame is held still while a transition is fading away from it:
+		if self.transition.is_none() {
+			self.previous_frame[..led_count].copy_from_slice(frame);
+		}
+		for (i, &color) in frame.iter().enumerate() {
+			self.set_color_at_index(i, color);
+		}
+	}
+
+	//this shows a frame streamed in from outside in place of the animation, until LIVE_TIMEOUT_MS
+	//after the last one. The colors aren't gamma corrected, since the tools sending them do that
+	//themselves. LEDs past the end of the frame are turned off.
+	fn show_live(&mut self, colors: &[c::Color], now_ms: u32) {
+		let led_count = self.map.led_count();
+		let brightness = self.brightness;
+		for (i, led) in self.color_buffer[..led_count].iter_mut().enumerate() {
+			*led = colors.get(i).map_or(c::C_OFF, |color| color.scaled(brightness));
+		}
+		self.live_ms = Some(now_ms);
+	}
+
+	//this makes a scene from what the strip is showing now:
+	fn capture_scene(&self, name: &str) -> Result<sc::Scene, sc::SceneError> {
+		let mut scene = sc::Scene::new(name)?;
+		scene.brightness = self.brightness;
+		scene.effect = self.animation.effect();
+		for (i, settings) in scene.segments.iter_mut().enumerate() {
+			*settings = self.segments.segment(i).and_then(sc::SegmentSettings::from_segment);
+		}
+		Ok(scene)
+	}
+
+	//this switches the strip over to a scene. seed is used by effects with randomness.
+	fn apply_scene(&mut self, scene: &sc::Scene, seed: u32) {
+		self.brightness = scene.brightness;
+		if let Some(effect) = scene.effect {
+			self.animation.set_effect(effect, seed);
+		}
+		//the scene's segments take the place of the ones there are now:
+		self.segments.clear();
+		for settings in scene.segments.iter().flatten() {
+			if let Ok(segment) = settings.to_segment(seed) {
+				let _ = self.segments.add(segment, self.map.led_count());
+			}
+		}
+	}
+
+	//this switches the strip over to a scene, moving from what is showing now to the scene with the transition:
+	fn transition_to_scene(&mut self, scene: &sc::Scene, transition: pl::Transition, seed: u32) {
+		self.apply_scene(scene, seed);
+		self.transition = match transition {
+			pl::Transition::Cut => None,
+			transition => Some(transition),
+		};
+		self.transition_start_ms = None;
+	}
+
+	//this draws the strip onto its physical LEDs, for sending along with other logical strips:
+	fn draw_onto(&self, frame: &mut vw::PhysicalFrame<NUM_LEDS>) {
+		if self.enabled {
+			let strip_lengths = get_strip_lengths(self.strips);
+			let colors = &self.color_buffer[..self.map.led_count()];
+			frame.draw_view(&self.map, colors, self.priority, &strip_lengths[..self.strips.len()]);
+		}
+	}
+
+	//this will send only this logical strip to the physical strips it is made from:
+	fn send_all_sequential<P1, P2, P3> ( &self, pins: &mut p::PinControl<P1, P2, P3>, buffers: &mut SendBuffers)
 	where P1: OutputPin + p::Push,
 		  P2: OutputPin + p::Push,
 		  P3: OutputPin + p::Push,
 	{
-		let mut start_index = 0;
-
-		for strip in self.strips {
-			let end_index = start_index + strip.led_count;
-
-			// generate byte array from color array (taking care of color order)
-			let current_strip_colors = &self.color_buffer[start_index..end_index];
-			let byte_count = strip.led_count * 3;
-			let bit_count = byte_count * 8;
-			let mut byte_buffer = [0_u8; MAX_SINGLE_STRIP_BYTE_BUFFER_LENGTH];
-			if strip.reversed {
-				for (i, color) in current_strip_colors.iter().rev().enumerate() {
-					let base = i * 3;
-					byte_buffer[base + 0] = color.b;
-					byte_buffer[base + 1] = color.r;
-					byte_buffer[base + 2] = color.g;
-				}
-			} else {
-				for (i, color) in current_strip_colors.iter().enumerate() {
-					let base = i * 3;
-					byte_buffer[base + 0] = color.b;
-					byte_buffer[base + 1] = color.r;
-					byte_buffer[base + 2] = color.g;
-				}
-			}
+		let mut frame = vw::PhysicalFrame::<NUM_LEDS>::new();
+		self.draw_onto(&mut frame);
+		send_physical_frame(self.strips, &frame.colors, pins, buffers);
+	}
+}
 
-			// from byte array to bit array
-			let mut bit_buffer = [ZERO; MAX_SINGLE_STRIP_BIT_BUFFER_LENGTH];
-			for (i, byte) in byte_buffer.iter().take(byte_count).enumerate() {
-				let base = i * 8;
-				for bit in 0..8_u8 {
-					bit_buffer[base + bit as usize] = match (byte >> bit) & 0x01 {
-						0x01 => ONE,
-						0x00 => ZERO,
-						_ => unreachable!(),
-					};
-				}
-			}
+//this will iterate over all the strips and send the led data from a buffer of all the strips end to end in series:
+fn send_physical_frame<P1, P2, P3> (strips: &[WS2811PhysicalStrip], colors: &[c::Color], pins: &mut p::PinControl<P1, P2, P3>, buffers: &mut SendBuffers)
+where P1: OutputPin + p::Push,
+	  P2: OutputPin + p::Push,
+	  P3: OutputPin + p::Push,
+{
+	let mut start_index = 0;
 
-			// from bit array to timing array
-			let mut timings = [(0_u32,0_u32); MAX_SINGLE_STRIP_BIT_BUFFER_LENGTH];
-			for (i, &bit) in bit_buffer.iter().take(bit_count).enumerate() {
-				let bit_timing = match bit {
-					ONE => WS2811_1H_TIME_CLOCKS,
-					ZERO => WS2811_0H_TIME_CLOCKS,
-				};
-				let base_time = WS2811_FULL_CYCLE_CLOCKS * i as u32;
-				timings[i] = (base_time, base_time + bit_timing);
+	for strip in strips {
+		let end_index = start_index + strip.led_count;
+
+		// generate byte array from color array (taking care of color order)
+		let current_strip_colors = &colors[start_index..end_index];
+		let byte_count = strip.led_count * 3;
+		let bit_count = byte_count * 8;
+		let byte_buffer = &mut buffers.bytes;
+		if strip.reversed {
+			for (i, color) in current_strip_colors.iter().rev().enumerate() {
+				let base = i * 3;
+				byte_buffer[base..base + 3].copy_from_slice(&strip.color_order.bytes(*color));
+			}
+		} else {
+			for (i, color) in current_strip_colors.iter().enumerate() {
+				let base = i * 3;
+				byte_buffer[base..base + 3].copy_from_slice(&strip.color_order.bytes(*color));
 			}
+		}
 
-			// add clock + offset to timing array
-			let offset_clocks = SEND_START_OFFSET_DELAY_CLOCKS;
-			let clock_and_offset = get_cycle_count() + offset_clocks;
-			for i in 0..timings.len() {
-				timings[i].0 = timings[i].0 + clock_and_offset;
-				timings[i].1 = timings[i].1 + clock_and_offset;
+		// from byte array to bit array
+		let bit_buffer = &mut buffers.bits;
+		for (i, byte) in byte_buffer.iter().take(byte_count).enumerate() {
+			let base = i * 8;
+			for bit in 0..8_u8 {
+				bit_buffer[base + bit as usize] = match (byte >> bit) & 0x01 {
+					0x01 => ONE,
+					0x00 => ZERO,
+					_ => unreachable!(),
+				};
 			}
+		}
 
-			// call send bits and send the timing array
-			strip.send_bits(pins, &timings);
+		// from bit array to timing array
+		let timings = &mut buffers.timings[..bit_count];
+		for (i, &bit) in bit_buffer.iter().take(bit_count).enumerate() {
+			let bit_timing = match bit {
+				ONE => WS2811_1H_TIME_CLOCKS,
+				ZERO => WS2811_0H_TIME_CLOCKS,
+			};
+			let base_time = WS2811_FULL_CYCLE_CLOCKS * i as u32;
+			timings[i] = (base_time, base_time + bit_timing);
+		}
 
-			start_index = end_index;
+		// add clock + offset to timing array
+		let offset_clocks = SEND_START_OFFSET_DELAY_CLOCKS;
+		let clock_and_offset = get_cycle_count() + offset_clocks;
+		for i in 0..timings.len() {
+			timings[i].0 = timings[i].0 + clock_and_offset;
+			timings[i].1 = timings[i].1 + clock_and_offset;
 		}
+
+		// call send bits and send the timing array
+		strip.send_bits(pins, timings);
+
+		start_index = end_index;
 	}
 }
 
@@ -267,11 +524,527 @@ fn delay_until(clocks: u32) {
 	}
 }
 
+//this waits for a number of clock cycles, moving what comes in over the serial port into
+//buffer as it waits so the receive FIFO doesn't overflow between frames:
+fn delay_reading(clocks: u32, uart: &mut ua::Uart0, buffer: &mut ua::RxBuffer) {
+	let start = get_cycle_count();
+	while get_cycle_count().wrapping_sub(start) < clocks {
+		uart.poll(buffer);
+	}
+}
+
+//this keeps a millisecond clock for the animations from the core clock cycle count.
+//the cycle count wraps every ~53 seconds, so tick() needs to be called more often than that.
+struct FrameClock {
+	last_cycle_count: u32,
+	leftover_clocks: u32,
+	now_ms: u32,
+}
+
+impl FrameClock {
+	fn new() -> Self {
+		FrameClock {
+			last_cycle_count: get_cycle_count(),
+			leftover_clocks: 0,
+			now_ms: 0,
+		}
+	}
+
+	//this updates the clock and returns the number of milliseconds since it was made:
+	fn tick(&mut self) -> u32 {
+		let cycle_count = get_cycle_count();
+		let elapsed_clocks = cycle_count.wrapping_sub(self.last_cycle_count);
+		self.last_cycle_count = cycle_count;
+		let leftover_clocks = elapsed_clocks % CORE_CLOCKS_PER_MS + self.leftover_clocks;
+		let elapsed_ms = elapsed_clocks / CORE_CLOCKS_PER_MS + leftover_clocks / CORE_CLOCKS_PER_MS;
+		self.leftover_clocks = leftover_clocks % CORE_CLOCKS_PER_MS;
+		self.now_ms = self.now_ms.wrapping_add(elapsed_ms);
+		self.now_ms
+	}
+}
+
+//this reads the strip configuration and the views of the strips out of flash. If there isn't a good
+//one there, the built in office strips and views are used instead, and the reason the configuration
+//was rejected is passed back.
+fn load_strips() -> ([WS2811PhysicalStrip; cf::MAX_PHYSICAL_STRIPS], usize, [cf::ViewConfig; cf::MAX_VIEWS], Option<cf::ConfigError>) {
+	let mut strips = [WS2811PhysicalStrip::from_config(&cf::StripConfig::EMPTY); cf::MAX_PHYSICAL_STRIPS];
+	let mut blob = [0_u8; cf::MAX_CONFIG_LENGTH];
+	let topology = match fl::read(fl::CONFIG_FLASH_ADDRESS, &mut blob) {
+		Ok(()) => cf::Topology::parse(&blob, &p::SUPPORTED_PINS),
+		Err(_) => Err(cf::ConfigError::Unreadable),
+	};
+	match topology {
+		Ok(topology) => {
+			for (strip, config) in strips.iter_mut().zip(topology.strips()) {
+				*strip = WS2811PhysicalStrip::from_config(config);
+			}
+			(strips, topology.strips().len(), *topology.views(), None)
+		}
+		Err(error) => {
+			strips[..DEFAULT_STRIPS.len()].copy_from_slice(&DEFAULT_STRIPS);
+			(strips, DEFAULT_STRIPS.len(), DEFAULT_VIEWS, Some(error))
+		}
+	}
+}
+
+//this says the built in strips are being used, and why the configuration in flash wasn't:
+fn write_config_error<W: fmt::Write>(out: &mut W, error: cf::ConfigError) -> fmt::Result {
+	writeln!(out, "using the built in strips, the configuration in flash was rejected: {:?}", error)
+}
+
+//this carries out a scheduled action on a logical strip, returning whether circadian mode is on after it:
+fn run_action<const NUM_LEDS: usize>(strip: &mut LogicalStrip<NUM_LEDS>, action: sch::Action, scene_bank: &sc::SceneBank, seed: u32) -> bool {
+	strip.enabled = true;
+	match action {
+		sch::Action::Scene(slot) => {
+			if let Some(scene) = scene_bank.get(slot as usize) {
+				strip.transition_to_scene(scene, pl::Transition::Fade(SCHEDULE_FADE_MS), seed);
+			}
+		}
+		sch::Action::Effect(effect) => strip.animation.set_effect(effect, seed),
+		sch::Action::Kelvin(kelvin) => strip.animation.set_effect(a::EffectParams::solid(c::kelvin_to_color(kelvin)), seed),
+		sch::Action::Brightness(brightness) => strip.brightness = brightness,
+		sch::Action::Circadian => return true,
+		sch::Action::Off => strip.enabled = false,
+	}
+	false
+}
+
+//how long frames take to draw and send, not counting the delay between them, for the stats command:
+struct FrameStats {
+	frames: u32,
+	last_frame_ms: u32,
+	max_frame_ms: u32,
+}
+
+impl FrameStats {
+	fn new() -> Self {
+		FrameStats {
+			frames: 0,
+			last_frame_ms: 0,
+			max_frame_ms: 0,
+		}
+	}
+
+	fn record(&mut self, frame_ms: u32) {
+		self.frames = self.frames.wrapping_add(1);
+		self.last_frame_ms = frame_ms;
+		self.max_frame_ms = self.max_frame_ms.max(frame_ms);
+	}
+}
+
+//everything besides the logical strips that commands can look at or change:
+struct Controls<F: fl::Flash> {
+	settings: sv::Settings,
+	settings_store: sv::SettingsStore<F>,
+	scene_bank: sc::SceneBank,
+	playlist: pl::Playlist,
+	rtc: ck::Rtc,
+	circadian: bool,
+	//why the strip configuration in flash wasn't used, if it wasn't:
+	config_error: Option<cf::ConfigError>,
+	stats: FrameStats,
+	//the view on, off, color, kelvin, brightness and effect commands go to:
+	view: ctl::View,
+}
+
+impl<F: fl::Flash> Controls<F> {
+	//this queues what the strip is doing now to be saved, so it comes back after a reboot:
+	fn remember<const NUM_LEDS: usize>(&mut self, strip: &LogicalStrip<NUM_LEDS>, now_ms: u32) {
+		self.settings.brightness = strip.brightness;
+		self.settings.on = strip.enabled;
+		if let Some(effect) = strip.animation.effect() {
+			self.settings.effect = effect;
+		}
+		self.settings_store.save_later(self.settings, now_ms);
+	}
+
+	//this shows the scene in a slot on the strip, returning false if the slot is empty:
+	fn recall_scene<const NUM_LEDS: usize>(&mut self, strip: &mut LogicalStrip<NUM_LEDS>, slot: usize, transition: pl::Transition) -> bool {
+		match self.scene_bank.get(slot) {
+			Some(scene) => {
+				strip.transition_to_scene(scene, transition, get_cycle_count());
+				strip.enabled = true;
+				self.settings.scene = Some(slot as u8);
+				self.circadian = false;
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+//this carries out a command on the logical strips, writing any reply to out. Commands that change
+//what is showing go to the view picked with the view command, and everything else to the room.
+fn run_command<F, W, const NUM_LEDS: usize>(
+	command: ctl::Command,
+	controls: &mut Controls<F>,
+	strips: &mut [LogicalStrip<NUM_LEDS>],
+	now_ms: u32,
+	out: &mut W,
+) -> fmt::Result
+where F: fl::Flash,
+	  W: fmt::Write,
+{
+	if let ctl::Command::View { view, on } = command {
+		return run_view_command(view, on, controls, strips, out);
+	}
+	let view = match command {
+		ctl::Command::On | ctl::Command::Off | ctl::Command::Color(_) | ctl::Command::Kelvin(_)
+			| ctl::Command::Brightness(_) | ctl::Command::Effect { .. } | ctl::Command::SegmentAdd(_)
+			| ctl::Command::SegmentRemove(_) | ctl::Command::SegmentList | ctl::Command::Layer { .. }
+			| ctl::Command::LayerOff(_) | ctl::Command::LayerList => controls.view,
+		_ => ctl::View::Room,
+	};
+	let room = view == ctl::View::Room;
+	let strip = &mut strips[view.index()];
+	let seed = get_cycle_count();
+	//commands that pick what to show turn the lights on, and on the room take over from circadian mode and scenes:
+	let show = |strip: &mut LogicalStrip<NUM_LEDS>, controls: &mut Controls<F>, effect: a::EffectParams| {
+		strip.animation.set_effect(effect, seed);
+		strip.enabled = true;
+		if room {
+			controls.settings.scene = None;
+			controls.circadian = false;
+		}
+	};
+	match command {
+		ctl::Command::Help => {
+			out.write_str(cmd::HELP)?;
+			out.write_str("effects:")?;
+			for effect in a::EFFECTS.iter() {
+				write!(out, " {}", effect.name())?;
+			}
+			writeln!(out)?;
+		}
+		ctl::Command::On => strip.enabled = true,
+		ctl::Command::Off => strip.enabled = false,
+		ctl::Command::Color(color) => show(strip, controls, a::EffectParams::solid(color)),
+		ctl::Command::Kelvin(kelvin) => show(strip, controls, a::EffectParams::solid(c::kelvin_to_color(kelvin))),
+		ctl::Command::Brightness(brightness) => {
+			strip.brightness = brightness;
+			controls.circadian &= !room;
+		}
+		ctl::Command::Effect { effect, options } => {
+			let current = strip.animation.effect().unwrap_or(controls.settings.effect);
+			show(strip, controls, options.params(effect, current));
+		}
+		ctl::Command::Circadian => {
+			controls.circadian = true;
+			strip.enabled = true;
+			controls.settings.scene = None;
+			if !controls.rtc.is_set() {
+				writeln!(out, "the clock isn't set, circadian mode starts once it is")?;
+			}
+		}
+		ctl::Command::SceneSave { slot, name } => {
+			let name = name.unwrap_or(DEFAULT_SCENE_NAMES[slot as usize]);
+			let saved = strip.capture_scene(name).and_then(|scene| controls.scene_bank.save(slot as usize, scene));
+			match saved {
+				Ok(()) => match controls.scene_bank.store(&mut fl::RomFlash, fl::SCENES_FLASH_ADDRESS) {
+					Ok(()) => {
+						controls.settings.scene = Some(slot);
+						writeln!(out, "saved {} to slot {}", name, slot)?;
+					}
+					Err(error) => writeln!(out, "couldn't write scenes to flash: {:?}", error)?,
+				},
+				Err(error) => writeln!(out, "couldn't save scene: {:?}", error)?,
+			}
+		}
+		ctl::Command::SceneRecall(scene) => {
+			let slot = match scene {
+				ctl::SceneRef::Slot(slot) => Some(slot as usize),
+				ctl::SceneRef::Name(name) => controls.scene_bank.find(name),
+			};
+			let recalled = slot.map_or(false, |slot| controls.recall_scene(strip, slot, pl::Transition::Fade(COMMAND_FADE_MS)));
+			if !recalled {
+				writeln!(out, "no such scene")?;
+			}
+		}
+		ctl::Command::SceneNext => {
+			let current = controls.settings.scene.map(|slot| slot as usize);
+			match controls.scene_bank.next(current) {
+				Some(slot) => {
+					controls.recall_scene(strip, slot, pl::Transition::Fade(COMMAND_FADE_MS));
+				}
+				None => writeln!(out, "no scenes saved")?,
+			}
+		}
+		ctl::Command::SceneDelete(slot) => {
+			if controls.scene_bank.clear(slot as usize).is_none() {
+				writeln!(out, "slot {} is already empty", slot)?;
+			} else if let Err(error) = controls.scene_bank.store(&mut fl::RomFlash, fl::SCENES_FLASH_ADDRESS) {
+				writeln!(out, "couldn't write scenes to flash: {:?}", error)?;
+			}
+			if controls.settings.scene == Some(slot) {
+				controls.settings.scene = None;
+			}
+		}
+		ctl::Command::SceneList => {
+			for (slot, scene) in controls.scene_bank.iter() {
+				let marker = if controls.settings.scene == Some(slot as u8) { "*" } else { " " };
+				writeln!(out, "{}{} {}", marker, slot, scene.name())?;
+			}
+		}
+		ctl::Command::PlaylistAdd(entry) => {
+			if controls.playlist.add(entry).is_err() {
+				writeln!(out, "the playlist is full")?;
+			}
+		}
+		ctl::Command::PlaylistClear => controls.playlist.clear(),
+		ctl::Command::PlaylistStart | ctl::Command::PlaylistNext => {
+			let entry = match command {
+				ctl::Command::PlaylistStart => controls.playlist.start(now_ms),
+				_ => controls.playlist.next(now_ms),
+			};
+			match entry {
+				Some(entry) => {
+					controls.recall_scene(strip, entry.slot as usize, entry.transition);
+				}
+				None => writeln!(out, "the playlist isn't playing")?,
+			}
+		}
+		ctl::Command::PlaylistStop => controls.playlist.stop(),
+		ctl::Command::PlaylistPause => controls.playlist.pause(now_ms),
+		ctl::Command::PlaylistResume => controls.playlist.resume(now_ms),
+		ctl::Command::PlaylistShuffle(shuffle) => controls.playlist.shuffle = shuffle,
+		ctl::Command::PlaylistRepeat(repeat) => controls.playlist.repeat = repeat,
+		ctl::Command::PlaylistStatus => {
+			let playlist = &controls.playlist;
+			let status = playlist.status();
+			write!(out, "{:?}, {} entries", status.state, playlist.len())?;
+			if let (Some(index), Some(entry)) = (status.entry, status.entry.and_then(|index| playlist.entry(index))) {
+				write!(out, ", showing entry {} (slot {}) for {}s more", index, entry.slot, status.remaining_ms / 1000)?;
+			}
+			writeln!(out, ", shuffle {}, repeat {}", on_off(playlist.shuffle), on_off(playlist.repeat))?;
+		}
+		ctl::Command::Time(Some(unix_seconds)) => controls.rtc.set(unix_seconds, now_ms),
+		ctl::Command::Time(None) => match controls.rtc.local_seconds() {
+			Some(local_seconds) => {
+				let (date, seconds) = ck::split_unix_time(local_seconds);
+				writeln!(
+					out,
+					"{}-{:02}-{:02} {:02}:{:02}:{:02}",
+					date.year, date.month, date.day, seconds / 3600, seconds / 60 % 60, seconds % 60
+				)?;
+			}
+			None => writeln!(out, "the clock isn't set")?,
+		},
+		ctl::Command::SegmentAdd(new) => {
+			let current = strip.animation.effect().unwrap_or(controls.settings.effect);
+			let effect = new.options.params(new.effect, current);
+			let (start, length) = (new.start as usize, new.length as usize);
+			let animation = a::Animation::from_effect(length, effect, seed);
+			let added = sg::Segment::new(new.name, start..start + length, animation).and_then(|mut segment| {
+				segment.brightness = new.brightness.unwrap_or(255);
+				segment.reversed = new.reversed;
+				segment.mirrored = new.mirrored;
+				strip.segments.add(segment, strip.map.led_count())
+			});
+			if let Err(error) = added {
+				writeln!(out, "couldn't add segment: {:?}", error)?;
+			}
+		}
+		ctl::Command::SegmentRemove(name) => {
+			match strip.segments.find(name) {
+				Some(index) => {
+					strip.segments.remove(index);
+				}
+				None => writeln!(out, "no such segment")?,
+			}
+		}
+		ctl::Command::SegmentList => {
+			for (_, segment) in strip.segments.iter() {
+				let effect = segment.animation.effect().map_or("", |effect| effect.effect.name());
+				write!(out, "{} {}..{} {}, brightness {}", segment.name(), segment.range.start, segment.range.end, effect, segment.brightness)?;
+				if segment.reversed {
+					write!(out, ", reversed")?;
+				}
+				if segment.mirrored {
+					write!(out, ", mirrored")?;
+				}
+				writeln!(out)?;
+			}
+		}
+		ctl::Command::Layer { index, effect, options, blend_mode, opacity } => {
+			let current = strip.animation.effect().unwrap_or(controls.settings.effect);
+			let animation = a::Animation::from_effect(strip.map.led_count(), options.params(effect, current), seed);
+			strip.layers.set_layer(index as usize, ly::Layer::new(animation, blend_mode, opacity));
+		}
+		ctl::Command::LayerOff(index) => {
+			if strip.layers.remove_layer(index as usize).is_none() {
+				writeln!(out, "layer {} is already off", index)?;
+			}
+		}
+		ctl::Command::LayerList => {
+			for index in 0..ly::MAX_LAYERS {
+				if let Some(layer) = strip.layers.layer(index) {
+					let effect = layer.animation.effect().map_or("", |effect| effect.effect.name());
+					writeln!(out, "{}: {}, {}, opacity {}", index, effect, layer.blend_mode.name(), layer.opacity)?;
+				}
+			}
+		}
+		//picking a view was taken care of before the strip was picked:
+		ctl::Command::View { .. } => {}
+		ctl::Command::Strips => {
+			if let Some(error) = controls.config_error {
+				write_config_error(out, error)?;
+			}
+			for (i, physical_strip) in strip.strips.iter().enumerate() {
+				let direction = if physical_strip.reversed { ", reversed" } else { "" };
+				write!(
+					out,
+					"{}: gpio {}, {} leds, {:?}{}",
+					i, physical_strip.pin, physical_strip.led_count, physical_strip.color_order, direction
+				)?;
+				match physical_strip.matrix {
+					Some(layout) => writeln!(out, ", {}x{} matrix", layout.width, layout.height)?,
+					None => writeln!(out)?,
+				}
+			}
+		}
+		ctl::Command::Stats => {
+			let stats = &controls.stats;
+			writeln!(
+				out,
+				"up {}s, {} frames, last frame {}ms, slowest frame {}ms",
+				now_ms / 1000, stats.frames, stats.last_frame_ms, stats.max_frame_ms
+			)?;
+		}
+	}
+	//only the room is saved, the other views start out off after a reboot:
+	if room {
+		controls.remember(strip, now_ms);
+	}
+	Ok(())
+}
+
+//this picks the view commands go to and turns it on or off, or lists the views:
+fn run_view_command<F, W, const NUM_LEDS: usize>(
+	view: Option<ctl::View>,
+	on: Option<bool>,
+	controls: &mut Controls<F>,
+	strips: &mut [LogicalStrip<NUM_LEDS>],
+	out: &mut W,
+) -> fmt::Result
+where F: fl::Flash,
+	  W: fmt::Write,
+{
+	match view {
+		Some(view) => {
+			controls.view = view;
+			if let (Some(on), Some(strip)) = (on, strips.get_mut(view.index())) {
+				strip.enabled = on;
+			}
+		}
+		None => {
+			for (view, strip) in ctl::VIEWS.iter().zip(strips.iter()) {
+				let marker = if *view == controls.view { "*" } else { " " };
+				writeln!(out, "{}{} {}, {} leds", marker, view.name(), on_off(strip.enabled), strip.map.led_count())?;
+			}
+		}
+	}
+	Ok(())
+}
+
+fn on_off(value: bool) -> &'static str {
+	if value { "on" } else { "off" }
+}
+
+//this echoes a byte typed at the serial console back, so the terminal shows what is typed:
+fn echo<W: fmt::Write>(out: &mut W, byte: u8, last_byte: u8) -> fmt::Result {
+	match byte {
+		b'\r' => out.write_str("\n"),
+		//\r\n only starts one new line:
+		b'\n' if last_byte != b'\r' => out.write_str("\n"),
+		0x08 | 0x7F => out.write_str("\x08 \x08"),
+		b' '..=b'~' => out.write_char(byte as char),
+		_ => Ok(()),
+	}
+}
+
+//this makes one of the smaller views of the strips, starting out turned off. The views were checked
+//against the strips when they were loaded, but if the slices don't fit anyway the view is left empty.
+fn make_view<'a>(strips: &'a [WS2811PhysicalStrip], view: &cf::ViewConfig, priority: u8) -> LogicalStrip<'a, { cf::MAX_LEDS }> {
+	let animation = a::Animation::new(cf::MAX_LEDS, a::Mode::Solid(c::C_T_4000K));
+	let mut view = match LogicalStrip::from_slices(strips, view.slices(), animation) {
+		Ok(view) => view,
+		Err(_) => LogicalStrip::from_slices(strips, &[], a::Animation::new(cf::MAX_LEDS, a::Mode::Solid(c::C_OFF)))
+			.expect("An empty view always fits"),
+	};
+	view.priority = priority;
+	view.enabled = false;
+	view
+}
+
 #[entry]
 fn main() -> ! {
-	//make the logical strip:
-	let initial_animation = a::Animation::new(NUM_LEDS);
-	let mut office_strip = LogicalStrip::<NUM_LEDS>::new(&ALL_STRIPS, initial_animation);
+	//load the physical strips from the configuration in flash, or use the built in ones if that fails:
+	let (strip_storage, strip_count, views, config_error) = load_strips();
+	let strips = &strip_storage[..strip_count];
+	let send_buffers = SendBuffers::take().expect("The send buffers are only taken once");
+
+	//pick up where the lights were before the last reboot:
+	let mut settings_store = sv::SettingsStore::new(fl::RomFlash, fl::SETTINGS_FLASH_ADDRESS);
+	let mut settings = settings_store.load();
+	let scene_bank = sc::SceneBank::load(&mut fl::RomFlash, fl::SCENES_FLASH_ADDRESS).unwrap_or_else(|_| sc::SceneBank::new());
+
+	//make the logical strips. The whole room shows unless one of the smaller, higher priority views is turned on:
+	let initial_animation = a::Animation::from_effect(cf::MAX_LEDS, settings.effect, get_cycle_count());
+	let mut office_strip = LogicalStrip::<{ cf::MAX_LEDS }>::new(strips, initial_animation);
+	//the room map was measured for the built in strips, so it is only used when they are the ones loaded:
+	if get_strip_lengths(strips) == get_strip_lengths(&DEFAULT_STRIPS) {
+		office_strip.animation.set_placement(a::Placement { map: &ROOM_MAP, ..office_strip.animation.placement() });
+	}
+	office_strip.brightness = settings.brightness;
+	office_strip.enabled = settings.on;
+	match settings.scene.and_then(|slot| scene_bank.get(slot as usize)) {
+		Some(scene) => office_strip.apply_scene(scene, get_cycle_count()),
+		//the scene was deleted since the settings were saved:
+		None => settings.scene = None,
+	}
+	settings_store.save_later(settings, 0);
+	let perimeter_strip = make_view(strips, &views[0], 1);
+	let closet_strip = make_view(strips, &views[1], 2);
+	let mut logical_strips = [office_strip, perimeter_strip, closet_strip];
+	let mut physical_frame = vw::PhysicalFrame::<{ cf::MAX_LEDS }>::new();
+	//the playlist starts out empty and stopped, until scenes are added to it:
+	let playlist = pl::Playlist::new(get_cycle_count());
+
+	//the clock isn't set until a time comes in from a command or SNTP, so the schedule waits until then:
+	let rtc = ck::Rtc::new(UTC_OFFSET_MINUTES);
+	let mut schedule = sch::Schedule::new();
+	for &entry in sch::OFFICE_SCHEDULE.iter() {
+		let _ = schedule.add(entry);
+	}
+	let mut last_schedule_check: Option<i64> = None;
+	let mut last_schedule_run: Option<i64> = None;
+	let mut controls = Controls {
+		settings,
+		settings_store,
+		scene_bank,
+		playlist,
+		rtc,
+		circadian: false,
+		config_error,
+		stats: FrameStats::new(),
+		view: ctl::View::Room,
+	};
+
+	//the serial console takes commands a line at a time:
+	let mut uart = ua::Uart0;
+	let mut line_buffer = cmd::LineBuffer::new();
+	let mut last_byte = 0_u8;
+	//bytes are read out of the UART all through the frame, and handled at the start of the next:
+	let mut rx_buffer = ua::RxBuffer::new();
+	//frames streamed over the serial port are put together here, and shown once they are whole:
+	let mut frame_parser = sr::FrameParser::new();
+	let mut serial_frame = [c::C_OFF; cf::MAX_LEDS];
+	//a rejected strip configuration is reported straight away, rather than waiting for the strips command:
+	if let Some(error) = controls.config_error {
+		let _ = write_config_error(&mut uart, error);
+	}
+	let _ = uart.write_str(PROMPT);
 
 	//get physical pins to a usable state:
 	let device_peripherals = target::Peripherals::take().expect("Failed to obtain Peripherals");
@@ -286,10 +1059,84 @@ fn main() -> ! {
 		p3: door_led_control_gpio,
 	};
 
-	office_strip.set_strip_to_solid_color(c::C_T_4000K);
+	let mut frame_clock = FrameClock::new();
 
 	loop {
-		office_strip.send_all_sequential(&mut pins);
-		delay(CORE_HZ);
+		let now_ms = frame_clock.tick();
+		//run any commands that came in over the serial console since the last frame:
+		loop {
+			//reading as bytes are handled keeps up while long command output is written:
+			uart.poll(&mut rx_buffer);
+			let byte = match rx_buffer.pop() {
+				Some(byte) => byte,
+				None => break,
+			};
+			match frame_parser.push(byte, now_ms, &mut serial_frame) {
+				sr::Feed::Pass => {}
+				//the start of the frame header went to the command line as if it was typed:
+				sr::Feed::Started(_) => {
+					line_buffer.clear();
+					continue;
+				}
+				sr::Feed::Frame(led_count) => {
+					logical_strips[OFFICE_VIEW_INDEX].show_live(&serial_frame[..led_count], now_ms);
+					continue;
+				}
+				sr::Feed::Data | sr::Feed::Dropped => continue,
+			}
+			let _ = echo(&mut uart, byte, last_byte);
+			let end_of_line = byte == b'\r' || (byte == b'\n' && last_byte != b'\r');
+			last_byte = byte;
+			let _ = match line_buffer.push(byte) {
+				Some(Ok(line)) => match cmd::parse(line) {
+					Ok(Some(command)) => run_command(command, &mut controls, &mut logical_strips, now_ms, &mut uart),
+					Ok(None) => Ok(()),
+					Err(error) => writeln!(uart, "{}", error),
+				},
+				Some(Err(error)) => writeln!(uart, "{}", error),
+				None => Ok(()),
+			};
+			if end_of_line {
+				let _ = uart.write_str(PROMPT);
+			}
+		}
+		if let Some(entry) = controls.playlist.update(now_ms) {
+			controls.recall_scene(&mut logical_strips[OFFICE_VIEW_INDEX], entry.slot as usize, entry.transition);
+		}
+		controls.rtc.update(now_ms);
+		//the schedule only needs checking once a second:
+		if let Some(local_seconds) = controls.rtc.local_seconds().filter(|&seconds| Some(seconds) != last_schedule_check) {
+			last_schedule_check = Some(local_seconds);
+			if let Some(run_at) = schedule.latest(local_seconds, OFFICE_LOCATION, UTC_OFFSET_MINUTES) {
+				if last_schedule_run != Some(run_at) {
+					last_schedule_run = Some(run_at);
+					for entry in schedule.entries_at(run_at, OFFICE_LOCATION, UTC_OFFSET_MINUTES) {
+						controls.circadian = run_action(&mut logical_strips[OFFICE_VIEW_INDEX], entry.action, &controls.scene_bank, get_cycle_count());
+					}
+				}
+			}
+			if controls.circadian {
+				let (kelvin, brightness) = sch::circadian_at(&sch::DEFAULT_CIRCADIAN_CURVE, sch::TimeOfDay::from_local_seconds(local_seconds));
+				let office_strip = &mut logical_strips[OFFICE_VIEW_INDEX];
+				//the color only moves every few minutes, and setting it starts the effect over:
+				let effect = a::EffectParams::solid(c::kelvin_to_color(kelvin));
+				if office_strip.animation.effect() != Some(effect) {
+					office_strip.animation.set_effect(effect, 0);
+				}
+				office_strip.brightness = brightness;
+			}
+		}
+		physical_frame.clear();
+		for logical_strip in logical_strips.iter_mut().filter(|strip| strip.enabled) {
+			logical_strip.update_animation(now_ms);
+			logical_strip.draw_onto(&mut physical_frame);
+		}
+		uart.poll(&mut rx_buffer);
+		send_physical_frame(strips, &physical_frame.colors, &mut pins, send_buffers);
+		uart.poll(&mut rx_buffer);
+		//a failed save is tried again next frame:
+		let _ = controls.settings_store.poll(now_ms);
+		controls.stats.record(frame_clock.tick().wrapping_sub(now_ms));
+		delay_reading(FRAME_DELAY_CLOCKS, &mut uart, &mut rx_buffer);
 	}
 }
@@ -0,0 +1,444 @@
+pub mod matrix {
+	use crate::c;
+
+	//which corner of the panel the first LED is in, looking at the front of the panel:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Corner {
+		TopLeft,
+		TopRight,
+		BottomLeft,
+		BottomRight,
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Wiring {
+		//every row runs the same direction as the first
+		Progressive,
+		//every other row runs back the other way
+		Serpentine,
+	}
+
+	//how far the picture is turned clockwise from the way the panel is wired:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Rotation {
+		R0,
+		R90,
+		R180,
+		R270,
+	}
+
+	//how the LEDs of a strip are laid out as a grid. The rows of the panel run from the first
+	//LED's corner, with width LEDs in each row and height rows.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Layout {
+		pub width: usize,
+		pub height: usize,
+		pub wiring: Wiring,
+		pub origin: Corner,
+		pub rotation: Rotation,
+	}
+
+	impl Layout {
+		pub const fn new(width: usize, height: usize, wiring: Wiring, origin: Corner, rotation: Rotation) -> Self {
+			Layout {
+				width,
+				height,
+				wiring,
+				origin,
+				rotation,
+			}
+		}
+
+		pub fn led_count(&self) -> usize {
+			self.width * self.height
+		}
+
+		//the size of the picture after rotation:
+		pub fn drawing_size(&self) -> (usize, usize) {
+			match self.rotation {
+				Rotation::R0 | Rotation::R180 => (self.width, self.height),
+				Rotation::R90 | Rotation::R270 => (self.height, self.width),
+			}
+		}
+
+		//this gets the LED index for a pixel of the rotated picture, with (0, 0) at the top left:
+		pub fn index(&self, x: i32, y: i32) -> Option<usize> {
+			let (drawing_width, drawing_height) = self.drawing_size();
+			if x < 0 || y < 0 || x as usize >= drawing_width || y as usize >= drawing_height {
+				return None;
+			}
+			let (x, y) = (x as usize, y as usize);
+			//undo the rotation to get the column and row on the panel as it is wired:
+			let (column, row) = match self.rotation {
+				Rotation::R0 => (x, y),
+				Rotation::R90 => (y, self.height - 1 - x),
+				Rotation::R180 => (self.width - 1 - x, self.height - 1 - y),
+				Rotation::R270 => (self.width - 1 - y, x),
+			};
+			//then count from the corner the first LED is in:
+			let column = match self.origin {
+				Corner::TopLeft | Corner::BottomLeft => column,
+				Corner::TopRight | Corner::BottomRight => self.width - 1 - column,
+			};
+			let row = match self.origin {
+				Corner::TopLeft | Corner::TopRight => row,
+				Corner::BottomLeft | Corner::BottomRight => self.height - 1 - row,
+			};
+			let column = match self.wiring {
+				Wiring::Serpentine if row % 2 == 1 => self.width - 1 - column,
+				_ => column,
+			};
+			Some(row * self.width + column)
+		}
+	}
+
+	//glyphs are 3 pixels wide and 5 tall, stored a row at a time from the top with the left pixel
+	//in the highest bit. Lowercase letters are drawn as uppercase.
+	pub const GLYPH_WIDTH: i32 = 3;
+	pub const GLYPH_HEIGHT: i32 = 5;
+	//the distance from the start of one character to the start of the next:
+	pub const GLYPH_ADVANCE: i32 = GLYPH_WIDTH + 1;
+
+	const FONT: [(u8, u16); 42] = [
+		(b' ', 0b000000000000000),
+		(b'!', 0b010010010000010),
+		(b'-', 0b000000111000000),
+		(b'.', 0b000000000000010),
+		(b':', 0b000010000010000),
+		(b'?', 0b110001010000010),
+		(b'0', 0b111101101101111),
+		(b'1', 0b010110010010111),
+		(b'2', 0b110001010100111),
+		(b'3', 0b110001010001110),
+		(b'4', 0b101101111001001),
+		(b'5', 0b111100110001110),
+		(b'6', 0b011100111101111),
+		(b'7', 0b111001010010010),
+		(b'8', 0b111101111101111),
+		(b'9', 0b111101111001110),
+		(b'A', 0b010101111101101),
+		(b'B', 0b110101110101110),
+		(b'C', 0b011100100100011),
+		(b'D', 0b110101101101110),
+		(b'E', 0b111100110100111),
+		(b'F', 0b111100110100100),
+		(b'G', 0b011100101101011),
+		(b'H', 0b101101111101101),
+		(b'I', 0b111010010010111),
+		(b'J', 0b001001001101010),
+		(b'K', 0b101101110101101),
+		(b'L', 0b100100100100111),
+		(b'M', 0b101111111101101),
+		(b'N', 0b110101101101101),
+		(b'O', 0b010101101101010),
+		(b'P', 0b110101110100100),
+		(b'Q', 0b010101101110011),
+		(b'R', 0b110101110101101),
+		(b'S', 0b011100010001110),
+		(b'T', 0b111010010010010),
+		(b'U', 0b101101101101111),
+		(b'V', 0b101101101101010),
+		(b'W', 0b101101111111101),
+		(b'X', 0b101101010101101),
+		(b'Y', 0b101101010010010),
+		(b'Z', 0b111001010100111),
+	];
+
+	fn glyph(character: u8) -> Option<u16> {
+		let character = character.to_ascii_uppercase();
+		FONT.iter().find(|(c, _)| *c == character).map(|&(_, bits)| bits)
+	}
+
+	//something to draw on: a frame of LEDs laid out as a grid.
+	pub struct Canvas<'a> {
+		pub layout: Layout,
+		frame: &'a mut [c::Color],
+	}
+
+	impl<'a> Canvas<'a> {
+		pub fn new(layout: Layout, frame: &'a mut [c::Color]) -> Self {
+			Canvas { layout, frame }
+		}
+
+		//pixels off the edge of the canvas are ignored, so shapes can be partly off screen:
+		pub fn set_pixel(&mut self, x: i32, y: i32, color: c::Color) {
+			if let Some(index) = self.layout.index(x, y) {
+				if let Some(led) = self.frame.get_mut(index) {
+					*led = color;
+				}
+			}
+		}
+
+		pub fn get_pixel(&self, x: i32, y: i32) -> Option<c::Color> {
+			self.layout.index(x, y).and_then(|index| self.frame.get(index).copied())
+		}
+
+		pub fn fill(&mut self, color: c::Color) {
+			let led_count = self.layout.led_count().min(self.frame.len());
+			for led in self.frame[..led_count].iter_mut() {
+				*led = color;
+			}
+		}
+
+		//Bresenham's line, including both end points:
+		pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: c::Color) {
+			let dx = (x1 - x0).abs();
+			let dy = -(y1 - y0).abs();
+			let step_x = if x0 < x1 { 1 } else { -1 };
+			let step_y = if y0 < y1 { 1 } else { -1 };
+			let mut error = dx + dy;
+			let (mut x, mut y) = (x0, y0);
+			loop {
+				self.set_pixel(x, y, color);
+				if x == x1 && y == y1 {
+					break;
+				}
+				let doubled_error = 2 * error;
+				if doubled_error >= dy {
+					error += dy;
+					x += step_x;
+				}
+				if doubled_error <= dx {
+					error += dx;
+					y += step_y;
+				}
+			}
+		}
+
+		pub fn rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: c::Color) {
+			if width <= 0 || height <= 0 {
+				return;
+			}
+			let (right, bottom) = (x + width - 1, y + height - 1);
+			self.line(x, y, right, y, color);
+			self.line(x, bottom, right, bottom, color);
+			self.line(x, y, x, bottom, color);
+			self.line(right, y, right, bottom, color);
+		}
+
+		pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: c::Color) {
+			for row in y..y + height {
+				for column in x..x + width {
+					self.set_pixel(column, row, color);
+				}
+			}
+		}
+
+		//fills every pixel whose center is within radius of (center_x, center_y):
+		pub fn fill_circle(&mut self, center_x: i32, center_y: i32, radius: i32, color: c::Color) {
+			let radius_squared = radius * radius;
+			for y in -radius..=radius {
+				for x in -radius..=radius {
+					if x * x + y * y <= radius_squared {
+						self.set_pixel(center_x + x, center_y + y, color);
+					}
+				}
+			}
+		}
+
+		//draws a character with its top left corner at (x, y). Unknown characters are skipped.
+		pub fn draw_char(&mut self, x: i32, y: i32, character: u8, color: c::Color) {
+			let bits = match glyph(character) {
+				Some(bits) => bits,
+				None => return,
+			};
+			for row in 0..GLYPH_HEIGHT {
+				for column in 0..GLYPH_WIDTH {
+					let bit = (GLYPH_HEIGHT - row) * GLYPH_WIDTH - column - 1;
+					if (bits >> bit) & 0x01 == 0x01 {
+						self.set_pixel(x + column, y + row, color);
+					}
+				}
+			}
+		}
+
+		pub fn draw_text(&mut self, x: i32, y: i32, text: &str, color: c::Color) {
+			for (i, character) in text.bytes().enumerate() {
+				self.draw_char(x + i as i32 * GLYPH_ADVANCE, y, character, color);
+			}
+		}
+	}
+
+	//text scrolling right to left across a panel, starting just off the right edge and
+	//coming back around once it has scrolled all the way off the left.
+	pub struct ScrollingText {
+		pub layout: Layout,
+		pub text: &'static str,
+		pub color: c::Color,
+		pub background: c::Color,
+		//how long the text takes to move one pixel:
+		pub step_ms: u32,
+		//the panel's first LED in the frame, for a panel that is one of several strips:
+		pub first_led: usize,
+		start_ms: Option<u32>,
+	}
+
+	impl ScrollingText {
+		pub fn new(layout: Layout, text: &'static str, color: c::Color, step_ms: u32) -> Self {
+			ScrollingText {
+				layout,
+				text,
+				color,
+				background: c::C_OFF,
+				step_ms,
+				first_led: 0,
+				start_ms: None,
+			}
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			let start_ms = *self.start_ms.get_or_insert(now_ms);
+			let (drawing_width, drawing_height) = self.layout.drawing_size();
+			let text_width = self.text.len() as i32 * GLYPH_ADVANCE;
+			let scroll_length = drawing_width as i32 + text_width;
+			let step = (now_ms.wrapping_sub(start_ms) / self.step_ms.max(1)) as i32 % scroll_length.max(1);
+			let y = (drawing_height as i32 - GLYPH_HEIGHT) / 2;
+			let frame_len = frame.len();
+			let mut canvas = Canvas::new(self.layout, &mut frame[self.first_led.min(frame_len)..]);
+			canvas.fill(self.background);
+			canvas.draw_text(drawing_width as i32 - step, y, self.text, self.color);
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const ON: c::Color = c::Color { r: 255, g: 0, b: 0 };
+
+		//the LED index of every pixel of the picture, a row at a time:
+		fn indexes(layout: Layout) -> Vec<usize> {
+			let (width, height) = layout.drawing_size();
+			let mut indexes = Vec::new();
+			for y in 0..height as i32 {
+				for x in 0..width as i32 {
+					indexes.push(layout.index(x, y).unwrap());
+				}
+			}
+			indexes
+		}
+
+		fn corners(layout: Layout) -> [usize; 4] {
+			let (width, height) = layout.drawing_size();
+			let (right, bottom) = (width as i32 - 1, height as i32 - 1);
+			[(0, 0), (right, 0), (0, bottom), (right, bottom)].map(|(x, y)| layout.index(x, y).unwrap())
+		}
+
+		//draws on a blank panel, and returns the picture with lit pixels as '#':
+		fn draw(width: usize, height: usize, paint: impl FnOnce(&mut Canvas)) -> Vec<String> {
+			let layout = Layout::new(width, height, Wiring::Progressive, Corner::TopLeft, Rotation::R0);
+			let mut frame = vec![c::C_OFF; layout.led_count()];
+			paint(&mut Canvas::new(layout, &mut frame));
+			frame
+				.chunks(width)
+				.map(|row| row.iter().map(|&led| if led == ON { '#' } else { '.' }).collect())
+				.collect()
+		}
+
+		#[test]
+		fn corners_of_a_panel() {
+			let panel = |origin| Layout::new(3, 2, Wiring::Progressive, origin, Rotation::R0);
+			assert_eq!(corners(panel(Corner::TopLeft)), [0, 2, 3, 5]);
+			assert_eq!(corners(panel(Corner::TopRight)), [2, 0, 5, 3]);
+			assert_eq!(corners(panel(Corner::BottomLeft)), [3, 5, 0, 2]);
+			assert_eq!(corners(panel(Corner::BottomRight)), [5, 3, 2, 0]);
+		}
+
+		#[test]
+		fn serpentine_rows_run_back() {
+			let panel = |origin| Layout::new(3, 2, Wiring::Serpentine, origin, Rotation::R0);
+			assert_eq!(indexes(panel(Corner::TopLeft)), [0, 1, 2, 5, 4, 3]);
+			assert_eq!(indexes(panel(Corner::TopRight)), [2, 1, 0, 3, 4, 5]);
+			assert_eq!(indexes(panel(Corner::BottomLeft)), [5, 4, 3, 0, 1, 2]);
+			assert_eq!(indexes(panel(Corner::BottomRight)), [3, 4, 5, 2, 1, 0]);
+		}
+
+		#[test]
+		fn rotations() {
+			let panel = |rotation| Layout::new(3, 2, Wiring::Progressive, Corner::TopLeft, rotation);
+			assert_eq!(panel(Rotation::R0).drawing_size(), (3, 2));
+			assert_eq!(panel(Rotation::R90).drawing_size(), (2, 3));
+			assert_eq!(indexes(panel(Rotation::R0)), [0, 1, 2, 3, 4, 5]);
+			assert_eq!(indexes(panel(Rotation::R90)), [3, 0, 4, 1, 5, 2]);
+			assert_eq!(indexes(panel(Rotation::R180)), [5, 4, 3, 2, 1, 0]);
+			assert_eq!(indexes(panel(Rotation::R270)), [2, 5, 1, 4, 0, 3]);
+			//off the rotated picture, even where the unrotated panel would be:
+			let turned = panel(Rotation::R90);
+			for (x, y) in [(2, 0), (0, 3), (-1, 0), (0, -1)] {
+				assert_eq!(turned.index(x, y), None);
+			}
+		}
+
+		#[test]
+		fn lines() {
+			let steep = draw(4, 4, |canvas| canvas.line(0, 0, 1, 3, ON));
+			assert_eq!(steep, ["#...", "#...", ".#..", ".#.."]);
+			let backwards = draw(4, 4, |canvas| canvas.line(3, 3, 0, 0, ON));
+			assert_eq!(backwards, ["#...", ".#..", "..#.", "...#"]);
+			let point = draw(4, 4, |canvas| canvas.line(2, 1, 2, 1, ON));
+			assert_eq!(point, ["....", "..#.", "....", "...."]);
+			//the parts off the canvas are clipped:
+			let clipped = draw(4, 4, |canvas| canvas.line(-2, 1, 9, 1, ON));
+			assert_eq!(clipped, ["....", "####", "....", "...."]);
+		}
+
+		#[test]
+		fn rectangles() {
+			let outline = draw(4, 4, |canvas| canvas.rect(0, 0, 4, 3, ON));
+			assert_eq!(outline, ["####", "#..#", "####", "...."]);
+			let thin = draw(4, 4, |canvas| canvas.rect(1, 1, 1, 2, ON));
+			assert_eq!(thin, ["....", ".#..", ".#..", "...."]);
+			let filled = draw(4, 4, |canvas| canvas.fill_rect(2, 2, 5, 5, ON));
+			assert_eq!(filled, ["....", "....", "..##", "..##"]);
+			for (width, height) in [(0, 3), (3, 0), (-2, 2)] {
+				let empty = draw(4, 4, |canvas| {
+					canvas.rect(1, 1, width, height, ON);
+					canvas.fill_rect(1, 1, width, height, ON);
+				});
+				assert_eq!(empty, ["....", "....", "....", "...."]);
+			}
+		}
+
+		#[test]
+		fn circles() {
+			let dot = draw(4, 4, |canvas| canvas.fill_circle(2, 2, 0, ON));
+			assert_eq!(dot, ["....", "....", "..#.", "...."]);
+			let plus = draw(4, 4, |canvas| canvas.fill_circle(1, 1, 1, ON));
+			assert_eq!(plus, [".#..", "###.", ".#..", "...."]);
+			let corner = draw(4, 4, |canvas| canvas.fill_circle(0, 0, 2, ON));
+			assert_eq!(corner, ["###.", "##..", "#...", "...."]);
+		}
+
+		#[test]
+		fn short_frames_and_pixels_off_the_canvas() {
+			let layout = Layout::new(4, 4, Wiring::Progressive, Corner::TopLeft, Rotation::R0);
+			let mut frame = [c::C_OFF; 6];
+			let mut canvas = Canvas::new(layout, &mut frame);
+			canvas.fill(ON);
+			canvas.set_pixel(3, 3, ON);
+			assert_eq!(canvas.get_pixel(1, 1), Some(ON));
+			assert_eq!(canvas.get_pixel(3, 3), None);
+			assert_eq!(canvas.get_pixel(4, 0), None);
+			assert_eq!(frame, [ON; 6]);
+		}
+
+		#[test]
+		fn font() {
+			let one = draw(3, 5, |canvas| canvas.draw_char(0, 0, b'1', ON));
+			assert_eq!(one, [".#.", "##.", ".#.", ".#.", "###"]);
+			let lowercase = draw(3, 5, |canvas| canvas.draw_char(0, 0, b'k', ON));
+			let uppercase = draw(3, 5, |canvas| canvas.draw_char(0, 0, b'K', ON));
+			assert_eq!(lowercase, uppercase);
+			let unknown = draw(3, 5, |canvas| canvas.draw_char(0, 0, b'#', ON));
+			assert_eq!(unknown, ["..."; 5]);
+			let text = draw(8, 5, |canvas| canvas.draw_text(1, 0, "-1", ON));
+			assert_eq!(text, ["......#.", ".....##.", ".###..#.", "......#.", ".....###"]);
+			//every character is in the table once, and everything but the space draws something:
+			for (i, &(character, bits)) in FONT.iter().enumerate() {
+				assert!(FONT[i + 1..].iter().all(|&(other, _)| other != character));
+				assert!(bits < 1 << (GLYPH_WIDTH * GLYPH_HEIGHT));
+				assert_eq!(bits == 0, character == b' ');
+			}
+		}
+	}
+}
@@ -0,0 +1,378 @@
+pub mod mqtt {
+	use core::fmt;
+
+	//the parts of MQTT 3.1.1 a client that publishes at QoS 0 needs. The network side sends the
+	//packets written here over the TCP connection to the broker, and hands what comes back to
+	//read_packet. Every packet starts with a fixed header:
+	//  1 byte     packet type in the top 4 bits, flags in the low 4
+	//  1-4 bytes  remaining length, 7 bits a byte, low bits first, with the top bit set on all
+	//             but the last byte
+	//then the packet's own fields. Strings are a 2 byte big endian length followed by UTF-8.
+	pub const MQTT_PORT: u16 = 1883;
+	const PROTOCOL_NAME: &str = "MQTT";
+	const PROTOCOL_LEVEL_3_1_1: u8 = 4;
+	//the longest remaining length, which takes 4 bytes to write:
+	const MAX_REMAINING_LENGTH: usize = 268_435_455;
+	const MAX_FIXED_HEADER_LENGTH: usize = 5;
+
+	//the packet types, which go in the top 4 bits of the first byte:
+	const CONNACK: u8 = 2;
+	const PUBLISH: u8 = 3;
+	const SUBACK: u8 = 9;
+	const PINGRESP: u8 = 13;
+	//the whole first byte of the packets that don't have any flags of their own:
+	const CONNECT: u8 = 0x10;
+	const SUBSCRIBE: u8 = 0x82;
+	const PINGREQ: u8 = 0xC0;
+	const DISCONNECT: u8 = 0xE0;
+
+	const CONNECT_CLEAN_SESSION: u8 = 0x02;
+	const CONNECT_WILL: u8 = 0x04;
+	const CONNECT_WILL_RETAIN: u8 = 0x20;
+	const CONNECT_PASSWORD: u8 = 0x40;
+	const CONNECT_USERNAME: u8 = 0x80;
+	const PUBLISH_RETAIN: u8 = 0x01;
+	//a SUBACK return code saying the broker turned the subscription down:
+	pub const SUBSCRIPTION_FAILED: u8 = 0x80;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum MqttError {
+		//the packet doesn't fit in the buffer it is being written into
+		BufferTooSmall,
+		//a packet from the broker doesn't make sense
+		Malformed,
+	}
+
+	//a message published on a topic. Everything here is sent at QoS 0:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Message<'a> {
+		pub topic: &'a str,
+		pub payload: &'a [u8],
+		//the broker keeps the last retained message on a topic, and sends it to new subscribers
+		pub retain: bool,
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Connect<'a> {
+		pub client_id: &'a str,
+		//the longest the client goes without sending anything, after which the broker drops it
+		pub keep_alive_s: u16,
+		pub username: Option<&'a str>,
+		pub password: Option<&'a [u8]>,
+		//the message the broker publishes if the client goes away without disconnecting
+		pub will: Option<Message<'a>>,
+	}
+
+	//the packets that come back from the broker:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Packet<'a> {
+		//the broker's answer to CONNECT. A return code other than 0 means it refused the connection.
+		ConnAck { session_present: bool, return_code: u8 },
+		//a message on a subscribed topic. Subscriptions are QoS 0, so these don't need acknowledging.
+		Publish(Message<'a>),
+		//the return codes are one byte for each topic subscribed to, see SUBSCRIPTION_FAILED
+		SubAck { packet_id: u16, return_codes: &'a [u8] },
+		PingResp,
+		//a packet a QoS 0 client doesn't expect, by its type
+		Other(u8),
+	}
+
+	//writes a packet's fields into a buffer. It also writes payloads with fmt::Write, for the
+	//ones built up from text.
+	pub struct Writer<'a> {
+		buffer: &'a mut [u8],
+		length: usize,
+	}
+
+	impl<'a> Writer<'a> {
+		fn bytes(&mut self, bytes: &[u8]) -> Result<(), MqttError> {
+			let end = self.length + bytes.len();
+			self.buffer.get_mut(self.length..end).ok_or(MqttError::BufferTooSmall)?.copy_from_slice(bytes);
+			self.length = end;
+			Ok(())
+		}
+
+		fn byte(&mut self, byte: u8) -> Result<(), MqttError> {
+			self.bytes(&[byte])
+		}
+
+		fn u16(&mut self, value: u16) -> Result<(), MqttError> {
+			self.bytes(&value.to_be_bytes())
+		}
+
+		//bytes with their length in front, which is how strings and passwords are sent:
+		fn prefixed(&mut self, bytes: &[u8]) -> Result<(), MqttError> {
+			if bytes.len() > u16::MAX as usize {
+				return Err(MqttError::BufferTooSmall);
+			}
+			self.u16(bytes.len() as u16)?;
+			self.bytes(bytes)
+		}
+
+		fn string(&mut self, text: &str) -> Result<(), MqttError> {
+			self.prefixed(text.as_bytes())
+		}
+	}
+
+	impl<'a> fmt::Write for Writer<'a> {
+		fn write_str(&mut self, text: &str) -> fmt::Result {
+			self.bytes(text.as_bytes()).map_err(|_| fmt::Error)
+		}
+	}
+
+	//this writes a packet, with its fields written by write_fields, and returns its length. The
+	//fields are written after room for the longest fixed header, and moved down once their
+	//length is known.
+	fn write_packet<F>(buffer: &mut [u8], first_byte: u8, write_fields: F) -> Result<usize, MqttError>
+	where F: FnOnce(&mut Writer) -> Result<(), MqttError>,
+	{
+		if buffer.len() < MAX_FIXED_HEADER_LENGTH {
+			return Err(MqttError::BufferTooSmall);
+		}
+		let mut writer = Writer { buffer: &mut *buffer, length: MAX_FIXED_HEADER_LENGTH };
+		write_fields(&mut writer)?;
+		let remaining_length = writer.length - MAX_FIXED_HEADER_LENGTH;
+		if remaining_length > MAX_REMAINING_LENGTH {
+			return Err(MqttError::BufferTooSmall);
+		}
+		let mut header = [first_byte, 0, 0, 0, 0];
+		let mut header_length = 1;
+		let mut rest = remaining_length;
+		loop {
+			header[header_length] = (rest % 128) as u8;
+			rest /= 128;
+			if rest > 0 {
+				header[header_length] |= 0x80;
+			}
+			header_length += 1;
+			if rest == 0 {
+				break;
+			}
+		}
+		buffer.copy_within(MAX_FIXED_HEADER_LENGTH..MAX_FIXED_HEADER_LENGTH + remaining_length, header_length);
+		buffer[..header_length].copy_from_slice(&header[..header_length]);
+		Ok(header_length + remaining_length)
+	}
+
+	pub fn write_connect(buffer: &mut [u8], connect: &Connect) -> Result<usize, MqttError> {
+		let mut flags = CONNECT_CLEAN_SESSION;
+		if let Some(will) = connect.will {
+			flags |= CONNECT_WILL;
+			if will.retain {
+				flags |= CONNECT_WILL_RETAIN;
+			}
+		}
+		if connect.username.is_some() {
+			flags |= CONNECT_USERNAME;
+		}
+		if connect.password.is_some() {
+			flags |= CONNECT_PASSWORD;
+		}
+		write_packet(buffer, CONNECT, |writer| {
+			writer.string(PROTOCOL_NAME)?;
+			writer.byte(PROTOCOL_LEVEL_3_1_1)?;
+			writer.byte(flags)?;
+			writer.u16(connect.keep_alive_s)?;
+			writer.string(connect.client_id)?;
+			if let Some(will) = connect.will {
+				writer.string(will.topic)?;
+				writer.prefixed(will.payload)?;
+			}
+			if let Some(username) = connect.username {
+				writer.string(username)?;
+			}
+			if let Some(password) = connect.password {
+				writer.prefixed(password)?;
+			}
+			Ok(())
+		})
+	}
+
+	//this writes a PUBLISH with its payload written by write_payload, for payloads that are put
+	//together as they are written, like JSON:
+	pub fn write_publish_with<F>(buffer: &mut [u8], topic: &str, retain: bool, write_payload: F) -> Result<usize, MqttError>
+	where F: FnOnce(&mut Writer) -> fmt::Result,
+	{
+		let first_byte = PUBLISH << 4 | if retain { PUBLISH_RETAIN } else { 0 };
+		write_packet(buffer, first_byte, |writer| {
+			writer.string(topic)?;
+			write_payload(writer).map_err(|_| MqttError::BufferTooSmall)
+		})
+	}
+
+	pub fn write_publish(buffer: &mut [u8], message: &Message) -> Result<usize, MqttError> {
+		write_publish_with(buffer, message.topic, message.retain, |writer| {
+			writer.bytes(message.payload).map_err(|_| fmt::Error)
+		})
+	}
+
+	//this subscribes to topics at QoS 0. Topics can have the + and # wildcards.
+	pub fn write_subscribe(buffer: &mut [u8], packet_id: u16, topics: &[&str]) -> Result<usize, MqttError> {
+		write_packet(buffer, SUBSCRIBE, |writer| {
+			writer.u16(packet_id)?;
+			for topic in topics {
+				writer.string(topic)?;
+				writer.byte(0)?;
+			}
+			Ok(())
+		})
+	}
+
+	pub fn write_ping(buffer: &mut [u8]) -> Result<usize, MqttError> {
+		write_packet(buffer, PINGREQ, |_| Ok(()))
+	}
+
+	//this ends the connection without the broker publishing the will:
+	pub fn write_disconnect(buffer: &mut [u8]) -> Result<usize, MqttError> {
+		write_packet(buffer, DISCONNECT, |_| Ok(()))
+	}
+
+	//this reads the packet at the start of bytes, returning it and its length, or None if it
+	//hasn't all come in yet.
+	pub fn read_packet(bytes: &[u8]) -> Result<Option<(Packet<'_>, usize)>, MqttError> {
+		let mut remaining_length = 0;
+		let mut header_length = 1;
+		loop {
+			let byte = match bytes.get(header_length) {
+				Some(&byte) => byte,
+				None => return Ok(None),
+			};
+			remaining_length |= (byte as usize & 0x7F) << (7 * (header_length - 1));
+			header_length += 1;
+			if byte & 0x80 == 0 {
+				break;
+			}
+			if header_length == MAX_FIXED_HEADER_LENGTH {
+				return Err(MqttError::Malformed);
+			}
+		}
+		let length = header_length + remaining_length;
+		let fields = match bytes.get(header_length..length) {
+			Some(fields) => fields,
+			None => return Ok(None),
+		};
+		let packet = match (bytes[0] >> 4, fields) {
+			(CONNACK, &[flags, return_code]) => Packet::ConnAck { session_present: flags & 0x01 != 0, return_code },
+			(CONNACK, _) => return Err(MqttError::Malformed),
+			(PUBLISH, [high, low, rest @ ..]) => {
+				let topic_length = u16::from_be_bytes([*high, *low]) as usize;
+				let topic = rest.get(..topic_length).ok_or(MqttError::Malformed)?;
+				//messages sent at QoS 1 or 2 have a packet ID after the topic:
+				let payload_start = match (bytes[0] >> 1) & 0x03 {
+					0 => topic_length,
+					_ => topic_length + 2,
+				};
+				Packet::Publish(Message {
+					topic: core::str::from_utf8(topic).map_err(|_| MqttError::Malformed)?,
+					payload: rest.get(payload_start..).ok_or(MqttError::Malformed)?,
+					retain: bytes[0] & PUBLISH_RETAIN != 0,
+				})
+			}
+			(SUBACK, [high, low, return_codes @ ..]) => Packet::SubAck { packet_id: u16::from_be_bytes([*high, *low]), return_codes },
+			(PUBLISH, _) | (SUBACK, _) => return Err(MqttError::Malformed),
+			(PINGRESP, _) => Packet::PingResp,
+			(packet_type, _) => Packet::Other(packet_type),
+		};
+		Ok(Some((packet, length)))
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		//this publishes a message whose remaining length is remaining_length, with a one letter topic:
+		fn publish_of_length(remaining_length: usize, buffer: &mut [u8]) -> usize {
+			let payload = vec![b'x'; remaining_length - 3];
+			write_publish(buffer, &Message { topic: "t", payload: &payload, retain: false }).unwrap()
+		}
+
+		#[test]
+		fn remaining_length() {
+			let mut buffer = vec![0_u8; 20_000];
+			assert_eq!(write_ping(&mut buffer), Ok(2));
+			assert_eq!(buffer[..2], [PINGREQ, 0x00]);
+			assert_eq!(write_disconnect(&mut buffer), Ok(2));
+			assert_eq!(buffer[..2], [DISCONNECT, 0x00]);
+			for &(remaining_length, header) in [
+				(127, &[0x7F][..]),
+				(128, &[0x80, 0x01][..]),
+				(16_383, &[0xFF, 0x7F][..]),
+				(16_384, &[0x80, 0x80, 0x01][..]),
+			].iter() {
+				let length = publish_of_length(remaining_length, &mut buffer);
+				assert_eq!(length, 1 + header.len() + remaining_length);
+				assert_eq!(buffer[0], PUBLISH << 4);
+				assert_eq!(&buffer[1..1 + header.len()], header, "{}", remaining_length);
+				assert_eq!(buffer[1 + header.len()..1 + header.len() + 3], [0x00, 0x01, b't']);
+				match read_packet(&buffer[..length]) {
+					Ok(Some((Packet::Publish(message), read_length))) => {
+						assert_eq!(read_length, length);
+						assert_eq!(message.topic, "t");
+						assert_eq!(message.payload.len(), remaining_length - 3);
+					}
+					other => panic!("{}: {:?}", remaining_length, other),
+				}
+			}
+			//the header is worked out after the fields are written, so they have to fit with the longest header:
+			let message = Message { topic: "t", payload: &[b'x'; 125], retain: false };
+			assert_eq!(write_publish(&mut buffer[..130], &message), Err(MqttError::BufferTooSmall));
+			assert_eq!(write_publish(&mut buffer[..133], &message), Ok(131));
+		}
+
+		#[test]
+		fn read_packet_waits_for_the_whole_packet() {
+			let mut buffer = [0_u8; 300];
+			let length = publish_of_length(200, &mut buffer);
+			//two bytes of remaining length, then the fields:
+			for end in 0..length {
+				assert_eq!(read_packet(&buffer[..end]), Ok(None), "{}", end);
+			}
+			assert!(matches!(read_packet(&buffer[..length]), Ok(Some((Packet::Publish(_), 203)))));
+			//anything after the packet is left for the next one:
+			assert!(matches!(read_packet(&buffer[..]), Ok(Some((Packet::Publish(_), 203)))));
+			//a remaining length can't take more than 4 bytes:
+			assert_eq!(read_packet(&[PUBLISH << 4, 0x80, 0x80, 0x80, 0x80, 0x01]), Err(MqttError::Malformed));
+			assert_eq!(read_packet(&[PUBLISH << 4, 0x80, 0x80, 0x80]), Ok(None));
+		}
+
+		#[test]
+		fn broker_packets() {
+			assert_eq!(read_packet(&[0x20, 0x02, 0x01, 0x00]), Ok(Some((Packet::ConnAck { session_present: true, return_code: 0 }, 4))));
+			assert_eq!(read_packet(&[0x20, 0x03, 0x00, 0x00, 0x00]), Err(MqttError::Malformed));
+			assert_eq!(read_packet(&[0x90, 0x04, 0x00, 0x07, 0x00, 0x80]), Ok(Some((Packet::SubAck { packet_id: 7, return_codes: &[0x00, SUBSCRIPTION_FAILED] }, 6))));
+			assert_eq!(read_packet(&[0xD0, 0x00]), Ok(Some((Packet::PingResp, 2))));
+			assert_eq!(read_packet(&[0xB0, 0x02, 0x00, 0x01]), Ok(Some((Packet::Other(11), 4))));
+			//a retained QoS 1 message has a packet ID between the topic and the payload:
+			assert_eq!(
+				read_packet(&[0x33, 0x07, 0x00, 0x01, b'a', 0x12, 0x34, b'h', b'i']),
+				Ok(Some((Packet::Publish(Message { topic: "a", payload: b"hi", retain: true }), 9)))
+			);
+			assert_eq!(read_packet(&[0x30, 0x03, 0x00, 0x05, b'a']), Err(MqttError::Malformed));
+		}
+
+		#[test]
+		fn connect() {
+			let mut buffer = [0_u8; 128];
+			let connect = Connect {
+				client_id: "id",
+				keep_alive_s: 60,
+				username: Some("u"),
+				password: Some(b"pw"),
+				will: Some(Message { topic: "w", payload: b"off", retain: true }),
+			};
+			let length = write_connect(&mut buffer, &connect).unwrap();
+			assert_eq!(buffer[..length], [
+				0x10, 29,
+				0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04,
+				CONNECT_USERNAME | CONNECT_PASSWORD | CONNECT_WILL_RETAIN | CONNECT_WILL | CONNECT_CLEAN_SESSION,
+				0x00, 60,
+				0x00, 0x02, b'i', b'd',
+				0x00, 0x01, b'w', 0x00, 0x03, b'o', b'f', b'f',
+				0x00, 0x01, b'u',
+				0x00, 0x02, b'p', b'w',
+			]);
+			assert_eq!(write_connect(&mut buffer[..20], &connect), Err(MqttError::BufferTooSmall));
+			assert_eq!(write_ping(&mut buffer[..4]), Err(MqttError::BufferTooSmall));
+		}
+	}
+}
@@ -0,0 +1,243 @@
+pub mod noise {
+	use crate::c;
+
+	//noise coordinates are 16.16 fixed point. The integer part picks the lattice cell
+	//and the fraction is the position inside it, so 0x1_0000 is one cell.
+	pub const NOISE_CELL: u32 = 0x1_0000;
+
+	//internally the interpolation runs on 12 bit fractions to keep everything in i32:
+	const FRACTION_BITS: u32 = 12;
+	const FRACTION_ONE: i32 = 1 << FRACTION_BITS;
+
+	//Ken Perlin's reference permutation table:
+	const PERMUTATION: [u8; 256] = [
+		151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225,
+		140, 36, 103, 30, 69, 142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148,
+		247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219, 203, 117, 35, 11, 32,
+		57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+		74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122,
+		60, 211, 133, 230, 220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54,
+		65, 25, 63, 161, 1, 216, 80, 73, 209, 76, 132, 187, 208, 89, 18, 169,
+		200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173, 186, 3, 64,
+		52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212,
+		207, 206, 59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213,
+		119, 248, 152, 2, 44, 154, 163, 70, 221, 153, 101, 155, 167, 43, 172, 9,
+		129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232, 178, 185, 112, 104,
+		218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162, 241,
+		81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157,
+		184, 84, 204, 176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93,
+		222, 114, 67, 29, 24, 72, 243, 141, 128, 195, 78, 66, 215, 61, 156, 180,
+	];
+
+	fn hash(x: u32) -> u8 {
+		PERMUTATION[(x & 0xFF) as usize]
+	}
+
+	fn hash_2d(x: u32, y: u32) -> u8 {
+		PERMUTATION[(hash(x) as u32 + (y & 0xFF)) as usize & 0xFF]
+	}
+
+	//smoothstep fade curve, 3t^2 - 2t^3, on a 12 bit fraction:
+	fn fade(t: i32) -> i32 {
+		let t_squared = (t * t) >> FRACTION_BITS;
+		(t_squared * (3 * FRACTION_ONE - 2 * t)) >> FRACTION_BITS
+	}
+
+	fn lerp(a: i32, b: i32, t: i32) -> i32 {
+		a + (((b - a) * t) >> FRACTION_BITS)
+	}
+
+	//1D gradients are slopes from -4 to 4, skipping 0 so every lattice point has some slope:
+	fn gradient_1d(hash: u8, x: i32) -> i32 {
+		let slope = (hash & 0x03) as i32 + 1;
+		if hash & 0x04 == 0 {
+			slope * x
+		} else {
+			-slope * x
+		}
+	}
+
+	//2D gradients point at the corners and edges of a square:
+	fn gradient_2d(hash: u8, x: i32, y: i32) -> i32 {
+		match hash & 0x07 {
+			0 => x + y,
+			1 => -x + y,
+			2 => x - y,
+			3 => -x - y,
+			4 => x,
+			5 => -x,
+			6 => y,
+			_ => -y,
+		}
+	}
+
+	fn fraction(coordinate: u32) -> i32 {
+		((coordinate & 0xFFFF) >> (16 - FRACTION_BITS)) as i32
+	}
+
+	//this converts a raw noise value to a u8 with 128 in the middle.
+	//the scale was picked so typical noise fills most of the range without clipping too often.
+	fn to_u8(value: i32, full_scale: i32) -> u8 {
+		(128 + value * 128 / full_scale).clamp(0, 255) as u8
+	}
+
+	//1D gradient noise at a 16.16 fixed point coordinate. Returns 0 to 255, centered on 128.
+	pub fn noise_1d(x: u32) -> u8 {
+		let cell = x >> 16;
+		let x_fraction = fraction(x);
+		let left = gradient_1d(hash(cell), x_fraction);
+		let right = gradient_1d(hash(cell.wrapping_add(1)), x_fraction - FRACTION_ONE);
+		to_u8(lerp(left, right, fade(x_fraction)), 2 * FRACTION_ONE)
+	}
+
+	//2D gradient noise at 16.16 fixed point coordinates. Returns 0 to 255, centered on 128.
+	pub fn noise_2d(x: u32, y: u32) -> u8 {
+		let x_cell = x >> 16;
+		let y_cell = y >> 16;
+		let x_fraction = fraction(x);
+		let y_fraction = fraction(y);
+		let x_next = x_cell.wrapping_add(1);
+		let y_next = y_cell.wrapping_add(1);
+
+		let bottom_left = gradient_2d(hash_2d(x_cell, y_cell), x_fraction, y_fraction);
+		let bottom_right = gradient_2d(hash_2d(x_next, y_cell), x_fraction - FRACTION_ONE, y_fraction);
+		let top_left = gradient_2d(hash_2d(x_cell, y_next), x_fraction, y_fraction - FRACTION_ONE);
+		let top_right = gradient_2d(hash_2d(x_next, y_next), x_fraction - FRACTION_ONE, y_fraction - FRACTION_ONE);
+
+		let x_fade = fade(x_fraction);
+		let bottom = lerp(bottom_left, bottom_right, x_fade);
+		let top = lerp(top_left, top_right, x_fade);
+		to_u8(lerp(bottom, top, fade(y_fraction)), FRACTION_ONE * 3 / 4)
+	}
+
+	//this sums several octaves of 2D noise, each at twice the frequency and half the weight of the last.
+	//more octaves give rougher, more detailed noise.
+	pub fn fractal_noise_2d(x: u32, y: u32, octaves: u8) -> u8 {
+		let mut total = 0_i32;
+		let mut total_weight = 0_i32;
+		let mut weight = 128_i32;
+		for octave in 0..octaves.clamp(1, 8) as u32 {
+			//offset each octave so their lattice points don't line up:
+			let offset = octave.wrapping_mul(0x3_7A5C);
+			let sample = noise_2d((x << octave).wrapping_add(offset), (y << octave).wrapping_add(offset));
+			total += (sample as i32 - 128) * weight;
+			total_weight += weight;
+			weight /= 2;
+		}
+		//the octaves partly cancel each other out, so stretch the result back out a bit:
+		(128 + total * 3 / (total_weight * 2)).clamp(0, 255) as u8
+	}
+
+	//an animation that samples 2D noise with the LED position on one axis and time on the other,
+	//then maps the noise value through a rainbow.
+	pub struct NoiseAnimation {
+		pub rainbow: c::Rainbow,
+		//distance between neighboring LEDs in noise space, 16.16 fixed point:
+		pub led_spacing: u32,
+		//how far the noise moves through time each second, 16.16 fixed point:
+		pub speed: u32,
+		//how far the pattern slides along the strip each second, 16.16 fixed point:
+		pub drift: i32,
+		pub octaves: u8,
+	}
+
+	impl NoiseAnimation {
+		pub fn new(rainbow: c::Rainbow, led_spacing: u32, speed: u32, drift: i32, octaves: u8) -> Self {
+			NoiseAnimation {
+				rainbow,
+				led_spacing,
+				speed,
+				drift,
+				octaves,
+			}
+		}
+
+		//slow greens and whites rippling across the ceiling:
+		pub fn aurora() -> Self {
+			NoiseAnimation::new(c::R_GW, NOISE_CELL / 24, NOISE_CELL / 6, NOISE_CELL as i32 / 16, 2)
+		}
+
+		//blue swells rolling along the strip:
+		pub fn ocean() -> Self {
+			NoiseAnimation::new(c::R_BW, NOISE_CELL / 16, NOISE_CELL / 4, NOISE_CELL as i32 / 3, 1)
+		}
+
+		//slow, lumpy reds with bright spots bubbling up:
+		pub fn lava() -> Self {
+			NoiseAnimation::new(c::R_RW, NOISE_CELL / 10, NOISE_CELL / 10, 0, 3)
+		}
+
+		//this gets the noise value for one LED at the current time:
+		pub fn sample(&self, now_ms: u32, led_index: usize) -> u8 {
+			let time = (now_ms as u64 * self.speed as u64 / 1000) as u32;
+			let drift = (now_ms as i64 * self.drift as i64 / 1000) as u32;
+			let x = (led_index as u32).wrapping_mul(self.led_spacing).wrapping_sub(drift);
+			fractal_noise_2d(x, time, self.octaves)
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			for (i, led) in frame.iter_mut().enumerate() {
+				*led = self.rainbow.color_at_position((self.sample(now_ms, i) as u16) << 8);
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		//a strip of samples a 256th of a cell apart, across 64 cells:
+		fn sweep(noise: impl Fn(u32) -> u8) -> Vec<u8> {
+			(0..256 * 64).map(|i| noise(i * NOISE_CELL / 256)).collect()
+		}
+
+		#[test]
+		fn lattice_points_are_in_the_middle() {
+			for cell in [0, 1, 7, 255, 256, 0xFFF0] {
+				assert_eq!(noise_1d(cell * NOISE_CELL), 128);
+				assert_eq!(noise_2d(cell * NOISE_CELL, (cell + 3) * NOISE_CELL), 128);
+			}
+		}
+
+		#[test]
+		fn noise_fills_the_range() {
+			for samples in [sweep(noise_1d), sweep(|x| noise_2d(x, 77_777)), sweep(|x| fractal_noise_2d(x, 77_777, 3))] {
+				assert!(*samples.iter().min().unwrap() < 48);
+				assert!(*samples.iter().max().unwrap() > 208);
+				let mean = samples.iter().map(|&sample| sample as u32).sum::<u32>() / samples.len() as u32;
+				assert!((108..=148).contains(&mean), "{}", mean);
+			}
+		}
+
+		#[test]
+		fn noise_is_smooth() {
+			for (samples, max_step) in [
+				(sweep(noise_1d), 2),
+				(sweep(|x| noise_2d(x, 77_777)), 2),
+				(sweep(|y| noise_2d(0x12_3456, y)), 2),
+				(sweep(|x| fractal_noise_2d(x, 77_777, 3)), 3),
+			] {
+				for pair in samples.windows(2) {
+					assert!((pair[0] as i32 - pair[1] as i32).abs() <= max_step, "{:?}", pair);
+				}
+			}
+		}
+
+		#[test]
+		fn noise_is_the_same_every_time() {
+			assert_eq!(noise_1d(0x1_8000), 112);
+			assert_eq!(noise_1d(0x2_4000), 125);
+			assert_eq!(noise_2d(0x1_8000, 0x2_4000), 226);
+			assert_eq!(fractal_noise_2d(0x1_8000, 0x2_4000, 4), 215);
+			//octaves are kept to 1 through 8:
+			assert_eq!(fractal_noise_2d(0x1_8000, 0x2_4000, 0), fractal_noise_2d(0x1_8000, 0x2_4000, 1));
+			assert_eq!(fractal_noise_2d(0x1_8000, 0x2_4000, 20), fractal_noise_2d(0x1_8000, 0x2_4000, 8));
+			let mut frames = [[c::C_OFF; 30]; 2];
+			for frame in frames.iter_mut() {
+				NoiseAnimation::lava().update(12_345, frame);
+			}
+			assert_eq!(frames[0], frames[1]);
+			assert!(frames[0].iter().any(|&led| led != frames[0][0]));
+		}
+	}
+}
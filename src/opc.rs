@@ -0,0 +1,302 @@
+pub mod opc {
+	use crate::c;
+	use crate::cf;
+	use core::ops::Range;
+
+	//Open Pixel Control runs over TCP. The network side feeds the bytes from a connection to an
+	//OpcParser as they arrive, however they are split up, and resets it for each new connection.
+	//a message is laid out as:
+	//  1 byte   channel, see ChannelMap
+	//  1 byte   command
+	//  2 bytes  data length, big endian
+	//  data
+	//set pixel colors data is red, green, blue for each LED. System exclusive data starts with a
+	//system ID saying whose commands follow. Ours are laid out as:
+	//  2 bytes  system ID, SYSTEM_ID
+	//  1 byte   command, see SYSEX_*
+	//  data     the command's
+	pub const OPC_PORT: u16 = 7890;
+	const HEADER_LENGTH: usize = 4;
+	const COMMAND_SET_PIXELS: u8 = 0x00;
+	const COMMAND_SYSTEM_EXCLUSIVE: u8 = 0xFF;
+	//"WS", for WS2811:
+	pub const SYSTEM_ID: u16 = 0x5753;
+	//set the brightness, with 1 byte from 0 to 255:
+	const SYSEX_BRIGHTNESS: u8 = 0x01;
+	//the longest system exclusive message kept. Anything past this is ignored:
+	const MAX_SYSEX_LENGTH: usize = 8;
+
+	//where each channel's LEDs are in the strip. Channel 0 is the whole strip, end to end, and
+	//channels 1 and up are each of the physical strips in it, in order.
+	#[derive(Clone, PartialEq, Debug)]
+	pub struct ChannelMap {
+		led_count: usize,
+		//where each physical strip starts, and how many LEDs it has:
+		strips: [(usize, usize); cf::MAX_PHYSICAL_STRIPS],
+		strip_count: usize,
+	}
+
+	impl ChannelMap {
+		//this makes a map for a strip of led_count LEDs, made of physical strips covering the
+		//ranges of it given. Strips past MAX_PHYSICAL_STRIPS are left out.
+		pub fn new(led_count: usize, strips: &[Range<usize>]) -> Self {
+			let mut map = ChannelMap {
+				led_count,
+				strips: [(0, 0); cf::MAX_PHYSICAL_STRIPS],
+				strip_count: strips.len().min(cf::MAX_PHYSICAL_STRIPS),
+			};
+			for (slot, range) in map.strips.iter_mut().zip(strips) {
+				*slot = (range.start, range.len());
+			}
+			map
+		}
+
+		//where a channel's LEDs start in the strip and how many there are, or None for a channel
+		//that isn't there:
+		pub fn channel(&self, channel: u8) -> Option<(usize, usize)> {
+			match channel as usize {
+				0 => Some((0, self.led_count)),
+				strip if strip <= self.strip_count => Some(self.strips[strip - 1]),
+				_ => None,
+			}
+		}
+	}
+
+	//what a message turned out to need:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Event {
+		Nothing,
+		//a set pixel colors message is done, and the whole strip's frame, this many LEDs, can be shown
+		Frame(usize),
+		Brightness(u8),
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	enum State {
+		Header { header: [u8; HEADER_LENGTH], received: usize },
+		//LED data for a channel, starting at LED start and stopping after led_count LEDs
+		Pixels { start: usize, led_count: usize, length: usize, received: usize },
+		SystemExclusive { data: [u8; MAX_SYSEX_LENGTH], length: usize, received: usize },
+		//a message for a channel or command that isn't handled
+		Skip { remaining: usize },
+	}
+
+	const NEW_MESSAGE: State = State::Header { header: [0; HEADER_LENGTH], received: 0 };
+
+	pub struct OpcParser {
+		pub map: ChannelMap,
+		state: State,
+	}
+
+	impl OpcParser {
+		pub fn new(map: ChannelMap) -> Self {
+			OpcParser {
+				map,
+				state: NEW_MESSAGE,
+			}
+		}
+
+		//this starts over, for a new connection:
+		pub fn reset(&mut self) {
+			self.state = NEW_MESSAGE;
+		}
+
+		//this takes the next byte from the connection. LED data is written straight into pixels,
+		//which holds the whole strip, as it arrives.
+		pub fn push(&mut self, byte: u8, pixels: &mut [c::Color]) -> Event {
+			let (state, event) = match self.state {
+				State::Header { mut header, received } => {
+					header[received] = byte;
+					match received + 1 {
+						HEADER_LENGTH => self.start_message(header, pixels),
+						received => (State::Header { header, received }, Event::Nothing),
+					}
+				}
+				State::Pixels { start, led_count, length, received } => {
+					let index = received / 3;
+					if let Some(pixel) = pixels.get_mut(start + index).filter(|_| index < led_count) {
+						match received % 3 {
+							0 => pixel.r = byte,
+							1 => pixel.g = byte,
+							_ => pixel.b = byte,
+						}
+					}
+					match received + 1 {
+						received if received == length => (NEW_MESSAGE, self.frame(pixels)),
+						received => (State::Pixels { start, led_count, length, received }, Event::Nothing),
+					}
+				}
+				State::SystemExclusive { mut data, length, received } => {
+					if let Some(slot) = data.get_mut(received) {
+						*slot = byte;
+					}
+					match received + 1 {
+						received if received == length => (NEW_MESSAGE, system_exclusive(&data[..length.min(MAX_SYSEX_LENGTH)])),
+						received => (State::SystemExclusive { data, length, received }, Event::Nothing),
+					}
+				}
+				State::Skip { remaining } => match remaining - 1 {
+					0 => (NEW_MESSAGE, Event::Nothing),
+					remaining => (State::Skip { remaining }, Event::Nothing),
+				},
+			};
+			self.state = state;
+			event
+		}
+
+		//this works out what to do with a message's data once its header is in. Messages
+		//without any data are done straight away.
+		fn start_message(&self, header: [u8; HEADER_LENGTH], pixels: &[c::Color]) -> (State, Event) {
+			let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+			let state = match (header[1], self.map.channel(header[0])) {
+				(COMMAND_SET_PIXELS, Some((start, led_count))) => State::Pixels { start, led_count, length, received: 0 },
+				(COMMAND_SYSTEM_EXCLUSIVE, _) => State::SystemExclusive { data: [0; MAX_SYSEX_LENGTH], length, received: 0 },
+				_ => State::Skip { remaining: length },
+			};
+			match (length, state) {
+				(0, State::Pixels { .. }) => (NEW_MESSAGE, self.frame(pixels)),
+				(0, _) => (NEW_MESSAGE, Event::Nothing),
+				_ => (state, Event::Nothing),
+			}
+		}
+
+		fn frame(&self, pixels: &[c::Color]) -> Event {
+			Event::Frame(self.map.led_count.min(pixels.len()))
+		}
+	}
+
+	fn system_exclusive(data: &[u8]) -> Event {
+		match data {
+			[high, low, SYSEX_BRIGHTNESS, brightness, ..] if u16::from_be_bytes([*high, *low]) == SYSTEM_ID => Event::Brightness(*brightness),
+			_ => Event::Nothing,
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const LED_COUNT: usize = 10;
+
+		//three physical strips of 2, 5 and 3 LEDs:
+		fn parser() -> OpcParser {
+			OpcParser::new(ChannelMap::new(LED_COUNT, &[0..2, 2..7, 7..10]))
+		}
+
+		fn message(channel: u8, command: u8, data: &[u8]) -> Vec<u8> {
+			let mut message = vec![channel, command];
+			message.extend(&(data.len() as u16).to_be_bytes());
+			message.extend(data);
+			message
+		}
+
+		fn rgb(values: &[u8]) -> Vec<u8> {
+			values.iter().flat_map(|&value| vec![value, value, value]).collect()
+		}
+
+		fn events(parser: &mut OpcParser, bytes: &[u8], pixels: &mut [c::Color]) -> Vec<Event> {
+			bytes.iter().map(|&byte| parser.push(byte, pixels)).filter(|&event| event != Event::Nothing).collect()
+		}
+
+		fn gray(value: u8) -> c::Color {
+			c::Color { r: value, g: value, b: value }
+		}
+
+		#[test]
+		fn channels() {
+			let map = parser().map;
+			assert_eq!(map.channel(0), Some((0, LED_COUNT)));
+			assert_eq!(map.channel(1), Some((0, 2)));
+			assert_eq!(map.channel(3), Some((7, 3)));
+			assert_eq!(map.channel(4), None);
+			//strips past the most there can be are left out:
+			let strips: Vec<Range<usize>> = (0..cf::MAX_PHYSICAL_STRIPS + 1).map(|strip| strip..strip + 1).collect();
+			let map = ChannelMap::new(strips.len(), &strips);
+			assert_eq!(map.channel(cf::MAX_PHYSICAL_STRIPS as u8), Some((cf::MAX_PHYSICAL_STRIPS - 1, 1)));
+			assert_eq!(map.channel(cf::MAX_PHYSICAL_STRIPS as u8 + 1), None);
+		}
+
+		#[test]
+		fn whole_strip_and_single_strips() {
+			let mut parser = parser();
+			let mut pixels = [c::C_OFF; LED_COUNT];
+			let whole = message(0, COMMAND_SET_PIXELS, &rgb(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]));
+			assert_eq!(events(&mut parser, &whole, &mut pixels), [Event::Frame(LED_COUNT)]);
+			assert_eq!(pixels[0], gray(1));
+			assert_eq!(pixels[9], gray(10));
+			//channel 2 only reaches the second strip, even with more data than it has LEDs:
+			let second = message(2, COMMAND_SET_PIXELS, &rgb(&[20, 21, 22, 23, 24, 25, 26]));
+			assert_eq!(events(&mut parser, &second, &mut pixels), [Event::Frame(LED_COUNT)]);
+			assert_eq!(pixels, [1, 2, 20, 21, 22, 23, 24, 8, 9, 10].map(gray));
+			//and less data only sets the LEDs it reaches:
+			let third = message(3, COMMAND_SET_PIXELS, &rgb(&[30]));
+			assert_eq!(events(&mut parser, &third, &mut pixels), [Event::Frame(LED_COUNT)]);
+			assert_eq!(pixels[7..], [30, 9, 10].map(gray));
+			//a message for a channel that isn't there is skipped, and the next one still works:
+			let mut bytes = message(4, COMMAND_SET_PIXELS, &rgb(&[40, 40]));
+			bytes.extend(message(1, COMMAND_SET_PIXELS, &rgb(&[11, 12])));
+			assert_eq!(events(&mut parser, &bytes, &mut pixels), [Event::Frame(LED_COUNT)]);
+			assert_eq!(pixels, [11, 12, 20, 21, 22, 23, 24, 30, 9, 10].map(gray));
+			//a message without data shows the frame as it is:
+			assert_eq!(events(&mut parser, &message(0, COMMAND_SET_PIXELS, &[]), &mut pixels), [Event::Frame(LED_COUNT)]);
+			//a shorter pixel buffer than the strip:
+			assert_eq!(events(&mut parser, &whole, &mut pixels[..4]), [Event::Frame(4)]);
+		}
+
+		#[test]
+		fn any_split_gives_the_same_result() {
+			let mut stream = message(0, COMMAND_SET_PIXELS, &rgb(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]));
+			stream.extend(message(0, 0x7F, &[1, 2, 3]));
+			stream.extend(message(0, COMMAND_SYSTEM_EXCLUSIVE, &[0x57, 0x53, SYSEX_BRIGHTNESS, 77]));
+			stream.extend(message(2, COMMAND_SET_PIXELS, &rgb(&[20, 21])));
+			let mut expected_pixels = [c::C_OFF; LED_COUNT];
+			let expected = events(&mut parser(), &stream, &mut expected_pixels);
+			assert_eq!(expected, [Event::Frame(LED_COUNT), Event::Brightness(77), Event::Frame(LED_COUNT)]);
+			for first in 0..stream.len() {
+				for second in first..stream.len() {
+					let mut parser = parser();
+					let mut pixels = [c::C_OFF; LED_COUNT];
+					let mut split = Vec::new();
+					for part in [&stream[..first], &stream[first..second], &stream[second..]].iter() {
+						split.extend(events(&mut parser, part, &mut pixels));
+					}
+					assert_eq!((split, pixels), (expected.clone(), expected_pixels), "split at {} and {}", first, second);
+				}
+			}
+		}
+
+		#[test]
+		fn brightness() {
+			let mut parser = parser();
+			let mut pixels = [c::C_OFF; LED_COUNT];
+			let sysex = |data: &[u8]| message(0, COMMAND_SYSTEM_EXCLUSIVE, data);
+			assert_eq!(events(&mut parser, &sysex(&[0x57, 0x53, SYSEX_BRIGHTNESS, 128]), &mut pixels), [Event::Brightness(128)]);
+			//system exclusive messages go to every channel:
+			assert_eq!(events(&mut parser, &message(9, COMMAND_SYSTEM_EXCLUSIVE, &[0x57, 0x53, SYSEX_BRIGHTNESS, 0]), &mut pixels), [Event::Brightness(0)]);
+			//someone else's system ID, another command, and the brightness missing:
+			assert!(events(&mut parser, &sysex(&[0x00, 0x01, SYSEX_BRIGHTNESS, 128]), &mut pixels).is_empty());
+			assert!(events(&mut parser, &sysex(&[0x57, 0x53, 0x02, 128]), &mut pixels).is_empty());
+			assert!(events(&mut parser, &sysex(&[0x57, 0x53, SYSEX_BRIGHTNESS]), &mut pixels).is_empty());
+			assert!(events(&mut parser, &sysex(&[]), &mut pixels).is_empty());
+			//a message too long to keep is still read to its end:
+			let mut long = vec![0x57, 0x53, SYSEX_BRIGHTNESS, 64];
+			long.extend(&[0; 100]);
+			let mut bytes = sysex(&long);
+			bytes.extend(sysex(&[0x57, 0x53, SYSEX_BRIGHTNESS, 32]));
+			assert_eq!(events(&mut parser, &bytes, &mut pixels), [Event::Brightness(64), Event::Brightness(32)]);
+			assert_eq!(pixels, [c::C_OFF; LED_COUNT]);
+		}
+
+		#[test]
+		fn reset() {
+			let mut parser = parser();
+			let mut pixels = [c::C_OFF; LED_COUNT];
+			let whole = message(0, COMMAND_SET_PIXELS, &rgb(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]));
+			//a connection that closes partway through a message:
+			events(&mut parser, &whole[..7], &mut pixels);
+			parser.reset();
+			assert_eq!(events(&mut parser, &whole, &mut pixels), [Event::Frame(LED_COUNT)]);
+			assert_eq!(pixels[9], gray(10));
+		}
+	}
+}
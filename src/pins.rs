@@ -8,6 +8,9 @@ pub mod pins {
 	pub const WINDOW_STRIP_PIN: u8 = 13;
 	pub const DOOR_STRIP_PIN: u8 = 25;
 
+	//strips can only be configured on the pins above:
+	pub const SUPPORTED_PINS: [u8; 3] = [CLOSET_STRIP_PIN, WINDOW_STRIP_PIN, DOOR_STRIP_PIN];
+
 	//struct to hold the actual pins.
 	//all pins must be of type OutputPin with a Push trait. The push trait allows
 	//them to be used with set_low() and set_high() even though they are
@@ -0,0 +1,388 @@
+pub mod playlist {
+	use crate::c;
+	use crate::ly;
+	use crate::rng;
+
+	pub const MAX_ENTRIES: usize = 16;
+
+	//how the lights get from one scene to the next:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Transition {
+		//switch straight over
+		Cut,
+		//crossfade from the old scene to the new one over this many milliseconds
+		Fade(u32),
+		//sweep the new scene in from the start of the strip over this many milliseconds
+		Wipe(u32),
+	}
+
+	impl Transition {
+		pub fn duration_ms(self) -> u32 {
+			match self {
+				Transition::Cut => 0,
+				Transition::Fade(duration_ms) | Transition::Wipe(duration_ms) => duration_ms,
+			}
+		}
+
+		//this mixes the frame the old scene left behind into the new scene's frame, elapsed_ms
+		//into the transition. Returns true once the transition is over and the new frame is left alone.
+		pub fn apply(self, from: &[c::Color], to: &mut [c::Color], elapsed_ms: u32) -> bool {
+			let duration_ms = self.duration_ms();
+			if elapsed_ms >= duration_ms {
+				return true;
+			}
+			let led_count = to.len();
+			for (i, (led, &old)) in to.iter_mut().zip(from).enumerate() {
+				*led = match self {
+					Transition::Cut => *led,
+					Transition::Fade(_) => {
+						let opacity = (elapsed_ms as u64 * 255 / duration_ms as u64) as u8;
+						ly::blend(old, *led, ly::BlendMode::Normal, opacity)
+					}
+					Transition::Wipe(_) => {
+						let edge = (elapsed_ms as u64 * led_count as u64 / duration_ms as u64) as usize;
+						if i < edge { *led } else { old }
+					}
+				};
+			}
+			false
+		}
+	}
+
+	//one step of a playlist: the scene slot to show, how long to show it for, and how to get to it.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Entry {
+		pub slot: u8,
+		pub duration_ms: u32,
+		pub transition: Transition,
+	}
+
+	impl Entry {
+		pub const fn new(slot: u8, duration_ms: u32, transition: Transition) -> Self {
+			Entry {
+				slot,
+				duration_ms,
+				transition,
+			}
+		}
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum State {
+		Stopped,
+		Playing,
+		Paused,
+	}
+
+	//where the playlist is, for reporting back to whatever is controlling it:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Status {
+		pub state: State,
+		//the index of the entry showing, in the order the entries were added:
+		pub entry: Option<usize>,
+		pub remaining_ms: u32,
+	}
+
+	//runs through a list of scenes, showing each for its duration. With shuffle on, the entries are
+	//played in a new random order each time through. With repeat off, the playlist stops after the
+	//last entry and the last scene stays up.
+	pub struct Playlist {
+		entries: [Option<Entry>; MAX_ENTRIES],
+		entry_count: usize,
+		pub shuffle: bool,
+		pub repeat: bool,
+		state: State,
+		//the order the entries are played in this time through:
+		order: [u8; MAX_ENTRIES],
+		//how far through the order we are:
+		position: usize,
+		//how long the current entry has been showing, not counting time paused:
+		elapsed_ms: u32,
+		last_update_ms: u32,
+		rng: rng::Rng,
+	}
+
+	impl Playlist {
+		pub fn new(seed: u32) -> Self {
+			Playlist {
+				entries: [None; MAX_ENTRIES],
+				entry_count: 0,
+				shuffle: false,
+				repeat: true,
+				state: State::Stopped,
+				order: [0; MAX_ENTRIES],
+				position: 0,
+				elapsed_ms: 0,
+				last_update_ms: 0,
+				rng: rng::Rng::new(seed),
+			}
+		}
+
+		//adds an entry to the end and returns its index, or hands it back if the playlist is full.
+		//while playing, the entry goes at the end of this time through.
+		pub fn add(&mut self, entry: Entry) -> Result<usize, Entry> {
+			if self.entry_count == MAX_ENTRIES {
+				return Err(entry);
+			}
+			let index = self.entry_count;
+			self.entries[index] = Some(entry);
+			self.order[index] = index as u8;
+			self.entry_count += 1;
+			Ok(index)
+		}
+
+		//this empties the playlist and stops it:
+		pub fn clear(&mut self) {
+			self.entries = [None; MAX_ENTRIES];
+			self.entry_count = 0;
+			self.state = State::Stopped;
+			self.order = [0; MAX_ENTRIES];
+			self.position = 0;
+			self.elapsed_ms = 0;
+		}
+
+		pub fn len(&self) -> usize {
+			self.entry_count
+		}
+
+		pub fn is_empty(&self) -> bool {
+			self.entry_count == 0
+		}
+
+		pub fn entry(&self, index: usize) -> Option<Entry> {
+			self.entries.get(index).copied().flatten()
+		}
+
+		pub fn state(&self) -> State {
+			self.state
+		}
+
+		//the index of the entry showing, in the order the entries were added:
+		pub fn current(&self) -> Option<usize> {
+			match self.state {
+				State::Stopped => None,
+				_ => Some(self.order[self.position] as usize),
+			}
+		}
+
+		//how long until the current entry is over:
+		pub fn remaining_ms(&self) -> u32 {
+			self.current()
+				.and_then(|index| self.entry(index))
+				.map_or(0, |entry| entry.duration_ms.saturating_sub(self.elapsed_ms))
+		}
+
+		pub fn status(&self) -> Status {
+			Status {
+				state: self.state,
+				entry: self.current(),
+				remaining_ms: self.remaining_ms(),
+			}
+		}
+
+		//this works out the order for the next time through. A shuffled order never starts with
+		//the entry that just finished, so the same scene doesn't show twice in a row.
+		fn reorder(&mut self) {
+			let last = self.current();
+			for i in 0..self.entry_count {
+				self.order[i] = i as u8;
+			}
+			if self.shuffle {
+				for i in (1..self.entry_count).rev() {
+					let j = self.rng.range(0, i as u32) as usize;
+					self.order.swap(i, j);
+				}
+				if self.entry_count > 1 && last == Some(self.order[0] as usize) {
+					self.order.swap(0, 1);
+				}
+			}
+			self.position = 0;
+		}
+
+		//this moves on to the entry at position and returns it for the caller to show:
+		fn begin_entry(&mut self, now_ms: u32) -> Option<Entry> {
+			self.elapsed_ms = 0;
+			self.last_update_ms = now_ms;
+			self.entry(self.order[self.position] as usize)
+		}
+
+		//this starts the playlist over from the beginning, returning the first entry to show:
+		pub fn start(&mut self, now_ms: u32) -> Option<Entry> {
+			if self.entry_count == 0 {
+				return None;
+			}
+			self.state = State::Stopped;
+			self.reorder();
+			self.state = State::Playing;
+			self.begin_entry(now_ms)
+		}
+
+		pub fn stop(&mut self) {
+			self.state = State::Stopped;
+		}
+
+		pub fn pause(&mut self, now_ms: u32) {
+			if self.state == State::Playing {
+				self.update_elapsed(now_ms);
+				self.state = State::Paused;
+			}
+		}
+
+		pub fn resume(&mut self, now_ms: u32) {
+			if self.state == State::Paused {
+				self.last_update_ms = now_ms;
+				self.state = State::Playing;
+			}
+		}
+
+		fn update_elapsed(&mut self, now_ms: u32) {
+			self.elapsed_ms = self.elapsed_ms.saturating_add(now_ms.wrapping_sub(self.last_update_ms));
+			self.last_update_ms = now_ms;
+		}
+
+		//this skips to the next entry straight away, returning it for the caller to show.
+		//at the end of the list with repeat off, the playlist stops and returns None.
+		pub fn next(&mut self, now_ms: u32) -> Option<Entry> {
+			if self.state == State::Stopped {
+				return None;
+			}
+			if self.position + 1 < self.entry_count {
+				self.position += 1;
+			} else if self.repeat {
+				self.reorder();
+			} else {
+				self.state = State::Stopped;
+				return None;
+			}
+			self.begin_entry(now_ms)
+		}
+
+		//this should be called every frame. When the current entry runs out, it moves on and
+		//returns the next entry for the caller to show.
+		pub fn update(&mut self, now_ms: u32) -> Option<Entry> {
+			if self.state != State::Playing {
+				return None;
+			}
+			self.update_elapsed(now_ms);
+			match self.remaining_ms() {
+				0 => self.next(now_ms),
+				_ => None,
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		fn playlist(entry_count: u8, seed: u32) -> Playlist {
+			let mut playlist = Playlist::new(seed);
+			for slot in 0..entry_count {
+				playlist.add(Entry::new(slot, 1_000, Transition::Cut)).unwrap();
+			}
+			playlist
+		}
+
+		#[test]
+		fn plays_in_order() {
+			let mut playlist = playlist(3, 1);
+			assert_eq!(playlist.len(), 3);
+			assert_eq!(playlist.current(), None);
+			assert_eq!(playlist.start(0).map(|entry| entry.slot), Some(0));
+			assert_eq!(playlist.update(999), None);
+			assert_eq!(playlist.status(), Status { state: State::Playing, entry: Some(0), remaining_ms: 1 });
+			assert_eq!(playlist.update(1_000).map(|entry| entry.slot), Some(1));
+			assert_eq!(playlist.next(1_500).map(|entry| entry.slot), Some(2));
+			//and around again, since repeat is on:
+			assert_eq!(playlist.update(2_500).map(|entry| entry.slot), Some(0));
+			assert_eq!(playlist.current(), Some(0));
+		}
+
+		#[test]
+		fn shuffle_never_repeats_the_last_entry() {
+			for seed in 1..50 {
+				let mut playlist = playlist(4, seed);
+				playlist.shuffle = true;
+				let mut last = playlist.start(0).unwrap().slot;
+				for pass in 0..20 {
+					let mut seen = [false; 4];
+					seen[last as usize] = true;
+					for step in 1..4 {
+						let slot = playlist.next(0).unwrap().slot;
+						assert!(!seen[slot as usize], "seed {} pass {} step {}", seed, pass, step);
+						seen[slot as usize] = true;
+						last = slot;
+					}
+					let first = playlist.next(0).unwrap().slot;
+					assert_ne!(first, last, "seed {} pass {}", seed, pass);
+					last = first;
+				}
+			}
+		}
+
+		#[test]
+		fn stops_at_the_end_without_repeat() {
+			let mut playlist = playlist(2, 1);
+			playlist.repeat = false;
+			playlist.start(0);
+			assert_eq!(playlist.update(1_000).map(|entry| entry.slot), Some(1));
+			assert_eq!(playlist.update(2_000), None);
+			assert_eq!(playlist.state(), State::Stopped);
+			assert_eq!(playlist.status(), Status { state: State::Stopped, entry: None, remaining_ms: 0 });
+			assert_eq!(playlist.update(3_000), None);
+			assert_eq!(playlist.next(3_000), None);
+			assert!(Playlist::new(1).start(0).is_none());
+		}
+
+		#[test]
+		fn pausing_stops_the_clock() {
+			let mut playlist = playlist(2, 1);
+			playlist.start(0);
+			playlist.update(400);
+			playlist.pause(400);
+			assert_eq!(playlist.state(), State::Paused);
+			assert_eq!(playlist.update(5_000), None);
+			assert_eq!(playlist.remaining_ms(), 600);
+			playlist.resume(5_000);
+			assert_eq!(playlist.update(5_599), None);
+			assert_eq!(playlist.remaining_ms(), 1);
+			assert_eq!(playlist.update(5_600).map(|entry| entry.slot), Some(1));
+			//resuming something that isn't paused does nothing:
+			playlist.resume(9_000);
+			assert_eq!(playlist.update(6_000), None);
+			assert_eq!(playlist.remaining_ms(), 600);
+		}
+
+		#[test]
+		fn adding_while_playing() {
+			let mut playlist = playlist(2, 1);
+			playlist.start(0);
+			assert_eq!(playlist.add(Entry::new(7, 1_000, Transition::Fade(100))), Ok(2));
+			assert_eq!(playlist.next(0).map(|entry| entry.slot), Some(1));
+			assert_eq!(playlist.next(0).map(|entry| entry.slot), Some(7));
+			assert_eq!(playlist.next(0).map(|entry| entry.slot), Some(0));
+			//a full playlist hands the entry back:
+			let extra = Entry::new(9, 1_000, Transition::Cut);
+			for _ in playlist.len()..MAX_ENTRIES {
+				playlist.add(extra).unwrap();
+			}
+			assert_eq!(playlist.add(extra), Err(extra));
+		}
+
+		#[test]
+		fn clearing() {
+			let mut playlist = playlist(3, 1);
+			playlist.start(0);
+			playlist.next(0);
+			playlist.next(0);
+			playlist.clear();
+			assert!(playlist.is_empty());
+			assert_eq!(playlist.status(), Status { state: State::Stopped, entry: None, remaining_ms: 0 });
+			assert_eq!(playlist.start(0), None);
+			//entries added after clearing play from the first one:
+			playlist.add(Entry::new(5, 1_000, Transition::Cut)).unwrap();
+			assert_eq!(playlist.start(0).map(|entry| entry.slot), Some(5));
+			assert_eq!(playlist.status(), Status { state: State::Playing, entry: Some(0), remaining_ms: 1_000 });
+		}
+	}
+}
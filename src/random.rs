@@ -0,0 +1,40 @@
+pub mod random {
+	//a small xorshift pseudo random number generator. The same seed always gives the same
+	//sequence, so effects using it can be replayed exactly.
+	#[derive(Copy, Clone)]
+	pub struct Rng {
+		state: u32,
+	}
+
+	impl Rng {
+		pub fn new(seed: u32) -> Self {
+			//xorshift gets stuck at zero forever, so swap in a fixed non-zero seed:
+			Rng {
+				state: if seed == 0 { 0x9E37_79B9 } else { seed },
+			}
+		}
+
+		pub fn next_u32(&mut self) -> u32 {
+			let mut x = self.state;
+			x ^= x << 13;
+			x ^= x >> 17;
+			x ^= x << 5;
+			self.state = x;
+			x
+		}
+
+		//a random value from min up to and including max:
+		pub fn range(&mut self, min: u32, max: u32) -> u32 {
+			if max <= min {
+				return min;
+			}
+			let span = (max - min) as u64 + 1;
+			min + ((self.next_u32() as u64 * span) >> 32) as u32
+		}
+
+		//true roughly per_thousand times out of every 1000 calls:
+		pub fn chance(&mut self, per_thousand: u16) -> bool {
+			self.range(0, 999) < per_thousand as u32
+		}
+	}
+}
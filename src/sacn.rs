@@ -0,0 +1,359 @@
+pub mod sacn {
+	use crate::c;
+	use crate::dx;
+
+	//E1.31, also called streaming ACN or sACN, sends DMX universes over UDP. The network side
+	//hands each packet that comes in on SACN_PORT to a Receiver. A data packet is laid out as:
+	//root layer
+	//  2 bytes   preamble size, 0x0010
+	//  2 bytes   postamble size, 0
+	//  12 bytes  ACN packet identifier "ASC-E1.17\0\0\0"
+	//  2 bytes   flags and length
+	//  4 bytes   vector, 4 for E1.31 data
+	//  16 bytes  CID, which identifies the sender
+	//framing layer
+	//  2 bytes   flags and length
+	//  4 bytes   vector, 2 for a data packet
+	//  64 bytes  source name, UTF-8 and nul terminated
+	//  1 byte    priority, 0 to 200
+	//  2 bytes   synchronization address
+	//  1 byte    sequence number
+	//  1 byte    options, see OPTION_*
+	//  2 bytes   universe
+	//DMP layer
+	//  2 bytes   flags and length
+	//  1 byte    vector, 2
+	//  1 byte    address and data type, 0xA1
+	//  2 bytes   first property address, 0
+	//  2 bytes   address increment, 1
+	//  2 bytes   property value count, the start code and channels
+	//  1 byte    DMX start code, 0 for levels
+	//  channels  up to 512
+	//all numbers are big endian.
+	pub const SACN_PORT: u16 = 5568;
+	const ACN_IDENTIFIER: [u8; 12] = *b"ASC-E1.17\0\0\0";
+	const PREAMBLE_SIZE: u16 = 0x0010;
+	const VECTOR_ROOT_E131_DATA: u32 = 0x0000_0004;
+	const VECTOR_E131_DATA_PACKET: u32 = 0x0000_0002;
+	const VECTOR_DMP_SET_PROPERTY: u8 = 0x02;
+	const DMP_ADDRESS_TYPE: u8 = 0xA1;
+	const HEADER_LENGTH: usize = 126;
+	const SOURCE_NAME_LENGTH: usize = 64;
+	const START_CODE_LEVELS: u8 = 0x00;
+	//the data is for visualizers and editors, not for showing:
+	const OPTION_PREVIEW: u8 = 0x80;
+	//the source is going away and won't send any more:
+	const OPTION_STREAM_TERMINATED: u8 = 0x40;
+
+	//a source that sends nothing for this long is treated as gone, as the standard says:
+	pub const SOURCE_TIMEOUT_MS: u32 = 2_500;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum SacnError {
+		//the packet ends before its header or channels do
+		Truncated,
+		//this isn't an E1.31 data packet
+		NotSacn,
+		//the packet carries something other than channel levels, like per channel priorities
+		UnsupportedStartCode(u8),
+		Preview,
+		//another source with the same or a higher priority is being followed
+		LowerPriority,
+		Universe(dx::UniverseError),
+	}
+
+	//the parts of a data packet the receiver needs:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct DataPacket<'a> {
+		pub cid: [u8; 16],
+		pub source_name: &'a str,
+		pub priority: u8,
+		pub sequence: u8,
+		pub options: u8,
+		pub universe: u16,
+		pub channels: &'a [u8],
+	}
+
+	fn u16_at(packet: &[u8], index: usize) -> u16 {
+		u16::from_be_bytes([packet[index], packet[index + 1]])
+	}
+
+	fn u32_at(packet: &[u8], index: usize) -> u32 {
+		u32::from_be_bytes([packet[index], packet[index + 1], packet[index + 2], packet[index + 3]])
+	}
+
+	impl<'a> DataPacket<'a> {
+		pub fn parse(packet: &'a [u8]) -> Result<Self, SacnError> {
+			if packet.len() < HEADER_LENGTH {
+				return Err(SacnError::Truncated);
+			}
+			if u16_at(packet, 0) != PREAMBLE_SIZE
+				|| u16_at(packet, 2) != 0
+				|| packet[4..16] != ACN_IDENTIFIER
+				|| u32_at(packet, 18) != VECTOR_ROOT_E131_DATA
+				|| u32_at(packet, 40) != VECTOR_E131_DATA_PACKET
+				|| packet[117] != VECTOR_DMP_SET_PROPERTY
+				|| packet[118] != DMP_ADDRESS_TYPE
+				|| u16_at(packet, 119) != 0
+				|| u16_at(packet, 121) != 1
+			{
+				return Err(SacnError::NotSacn);
+			}
+			//the property values are the start code followed by the channels:
+			let value_count = u16_at(packet, 123) as usize;
+			if value_count == 0 || value_count > dx::CHANNELS_PER_UNIVERSE + 1 {
+				return Err(SacnError::NotSacn);
+			}
+			let channels = packet.get(HEADER_LENGTH..HEADER_LENGTH + value_count - 1).ok_or(SacnError::Truncated)?;
+			if packet[125] != START_CODE_LEVELS {
+				return Err(SacnError::UnsupportedStartCode(packet[125]));
+			}
+			let mut cid = [0_u8; 16];
+			cid.copy_from_slice(&packet[22..38]);
+			let source_name = &packet[44..44 + SOURCE_NAME_LENGTH];
+			let name_length = source_name.iter().position(|&byte| byte == 0).unwrap_or(SOURCE_NAME_LENGTH);
+			Ok(DataPacket {
+				cid,
+				source_name: core::str::from_utf8(&source_name[..name_length]).unwrap_or(""),
+				priority: packet[108],
+				sequence: packet[111],
+				options: packet[112],
+				universe: u16_at(packet, 113),
+				channels,
+			})
+		}
+	}
+
+	//the multicast group a universe is sent to:
+	pub fn multicast_address(universe: u16) -> [u8; 4] {
+		let [high, low] = universe.to_be_bytes();
+		[239, 255, high, low]
+	}
+
+	//the source being followed:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	struct Source {
+		cid: [u8; 16],
+		priority: u8,
+		last_packet_ms: u32,
+	}
+
+	//this takes data packets for a strip's universes and puts them together into frames. Only the
+	//highest priority source is followed, and a source that takes over keeps control until it
+	//stops sending or one with a higher priority turns up.
+	pub struct Receiver {
+		pub frames: dx::FrameAssembler,
+		source: Option<Source>,
+	}
+
+	impl Receiver {
+		pub fn new(map: dx::UniverseMap) -> Self {
+			Receiver {
+				frames: dx::FrameAssembler::new(map),
+				source: None,
+			}
+		}
+
+		fn follow(&mut self, source: Option<Source>) {
+			self.source = source;
+			self.frames.reset();
+		}
+
+		//this forgets the source being followed once it has sent nothing for SOURCE_TIMEOUT_MS,
+		//returning true when it does, so the network side can go back to the animations without
+		//waiting for another packet to come in.
+		pub fn poll(&mut self, now_ms: u32) -> bool {
+			let timed_out = self.source.is_some_and(|source| now_ms.wrapping_sub(source.last_packet_ms) > SOURCE_TIMEOUT_MS);
+			if timed_out {
+				self.follow(None);
+			}
+			timed_out
+		}
+
+		//whether a source is being followed:
+		pub fn has_source(&self) -> bool {
+			self.source.is_some()
+		}
+
+		//this takes a packet that came in at now_ms, writing its channels straight into pixels.
+		//once every one of the strip's universes has come in, the frame is done and the number
+		//of LEDs in it is returned.
+		pub fn receive(&mut self, packet: &[u8], now_ms: u32, pixels: &mut [c::Color]) -> Result<Option<usize>, SacnError> {
+			let packet = DataPacket::parse(packet)?;
+			if packet.options & OPTION_PREVIEW != 0 {
+				return Err(SacnError::Preview);
+			}
+			self.poll(now_ms);
+			let source = Source { cid: packet.cid, priority: packet.priority, last_packet_ms: now_ms };
+			match self.source {
+				Some(current) if current.cid == packet.cid => self.source = Some(source),
+				Some(current) if current.priority >= packet.priority => return Err(SacnError::LowerPriority),
+				_ => self.follow(Some(source)),
+			}
+			if packet.options & OPTION_STREAM_TERMINATED != 0 {
+				self.follow(None);
+				return Ok(None);
+			}
+			self.frames
+				.write(packet.universe, Some(packet.sequence), packet.channels, pixels)
+				.map_err(SacnError::Universe)
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use std::net::UdpSocket;
+
+		//the header of a packet captured from sACNView sending universe 1 at priority 100, with
+		//all 512 channels:
+		const CAPTURED_HEADER: [u8; HEADER_LENGTH] = [
+			0x00, 0x10, 0x00, 0x00, 0x41, 0x53, 0x43, 0x2d, 0x45, 0x31, 0x2e, 0x31, 0x37, 0x00, 0x00, 0x00,
+			0x72, 0x6e, 0x00, 0x00, 0x00, 0x04, 0x9c, 0x3f, 0x51, 0x0e, 0x2a, 0x77, 0x4b, 0xd2, 0x8f, 0x16,
+			0x3e, 0x5a, 0xc1, 0x70, 0x04, 0xbb, 0x72, 0x58, 0x00, 0x00, 0x00, 0x02, 0x73, 0x41, 0x43, 0x4e,
+			0x56, 0x69, 0x65, 0x77, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x2a,
+			0x00, 0x00, 0x01, 0x72, 0x0b, 0x02, 0xa1, 0x00, 0x00, 0x00, 0x01, 0x02, 0x01, 0x00,
+		];
+
+		const SOURCE_A: u8 = 0x9c;
+		const SOURCE_B: u8 = 0x11;
+
+		//the captured packet with its fields changed. Each LED is set to its number within the
+		//universe in red and the universe in green.
+		fn packet(cid: u8, priority: u8, sequence: u8, options: u8, universe: u16, channel_count: usize) -> Vec<u8> {
+			let mut packet = CAPTURED_HEADER.to_vec();
+			packet[22] = cid;
+			packet[108] = priority;
+			packet[111] = sequence;
+			packet[112] = options;
+			packet[113..115].copy_from_slice(&universe.to_be_bytes());
+			packet[123..125].copy_from_slice(&(channel_count as u16 + 1).to_be_bytes());
+			packet.extend((0..channel_count).map(|channel| match channel % 3 {
+				0 => (channel / 3) as u8,
+				1 => universe as u8,
+				_ => 0,
+			}));
+			packet
+		}
+
+		fn receiver(start_universe: u16, start_channel: u16, led_count: usize) -> (Receiver, Vec<c::Color>) {
+			(Receiver::new(dx::UniverseMap::new(start_universe, start_channel, led_count)), vec![c::C_OFF; led_count])
+		}
+
+		#[test]
+		fn parses_the_captured_packet() {
+			let captured = packet(SOURCE_A, 100, 0x2a, 0, 1, 512);
+			assert_eq!(captured[..HEADER_LENGTH], CAPTURED_HEADER[..]);
+			let parsed = DataPacket::parse(&captured).unwrap();
+			assert_eq!(parsed.cid[..2], [0x9c, 0x3f]);
+			assert_eq!(parsed.source_name, "sACNView");
+			assert_eq!((parsed.priority, parsed.sequence, parsed.options, parsed.universe), (100, 0x2a, 0, 1));
+			assert_eq!(parsed.channels.len(), 512);
+			assert_eq!(parsed.channels[..6], [0, 1, 0, 1, 1, 0]);
+		}
+
+		#[test]
+		fn rejects_what_isnt_levels() {
+			let captured = packet(SOURCE_A, 100, 0, 0, 1, 512);
+			assert_eq!(DataPacket::parse(&captured[..HEADER_LENGTH - 1]), Err(SacnError::Truncated));
+			assert_eq!(DataPacket::parse(&captured[..HEADER_LENGTH + 10]), Err(SacnError::Truncated));
+			let mut art_net = captured.clone();
+			art_net[4..12].copy_from_slice(b"Art-Net\0");
+			assert_eq!(DataPacket::parse(&art_net), Err(SacnError::NotSacn));
+			let mut priorities = captured.clone();
+			priorities[125] = 0xDD;
+			assert_eq!(DataPacket::parse(&priorities), Err(SacnError::UnsupportedStartCode(0xDD)));
+			let (mut receiver, mut pixels) = receiver(1, 1, 170);
+			assert_eq!(receiver.receive(&packet(SOURCE_A, 100, 0, OPTION_PREVIEW, 1, 512), 0, &mut pixels), Err(SacnError::Preview));
+			assert_eq!(receiver.receive(&packet(SOURCE_A, 100, 0, 0, 7, 512), 0, &mut pixels), Err(SacnError::Universe(dx::UniverseError::NotMapped(7))));
+		}
+
+		#[test]
+		fn spans_universes_from_a_start_channel() {
+			//starting at channel 4 leaves room for 169 LEDs in the first universe:
+			let (mut receiver, mut pixels) = receiver(10, 4, 200);
+			assert_eq!(receiver.frames.map.universe_count(), 2);
+			assert_eq!(receiver.receive(&packet(SOURCE_A, 100, 0, 0, 10, 512), 0, &mut pixels), Ok(None));
+			assert_eq!(receiver.receive(&packet(SOURCE_A, 100, 0, 0, 11, 93), 0, &mut pixels), Ok(Some(200)));
+			assert_eq!(pixels[0], c::Color { r: 1, g: 10, b: 0 });
+			assert_eq!(pixels[168], c::Color { r: 169, g: 10, b: 0 });
+			assert_eq!(pixels[169], c::Color { r: 0, g: 11, b: 0 });
+			assert_eq!(pixels[199], c::Color { r: 30, g: 11, b: 0 });
+		}
+
+		#[test]
+		fn higher_priority_takes_over() {
+			let (mut receiver, mut pixels) = receiver(1, 1, 170);
+			assert_eq!(receiver.receive(&packet(SOURCE_A, 100, 0, 0, 1, 512), 0, &mut pixels), Ok(Some(170)));
+			assert_eq!(receiver.receive(&packet(SOURCE_B, 100, 0, 0, 1, 512), 10, &mut pixels), Err(SacnError::LowerPriority));
+			assert_eq!(receiver.receive(&packet(SOURCE_B, 150, 0, 0, 1, 512), 20, &mut pixels), Ok(Some(170)));
+			assert_eq!(receiver.receive(&packet(SOURCE_A, 100, 1, 0, 1, 512), 30, &mut pixels), Err(SacnError::LowerPriority));
+			//a lower priority source gets control back once the higher one has gone quiet:
+			let quiet_ms = 20 + SOURCE_TIMEOUT_MS + 1;
+			assert_eq!(receiver.receive(&packet(SOURCE_A, 100, 2, 0, 1, 512), quiet_ms, &mut pixels), Ok(Some(170)));
+		}
+
+		#[test]
+		fn sequence_window() {
+			let (mut receiver, mut pixels) = receiver(1, 1, 170);
+			let mut receive = |sequence: u8| receiver.receive(&packet(SOURCE_A, 100, sequence, 0, 1, 512), 0, &mut pixels);
+			let out_of_order = Err(SacnError::Universe(dx::UniverseError::OutOfOrder));
+			assert_eq!(receive(250), Ok(Some(170)));
+			//wraps around from 255 to 0:
+			assert_eq!(receive(4), Ok(Some(170)));
+			assert_eq!(receive(4), out_of_order);
+			assert_eq!(receive(3), out_of_order);
+			let window = dx::SEQUENCE_WINDOW.unsigned_abs();
+			assert_eq!(receive(4u8.wrapping_sub(window - 1)), out_of_order);
+			//anything further back is taken as the source starting over:
+			assert_eq!(receive(4u8.wrapping_sub(window)), Ok(Some(170)));
+		}
+
+		#[test]
+		fn stream_terminated() {
+			let (mut receiver, mut pixels) = receiver(1, 1, 170);
+			assert_eq!(receiver.receive(&packet(SOURCE_B, 150, 0, 0, 1, 512), 0, &mut pixels), Ok(Some(170)));
+			assert_eq!(receiver.receive(&packet(SOURCE_B, 150, 1, OPTION_STREAM_TERMINATED, 1, 512), 10, &mut pixels), Ok(None));
+			assert!(!receiver.has_source());
+			//another source doesn't have to wait for the timeout:
+			assert_eq!(receiver.receive(&packet(SOURCE_A, 100, 0, 0, 1, 512), 20, &mut pixels), Ok(Some(170)));
+		}
+
+		#[test]
+		fn poll_times_out_a_quiet_source() {
+			let (mut receiver, mut pixels) = receiver(1, 1, 340);
+			assert_eq!(receiver.receive(&packet(SOURCE_A, 100, 0, 0, 1, 512), 1000, &mut pixels), Ok(None));
+			assert!(!receiver.poll(1000 + SOURCE_TIMEOUT_MS));
+			assert!(receiver.has_source());
+			assert!(receiver.poll(1001 + SOURCE_TIMEOUT_MS));
+			assert!(!receiver.has_source());
+			assert!(!receiver.poll(1002 + SOURCE_TIMEOUT_MS));
+			//the half a frame that came in before is forgotten too:
+			assert_eq!(receiver.receive(&packet(SOURCE_B, 100, 0, 0, 2, 512), 5000, &mut pixels), Ok(None));
+		}
+
+		#[test]
+		fn frames_over_udp() {
+			let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+			let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+			sender.connect(socket.local_addr().unwrap()).unwrap();
+			for universe in 1..=2 {
+				sender.send(&packet(SOURCE_A, 100, 7, 0, universe, 510)).unwrap();
+			}
+			sender.send(&packet(SOURCE_A, 100, 8, OPTION_STREAM_TERMINATED, 1, 0)).unwrap();
+			let (mut receiver, mut pixels) = receiver(1, 1, 340);
+			let mut buffer = [0_u8; 1500];
+			let mut results = Vec::new();
+			for _ in 0..3 {
+				let length = socket.recv(&mut buffer).unwrap();
+				results.push(receiver.receive(&buffer[..length], 0, &mut pixels));
+			}
+			assert_eq!(results, vec![Ok(None), Ok(Some(340)), Ok(None)]);
+			assert_eq!(pixels[339], c::Color { r: 169, g: 2, b: 0 });
+			assert!(!receiver.has_source());
+		}
+	}
+}
@@ -0,0 +1,455 @@
+pub mod scenes {
+	use crate::a;
+	use crate::fl;
+	use crate::sg;
+	use crate::sv;
+
+	pub const SCENE_SLOTS: usize = 8;
+	pub const MAX_NAME_LENGTH: usize = 12;
+
+	//a scene is saved as:
+	//  1 byte   name length, then the name
+	//  1 byte   brightness
+	//  1 byte   flags, bit 0 set if the main effect follows
+	//  effect   main effect
+	//  1 byte   which segments follow, bit n set for segment n
+	//then for each of those segments, in order:
+	//  1 byte   name length, then the name
+	//  2 bytes  first LED, little endian
+	//  2 bytes  number of LEDs, little endian
+	//  1 byte   flags, see SEGMENT_* below
+	//  1 byte   brightness
+	//  effect
+	//an effect is saved as EffectParams::to_bytes.
+	const FLAG_HAS_EFFECT: u8 = 0x01;
+	const SEGMENT_ENABLED: u8 = 0x01;
+	const SEGMENT_REVERSED: u8 = 0x02;
+	const SEGMENT_MIRRORED: u8 = 0x04;
+	const MAX_SEGMENT_LENGTH: usize = 1 + sg::MAX_NAME_LENGTH + 4 + 2 + a::EFFECT_PARAMS_LENGTH;
+	pub const MAX_SCENE_LENGTH: usize =
+		1 + MAX_NAME_LENGTH + 2 + a::EFFECT_PARAMS_LENGTH + 1 + sg::MAX_SEGMENTS * MAX_SEGMENT_LENGTH;
+
+	//all the slots are saved together in one flash sector as:
+	//  4 bytes  magic "WSSN"
+	//  1 byte   version
+	//then for each slot, 1 byte that is 1 if a scene follows, then the scene,
+	//and after the last slot, 4 bytes of CRC-32 of everything before it.
+	pub const SCENES_MAGIC: [u8; 4] = *b"WSSN";
+	pub const SCENES_VERSION: u8 = 1;
+	const BANK_HEADER_LENGTH: usize = 5;
+	pub const MAX_BANK_LENGTH: usize = BANK_HEADER_LENGTH + SCENE_SLOTS * (1 + MAX_SCENE_LENGTH) + 4;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum SceneError {
+		//names need to be 1 to MAX_NAME_LENGTH letters, numbers, - or _
+		BadName,
+		//there is no slot with this index
+		NoSuchSlot(usize),
+		Unreadable,
+		BadMagic,
+		UnsupportedVersion(u8),
+		//the data ends in the middle of a scene
+		Truncated,
+		BadCrc,
+		//a saved effect number isn't one this firmware has
+		UnknownEffect(u8),
+	}
+
+	//everything needed to make a segment again, so recalling a scene puts its segments back:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct SegmentSettings {
+		name: [u8; sg::MAX_NAME_LENGTH],
+		name_length: u8,
+		pub start: u16,
+		pub length: u16,
+		pub enabled: bool,
+		pub brightness: u8,
+		pub reversed: bool,
+		pub mirrored: bool,
+		pub effect: a::EffectParams,
+	}
+
+	impl SegmentSettings {
+		//this gets the settings of a segment, or None if its animation wasn't made from an effect,
+		//since there is no way to make it again:
+		pub fn from_segment(segment: &sg::Segment) -> Option<Self> {
+			let mut settings = SegmentSettings {
+				name: [0; sg::MAX_NAME_LENGTH],
+				name_length: 0,
+				start: segment.range.start as u16,
+				length: segment.len() as u16,
+				enabled: segment.enabled,
+				brightness: segment.brightness,
+				reversed: segment.reversed,
+				mirrored: segment.mirrored,
+				effect: segment.animation.effect()?,
+			};
+			settings.set_name(segment.name()).ok()?;
+			Some(settings)
+		}
+
+		pub fn name(&self) -> &str {
+			core::str::from_utf8(&self.name[..self.name_length as usize]).unwrap_or("")
+		}
+
+		fn set_name(&mut self, name: &str) -> Result<(), SceneError> {
+			let bytes = name.as_bytes();
+			if bytes.is_empty() || bytes.len() > sg::MAX_NAME_LENGTH || !bytes.iter().all(|&byte| is_name_byte(byte)) {
+				return Err(SceneError::BadName);
+			}
+			self.name[..bytes.len()].copy_from_slice(bytes);
+			self.name_length = bytes.len() as u8;
+			Ok(())
+		}
+
+		//this makes the segment. seed is used by effects with randomness.
+		pub fn to_segment(&self, seed: u32) -> Result<sg::Segment, sg::SegmentError> {
+			let (start, length) = (self.start as usize, self.length as usize);
+			let animation = a::Animation::from_effect(length, self.effect, seed);
+			let mut segment = sg::Segment::new(self.name(), start..start + length, animation)?;
+			segment.enabled = self.enabled;
+			segment.brightness = self.brightness;
+			segment.reversed = self.reversed;
+			segment.mirrored = self.mirrored;
+			Ok(segment)
+		}
+	}
+
+	//a saved look for a logical strip: its effect, brightness and segments.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Scene {
+		name: [u8; MAX_NAME_LENGTH],
+		name_length: u8,
+		pub brightness: u8,
+		//None leaves the main animation alone, for animations that weren't made from an effect:
+		pub effect: Option<a::EffectParams>,
+		//the segment in each slot of the strip's SegmentList. Recalling the scene replaces the
+		//strip's segments with these.
+		pub segments: [Option<SegmentSettings>; sg::MAX_SEGMENTS],
+	}
+
+	fn is_name_byte(byte: u8) -> bool {
+		byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'
+	}
+
+	//this reads size bytes from data at position, moving position past them:
+	fn take<'a>(data: &'a [u8], position: &mut usize, size: usize) -> Result<&'a [u8], SceneError> {
+		let bytes = data.get(*position..*position + size).ok_or(SceneError::Truncated)?;
+		*position += size;
+		Ok(bytes)
+	}
+
+	fn take_effect(data: &[u8], position: &mut usize) -> Result<a::EffectParams, SceneError> {
+		let mut bytes = [0_u8; a::EFFECT_PARAMS_LENGTH];
+		bytes.copy_from_slice(take(data, position, a::EFFECT_PARAMS_LENGTH)?);
+		a::EffectParams::from_bytes(&bytes).ok_or(SceneError::UnknownEffect(bytes[0]))
+	}
+
+	//this appends bytes to buffer at position, moving position past them:
+	fn put(buffer: &mut [u8], position: &mut usize, bytes: &[u8]) {
+		buffer[*position..*position + bytes.len()].copy_from_slice(bytes);
+		*position += bytes.len();
+	}
+
+	impl Scene {
+		pub fn new(name: &str) -> Result<Self, SceneError> {
+			let mut scene = Scene {
+				name: [0; MAX_NAME_LENGTH],
+				name_length: 0,
+				brightness: 255,
+				effect: None,
+				segments: [None; sg::MAX_SEGMENTS],
+			};
+			scene.set_name(name)?;
+			Ok(scene)
+		}
+
+		pub fn name(&self) -> &str {
+			core::str::from_utf8(&self.name[..self.name_length as usize]).unwrap_or("")
+		}
+
+		pub fn set_name(&mut self, name: &str) -> Result<(), SceneError> {
+			let bytes = name.as_bytes();
+			if bytes.is_empty() || bytes.len() > MAX_NAME_LENGTH || !bytes.iter().all(|&byte| is_name_byte(byte)) {
+				return Err(SceneError::BadName);
+			}
+			self.name = [0; MAX_NAME_LENGTH];
+			self.name[..bytes.len()].copy_from_slice(bytes);
+			self.name_length = bytes.len() as u8;
+			Ok(())
+		}
+
+		//this writes the scene out, returning the number of bytes used:
+		pub fn encode(&self, buffer: &mut [u8; MAX_SCENE_LENGTH]) -> usize {
+			let mut position = 0;
+			put(buffer, &mut position, &[self.name_length]);
+			put(buffer, &mut position, &self.name[..self.name_length as usize]);
+			let flags = if self.effect.is_some() { FLAG_HAS_EFFECT } else { 0 };
+			put(buffer, &mut position, &[self.brightness, flags]);
+			if let Some(effect) = &self.effect {
+				put(buffer, &mut position, &effect.to_bytes());
+			}
+			let mut segment_mask = 0_u8;
+			for (i, segment) in self.segments.iter().enumerate() {
+				if segment.is_some() {
+					segment_mask |= 1 << i;
+				}
+			}
+			put(buffer, &mut position, &[segment_mask]);
+			for segment in self.segments.iter().filter_map(|segment| segment.as_ref()) {
+				put(buffer, &mut position, &[segment.name_length]);
+				put(buffer, &mut position, &segment.name[..segment.name_length as usize]);
+				put(buffer, &mut position, &segment.start.to_le_bytes());
+				put(buffer, &mut position, &segment.length.to_le_bytes());
+				let mut flags = 0;
+				if segment.enabled {
+					flags |= SEGMENT_ENABLED;
+				}
+				if segment.reversed {
+					flags |= SEGMENT_REVERSED;
+				}
+				if segment.mirrored {
+					flags |= SEGMENT_MIRRORED;
+				}
+				put(buffer, &mut position, &[flags, segment.brightness]);
+				put(buffer, &mut position, &segment.effect.to_bytes());
+			}
+			position
+		}
+
+		//this reads a scene from the start of data, returning it and the number of bytes it took up:
+		pub fn decode(data: &[u8]) -> Result<(Self, usize), SceneError> {
+			let mut position = 0;
+			let name_length = take(data, &mut position, 1)?[0] as usize;
+			let name = core::str::from_utf8(take(data, &mut position, name_length)?).map_err(|_| SceneError::BadName)?;
+			let mut scene = Scene::new(name)?;
+			let header = take(data, &mut position, 2)?;
+			scene.brightness = header[0];
+			if header[1] & FLAG_HAS_EFFECT == FLAG_HAS_EFFECT {
+				scene.effect = Some(take_effect(data, &mut position)?);
+			}
+			let segment_mask = take(data, &mut position, 1)?[0];
+			for (i, slot) in scene.segments.iter_mut().enumerate() {
+				if segment_mask & (1 << i) == 0 {
+					continue;
+				}
+				let name_length = take(data, &mut position, 1)?[0] as usize;
+				let name = core::str::from_utf8(take(data, &mut position, name_length)?).map_err(|_| SceneError::BadName)?;
+				let header = take(data, &mut position, 6)?;
+				let flags = header[4];
+				let mut segment = SegmentSettings {
+					name: [0; sg::MAX_NAME_LENGTH],
+					name_length: 0,
+					start: u16::from_le_bytes([header[0], header[1]]),
+					length: u16::from_le_bytes([header[2], header[3]]),
+					enabled: flags & SEGMENT_ENABLED == SEGMENT_ENABLED,
+					brightness: header[5],
+					reversed: flags & SEGMENT_REVERSED == SEGMENT_REVERSED,
+					mirrored: flags & SEGMENT_MIRRORED == SEGMENT_MIRRORED,
+					effect: take_effect(data, &mut position)?,
+				};
+				segment.set_name(name)?;
+				*slot = Some(segment);
+			}
+			Ok((scene, position))
+		}
+	}
+
+	//the preset slots scenes are saved into.
+	#[derive(Copy, Clone, PartialEq, Debug, Default)]
+	pub struct SceneBank {
+		slots: [Option<Scene>; SCENE_SLOTS],
+	}
+
+	impl SceneBank {
+		pub fn new() -> Self {
+			SceneBank {
+				slots: [None; SCENE_SLOTS],
+			}
+		}
+
+		pub fn save(&mut self, slot: usize, scene: Scene) -> Result<(), SceneError> {
+			let slot = self.slots.get_mut(slot).ok_or(SceneError::NoSuchSlot(slot))?;
+			*slot = Some(scene);
+			Ok(())
+		}
+
+		pub fn clear(&mut self, slot: usize) -> Option<Scene> {
+			self.slots.get_mut(slot).and_then(|slot| slot.take())
+		}
+
+		pub fn get(&self, slot: usize) -> Option<&Scene> {
+			self.slots.get(slot).and_then(|slot| slot.as_ref())
+		}
+
+		pub fn find(&self, name: &str) -> Option<usize> {
+			self.slots
+				.iter()
+				.position(|slot| matches!(slot, Some(scene) if scene.name() == name))
+		}
+
+		//this gets the next slot with a scene in it after current, wrapping around to the first.
+		//with no current scene, it starts from the first slot.
+		pub fn next(&self, current: Option<usize>) -> Option<usize> {
+			let start = current.map_or(0, |current| current + 1);
+			(0..SCENE_SLOTS)
+				.map(|offset| (start + offset) % SCENE_SLOTS)
+				.find(|&slot| self.slots[slot].is_some())
+		}
+
+		pub fn iter(&self) -> impl Iterator<Item = (usize, &Scene)> {
+			self.slots
+				.iter()
+				.enumerate()
+				.filter_map(|(i, slot)| slot.as_ref().map(|scene| (i, scene)))
+		}
+
+		//this writes all the slots out, returning the number of bytes used:
+		pub fn encode(&self, blob: &mut [u8; MAX_BANK_LENGTH]) -> usize {
+			let mut position = 0;
+			put(blob, &mut position, &SCENES_MAGIC);
+			put(blob, &mut position, &[SCENES_VERSION]);
+			for slot in self.slots.iter() {
+				match slot {
+					Some(scene) => {
+						let mut buffer = [0_u8; MAX_SCENE_LENGTH];
+						let length = scene.encode(&mut buffer);
+						put(blob, &mut position, &[1]);
+						put(blob, &mut position, &buffer[..length]);
+					}
+					None => put(blob, &mut position, &[0]),
+				}
+			}
+			let crc = sv::crc32(&blob[..position]);
+			put(blob, &mut position, &crc.to_le_bytes());
+			position
+		}
+
+		pub fn decode(blob: &[u8]) -> Result<Self, SceneError> {
+			if blob.len() < BANK_HEADER_LENGTH || blob[..4] != SCENES_MAGIC {
+				return Err(SceneError::BadMagic);
+			}
+			let version = blob[4];
+			if version != SCENES_VERSION {
+				return Err(SceneError::UnsupportedVersion(version));
+			}
+			let mut bank = SceneBank::new();
+			let mut position = BANK_HEADER_LENGTH;
+			for slot in bank.slots.iter_mut() {
+				if take(blob, &mut position, 1)?[0] == 1 {
+					let (scene, length) = Scene::decode(&blob[position..])?;
+					*slot = Some(scene);
+					position += length;
+				}
+			}
+			let mut crc = [0_u8; 4];
+			crc.copy_from_slice(take(blob, &mut position, 4)?);
+			if sv::crc32(&blob[..position - 4]) != u32::from_le_bytes(crc) {
+				return Err(SceneError::BadCrc);
+			}
+			Ok(bank)
+		}
+
+		//this reads the scenes saved in the flash sector at address:
+		pub fn load<F: fl::Flash>(flash: &mut F, address: u32) -> Result<Self, SceneError> {
+			let mut blob = [0_u8; MAX_BANK_LENGTH];
+			flash.read(address, &mut blob).map_err(|_| SceneError::Unreadable)?;
+			SceneBank::decode(&blob)
+		}
+
+		//this replaces the scenes saved in the flash sector at address. Scenes are only saved
+		//when asked to, so erasing the sector each time doesn't wear it out.
+		pub fn store<F: fl::Flash>(&self, flash: &mut F, address: u32) -> Result<(), fl::FlashError> {
+			let mut blob = [0_u8; MAX_BANK_LENGTH];
+			let length = self.encode(&mut blob);
+			flash.erase_sector(address)?;
+			flash.write(address, &blob[..length])
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use crate::c;
+
+		fn bank_blob(version: u8, scene: &[u8]) -> Vec<u8> {
+			let mut blob = SCENES_MAGIC.to_vec();
+			blob.push(version);
+			blob.push(1);
+			blob.extend_from_slice(scene);
+			blob.extend((1..SCENE_SLOTS).map(|_| 0));
+			let crc = sv::crc32(&blob);
+			blob.extend_from_slice(&crc.to_le_bytes());
+			blob
+		}
+
+		#[test]
+		fn round_trip() {
+			let mut scene = Scene::new("evening").unwrap();
+			scene.brightness = 90;
+			scene.effect = Some(a::EffectParams { effect: a::Effect::Automaton, intensity: 30, ..a::EffectParams::solid(c::C_RED) });
+			let mut bank = SceneBank::new();
+			bank.save(3, scene).unwrap();
+			let mut blob = [0_u8; MAX_BANK_LENGTH];
+			let length = bank.encode(&mut blob);
+			assert_eq!(SceneBank::decode(&blob[..length]), Ok(bank));
+			blob[length - 1] ^= 1;
+			assert_eq!(SceneBank::decode(&blob[..length]), Err(SceneError::BadCrc));
+		}
+
+		#[test]
+		fn segments_are_made_again() {
+			let chase = a::EffectParams { effect: a::Effect::TheaterChase, speed: 9, ..a::EffectParams::solid(c::C_RED) };
+			let mut desk = sg::Segment::new("desk", 70..90, a::Animation::from_effect(20, chase, 1)).unwrap();
+			desk.brightness = 128;
+			desk.mirrored = true;
+			let mut scene = Scene::new("work").unwrap();
+			scene.segments[2] = SegmentSettings::from_segment(&desk);
+			//a segment with an animation that wasn't made from an effect can't be saved:
+			let by_hand = sg::Segment::new("glow", 0..10, a::Animation::new(10, a::Mode::Solid(c::C_RED))).unwrap();
+			assert_eq!(SegmentSettings::from_segment(&by_hand), None);
+
+			let mut bank = SceneBank::new();
+			bank.save(0, scene).unwrap();
+			let mut blob = [0_u8; MAX_BANK_LENGTH];
+			let length = bank.encode(&mut blob);
+			let loaded = SceneBank::decode(&blob[..length]).unwrap();
+			let settings = loaded.get(0).unwrap().segments[2].unwrap();
+			assert_eq!(settings.name(), "desk");
+			let segment = settings.to_segment(1).unwrap();
+			assert_eq!((segment.name(), segment.range.clone(), segment.brightness), ("desk", 70..90, 128));
+			assert_eq!((segment.enabled, segment.reversed, segment.mirrored), (true, false, true));
+			assert_eq!(segment.animation.effect(), Some(chase));
+		}
+
+		#[test]
+		fn saved_layout() {
+			//"eve" at brightness 100, with the automaton on palette 4 at speed 5 running backwards, and no segments:
+			let saved = [3, b'e', b'v', b'e', 100, FLAG_HAS_EFFECT, 9, 1, 2, 3, 4, 5, 30, a::FLAG_PER_STRIP | a::FLAG_REVERSED, 20, 0];
+			let bank = SceneBank::decode(&bank_blob(SCENES_VERSION, &saved)).unwrap();
+			let scene = bank.get(0).unwrap();
+			assert_eq!(scene.name(), "eve");
+			assert_eq!(scene.effect, Some(a::EffectParams {
+				effect: a::Effect::Automaton,
+				color: c::Color { r: 1, g: 2, b: 3 },
+				palette: 4,
+				speed: 5,
+				intensity: 30,
+				custom: 20,
+				per_strip: true,
+				reversed: true,
+			}));
+			let mut buffer = [0_u8; MAX_SCENE_LENGTH];
+			let length = scene.encode(&mut buffer);
+			assert_eq!(&buffer[..length], &saved[..]);
+			assert_eq!(Scene::decode(&saved), Ok((*scene, saved.len())));
+			//cut short, with an effect this firmware doesn't have, and from other versions:
+			assert_eq!(Scene::decode(&saved[..10]), Err(SceneError::Truncated));
+			let mut unknown = saved;
+			unknown[6] = a::EFFECTS.len() as u8;
+			assert_eq!(SceneBank::decode(&bank_blob(SCENES_VERSION, &unknown)), Err(SceneError::UnknownEffect(a::EFFECTS.len() as u8)));
+			assert_eq!(SceneBank::decode(&bank_blob(0, &saved)), Err(SceneError::UnsupportedVersion(0)));
+			assert_eq!(SceneBank::decode(&bank_blob(SCENES_VERSION + 1, &saved)), Err(SceneError::UnsupportedVersion(SCENES_VERSION + 1)));
+		}
+	}
+}
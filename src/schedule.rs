@@ -0,0 +1,340 @@
+pub mod schedule {
+	use crate::a;
+	use crate::ast;
+	use crate::c;
+	use crate::ck;
+
+	pub const MAX_SCHEDULE_ENTRIES: usize = 16;
+
+	//a time of day, in seconds since midnight:
+	#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+	pub struct TimeOfDay {
+		pub seconds: u32,
+	}
+
+	impl TimeOfDay {
+		pub const fn new(hours: u32, minutes: u32) -> Self {
+			TimeOfDay {
+				seconds: (hours * 60 + minutes) * 60 % ck::SECONDS_PER_DAY,
+			}
+		}
+
+		//the time of day from a local time as returned by Rtc::local_seconds:
+		pub fn from_local_seconds(local_seconds: i64) -> Self {
+			TimeOfDay {
+				seconds: local_seconds.rem_euclid(ck::SECONDS_PER_DAY as i64) as u32,
+			}
+		}
+
+		pub fn hours(&self) -> u32 {
+			self.seconds / 3600
+		}
+
+		pub fn minutes(&self) -> u32 {
+			self.seconds / 60 % 60
+		}
+	}
+
+	//something that happens at a time of day:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Action {
+		//recall the scene in this slot
+		Scene(u8),
+		Effect(a::EffectParams),
+		//a solid white of this color temperature
+		Kelvin(u16),
+		Brightness(u8),
+		//follow the circadian curve until another action takes over
+		Circadian,
+		Off,
+	}
+
+	//when in the day an action happens:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Trigger {
+		At(TimeOfDay),
+		//this many minutes after sunrise, or before it if negative
+		Sunrise(i16),
+		//this many minutes after sunset, or before it if negative
+		Sunset(i16),
+	}
+
+	impl Trigger {
+		//this works out when the trigger goes off on a local date, in seconds after local midnight.
+		//sun triggers need a location, and don't go off on days the sun doesn't rise or set.
+		pub fn seconds_on(self, date: ck::Date, location: Option<ast::Location>, utc_offset_minutes: i32) -> Option<i32> {
+			let (time, offset_minutes) = match (self, location) {
+				(Trigger::At(time), _) => return Some(time.seconds as i32),
+				(Trigger::Sunrise(offset_minutes), Some(location)) => (ast::sun_times(date, location).sunrise, offset_minutes),
+				(Trigger::Sunset(offset_minutes), Some(location)) => (ast::sun_times(date, location).sunset, offset_minutes),
+				(_, None) => return None,
+			};
+			ast::local_seconds_of_day(time, utc_offset_minutes).map(|seconds| seconds + offset_minutes as i32 * 60)
+		}
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct ScheduleEntry {
+		pub at: Trigger,
+		pub action: Action,
+	}
+
+	impl ScheduleEntry {
+		pub const fn new(at: Trigger, action: Action) -> Self {
+			ScheduleEntry { at, action }
+		}
+	}
+
+	//a list of actions for each day.
+	pub struct Schedule {
+		entries: [Option<ScheduleEntry>; MAX_SCHEDULE_ENTRIES],
+		entry_count: usize,
+	}
+
+	impl Default for Schedule {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	impl Schedule {
+		pub fn new() -> Self {
+			Schedule {
+				entries: [None; MAX_SCHEDULE_ENTRIES],
+				entry_count: 0,
+			}
+		}
+
+		//adds an entry to the end, or hands it back if the schedule is full.
+		//entries that go off at the same time run in the order they were added.
+		pub fn add(&mut self, entry: ScheduleEntry) -> Result<(), ScheduleEntry> {
+			if self.entry_count == MAX_SCHEDULE_ENTRIES {
+				return Err(entry);
+			}
+			self.entries[self.entry_count] = Some(entry);
+			self.entry_count += 1;
+			Ok(())
+		}
+
+		pub fn remove(&mut self, index: usize) -> Option<ScheduleEntry> {
+			if index >= self.entry_count {
+				return None;
+			}
+			let entry = self.entries[index];
+			for i in index..self.entry_count - 1 {
+				self.entries[i] = self.entries[i + 1];
+			}
+			self.entry_count -= 1;
+			self.entries[self.entry_count] = None;
+			entry
+		}
+
+		pub fn len(&self) -> usize {
+			self.entry_count
+		}
+
+		pub fn is_empty(&self) -> bool {
+			self.entry_count == 0
+		}
+
+		pub fn iter(&self) -> impl Iterator<Item = &ScheduleEntry> {
+			self.entries.iter().filter_map(|slot| slot.as_ref())
+		}
+
+		//this finds when the entries in charge at a local time went off: the last time anything went
+		//off today, or the day before if nothing has happened yet today. A change in that time means
+		//the entries that went off then, from entries_at, are due.
+		//this only depends on its arguments, so a whole day can be played through on a computer.
+		pub fn latest(&self, local_seconds: i64, location: Option<ast::Location>, utc_offset_minutes: i32) -> Option<i64> {
+			self.run_times(local_seconds, location, utc_offset_minutes).map(|(run_at, _)| run_at).filter(|&run_at| run_at <= local_seconds).max()
+		}
+
+		//the entries that go off at a local time, in the order they were added:
+		pub fn entries_at(&self, run_at: i64, location: Option<ast::Location>, utc_offset_minutes: i32) -> impl Iterator<Item = ScheduleEntry> + '_ {
+			self.run_times(run_at, location, utc_offset_minutes).filter(move |&(at, _)| at == run_at).map(|(_, entry)| entry)
+		}
+
+		//when each entry goes off the day before a local time and on the day itself, entry by entry:
+		fn run_times(&self, local_seconds: i64, location: Option<ast::Location>, utc_offset_minutes: i32) -> impl Iterator<Item = (i64, ScheduleEntry)> + '_ {
+			let midnight = local_seconds - TimeOfDay::from_local_seconds(local_seconds).seconds as i64;
+			self.iter().flat_map(move |&entry| {
+				(0..2).rev().filter_map(move |days_before| {
+					let day_start = midnight - days_before * ck::SECONDS_PER_DAY as i64;
+					let (date, _) = ck::split_unix_time(day_start);
+					entry.at.seconds_on(date, location, utc_offset_minutes).map(|seconds| (day_start + seconds as i64, entry))
+				})
+			})
+		}
+	}
+
+	//what the office lights do through the day, once the clock has been set:
+	pub const OFFICE_SCHEDULE: [ScheduleEntry; 4] = [
+		ScheduleEntry::new(
+			Trigger::At(TimeOfDay::new(7, 30)),
+			Action::Effect(a::EffectParams { effect: a::Effect::Sunrise, ..a::EffectParams::solid(c::C_OFF) }),
+		),
+		ScheduleEntry::new(Trigger::At(TimeOfDay::new(12, 0)), Action::Kelvin(5000)),
+		ScheduleEntry::new(Trigger::At(TimeOfDay::new(18, 0)), Action::Kelvin(3000)),
+		ScheduleEntry::new(Trigger::At(TimeOfDay::new(23, 0)), Action::Off),
+	];
+
+	//a point on the circadian curve:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct CircadianPoint {
+		pub at: TimeOfDay,
+		pub kelvin: u16,
+		pub brightness: u8,
+	}
+
+	impl CircadianPoint {
+		pub const fn new(at: TimeOfDay, kelvin: u16, brightness: u8) -> Self {
+			CircadianPoint { at, kelvin, brightness }
+		}
+	}
+
+	//warm and dim at night, cool and bright around midday:
+	pub const DEFAULT_CIRCADIAN_CURVE: [CircadianPoint; 7] = [
+		CircadianPoint::new(TimeOfDay::new(0, 0), 1900, 25),
+		CircadianPoint::new(TimeOfDay::new(6, 0), 2200, 60),
+		CircadianPoint::new(TimeOfDay::new(8, 0), 3500, 200),
+		CircadianPoint::new(TimeOfDay::new(12, 0), 5000, 255),
+		CircadianPoint::new(TimeOfDay::new(17, 0), 4000, 230),
+		CircadianPoint::new(TimeOfDay::new(20, 0), 2700, 150),
+		CircadianPoint::new(TimeOfDay::new(22, 0), 2000, 60),
+	];
+
+	fn lerp(from: i32, to: i32, position: u32, span: u32) -> i32 {
+		from + ((to - from) as i64 * position as i64 / span.max(1) as i64) as i32
+	}
+
+	//this gets the color temperature and brightness at a time of day, going in a straight line
+	//between the points of the curve. The curve has to be in time order, and wraps from the last
+	//point around to the first.
+	pub fn circadian_at(curve: &[CircadianPoint], time: TimeOfDay) -> (u16, u8) {
+		let (first, last) = match (curve.first(), curve.last()) {
+			(Some(&first), Some(&last)) => (first, last),
+			_ => return (c::KELVIN_MAX, 255),
+		};
+		let next_index = curve.iter().position(|point| point.at > time);
+		let (from, to) = match next_index {
+			Some(0) | None => (last, first),
+			Some(index) => (curve[index - 1], curve[index]),
+		};
+		let day = ck::SECONDS_PER_DAY;
+		let span = (to.at.seconds + day - from.at.seconds) % day;
+		let position = (time.seconds + day - from.at.seconds) % day;
+		let kelvin = lerp(from.kelvin as i32, to.kelvin as i32, position, span);
+		let brightness = lerp(from.brightness as i32, to.brightness as i32, position, span);
+		(kelvin as u16, brightness as u8)
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		//2024-06-20 00:00, local time:
+		const MIDNIGHT: i64 = 1_718_841_600;
+
+		fn office_schedule() -> Schedule {
+			let mut schedule = Schedule::new();
+			for &entry in OFFICE_SCHEDULE.iter() {
+				schedule.add(entry).unwrap();
+			}
+			schedule
+		}
+
+		fn actions_at(schedule: &Schedule, run_at: i64) -> Vec<Action> {
+			schedule.entries_at(run_at, None, 0).map(|entry| entry.action).collect()
+		}
+
+		#[test]
+		fn office_schedule_through_a_day() {
+			let schedule = office_schedule();
+			//plays the day through a second at a time, the way the main loop checks it:
+			let mut last_run = None;
+			let mut runs = Vec::new();
+			for local_seconds in MIDNIGHT..MIDNIGHT + ck::SECONDS_PER_DAY as i64 {
+				let run_at = schedule.latest(local_seconds, None, 0);
+				if run_at != last_run {
+					last_run = run_at;
+					runs.push((local_seconds - MIDNIGHT, run_at.unwrap() - MIDNIGHT, actions_at(&schedule, run_at.unwrap())));
+				}
+			}
+			let sunrise = OFFICE_SCHEDULE[0].action;
+			assert_eq!(runs, vec![
+				//the day before's last entry is in charge until the first one today:
+				(0, -3600, vec![Action::Off]),
+				(7 * 3600 + 1800, 7 * 3600 + 1800, vec![sunrise]),
+				(12 * 3600, 12 * 3600, vec![Action::Kelvin(5000)]),
+				(18 * 3600, 18 * 3600, vec![Action::Kelvin(3000)]),
+				(23 * 3600, 23 * 3600, vec![Action::Off]),
+			]);
+		}
+
+		#[test]
+		fn day_before_rollover() {
+			let schedule = office_schedule();
+			assert_eq!(schedule.latest(MIDNIGHT + 3 * 3600, None, 0), Some(MIDNIGHT - 3600));
+			assert_eq!(schedule.latest(MIDNIGHT + 7 * 3600 + 1799, None, 0), Some(MIDNIGHT - 3600));
+			assert_eq!(schedule.latest(MIDNIGHT - 1, None, 0), Some(MIDNIGHT - 3600));
+			assert_eq!(schedule.latest(MIDNIGHT - 3601, None, 0), Some(MIDNIGHT - 6 * 3600));
+			assert_eq!(Schedule::new().latest(MIDNIGHT, None, 0), None);
+		}
+
+		#[test]
+		fn adding_and_removing() {
+			let mut schedule = Schedule::default();
+			assert!(schedule.is_empty());
+			let entry = |hour| ScheduleEntry::new(Trigger::At(TimeOfDay::new(hour, 0)), Action::Brightness(hour as u8));
+			for hour in 0..MAX_SCHEDULE_ENTRIES as u32 {
+				schedule.add(entry(hour)).unwrap();
+			}
+			assert!(schedule.add(entry(20)).is_err());
+			assert_eq!(schedule.remove(1).map(|entry| entry.action), Some(Action::Brightness(1)));
+			assert_eq!(schedule.remove(MAX_SCHEDULE_ENTRIES - 1), None);
+			assert_eq!(schedule.len(), MAX_SCHEDULE_ENTRIES - 1);
+			assert_eq!(schedule.iter().nth(1).map(|entry| entry.action), Some(Action::Brightness(2)));
+			while schedule.remove(0).is_some() {}
+			assert!(schedule.is_empty());
+			assert_eq!(schedule.iter().count(), 0);
+		}
+
+		#[test]
+		fn same_time_entries_run_in_the_order_they_were_added() {
+			let mut schedule = Schedule::new();
+			let at = Trigger::At(TimeOfDay::new(20, 0));
+			schedule.add(ScheduleEntry::new(at, Action::Scene(3))).unwrap();
+			schedule.add(ScheduleEntry::new(Trigger::At(TimeOfDay::new(21, 0)), Action::Off)).unwrap();
+			schedule.add(ScheduleEntry::new(at, Action::Brightness(40))).unwrap();
+			schedule.add(ScheduleEntry::new(at, Action::Circadian)).unwrap();
+			let run_at = schedule.latest(MIDNIGHT + 20 * 3600 + 5, None, 0).unwrap();
+			assert_eq!(run_at, MIDNIGHT + 20 * 3600);
+			assert_eq!(actions_at(&schedule, run_at), vec![Action::Scene(3), Action::Brightness(40), Action::Circadian]);
+			//the same entries are in charge from the day before until 20:00:
+			let run_at = schedule.latest(MIDNIGHT + 19 * 3600, None, 0).unwrap();
+			assert_eq!(run_at, MIDNIGHT - 3 * 3600);
+			assert_eq!(actions_at(&schedule, run_at), vec![Action::Off]);
+		}
+
+		#[test]
+		fn sun_triggers_need_a_location() {
+			let mut schedule = Schedule::new();
+			schedule.add(ScheduleEntry::new(Trigger::Sunset(-30), Action::Kelvin(2700))).unwrap();
+			assert_eq!(schedule.latest(MIDNIGHT + 23 * 3600, None, 0), None);
+			//new york, where the sun sets at 20:30 local time (UTC-4) on 2024-06-20:
+			let location = Some(ast::Location::new(40_713, -74_006));
+			let run_at = schedule.latest(MIDNIGHT + 23 * 3600, location, -240).unwrap();
+			assert!((run_at - (MIDNIGHT + 20 * 3600)).abs() <= 180, "{}", run_at - MIDNIGHT);
+			assert_eq!(schedule.entries_at(run_at, location, -240).map(|entry| entry.action).collect::<Vec<_>>(), vec![Action::Kelvin(2700)]);
+		}
+
+		#[test]
+		fn circadian_curve() {
+			let curve = &DEFAULT_CIRCADIAN_CURVE;
+			assert_eq!(circadian_at(curve, TimeOfDay::new(12, 0)), (5000, 255));
+			assert_eq!(circadian_at(curve, TimeOfDay::new(7, 0)), (2850, 130));
+			//wraps from 22:00 around to midnight:
+			assert_eq!(circadian_at(curve, TimeOfDay::new(23, 0)), (1950, 43));
+		}
+	}
+}
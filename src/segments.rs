@@ -0,0 +1,278 @@
+pub mod segments {
+	use crate::a;
+	use crate::c;
+	use core::ops::Range;
+
+	pub const MAX_SEGMENTS: usize = 8;
+	pub const MAX_NAME_LENGTH: usize = 12;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum SegmentError {
+		//names need to be 1 to MAX_NAME_LENGTH letters, numbers, - or _
+		BadName,
+		//another segment on the strip already has this name
+		NameTaken,
+		//the segment doesn't cover any LEDs
+		Empty,
+		//the segment runs past the end of the strip, which has this many LEDs
+		PastEnd(usize),
+		//all MAX_SEGMENTS slots are in use
+		Full,
+	}
+
+	fn is_name_byte(byte: u8) -> bool {
+		byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'
+	}
+
+	//a named zone of the logical strip running its own animation. The range is in logical LED
+	//indexes, so a segment can run across the end of one physical strip and onto the next.
+	pub struct Segment {
+		name: [u8; MAX_NAME_LENGTH],
+		name_length: u8,
+		pub range: Range<usize>,
+		pub animation: a::Animation,
+		pub brightness: u8,
+		//the animation runs from the end of the range back to the start:
+		pub reversed: bool,
+		//the animation only fills the first half of the range and is reflected onto the second half:
+		pub mirrored: bool,
+		pub enabled: bool,
+	}
+
+	impl Segment {
+		pub fn new(name: &str, range: Range<usize>, animation: a::Animation) -> Result<Self, SegmentError> {
+			let bytes = name.as_bytes();
+			if bytes.is_empty() || bytes.len() > MAX_NAME_LENGTH || !bytes.iter().all(|&byte| is_name_byte(byte)) {
+				return Err(SegmentError::BadName);
+			}
+			let mut segment = Segment {
+				name: [0; MAX_NAME_LENGTH],
+				name_length: bytes.len() as u8,
+				range,
+				animation,
+				brightness: 255,
+				reversed: false,
+				mirrored: false,
+				enabled: true,
+			};
+			segment.name[..bytes.len()].copy_from_slice(bytes);
+			Ok(segment)
+		}
+
+		pub fn name(&self) -> &str {
+			core::str::from_utf8(&self.name[..self.name_length as usize]).unwrap_or("")
+		}
+
+		pub fn len(&self) -> usize {
+			self.range.end.saturating_sub(self.range.start)
+		}
+
+		pub fn is_empty(&self) -> bool {
+			self.len() == 0
+		}
+
+		//the number of LEDs the animation itself draws, before mirroring:
+		fn animation_len(&self, segment_len: usize) -> usize {
+			match self.mirrored {
+				true => segment_len.div_ceil(2),
+				false => segment_len,
+			}
+		}
+
+		//this maps an LED in the segment to the LED the animation drew for it:
+		fn source_index(&self, index: usize, segment_len: usize) -> usize {
+			let index = match self.mirrored && index >= self.animation_len(segment_len) {
+				true => segment_len - 1 - index,
+				false => index,
+			};
+			match self.reversed {
+				true => self.animation_len(segment_len) - 1 - index,
+				false => index,
+			}
+		}
+
+		//this renders the segment's animation into the scratch frame, then copies it over its range of the frame:
+		pub fn render_over(&mut self, now_ms: u32, frame: &mut [c::Color], scratch: &mut [c::Color]) {
+			let start = self.range.start.min(frame.len());
+			let end = self.range.end.min(frame.len());
+			if start >= end {
+				return;
+			}
+			let segment_len = end - start;
+			let animation_frame = &mut scratch[..self.animation_len(segment_len)];
+			for led in animation_frame.iter_mut() {
+				*led = c::C_OFF;
+			}
+			self.animation.update(now_ms, animation_frame);
+			for (i, led) in frame[start..end].iter_mut().enumerate() {
+				*led = animation_frame[self.source_index(i, segment_len)].scaled(self.brightness);
+			}
+		}
+	}
+
+	//the segments on a logical strip. Segments are drawn in order, so later segments cover
+	//earlier ones where they overlap.
+	pub struct SegmentList {
+		segments: [Option<Segment>; MAX_SEGMENTS],
+	}
+
+	impl SegmentList {
+		pub fn new() -> Self {
+			SegmentList {
+				segments: Default::default(),
+			}
+		}
+
+		//adds a segment to the first open slot of a strip of led_count LEDs and returns its index.
+		//the segment has to fit on the strip and have a name no other segment has.
+		pub fn add(&mut self, segment: Segment, led_count: usize) -> Result<usize, SegmentError> {
+			if segment.is_empty() {
+				return Err(SegmentError::Empty);
+			}
+			if segment.range.end > led_count {
+				return Err(SegmentError::PastEnd(led_count));
+			}
+			if self.find(segment.name()).is_some() {
+				return Err(SegmentError::NameTaken);
+			}
+			let index = self.segments.iter().position(|slot| slot.is_none()).ok_or(SegmentError::Full)?;
+			self.segments[index] = Some(segment);
+			Ok(index)
+		}
+
+		pub fn remove(&mut self, index: usize) -> Option<Segment> {
+			self.segments.get_mut(index).and_then(|slot| slot.take())
+		}
+
+		pub fn clear(&mut self) {
+			for slot in self.segments.iter_mut() {
+				*slot = None;
+			}
+		}
+
+		pub fn find(&self, name: &str) -> Option<usize> {
+			self.segments
+				.iter()
+				.position(|slot| matches!(slot, Some(segment) if segment.name() == name))
+		}
+
+		pub fn segment(&self, index: usize) -> Option<&Segment> {
+			self.segments.get(index).and_then(|slot| slot.as_ref())
+		}
+
+		pub fn segment_mut(&mut self, index: usize) -> Option<&mut Segment> {
+			self.segments.get_mut(index).and_then(|slot| slot.as_mut())
+		}
+
+		//the segments with the slots they are in:
+		pub fn iter(&self) -> impl Iterator<Item = (usize, &Segment)> {
+			self.segments.iter().enumerate().filter_map(|(index, slot)| slot.as_ref().map(|segment| (index, segment)))
+		}
+
+		pub fn render_over(&mut self, now_ms: u32, frame: &mut [c::Color], scratch: &mut [c::Color]) {
+			for segment in self.segments.iter_mut().filter_map(|slot| slot.as_mut()) {
+				if segment.enabled {
+					segment.render_over(now_ms, frame, scratch);
+				}
+			}
+		}
+	}
+
+	impl Default for SegmentList {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const RED: c::Color = c::Color { r: 200, g: 0, b: 0 };
+		const BLUE: c::Color = c::Color { r: 0, g: 0, b: 200 };
+
+		fn solid(name: &str, range: Range<usize>, color: c::Color) -> Segment {
+			Segment::new(name, range.clone(), a::Animation::new(range.len(), a::Mode::Solid(color))).unwrap()
+		}
+
+		fn render(segments: &mut SegmentList, led_count: usize) -> Vec<c::Color> {
+			let mut frame = vec![c::C_WHITE; led_count];
+			let mut scratch = vec![c::C_OFF; led_count];
+			segments.render_over(0, &mut frame, &mut scratch);
+			frame
+		}
+
+		#[test]
+		fn segments_cover_their_range() {
+			let mut segments = SegmentList::new();
+			let mut desk = solid("desk", 2..5, RED);
+			desk.brightness = 128;
+			segments.add(desk, 8).unwrap();
+			let dim_red = RED.scaled(128);
+			assert_eq!(render(&mut segments, 8), [c::C_WHITE, c::C_WHITE, dim_red, dim_red, dim_red, c::C_WHITE, c::C_WHITE, c::C_WHITE]);
+			//a frame shorter than the segment only gets the part that fits:
+			assert_eq!(render(&mut segments, 4), [c::C_WHITE, c::C_WHITE, dim_red, dim_red]);
+			assert_eq!(render(&mut segments, 2), [c::C_WHITE, c::C_WHITE]);
+		}
+
+		#[test]
+		fn later_segments_cover_earlier_ones() {
+			let mut segments = SegmentList::new();
+			assert_eq!(segments.add(solid("left", 0..4, RED), 8), Ok(0));
+			assert_eq!(segments.add(solid("right", 2..6, BLUE), 8), Ok(1));
+			assert_eq!(render(&mut segments, 6), [RED, RED, BLUE, BLUE, BLUE, BLUE]);
+			//a removed segment's slot is the next one used, so the new segment goes underneath:
+			segments.remove(0);
+			assert_eq!(segments.add(solid("top", 0..4, c::C_GREEN), 8), Ok(0));
+			assert_eq!(render(&mut segments, 6), [c::C_GREEN, c::C_GREEN, BLUE, BLUE, BLUE, BLUE]);
+			//disabled segments aren't drawn:
+			segments.segment_mut(1).unwrap().enabled = false;
+			assert_eq!(render(&mut segments, 6), [c::C_GREEN, c::C_GREEN, c::C_GREEN, c::C_GREEN, c::C_WHITE, c::C_WHITE]);
+			assert_eq!(segments.find("right"), Some(1));
+			assert_eq!(segments.iter().map(|(index, segment)| (index, segment.name())).collect::<Vec<_>>(), [(0, "top"), (1, "right")]);
+			segments.clear();
+			assert_eq!(segments.iter().count(), 0);
+		}
+
+		#[test]
+		fn reversed_and_mirrored() {
+			let source = |segment: &Segment, length: usize| (0..length).map(|i| segment.source_index(i, length)).collect::<Vec<_>>();
+			let mut segment = solid("desk", 0..5, RED);
+			assert_eq!(source(&segment, 5), [0, 1, 2, 3, 4]);
+			segment.reversed = true;
+			assert_eq!(source(&segment, 5), [4, 3, 2, 1, 0]);
+			//mirrored, the animation draws the first half, with the middle LED of an odd length once:
+			segment.reversed = false;
+			segment.mirrored = true;
+			assert_eq!(segment.animation_len(5), 3);
+			assert_eq!(source(&segment, 5), [0, 1, 2, 1, 0]);
+			assert_eq!(source(&segment, 4), [0, 1, 1, 0]);
+			//and reversed as well, the animation runs in from the ends:
+			segment.reversed = true;
+			assert_eq!(source(&segment, 5), [2, 1, 0, 1, 2]);
+			assert_eq!(source(&segment, 4), [1, 0, 0, 1]);
+			assert_eq!(source(&segment, 1), [0]);
+		}
+
+		#[test]
+		fn segments_have_to_fit() {
+			let animation = || a::Animation::new(4, a::Mode::Solid(RED));
+			assert_eq!(Segment::new("", 0..4, animation()).err(), Some(SegmentError::BadName));
+			assert_eq!(Segment::new("desk area", 0..4, animation()).err(), Some(SegmentError::BadName));
+			assert_eq!(Segment::new("a-very-long-name", 0..4, animation()).err(), Some(SegmentError::BadName));
+			let mut segments = SegmentList::default();
+			assert_eq!(segments.add(solid("empty", 4..4, RED), 8), Err(SegmentError::Empty));
+			let mut backwards = solid("backwards", 0..4, RED);
+			let (start, end) = (5, 3);
+			backwards.range = start..end;
+			assert_eq!(segments.add(backwards, 8), Err(SegmentError::Empty));
+			assert_eq!(segments.add(solid("long", 6..9, RED), 8), Err(SegmentError::PastEnd(8)));
+			assert_eq!(segments.add(solid("desk", 4..8, RED), 8), Ok(0));
+			assert_eq!(segments.add(solid("desk", 0..2, RED), 8), Err(SegmentError::NameTaken));
+			for i in 1..MAX_SEGMENTS {
+				assert_eq!(segments.add(solid(&format!("zone{}", i), 0..1, RED), 8), Ok(i));
+			}
+			assert_eq!(segments.add(solid("more", 0..1, RED), 8), Err(SegmentError::Full));
+		}
+	}
+}
@@ -0,0 +1,489 @@
+pub mod settings {
+	use crate::a;
+	use crate::c;
+	use crate::fl;
+
+	//the settings are saved as small records, one after another through a sector of flash. When a
+	//sector fills up, the other one is erased and saving carries on there, so every record is
+	//written to fresh flash and each sector is only erased once every SLOTS_PER_SECTOR saves.
+	//a record is laid out as:
+	//  4 bytes  magic "WSST"
+	//  1 byte   version of the payload
+	//  1 byte   payload length
+	//  4 bytes  sequence number, little endian, one higher for each save
+	//  payload  see Settings::encode
+	//  4 bytes  CRC-32 of everything before it, little endian
+	pub const SETTINGS_MAGIC: [u8; 4] = *b"WSST";
+	pub const SETTINGS_VERSION: u8 = 1;
+	const HEADER_LENGTH: usize = 10;
+	const CRC_LENGTH: usize = 4;
+	pub const RECORD_LENGTH: usize = 32;
+	pub const MAX_PAYLOAD_LENGTH: usize = RECORD_LENGTH - HEADER_LENGTH - CRC_LENGTH;
+	pub const SECTOR_COUNT: u32 = 2;
+	pub const SLOTS_PER_SECTOR: u32 = fl::SECTOR_SIZE / RECORD_LENGTH as u32;
+	pub const PAYLOAD_LENGTH: usize = 12;
+
+	//settings are saved this long after they stop changing, so dragging a brightness slider
+	//doesn't write to flash on every step:
+	pub const SAVE_DELAY_MS: u32 = 5_000;
+
+	//the value of a scene byte when no scene is active:
+	const NO_SCENE: u8 = 0xFF;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum SettingsError {
+		BadMagic,
+		//the record is from other firmware than this
+		UnsupportedVersion(u8),
+		BadLength,
+		BadCrc,
+		//the record's effect number isn't one this firmware has
+		UnknownEffect(u8),
+	}
+
+	//what the lights were doing, so they come back the same way after a reboot:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Settings {
+		//the scene that was last recalled, if the lights are still showing it:
+		pub scene: Option<u8>,
+		pub brightness: u8,
+		pub effect: a::EffectParams,
+		//whether the lights were turned on:
+		pub on: bool,
+	}
+
+	impl Default for Settings {
+		fn default() -> Self {
+			Settings {
+				scene: None,
+				brightness: 255,
+				effect: a::EffectParams::solid(c::C_T_4000K),
+				on: true,
+			}
+		}
+	}
+
+	impl Settings {
+		//this writes the payload, returning the number of bytes used:
+		pub fn encode(&self, payload: &mut [u8; MAX_PAYLOAD_LENGTH]) -> usize {
+			let fields: [u8; PAYLOAD_LENGTH] = [
+				self.scene.unwrap_or(NO_SCENE),
+				self.brightness,
+				self.effect.effect.to_u8(),
+				self.effect.color.r,
+				self.effect.color.g,
+				self.effect.color.b,
+				self.effect.palette,
+				self.effect.speed,
+				self.on as u8,
+				self.effect.intensity,
+				self.effect.flags(),
+				self.effect.custom,
+			];
+			payload[..fields.len()].copy_from_slice(&fields);
+			fields.len()
+		}
+
+		//this reads a payload written by encode:
+		pub fn decode(version: u8, payload: &[u8]) -> Result<Self, SettingsError> {
+			if version != SETTINGS_VERSION {
+				return Err(SettingsError::UnsupportedVersion(version));
+			}
+			if payload.len() != PAYLOAD_LENGTH {
+				return Err(SettingsError::BadLength);
+			}
+			let effect_number = payload[2];
+			Ok(Settings {
+				scene: match payload[0] {
+					NO_SCENE => None,
+					scene => Some(scene),
+				},
+				brightness: payload[1],
+				effect: a::EffectParams {
+					effect: a::Effect::from_u8(effect_number).ok_or(SettingsError::UnknownEffect(effect_number))?,
+					color: c::Color {
+						r: payload[3],
+						g: payload[4],
+						b: payload[5],
+					},
+					palette: payload[6],
+					speed: payload[7],
+					intensity: payload[9],
+					custom: payload[11],
+					per_strip: payload[10] & a::FLAG_PER_STRIP != 0,
+					reversed: payload[10] & a::FLAG_REVERSED != 0,
+				},
+				on: payload[8] != 0,
+			})
+		}
+	}
+
+	//CRC-32 as used by zip and ethernet, worked out a bit at a time since records are tiny:
+	pub fn crc32(data: &[u8]) -> u32 {
+		let mut crc = 0xFFFF_FFFF_u32;
+		for &byte in data {
+			crc ^= byte as u32;
+			for _ in 0..8 {
+				crc = match crc & 1 {
+					1 => (crc >> 1) ^ 0xEDB8_8320,
+					_ => crc >> 1,
+				};
+			}
+		}
+		!crc
+	}
+
+	//this makes a whole record from settings. The unused end of the record is left erased.
+	pub fn encode_record(settings: &Settings, sequence: u32) -> [u8; RECORD_LENGTH] {
+		let mut record = [0xFF_u8; RECORD_LENGTH];
+		let mut payload = [0_u8; MAX_PAYLOAD_LENGTH];
+		let payload_length = settings.encode(&mut payload);
+		record[..4].copy_from_slice(&SETTINGS_MAGIC);
+		record[4] = SETTINGS_VERSION;
+		record[5] = payload_length as u8;
+		record[6..HEADER_LENGTH].copy_from_slice(&sequence.to_le_bytes());
+		let crc_start = HEADER_LENGTH + payload_length;
+		record[HEADER_LENGTH..crc_start].copy_from_slice(&payload[..payload_length]);
+		let crc = crc32(&record[..crc_start]);
+		record[crc_start..crc_start + CRC_LENGTH].copy_from_slice(&crc.to_le_bytes());
+		record
+	}
+
+	//this checks a record and gets the settings and sequence number out of it:
+	pub fn decode_record(record: &[u8; RECORD_LENGTH]) -> Result<(Settings, u32), SettingsError> {
+		if record[..4] != SETTINGS_MAGIC {
+			return Err(SettingsError::BadMagic);
+		}
+		let payload_length = record[5] as usize;
+		if payload_length > MAX_PAYLOAD_LENGTH {
+			return Err(SettingsError::BadLength);
+		}
+		let crc_start = HEADER_LENGTH + payload_length;
+		let mut crc = [0_u8; CRC_LENGTH];
+		crc.copy_from_slice(&record[crc_start..crc_start + CRC_LENGTH]);
+		if crc32(&record[..crc_start]) != u32::from_le_bytes(crc) {
+			return Err(SettingsError::BadCrc);
+		}
+		let mut sequence = [0_u8; 4];
+		sequence.copy_from_slice(&record[6..HEADER_LENGTH]);
+		let settings = Settings::decode(record[4], &record[HEADER_LENGTH..crc_start])?;
+		Ok((settings, u32::from_le_bytes(sequence)))
+	}
+
+	//keeps the settings in flash, starting at base_address and taking SECTOR_COUNT sectors.
+	pub struct SettingsStore<F: fl::Flash> {
+		flash: F,
+		base_address: u32,
+		//the address and sequence number of the newest good record:
+		newest: Option<(u32, u32)>,
+		saved: Settings,
+		//settings waiting to be saved, and when they last changed:
+		pending: Option<(Settings, u32)>,
+	}
+
+	impl<F: fl::Flash> SettingsStore<F> {
+		pub fn new(flash: F, base_address: u32) -> Self {
+			SettingsStore {
+				flash,
+				base_address,
+				newest: None,
+				saved: Settings::default(),
+				pending: None,
+			}
+		}
+
+		pub fn flash(&self) -> &F {
+			&self.flash
+		}
+
+		fn slot_address(&self, sector: u32, slot: u32) -> u32 {
+			self.base_address + sector * fl::SECTOR_SIZE + slot * RECORD_LENGTH as u32
+		}
+
+		fn read_slot(&mut self, address: u32) -> Result<[u8; RECORD_LENGTH], fl::FlashError> {
+			let mut record = [0_u8; RECORD_LENGTH];
+			self.flash.read(address, &mut record)?;
+			Ok(record)
+		}
+
+		//this finds the newest good record in flash and returns its settings, or the defaults if
+		//there isn't one. Records that are damaged or can't be read are skipped. Sequence numbers
+		//wrap, so a record is newer if it is less than half the number range ahead.
+		pub fn load(&mut self) -> Settings {
+			self.newest = None;
+			self.saved = Settings::default();
+			for sector in 0..SECTOR_COUNT {
+				for slot in 0..SLOTS_PER_SECTOR {
+					let address = self.slot_address(sector, slot);
+					let (settings, sequence) = match self.read_slot(address).ok().map(|record| decode_record(&record)) {
+						Some(Ok(found)) => found,
+						_ => continue,
+					};
+					if self.newest.map_or(true, |(_, newest_sequence)| sequence.wrapping_sub(newest_sequence) as i32 > 0) {
+						self.newest = Some((address, sequence));
+						self.saved = settings;
+					}
+				}
+			}
+			self.saved
+		}
+
+		//this finds the next slot to write to, erasing the other sector when the current one is full:
+		fn next_slot(&mut self) -> Result<u32, fl::FlashError> {
+			let (sector, first_slot) = match self.newest {
+				Some((address, _)) => {
+					let offset = address - self.base_address;
+					(offset / fl::SECTOR_SIZE, offset % fl::SECTOR_SIZE / RECORD_LENGTH as u32 + 1)
+				}
+				None => (SECTOR_COUNT - 1, SLOTS_PER_SECTOR),
+			};
+			//anything after the newest record that isn't erased is left over from a save that
+			//didn't finish, so it is skipped:
+			for slot in first_slot..SLOTS_PER_SECTOR {
+				let address = self.slot_address(sector, slot);
+				if self.read_slot(address)?.iter().all(|&byte| byte == 0xFF) {
+					return Ok(address);
+				}
+			}
+			let next_sector = (sector + 1) % SECTOR_COUNT;
+			let address = self.slot_address(next_sector, 0);
+			self.flash.erase_sector(address)?;
+			Ok(address)
+		}
+
+		//this writes settings to flash straight away:
+		pub fn save_now(&mut self, settings: Settings) -> Result<(), fl::FlashError> {
+			let sequence = self.newest.map_or(0, |(_, sequence)| sequence.wrapping_add(1));
+			let address = self.next_slot()?;
+			self.flash.write(address, &encode_record(&settings, sequence))?;
+			self.newest = Some((address, sequence));
+			self.saved = settings;
+			self.pending = None;
+			Ok(())
+		}
+
+		//this queues settings to be saved once they have stopped changing for SAVE_DELAY_MS:
+		pub fn save_later(&mut self, settings: Settings, now_ms: u32) {
+			self.pending = match self.pending {
+				Some((pending, changed_ms)) if pending == settings => Some((pending, changed_ms)),
+				_ if settings == self.saved => None,
+				_ => Some((settings, now_ms)),
+			};
+		}
+
+		//this saves the queued settings if they are due, returning true if anything was written.
+		//it should be called regularly, like once a frame.
+		pub fn poll(&mut self, now_ms: u32) -> Result<bool, fl::FlashError> {
+			match self.pending {
+				Some((settings, changed_ms)) if now_ms.wrapping_sub(changed_ms) >= SAVE_DELAY_MS => {
+					self.save_now(settings)?;
+					Ok(true)
+				}
+				_ => Ok(false),
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use crate::fl::Flash;
+
+		const BASE: u32 = 0x3F1000;
+		type TestFlash = fl::MemoryFlash<{ 2 * fl::SECTOR_SIZE as usize }>;
+
+		fn settings(brightness: u8) -> Settings {
+			Settings {
+				scene: Some(3),
+				brightness,
+				effect: a::EffectParams {
+					effect: a::Effect::Balls,
+					color: c::Color { r: 1, g: 2, b: 3 },
+					palette: 14,
+					speed: 9,
+					intensity: 5,
+					custom: 20,
+					per_strip: true,
+					reversed: true,
+				},
+				on: false,
+			}
+		}
+
+		//a store on a copy of the flash, as it would be found after a reboot:
+		fn reboot(store: &SettingsStore<TestFlash>) -> SettingsStore<TestFlash> {
+			let mut flash = TestFlash::new(BASE);
+			flash.data = store.flash().data;
+			SettingsStore::new(flash, BASE)
+		}
+
+		//a record with any version and payload:
+		fn raw_record(version: u8, payload: &[u8], sequence: u32) -> [u8; RECORD_LENGTH] {
+			let mut record = [0xFF_u8; RECORD_LENGTH];
+			record[..4].copy_from_slice(&SETTINGS_MAGIC);
+			record[4] = version;
+			record[5] = payload.len() as u8;
+			record[6..HEADER_LENGTH].copy_from_slice(&sequence.to_le_bytes());
+			let crc_start = HEADER_LENGTH + payload.len();
+			record[HEADER_LENGTH..crc_start].copy_from_slice(payload);
+			let crc = crc32(&record[..crc_start]);
+			record[crc_start..crc_start + CRC_LENGTH].copy_from_slice(&crc.to_le_bytes());
+			record
+		}
+
+		fn load_record(record: &[u8; RECORD_LENGTH]) -> Settings {
+			let mut flash = TestFlash::new(BASE);
+			flash.write(BASE, record).unwrap();
+			SettingsStore::new(flash, BASE).load()
+		}
+
+		#[test]
+		fn crc() {
+			//the standard check value:
+			assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+		}
+
+		#[test]
+		fn round_trip() {
+			let mut store = SettingsStore::new(TestFlash::new(BASE), BASE);
+			assert_eq!(store.load(), Settings::default());
+			store.save_now(settings(10)).unwrap();
+			store.save_now(settings(20)).unwrap();
+			assert_eq!(reboot(&store).load(), settings(20));
+			//a loaded store carries on after the newest record:
+			let mut rebooted = reboot(&store);
+			rebooted.load();
+			rebooted.save_now(settings(30)).unwrap();
+			assert_eq!(decode_record(&rebooted.read_slot(BASE + 2 * RECORD_LENGTH as u32).unwrap()), Ok((settings(30), 2)));
+			assert_eq!(reboot(&rebooted).load(), settings(30));
+		}
+
+		#[test]
+		fn saves_once_changes_stop() {
+			let mut store = SettingsStore::new(TestFlash::new(BASE), BASE);
+			store.load();
+			store.save_later(settings(10), 0);
+			store.save_later(settings(20), 1_000);
+			assert_eq!(store.poll(1_000 + SAVE_DELAY_MS - 1), Ok(false));
+			//the same settings again don't start the wait over:
+			store.save_later(settings(20), 2_000);
+			assert_eq!(store.poll(1_000 + SAVE_DELAY_MS), Ok(true));
+			assert_eq!(store.poll(1_000 + 2 * SAVE_DELAY_MS), Ok(false));
+			assert_eq!(reboot(&store).load(), settings(20));
+			//going back to what is saved cancels the save:
+			store.save_later(settings(30), 20_000);
+			store.save_later(settings(20), 21_000);
+			assert_eq!(store.poll(40_000), Ok(false));
+		}
+
+		#[test]
+		fn damaged_records_are_skipped() {
+			let mut store = SettingsStore::new(TestFlash::new(BASE), BASE);
+			store.load();
+			store.save_now(settings(10)).unwrap();
+			store.save_now(settings(20)).unwrap();
+			let mut rebooted = reboot(&store);
+			//flip a bit of the newest record's CRC, as if the power went while it was written:
+			let crc_end = RECORD_LENGTH + HEADER_LENGTH + 12 + CRC_LENGTH;
+			rebooted.flash.data[crc_end - 1] ^= 0x01;
+			assert_eq!(rebooted.load(), settings(10));
+			let record = rebooted.read_slot(BASE + RECORD_LENGTH as u32).unwrap();
+			assert_eq!(decode_record(&record), Err(SettingsError::BadCrc));
+			//the next save skips the damaged slot rather than writing over it:
+			rebooted.save_now(settings(30)).unwrap();
+			assert_eq!(decode_record(&rebooted.read_slot(BASE + 2 * RECORD_LENGTH as u32).unwrap()), Ok((settings(30), 1)));
+			assert_eq!(reboot(&rebooted).load(), settings(30));
+			//a record with a length too long for it:
+			let mut record = encode_record(&settings(40), 0);
+			record[5] = MAX_PAYLOAD_LENGTH as u8 + 1;
+			assert_eq!(decode_record(&record), Err(SettingsError::BadLength));
+			assert_eq!(load_record(&record), Settings::default());
+		}
+
+		#[test]
+		fn sectors_take_turns() {
+			let mut store = SettingsStore::new(TestFlash::new(BASE), BASE);
+			store.load();
+			//the first save goes at the start of the first sector:
+			store.save_now(settings(0)).unwrap();
+			assert_eq!(store.newest, Some((BASE, 0)));
+			for sequence in 1..SLOTS_PER_SECTOR {
+				store.save_now(settings(sequence as u8)).unwrap();
+			}
+			assert_eq!(store.newest, Some((BASE + fl::SECTOR_SIZE - RECORD_LENGTH as u32, SLOTS_PER_SECTOR - 1)));
+			//once the first sector is full, the second is erased and used:
+			store.save_now(settings(100)).unwrap();
+			assert_eq!(store.newest, Some((BASE + fl::SECTOR_SIZE, SLOTS_PER_SECTOR)));
+			assert_eq!(reboot(&store).load(), settings(100));
+			for sequence in 1..SLOTS_PER_SECTOR {
+				store.save_now(settings(sequence as u8)).unwrap();
+			}
+			//and then the first again, which is erased so only the newest record is in it:
+			store.save_now(settings(200)).unwrap();
+			assert_eq!(store.newest, Some((BASE, 2 * SLOTS_PER_SECTOR)));
+			assert!(store.flash().data[RECORD_LENGTH..fl::SECTOR_SIZE as usize].iter().all(|&byte| byte == 0xFF));
+			let mut rebooted = reboot(&store);
+			assert_eq!(rebooted.load(), settings(200));
+			assert_eq!(rebooted.newest, Some((BASE, 2 * SLOTS_PER_SECTOR)));
+		}
+
+		#[test]
+		fn sequence_numbers_wrap() {
+			let mut flash = TestFlash::new(BASE);
+			flash.write(BASE, &encode_record(&settings(10), u32::MAX)).unwrap();
+			let mut store = SettingsStore::new(flash, BASE);
+			assert_eq!(store.load(), settings(10));
+			store.save_now(settings(20)).unwrap();
+			assert_eq!(store.newest, Some((BASE + RECORD_LENGTH as u32, 0)));
+			assert_eq!(reboot(&store).load(), settings(20));
+
+			//records from both sides of the wrap, in either sector:
+			let sector = BASE + fl::SECTOR_SIZE;
+			for &(first, second) in [(BASE, sector), (sector, BASE)].iter() {
+				let mut flash = TestFlash::new(BASE);
+				flash.write(first, &encode_record(&settings(1), u32::MAX - 1)).unwrap();
+				flash.write(first + RECORD_LENGTH as u32, &encode_record(&settings(2), u32::MAX)).unwrap();
+				flash.write(second, &encode_record(&settings(3), 0)).unwrap();
+				flash.write(second + RECORD_LENGTH as u32, &encode_record(&settings(4), 1)).unwrap();
+				let mut store = SettingsStore::new(flash, BASE);
+				assert_eq!(store.load(), settings(4));
+				assert_eq!(store.newest, Some((second + RECORD_LENGTH as u32, 1)));
+			}
+		}
+
+		#[test]
+		fn payloads() {
+			let mut payload = [0_u8; MAX_PAYLOAD_LENGTH];
+			let length = settings(10).encode(&mut payload);
+			assert_eq!(length, PAYLOAD_LENGTH);
+			assert_eq!(payload[10], a::FLAG_PER_STRIP | a::FLAG_REVERSED);
+			assert_eq!(Settings::decode(SETTINGS_VERSION, &payload[..length]), Ok(settings(10)));
+			assert_eq!(Settings::decode(SETTINGS_VERSION, &payload[..length - 1]), Err(SettingsError::BadLength));
+			assert_eq!(Settings::decode(SETTINGS_VERSION, &payload[..length + 1]), Err(SettingsError::BadLength));
+			//no scene, and an effect this firmware doesn't have:
+			let no_scene = Settings { scene: None, ..settings(10) };
+			no_scene.encode(&mut payload);
+			assert_eq!(payload[0], NO_SCENE);
+			assert_eq!(load_record(&raw_record(SETTINGS_VERSION, &payload[..length], 0)), no_scene);
+			payload[2] = a::EFFECTS.len() as u8;
+			assert_eq!(Settings::decode(SETTINGS_VERSION, &payload[..length]), Err(SettingsError::UnknownEffect(a::EFFECTS.len() as u8)));
+			assert_eq!(load_record(&raw_record(SETTINGS_VERSION, &payload[..length], 0)), Settings::default());
+		}
+
+		#[test]
+		fn other_versions_are_skipped() {
+			//a record from newer firmware, with a field this firmware doesn't know on the end:
+			let mut payload = [0_u8; MAX_PAYLOAD_LENGTH];
+			let length = settings(10).encode(&mut payload);
+			payload[length] = 0x55;
+			let newer = raw_record(SETTINGS_VERSION + 1, &payload[..length + 1], 5);
+			assert_eq!(decode_record(&newer), Err(SettingsError::UnsupportedVersion(SETTINGS_VERSION + 1)));
+			assert_eq!(Settings::decode(0, &payload), Err(SettingsError::UnsupportedVersion(0)));
+			//the older record this firmware wrote is used in its place:
+			let mut flash = TestFlash::new(BASE);
+			flash.write(BASE, &encode_record(&settings(20), 4)).unwrap();
+			flash.write(BASE + RECORD_LENGTH as u32, &newer).unwrap();
+			assert_eq!(SettingsStore::new(flash, BASE).load(), settings(20));
+		}
+	}
+}
@@ -0,0 +1,332 @@
+pub mod spatial {
+	use crate::c;
+	use crate::fx;
+
+	//where an LED is in the room, in millimeters. Flat layouts can leave z at 0.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Point {
+		pub x: i32,
+		pub y: i32,
+		pub z: i32,
+	}
+
+	impl Point {
+		pub const ORIGIN: Point = Point::new(0, 0, 0);
+
+		pub const fn new(x: i32, y: i32, z: i32) -> Self {
+			Point { x, y, z }
+		}
+	}
+
+	//a straight run of evenly spaced LEDs from one corner to the next:
+	#[derive(Copy, Clone)]
+	pub struct MapLine {
+		pub start: Point,
+		pub end: Point,
+		pub led_count: usize,
+	}
+
+	impl MapLine {
+		pub const fn new(start: Point, end: Point, led_count: usize) -> Self {
+			MapLine {
+				start,
+				end,
+				led_count,
+			}
+		}
+
+		//LEDs sit in the middle of their share of the line, so lines can meet at a corner without doubling up:
+		const fn point_at(&self, index: usize) -> Point {
+			let numerator = 2 * index as i32 + 1;
+			let denominator = 2 * self.led_count as i32;
+			Point::new(
+				self.start.x + (self.end.x - self.start.x) * numerator / denominator,
+				self.start.y + (self.end.y - self.start.y) * numerator / denominator,
+				self.start.z + (self.end.z - self.start.z) * numerator / denominator,
+			)
+		}
+	}
+
+	//this lays out LEDs along a list of lines in order, one entry per logical LED index.
+	//it is a const fn so a room's map can be worked out at compile time.
+	pub const fn map_lines<const NUM_LEDS: usize>(lines: &[MapLine]) -> [Point; NUM_LEDS] {
+		let mut points = [Point::ORIGIN; NUM_LEDS];
+		let mut index = 0;
+		let mut line = 0;
+		while line < lines.len() {
+			let mut led = 0;
+			while led < lines[line].led_count && index < NUM_LEDS {
+				points[index] = lines[line].point_at(led);
+				index += 1;
+				led += 1;
+			}
+			line += 1;
+		}
+		points
+	}
+
+	//the middle of the box around a map, for patterns that go around or out from the middle of the room:
+	pub fn center(map: &[Point]) -> Point {
+		let first = match map.first() {
+			Some(&first) => first,
+			None => return Point::ORIGIN,
+		};
+		let (min, max) = map.iter().fold((first, first), |(min, max), point| (
+			Point::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z)),
+			Point::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z)),
+		));
+		Point::new((min.x + max.x) / 2, (min.y + max.y) / 2, (min.z + max.z) / 2)
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum MapError {
+		//something other than a number was found on this line
+		BadNumber(usize),
+		//this line doesn't have 2 or 3 coordinates
+		WrongFieldCount(usize),
+		//this line is past the number of LEDs in the map
+		TooManyPoints(usize),
+		//the file ended after this many points
+		TooFewPoints(usize),
+	}
+
+	//this reads a mapping file with one LED per line in logical index order, as x,y or x,y,z in
+	//millimeters. Spaces are allowed around the numbers and everything after a # is a comment.
+	//it is a const fn so a map file can be pulled in with include_str!() and checked at compile time.
+	pub const fn parse_map<const NUM_LEDS: usize>(text: &str) -> Result<[Point; NUM_LEDS], MapError> {
+		let bytes = text.as_bytes();
+		let mut points = [Point::ORIGIN; NUM_LEDS];
+		let mut point_count = 0;
+		let mut line = 1;
+		let mut fields = [0_i32; 3];
+		let mut field_count = 0;
+		let mut value = 0_i32;
+		let mut negative = false;
+		let mut in_number = false;
+		let mut in_comment = false;
+		let mut i = 0;
+		while i <= bytes.len() {
+			let byte = if i < bytes.len() { bytes[i] } else { b'\n' };
+			i += 1;
+			if in_comment && byte != b'\n' {
+				continue;
+			}
+			match byte {
+				b'0'..=b'9' => {
+					if value > i32::MAX / 10 - 10 {
+						return Err(MapError::BadNumber(line));
+					}
+					value = value * 10 + (byte - b'0') as i32;
+					in_number = true;
+					continue;
+				}
+				b'-' if !in_number && !negative => {
+					negative = true;
+					continue;
+				}
+				b'#' => in_comment = true,
+				b',' | b' ' | b'\t' | b'\r' | b'\n' => {}
+				_ => return Err(MapError::BadNumber(line)),
+			}
+			//anything that isn't part of a number ends the number before it:
+			if in_number {
+				if field_count == 3 {
+					return Err(MapError::WrongFieldCount(line));
+				}
+				fields[field_count] = if negative { -value } else { value };
+				field_count += 1;
+			} else if negative {
+				return Err(MapError::BadNumber(line));
+			}
+			value = 0;
+			negative = false;
+			in_number = false;
+			if byte == b'\n' {
+				if field_count == 1 {
+					return Err(MapError::WrongFieldCount(line));
+				}
+				if field_count > 1 {
+					if point_count == NUM_LEDS {
+						return Err(MapError::TooManyPoints(line));
+					}
+					points[point_count] = Point::new(fields[0], fields[1], fields[2]);
+					point_count += 1;
+				}
+				fields = [0; 3];
+				field_count = 0;
+				in_comment = false;
+				line += 1;
+			}
+		}
+		if point_count < NUM_LEDS {
+			return Err(MapError::TooFewPoints(point_count));
+		}
+		Ok(points)
+	}
+
+	#[derive(Copy, Clone, PartialEq)]
+	pub enum Pattern {
+		//rings of color moving out from a point
+		RadialWave { center: Point },
+		//bands of color moving across the room in the direction of the angle, 0 is along +x
+		LinearSweep { angle: u8 },
+		//a beam sweeping around a point like a lighthouse, beam_width is the angle to either side it lights up
+		RotatingBeam { center: Point, beam_width: u8 },
+	}
+
+	//an animation that colors LEDs by where they are in the room instead of by their index.
+	pub struct SpatialAnimation {
+		//the position of each logical LED, LEDs past the end of the map are left dark:
+		pub map: &'static [Point],
+		pub pattern: Pattern,
+		pub rainbow: c::Rainbow,
+		//the distance one trip around the rainbow covers:
+		pub wavelength_mm: u32,
+		//how long it takes the pattern to move one wavelength, or for the beam to go around once:
+		pub period_ms: u32,
+	}
+
+	impl SpatialAnimation {
+		pub fn new(map: &'static [Point], pattern: Pattern, rainbow: c::Rainbow, wavelength_mm: u32, period_ms: u32) -> Self {
+			SpatialAnimation {
+				map,
+				pattern,
+				rainbow,
+				wavelength_mm,
+				period_ms,
+			}
+		}
+
+		//how far through the period we are, as a fraction of 65536:
+		fn phase(&self, now_ms: u32) -> u32 {
+			let period_ms = self.period_ms.max(1);
+			((now_ms % period_ms) as u64 * 0x1_0000 / period_ms as u64) as u32
+		}
+
+		//this colors LEDs by a distance, with the pattern moving away from 0 over time:
+		fn wave_color(&self, distance_mm: i32, phase: u32) -> c::Color {
+			let wavelength_mm = self.wavelength_mm.max(1) as i64;
+			let position = (distance_mm as i64 * 0x1_0000 / wavelength_mm) as u32;
+			self.rainbow.color_at_position(position.wrapping_sub(phase) as u16)
+		}
+
+		pub fn color_at(&self, point: Point, now_ms: u32) -> c::Color {
+			let phase = self.phase(now_ms);
+			match self.pattern {
+				Pattern::RadialWave { center } => {
+					let (dx, dy, dz) = ((point.x - center.x) as i64, (point.y - center.y) as i64, (point.z - center.z) as i64);
+					let distance_mm = fx::isqrt((dx * dx + dy * dy + dz * dz) as u64) as i32;
+					self.wave_color(distance_mm, phase)
+				}
+				Pattern::LinearSweep { angle } => {
+					let distance_mm = (point.x * fx::cos_i8(angle) + point.y * fx::sin_i8(angle)) / 127;
+					self.wave_color(distance_mm, phase)
+				}
+				Pattern::RotatingBeam { center, beam_width } => {
+					let beam_angle = (phase >> 8) as u8;
+					let led_angle = fx::atan2_8(point.y - center.y, point.x - center.x);
+					let offset = (led_angle.wrapping_sub(beam_angle) as i8 as i32).abs();
+					let beam_width = beam_width.max(1) as i32;
+					if offset >= beam_width {
+						c::C_OFF
+					} else {
+						let brightness = (255 * (beam_width - offset) / beam_width) as u8;
+						self.rainbow.color_at_position(phase as u16).scaled(brightness)
+					}
+				}
+			}
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			for (i, led) in frame.iter_mut().enumerate() {
+				*led = match self.map.get(i) {
+					Some(&point) => self.color_at(point, now_ms),
+					None => c::C_OFF,
+				};
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		//a 1000 by 500 room with 4 LEDs along each wall, going around from the origin:
+		const LINES: [MapLine; 4] = [
+			MapLine::new(Point::new(0, 0, 0), Point::new(1000, 0, 0), 4),
+			MapLine::new(Point::new(1000, 0, 0), Point::new(1000, 500, 0), 4),
+			MapLine::new(Point::new(1000, 500, 0), Point::new(0, 500, 0), 4),
+			MapLine::new(Point::new(0, 500, 0), Point::new(0, 0, 0), 4),
+		];
+		static MAP: [Point; 16] = map_lines::<16>(&LINES);
+
+		#[test]
+		fn map_lines_turn_the_corners() {
+			//LEDs sit in the middle of their eighth of a wall, so none of them lands on a corner:
+			assert_eq!(MAP[0], Point::new(125, 0, 0));
+			assert_eq!(MAP[3], Point::new(875, 0, 0));
+			assert_eq!(MAP[4], Point::new(1000, 62, 0));
+			assert_eq!(MAP[7], Point::new(1000, 437, 0));
+			assert_eq!(MAP[8], Point::new(875, 500, 0));
+			assert_eq!(MAP[11], Point::new(125, 500, 0));
+			assert_eq!(MAP[12], Point::new(0, 438, 0));
+			assert_eq!(MAP[15], Point::new(0, 63, 0));
+			assert_eq!(center(&MAP), Point::new(500, 250, 0));
+			assert_eq!(center(&[]), Point::ORIGIN);
+		}
+
+		#[test]
+		fn map_lines_fill_what_fits() {
+			//lines past the end of the map are cut off, and LEDs past the end of the lines are left at the origin:
+			assert_eq!(map_lines::<6>(&LINES)[5], Point::new(1000, 187, 0));
+			let short = map_lines::<6>(&LINES[..1]);
+			assert_eq!(short[3], Point::new(875, 0, 0));
+			assert_eq!(short[4], Point::ORIGIN);
+			assert_eq!(map_lines::<2>(&[MapLine::new(Point::new(0, 0, 2400), Point::new(0, 0, 2400), 0), LINES[0]])[0], Point::new(125, 0, 0));
+		}
+
+		#[test]
+		fn patterns_follow_the_map() {
+			let mut ripple = SpatialAnimation::new(&MAP, Pattern::RadialWave { center: center(&MAP) }, c::R_ROYGBIV, 2000, 1000);
+			let mut frame = [c::C_OFF; 18];
+			ripple.update(0, &mut frame);
+			//LEDs the same distance from the middle are the same color, and LEDs past the map are dark:
+			assert_eq!(frame[0], frame[3]);
+			assert_eq!(frame[0], frame[8]);
+			assert!(frame[0] != frame[1]);
+			assert_eq!(frame[16], c::C_OFF);
+			let beam = SpatialAnimation::new(&MAP, Pattern::RotatingBeam { center: center(&MAP), beam_width: 16 }, c::R_ROYGBIV, 2000, 1000);
+			//at the start the beam points along +x, at the middle of the far wall:
+			assert!(beam.color_at(Point::new(1000, 250, 0), 0) != c::C_OFF);
+			assert_eq!(beam.color_at(Point::new(0, 250, 0), 0), c::C_OFF);
+		}
+
+		#[test]
+		fn map_files() {
+			let text = "# the desk, in millimeters\n\n  10, 20\n-30,-40, 2400 # over the door\r\n\t\n5 6 7\n";
+			assert_eq!(parse_map::<3>(text), Ok([Point::new(10, 20, 0), Point::new(-30, -40, 2400), Point::new(5, 6, 7)]));
+			//the last line doesn't need a line break:
+			assert_eq!(parse_map::<1>("1,2"), Ok([Point::new(1, 2, 0)]));
+			assert_eq!(parse_map::<1>("2147483000,-2147483000"), Ok([Point::new(2_147_483_000, -2_147_483_000, 0)]));
+			assert_eq!(parse_map::<0>("# nothing here\n"), Ok([]));
+		}
+
+		#[test]
+		fn bad_map_files() {
+			assert_eq!(parse_map::<2>("1,2\n1,x\n"), Err(MapError::BadNumber(2)));
+			assert_eq!(parse_map::<1>("1.5,2"), Err(MapError::BadNumber(1)));
+			assert_eq!(parse_map::<1>("--1,2"), Err(MapError::BadNumber(1)));
+			assert_eq!(parse_map::<1>("1-2,3"), Err(MapError::BadNumber(1)));
+			assert_eq!(parse_map::<1>("- 1,2"), Err(MapError::BadNumber(1)));
+			assert_eq!(parse_map::<1>("1,-"), Err(MapError::BadNumber(1)));
+			//numbers too big for an i32 millimeter count:
+			assert_eq!(parse_map::<1>("1,9999999999"), Err(MapError::BadNumber(1)));
+			assert_eq!(parse_map::<1>("-9999999999,1"), Err(MapError::BadNumber(1)));
+			assert_eq!(parse_map::<2>("1,2\n\n3\n"), Err(MapError::WrongFieldCount(3)));
+			assert_eq!(parse_map::<1>("1,2,3,4"), Err(MapError::WrongFieldCount(1)));
+			assert_eq!(parse_map::<1>("1,2\n3,4\n"), Err(MapError::TooManyPoints(2)));
+			assert_eq!(parse_map::<3>("1,2\n3,4\n"), Err(MapError::TooFewPoints(2)));
+			assert_eq!(parse_map::<1>(""), Err(MapError::TooFewPoints(0)));
+		}
+	}
+}
@@ -0,0 +1,219 @@
+pub mod storm {
+	use crate::c;
+	use crate::rng;
+	use crate::vw;
+	use core::ops::Range;
+
+	//true once now_ms has reached time_ms, even if the millisecond clock wrapped in between:
+	fn reached(now_ms: u32, time_ms: u32) -> bool {
+		now_ms.wrapping_sub(time_ms) < 0x8000_0000
+	}
+
+	pub struct StormSettings {
+		//the color behind the lightning, use C_OFF for a dark sky:
+		pub background: c::Color,
+		pub flash_color: c::Color,
+		//LEDs the storm can strike, None for the whole frame. A single physical strip can be
+		//picked by passing that strip's range of the logical strip.
+		pub range: Option<Range<usize>>,
+		//when there are any, each strike picks one of these physical strips at random and stays
+		//on it, in place of range:
+		pub strips: vw::StripRanges,
+		//time between strikes is picked randomly from this range:
+		pub min_strike_interval_ms: u32,
+		pub max_strike_interval_ms: u32,
+		pub max_flashes_per_strike: u8,
+		//the length of the piece of strip lit up by each strike:
+		pub min_strike_length: usize,
+		pub max_strike_length: usize,
+	}
+
+	impl Default for StormSettings {
+		fn default() -> Self {
+			StormSettings {
+				background: c::Color { r: 0, g: 0, b: 24 },
+				flash_color: c::C_T_6500K,
+				range: None,
+				strips: vw::StripRanges::new(),
+				min_strike_interval_ms: 2_000,
+				max_strike_interval_ms: 12_000,
+				max_flashes_per_strike: 5,
+				min_strike_length: 8,
+				max_strike_length: 40,
+			}
+		}
+	}
+
+	struct Strike {
+		start: usize,
+		length: usize,
+		flashes_remaining: u8,
+		intensity: u8,
+		flash_on: bool,
+		//when the current flash or the gap after it ends:
+		next_change_ms: u32,
+	}
+
+	pub struct Storm {
+		pub settings: StormSettings,
+		rng: rng::Rng,
+		next_strike_ms: Option<u32>,
+		strike: Option<Strike>,
+	}
+
+	impl Storm {
+		pub fn new(settings: StormSettings, seed: u32) -> Self {
+			Storm {
+				settings,
+				rng: rng::Rng::new(seed),
+				next_strike_ms: None,
+				strike: None,
+			}
+		}
+
+		fn schedule_next_strike(&mut self, now_ms: u32) {
+			let wait_ms = self.rng.range(self.settings.min_strike_interval_ms, self.settings.max_strike_interval_ms);
+			self.next_strike_ms = Some(now_ms.wrapping_add(wait_ms));
+		}
+
+		fn start_strike(&mut self, now_ms: u32, led_count: usize) {
+			let range = match (self.settings.strips.len(), &self.settings.range) {
+				(0, Some(range)) => range.clone(),
+				(0, None) => 0..led_count,
+				(strip_count, _) => {
+					let strip = self.rng.range(0, strip_count as u32 - 1) as usize;
+					self.settings.strips.get(strip).unwrap_or(0..led_count)
+				}
+			};
+			let range = range.start.min(led_count)..range.end.min(led_count);
+			if range.start >= range.end {
+				return;
+			}
+			let available = range.end - range.start;
+			let length = (self.rng.range(
+				self.settings.min_strike_length as u32,
+				self.settings.max_strike_length as u32,
+			) as usize).max(1).min(available);
+			let start = range.start + self.rng.range(0, (available - length) as u32) as usize;
+			self.strike = Some(Strike {
+				start,
+				length,
+				flashes_remaining: self.rng.range(1, self.settings.max_flashes_per_strike.max(1) as u32) as u8,
+				intensity: 0,
+				flash_on: false,
+				next_change_ms: now_ms,
+			});
+		}
+
+		//this steps the current strike through its flashes and the dark gaps between them:
+		fn advance_strike(&mut self, now_ms: u32) {
+			let rng = &mut self.rng;
+			let finished = match &mut self.strike {
+				Some(strike) => {
+					if reached(now_ms, strike.next_change_ms) {
+						if strike.flash_on {
+							strike.flash_on = false;
+							strike.flashes_remaining -= 1;
+							strike.next_change_ms = now_ms.wrapping_add(rng.range(40, 200));
+						} else if strike.flashes_remaining > 0 {
+							strike.flash_on = true;
+							strike.intensity = rng.range(80, 255) as u8;
+							strike.next_change_ms = now_ms.wrapping_add(rng.range(20, 80));
+						}
+					}
+					!strike.flash_on && strike.flashes_remaining == 0
+				}
+				None => false,
+			};
+			if finished {
+				self.strike = None;
+				self.schedule_next_strike(now_ms);
+			}
+		}
+
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			match self.next_strike_ms {
+				None => self.schedule_next_strike(now_ms),
+				Some(next_strike_ms) => {
+					if self.strike.is_none() && reached(now_ms, next_strike_ms) {
+						self.start_strike(now_ms, frame.len());
+						if self.strike.is_none() {
+							self.schedule_next_strike(now_ms);
+						}
+					}
+				}
+			}
+			self.advance_strike(now_ms);
+
+			for led in frame.iter_mut() {
+				*led = self.settings.background;
+			}
+			if let Some(strike) = &self.strike {
+				if strike.flash_on {
+					let flash = self.settings.flash_color.scaled(strike.intensity);
+					for led in frame.iter_mut().skip(strike.start).take(strike.length) {
+						*led = flash;
+					}
+				}
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const STRIPS: [Range<usize>; 3] = [0..10, 10..30, 30..40];
+
+		fn settings() -> StormSettings {
+			StormSettings {
+				min_strike_interval_ms: 100,
+				max_strike_interval_ms: 400,
+				max_strike_length: 60,
+				..StormSettings::default()
+			}
+		}
+
+		//the frames of the first minute, every 10ms:
+		fn frames(settings: StormSettings, seed: u32) -> Vec<[c::Color; 40]> {
+			let mut storm = Storm::new(settings, seed);
+			(0..60_000).step_by(10).map(|now_ms| {
+				let mut frame = [c::C_OFF; 40];
+				storm.update(now_ms, &mut frame);
+				frame
+			}).collect()
+		}
+
+		fn lit(settings: &StormSettings, frame: &[c::Color]) -> Vec<usize> {
+			frame.iter().enumerate().filter(|&(_, &led)| led != settings.background).map(|(i, _)| i).collect()
+		}
+
+		#[test]
+		fn the_same_seed_gives_the_same_storm() {
+			assert!(frames(settings(), 7) == frames(settings(), 7));
+			assert!(frames(settings(), 7) != frames(settings(), 8));
+			assert!(frames(settings(), 7).iter().any(|frame| !lit(&settings(), frame).is_empty()));
+		}
+
+		#[test]
+		fn strikes_stay_on_one_strip() {
+			let mut strips = vw::StripRanges::new();
+			for strip in STRIPS.iter() {
+				assert!(strips.push(strip.clone()));
+			}
+			let per_strip = || StormSettings { strips, ..settings() };
+			let mut struck = [false; 3];
+			for frame in frames(per_strip(), 3) {
+				let lit = lit(&per_strip(), &frame);
+				if let (Some(&first), Some(&last)) = (lit.first(), lit.last()) {
+					let strip = STRIPS.iter().position(|strip| strip.contains(&first)).unwrap();
+					assert!(STRIPS[strip].contains(&last), "{:?} crosses strips", lit);
+					struck[strip] = true;
+				}
+			}
+			assert_eq!(struck, [true; 3]);
+			//without the strips, strikes cross from one strip to the next:
+			assert!(frames(settings(), 3).iter().any(|frame| lit(&settings(), frame).len() > 20));
+		}
+	}
+}
@@ -0,0 +1,301 @@
+pub mod stream {
+	use crate::c;
+
+	//frames can be streamed from a PC over the serial port in either of the two formats ambilight
+	//and desktop lighting tools use. Adalight frames are laid out as:
+	//  3 bytes  magic "Ada"
+	//  2 bytes  number of LEDs minus one, big endian
+	//  1 byte   checksum, the two count bytes xored with each other and 0x55
+	//  3 bytes  red, green, blue for each LED
+	//TPM2 frames are laid out as:
+	//  1 byte   0xC9
+	//  1 byte   packet type, 0xDA for data
+	//  2 bytes  number of data bytes, big endian
+	//  3 bytes  red, green, blue for each LED
+	//  1 byte   0x36
+	const ADALIGHT_MAGIC: [u8; 3] = *b"Ada";
+	const ADALIGHT_CHECKSUM_KEY: u8 = 0x55;
+	const TPM2_START: u8 = 0xC9;
+	const TPM2_DATA: u8 = 0xDA;
+	const TPM2_END: u8 = 0x36;
+
+	//a frame that stops coming in for this long is given up on, so a tool that goes away in the
+	//middle of a frame doesn't leave the rest of the port's traffic stuck in it:
+	pub const BYTE_TIMEOUT_MS: u32 = 100;
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Protocol {
+		Adalight,
+		Tpm2,
+	}
+
+	//what a byte turned out to be part of:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum Feed {
+		//not part of a frame, so it is for the command line. Header bytes are passed along too,
+		//since they can't be told apart from typing until the whole header has come in.
+		Pass,
+		//a frame header has just finished, so whatever was passed along since it began wasn't typing
+		Started(Protocol),
+		//part of a frame
+		Data,
+		//the last byte of a frame, with how many LEDs it set
+		Frame(usize),
+		//the frame didn't end properly, so it has been thrown away
+		Dropped,
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	enum State {
+		Idle,
+		//this many bytes of the Adalight magic have matched
+		AdalightMagic(usize),
+		AdalightCountHigh,
+		AdalightCountLow(u8),
+		AdalightChecksum(u8, u8),
+		Tpm2Type,
+		Tpm2LengthHigh,
+		Tpm2LengthLow(u8),
+		//the frame's LED data, and how many bytes of it have come in
+		Data { protocol: Protocol, length: usize, received: usize },
+		Tpm2End { length: usize },
+	}
+
+	//this picks frames out of the bytes coming in over the serial port, a byte at a time so it
+	//doesn't matter how the bytes are split up between reads. After anything it doesn't
+	//understand, it goes back to looking for the start of a frame.
+	pub struct FrameParser {
+		state: State,
+		last_byte_ms: u32,
+	}
+
+	impl Default for FrameParser {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	impl FrameParser {
+		pub fn new() -> Self {
+			FrameParser {
+				state: State::Idle,
+				last_byte_ms: 0,
+			}
+		}
+
+		//this takes the next byte from the port, which came in at now_ms. LED data is written
+		//straight into pixels as it arrives. LEDs past the end of pixels are read and ignored.
+		pub fn push(&mut self, byte: u8, now_ms: u32, pixels: &mut [c::Color]) -> Feed {
+			if now_ms.wrapping_sub(self.last_byte_ms) > BYTE_TIMEOUT_MS {
+				self.state = State::Idle;
+			}
+			self.last_byte_ms = now_ms;
+			let (state, feed) = match self.state {
+				State::Idle => idle(byte),
+				State::AdalightMagic(matched) if byte == ADALIGHT_MAGIC[matched] => match matched + 1 {
+					3 => (State::AdalightCountHigh, Feed::Pass),
+					matched => (State::AdalightMagic(matched), Feed::Pass),
+				},
+				State::AdalightCountHigh => (State::AdalightCountLow(byte), Feed::Pass),
+				State::AdalightCountLow(high) => (State::AdalightChecksum(high, byte), Feed::Pass),
+				State::AdalightChecksum(high, low) if byte == high ^ low ^ ADALIGHT_CHECKSUM_KEY => {
+					let length = (u16::from_be_bytes([high, low]) as usize + 1) * 3;
+					(State::Data { protocol: Protocol::Adalight, length, received: 0 }, Feed::Started(Protocol::Adalight))
+				}
+				State::Tpm2Type if byte == TPM2_DATA => (State::Tpm2LengthHigh, Feed::Pass),
+				State::Tpm2LengthHigh => (State::Tpm2LengthLow(byte), Feed::Pass),
+				State::Tpm2LengthLow(high) => {
+					let length = u16::from_be_bytes([high, byte]) as usize;
+					let state = match length {
+						0 => State::Tpm2End { length },
+						_ => State::Data { protocol: Protocol::Tpm2, length, received: 0 },
+					};
+					(state, Feed::Started(Protocol::Tpm2))
+				}
+				State::Data { protocol, length, received } => {
+					if let Some(pixel) = pixels.get_mut(received / 3) {
+						match received % 3 {
+							0 => pixel.r = byte,
+							1 => pixel.g = byte,
+							_ => pixel.b = byte,
+						}
+					}
+					let received = received + 1;
+					match (received == length, protocol) {
+						(false, _) => (State::Data { protocol, length, received }, Feed::Data),
+						(true, Protocol::Adalight) => (State::Idle, Feed::Frame(pixel_count(length, pixels))),
+						(true, Protocol::Tpm2) => (State::Tpm2End { length }, Feed::Data),
+					}
+				}
+				State::Tpm2End { length } if byte == TPM2_END => (State::Idle, Feed::Frame(pixel_count(length, pixels))),
+				//a byte that doesn't fit in the end of a frame might be the start of the next one:
+				State::Tpm2End { .. } => (idle(byte).0, Feed::Dropped),
+				//a header that stops matching is given up on, and the byte looked at afresh:
+				_ => idle(byte),
+			};
+			self.state = state;
+			feed
+		}
+	}
+
+	//this looks for the first byte of a frame:
+	fn idle(byte: u8) -> (State, Feed) {
+		match byte {
+			_ if byte == ADALIGHT_MAGIC[0] => (State::AdalightMagic(1), Feed::Pass),
+			TPM2_START => (State::Tpm2Type, Feed::Pass),
+			_ => (State::Idle, Feed::Pass),
+		}
+	}
+
+	fn pixel_count(length: usize, pixels: &[c::Color]) -> usize {
+		(length / 3).min(pixels.len())
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use crate::rng;
+
+		const PIXEL_COUNT: usize = 20;
+
+		fn frame(protocol: Protocol, colors: &[c::Color]) -> Vec<u8> {
+			let data: Vec<u8> = colors.iter().flat_map(|color| vec![color.r, color.g, color.b]).collect();
+			let mut frame = Vec::new();
+			match protocol {
+				Protocol::Adalight => {
+					let [high, low] = (colors.len() as u16 - 1).to_be_bytes();
+					frame.extend(&ADALIGHT_MAGIC);
+					frame.extend(&[high, low, high ^ low ^ ADALIGHT_CHECKSUM_KEY]);
+					frame.extend(data);
+				}
+				Protocol::Tpm2 => {
+					frame.extend(&[TPM2_START, TPM2_DATA]);
+					frame.extend(&(data.len() as u16).to_be_bytes());
+					frame.extend(data);
+					frame.push(TPM2_END);
+				}
+			}
+			frame
+		}
+
+		//this feeds bytes to a parser the way the port would, a few at a time with random waits
+		//between them that are all shorter than the timeout:
+		struct Port {
+			parser: FrameParser,
+			rng: rng::Rng,
+			now_ms: u32,
+			pixels: [c::Color; PIXEL_COUNT],
+			frames: Vec<Vec<c::Color>>,
+			dropped: usize,
+		}
+
+		impl Port {
+			fn new(seed: u32) -> Self {
+				Port {
+					parser: FrameParser::new(),
+					rng: rng::Rng::new(seed),
+					//the millisecond clock wraps partway through:
+					now_ms: u32::MAX - 20_000,
+					pixels: [c::C_OFF; PIXEL_COUNT],
+					frames: Vec::new(),
+					dropped: 0,
+				}
+			}
+
+			fn send(&mut self, bytes: &[u8]) -> Vec<Feed> {
+				let mut feeds = Vec::new();
+				for &byte in bytes {
+					if self.rng.chance(125) {
+						self.now_ms = self.now_ms.wrapping_add(self.rng.range(0, BYTE_TIMEOUT_MS));
+					}
+					let feed = self.parser.push(byte, self.now_ms, &mut self.pixels);
+					match feed {
+						Feed::Frame(led_count) => self.frames.push(self.pixels[..led_count].to_vec()),
+						Feed::Dropped => self.dropped += 1,
+						_ => {}
+					}
+					feeds.push(feed);
+				}
+				feeds
+			}
+
+			fn random_bytes(&mut self, length: usize, allowed: impl Fn(u8) -> bool) -> Vec<u8> {
+				let mut bytes = Vec::new();
+				while bytes.len() < length {
+					let byte = self.rng.range(0, 255) as u8;
+					if allowed(byte) {
+						bytes.push(byte);
+					}
+				}
+				bytes
+			}
+		}
+
+		#[test]
+		fn frames() {
+			let colors = [c::Color { r: 1, g: 2, b: 3 }, c::Color { r: 4, g: 5, b: 6 }];
+			for &protocol in [Protocol::Adalight, Protocol::Tpm2].iter() {
+				let mut port = Port::new(1);
+				let bytes = frame(protocol, &colors);
+				let feeds = port.send(&bytes);
+				let header_length = bytes.len() - 6 - if protocol == Protocol::Tpm2 { 1 } else { 0 };
+				assert!(feeds[..header_length - 1].iter().all(|&feed| feed == Feed::Pass));
+				assert_eq!(feeds[header_length - 1], Feed::Started(protocol));
+				assert_eq!(feeds.last(), Some(&Feed::Frame(2)));
+				assert_eq!(port.frames, [colors.to_vec()]);
+			}
+			//typing that happens to start like a header goes to the command line:
+			let mut port = Port::new(1);
+			assert!(port.send(b"Adam\r").iter().all(|&feed| feed == Feed::Pass));
+			//a frame with more LEDs than there are pixels sets the ones there are:
+			let long: Vec<c::Color> = (0..PIXEL_COUNT as u8 + 5).map(|i| c::Color { r: i, g: i, b: i }).collect();
+			assert_eq!(port.send(&frame(Protocol::Tpm2, &long)).last(), Some(&Feed::Frame(PIXEL_COUNT)));
+			assert_eq!(port.frames, [long[..PIXEL_COUNT].to_vec()]);
+		}
+
+		//random frames in both formats, with typing between them and frames that are cut off or
+		//end badly, all split up at random:
+		#[test]
+		fn fuzz() {
+			for seed in 1..=20 {
+				let mut port = Port::new(seed);
+				let mut expected_frames = Vec::new();
+				let mut expected_dropped = 0;
+				for _ in 0..200 {
+					//bytes between frames never complete a header on their own, but can start one:
+					let length = port.rng.range(0, 12) as usize;
+					let typing = port.random_bytes(length, |byte| byte != b'a' && byte != TPM2_DATA);
+					assert!(port.send(&typing).iter().all(|&feed| feed == Feed::Pass), "seed {}", seed);
+
+					let protocol = if port.rng.chance(500) { Protocol::Adalight } else { Protocol::Tpm2 };
+					let led_count = port.rng.range(1, PIXEL_COUNT as u32 + 4) as usize;
+					let colors: Vec<c::Color> = port.random_bytes(3 * led_count, |_| true)
+						.chunks(3)
+						.map(|rgb| c::Color { r: rgb[0], g: rgb[1], b: rgb[2] })
+						.collect();
+					let mut bytes = frame(protocol, &colors);
+					match port.rng.range(0, 9) {
+						//cut off, and then the tool goes quiet long enough to give up on it:
+						0 => {
+							let length = port.rng.range(1, bytes.len() as u32 - 1) as usize;
+							port.send(&bytes[..length]);
+							port.now_ms = port.now_ms.wrapping_add(BYTE_TIMEOUT_MS + 1 + port.rng.range(0, 1_000));
+						}
+						//a TPM2 frame with the wrong last byte, which can't start a new frame:
+						1 if protocol == Protocol::Tpm2 => {
+							*bytes.last_mut().unwrap() = port.random_bytes(1, |byte| byte != TPM2_END && byte != ADALIGHT_MAGIC[0] && byte != TPM2_START)[0];
+							port.send(&bytes);
+							expected_dropped += 1;
+						}
+						_ => {
+							port.send(&bytes);
+							expected_frames.push(colors[..led_count.min(PIXEL_COUNT)].to_vec());
+						}
+					}
+				}
+				assert_eq!(port.frames, expected_frames, "seed {}", seed);
+				assert_eq!(port.dropped, expected_dropped, "seed {}", seed);
+			}
+		}
+	}
+}
@@ -0,0 +1,189 @@
+pub mod sunrise {
+	use crate::c;
+
+	//progress through the curve is tracked as a fixed point value from 0 to this:
+	pub const PROGRESS_MAX: i32 = 10_000;
+
+	//the default length of a sunrise or sunset, 30 minutes:
+	pub const DEFAULT_DURATION_MS: u32 = 30 * 60 * 1000;
+
+	//the curve passes through these (progress, color) points before it starts walking up
+	//the kelvin whites. The early colors are dim so the room starts out nearly dark.
+	const DAWN_WAYPOINTS: [(i32, c::Color); 5] = [
+		(0, c::C_OFF),
+		(1_500, c::Color { r: 96, g: 0, b: 0 }),
+		(3_500, c::Color { r: 200, g: 48, b: 0 }),
+		(5_500, c::C_T_2000K),
+		(DAWN_END_PROGRESS, c::C_T_3000K),
+	];
+
+	//after this point the curve interpolates color temperature from DAWN_END_KELVIN to the target:
+	const DAWN_END_PROGRESS: i32 = 7_500;
+	const DAWN_END_KELVIN: u16 = 3000;
+
+	#[derive(Copy, Clone, PartialEq)]
+	pub enum Direction {
+		//off to target color temperature
+		Sunrise,
+		//target color temperature to off
+		Sunset,
+	}
+
+	pub struct Sunrise {
+		pub direction: Direction,
+		pub duration_ms: u32,
+		pub target_kelvin: u16,
+		start_ms: Option<u32>,
+	}
+
+	impl Sunrise {
+		pub fn new(direction: Direction, duration_ms: u32, target_kelvin: u16) -> Self {
+			Sunrise {
+				direction,
+				duration_ms,
+				target_kelvin,
+				start_ms: None,
+			}
+		}
+
+		//the curve starts over from the beginning on the next update:
+		pub fn restart(&mut self) {
+			self.start_ms = None;
+		}
+
+		//this gets the color of the sunrise curve at a progress between 0 and PROGRESS_MAX.
+		//the curve is the same for both directions, sunsets just walk it backwards.
+		pub fn color_at(&self, progress: i32) -> c::Color {
+			let progress = progress.clamp(0, PROGRESS_MAX);
+			if progress >= DAWN_END_PROGRESS {
+				let kelvin = Self::lerp_i32(
+					progress,
+					DAWN_END_PROGRESS,
+					PROGRESS_MAX,
+					DAWN_END_KELVIN as i32,
+					self.target_kelvin as i32,
+				);
+				return c::kelvin_to_color(kelvin as u16);
+			}
+			for pair in DAWN_WAYPOINTS.windows(2) {
+				let (start_progress, start_color) = pair[0];
+				let (end_progress, end_color) = pair[1];
+				if progress <= end_progress {
+					return c::Color::color_lerp(progress, start_progress, end_progress, start_color, end_color);
+				}
+			}
+			c::C_T_3000K
+		}
+
+		//this gets the progress through the curve in the direction it is running:
+		pub fn progress(&self, now_ms: u32) -> i32 {
+			let elapsed_ms = match self.start_ms {
+				Some(start_ms) => now_ms.wrapping_sub(start_ms),
+				None => 0,
+			};
+			let progress = if self.duration_ms == 0 || elapsed_ms >= self.duration_ms {
+				PROGRESS_MAX
+			} else {
+				(elapsed_ms as u64 * PROGRESS_MAX as u64 / self.duration_ms as u64) as i32
+			};
+			match self.direction {
+				Direction::Sunrise => progress,
+				Direction::Sunset => PROGRESS_MAX - progress,
+			}
+		}
+
+		pub fn is_finished(&self, now_ms: u32) -> bool {
+			match self.start_ms {
+				Some(start_ms) => now_ms.wrapping_sub(start_ms) >= self.duration_ms,
+				None => false,
+			}
+		}
+
+		//fills the frame with the current color of the curve. The first update starts the clock.
+		pub fn update(&mut self, now_ms: u32, frame: &mut [c::Color]) {
+			if self.start_ms.is_none() {
+				self.start_ms = Some(now_ms);
+			}
+			let color = self.color_at(self.progress(now_ms));
+			for led in frame.iter_mut() {
+				*led = color;
+			}
+		}
+
+		fn lerp_i32(factor: i32, in_min: i32, in_max: i32, out_min: i32, out_max: i32) -> i32 {
+			(factor - in_min) * (out_max - out_min) / (in_max - in_min) + out_min
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const DEEP_RED: c::Color = c::Color { r: 96, g: 0, b: 0 };
+		const ORANGE: c::Color = c::Color { r: 200, g: 48, b: 0 };
+
+		//a sunrise that takes 10 seconds, so a millisecond is one step of progress, started at start_ms:
+		fn colors_at(direction: Direction, start_ms: u32, times_ms: &[u32]) -> Vec<c::Color> {
+			let mut sunrise = Sunrise::new(direction, PROGRESS_MAX as u32, 5000);
+			let mut frame = [c::C_OFF; 3];
+			times_ms
+				.iter()
+				.map(|&time_ms| {
+					sunrise.update(start_ms.wrapping_add(time_ms), &mut frame);
+					assert!(frame.iter().all(|&led| led == frame[0]));
+					frame[0]
+				})
+				.collect()
+		}
+
+		const WAYPOINT_TIMES_MS: [u32; 7] = [0, 1_500, 3_500, 5_500, 7_500, 10_000, 60_000];
+
+		#[test]
+		fn sunrise_passes_through_the_waypoints() {
+			let target = c::kelvin_to_color(5000);
+			assert_eq!(
+				colors_at(Direction::Sunrise, 1000, &WAYPOINT_TIMES_MS),
+				[c::C_OFF, DEEP_RED, ORANGE, c::C_T_2000K, c::kelvin_to_color(3000), target, target]
+			);
+		}
+
+		#[test]
+		fn sunset_walks_them_backwards() {
+			assert_eq!(
+				colors_at(Direction::Sunset, 1000, &[0, 2_500, 4_500, 6_500, 8_500, 10_000, 60_000]),
+				[c::kelvin_to_color(5000), c::kelvin_to_color(3000), c::C_T_2000K, ORANGE, DEEP_RED, c::C_OFF, c::C_OFF]
+			);
+		}
+
+		#[test]
+		fn sunrise_gets_brighter() {
+			let times_ms: Vec<u32> = (0..=DAWN_END_PROGRESS as u32).step_by(100).collect();
+			let colors = colors_at(Direction::Sunrise, 0, &times_ms);
+			for pair in colors.windows(2) {
+				let brightness = |color: c::Color| color.r as u32 + color.g as u32 + color.b as u32;
+				assert!(brightness(pair[0]) <= brightness(pair[1]), "{:?}", pair);
+			}
+			//half way between two waypoints is half way between their colors:
+			let sunrise = Sunrise::new(Direction::Sunrise, DEFAULT_DURATION_MS, 5000);
+			assert_eq!(sunrise.color_at(2_500), c::Color { r: 148, g: 24, b: 0 });
+		}
+
+		#[test]
+		fn finishing() {
+			let mut sunrise = Sunrise::new(Direction::Sunrise, 10_000, 4000);
+			let mut frame = [c::C_OFF; 1];
+			assert!(!sunrise.is_finished(0));
+			//across the frame clock wrapping:
+			sunrise.update(u32::MAX - 4_999, &mut frame);
+			assert!(!sunrise.is_finished(4_999));
+			assert!(sunrise.is_finished(5_000));
+			assert_eq!(sunrise.progress(5_000), PROGRESS_MAX);
+			sunrise.restart();
+			assert_eq!(sunrise.progress(5_000), 0);
+			let instant = Sunrise::new(Direction::Sunset, 0, 4000);
+			assert_eq!(instant.progress(0), 0);
+			assert_eq!(instant.color_at(-5), c::C_OFF);
+			assert_eq!(instant.color_at(PROGRESS_MAX + 5), c::kelvin_to_color(4000));
+		}
+	}
+}
@@ -0,0 +1,154 @@
+pub mod uart {
+	use core::fmt;
+
+	//UART0 is the USB serial port on most boards. The ROM bootloader has already set it up at
+	//115200 baud, so it only needs its FIFOs read and written.
+	const UART0_FIFO: usize = 0x3FF4_0000;
+	//reading the receive FIFO through the DPORT address can drop bytes, so reads go through the AHB address:
+	const UART0_FIFO_AHB: usize = 0x6000_0000;
+	const UART0_STATUS: usize = 0x3FF4_001C;
+	//the number of bytes waiting in the receive FIFO is in the low byte of the status register,
+	//and the number waiting to be sent is in the third byte:
+	const RX_COUNT_MASK: u32 = 0xFF;
+	const TX_COUNT_SHIFT: u32 = 16;
+	const TX_COUNT_MASK: u32 = 0xFF;
+	const FIFO_LENGTH: u32 = 128;
+	//the receive FIFO fills in about 11ms at 115200 baud, less than a frame takes, so bytes are
+	//moved out into an RxBuffer while frames are drawn and waited for. This holds a little over
+	//a frame's worth:
+	pub const RX_BUFFER_LENGTH: usize = 512;
+
+	pub struct Uart0;
+
+	impl Uart0 {
+		fn status() -> u32 {
+			unsafe { core::ptr::read_volatile(UART0_STATUS as *const u32) }
+		}
+
+		//this gets the next byte that came in, if there is one. The receive FIFO only holds 128
+		//bytes, so this needs to be called often enough to keep up.
+		pub fn read_byte(&mut self) -> Option<u8> {
+			match Self::status() & RX_COUNT_MASK {
+				0 => None,
+				_ => Some(unsafe { core::ptr::read_volatile(UART0_FIFO_AHB as *const u32) } as u8),
+			}
+		}
+
+		//this moves everything waiting in the receive FIFO into buffer, leaving it in the FIFO
+		//if buffer fills up:
+		pub fn poll(&mut self, buffer: &mut RxBuffer) {
+			while !buffer.is_full() {
+				match self.read_byte() {
+					Some(byte) => buffer.push(byte),
+					None => break,
+				};
+			}
+		}
+
+		//this waits for room in the transmit FIFO, then queues the byte:
+		pub fn write_byte(&mut self, byte: u8) {
+			while (Self::status() >> TX_COUNT_SHIFT) & TX_COUNT_MASK >= FIFO_LENGTH - 1 {}
+			unsafe { core::ptr::write_volatile(UART0_FIFO as *mut u32, byte as u32) };
+		}
+	}
+
+	//a ring buffer of bytes that came in, oldest first:
+	pub struct RxBuffer {
+		bytes: [u8; RX_BUFFER_LENGTH],
+		start: usize,
+		length: usize,
+	}
+
+	impl RxBuffer {
+		pub const fn new() -> Self {
+			RxBuffer {
+				bytes: [0; RX_BUFFER_LENGTH],
+				start: 0,
+				length: 0,
+			}
+		}
+
+		pub fn len(&self) -> usize {
+			self.length
+		}
+
+		pub fn is_empty(&self) -> bool {
+			self.length == 0
+		}
+
+		pub fn is_full(&self) -> bool {
+			self.length == RX_BUFFER_LENGTH
+		}
+
+		//this adds a byte to the end, returning false if there isn't room for it:
+		pub fn push(&mut self, byte: u8) -> bool {
+			if self.is_full() {
+				return false;
+			}
+			self.bytes[(self.start + self.length) % RX_BUFFER_LENGTH] = byte;
+			self.length += 1;
+			true
+		}
+
+		//this takes the oldest byte:
+		pub fn pop(&mut self) -> Option<u8> {
+			if self.is_empty() {
+				return None;
+			}
+			let byte = self.bytes[self.start];
+			self.start = (self.start + 1) % RX_BUFFER_LENGTH;
+			self.length -= 1;
+			Some(byte)
+		}
+	}
+
+	impl Default for RxBuffer {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	//terminals want \r\n line endings, so \n gets a \r put in front of it:
+	impl fmt::Write for Uart0 {
+		fn write_str(&mut self, text: &str) -> fmt::Result {
+			for byte in text.bytes() {
+				if byte == b'\n' {
+					self.write_byte(b'\r');
+				}
+				self.write_byte(byte);
+			}
+			Ok(())
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		#[test]
+		fn rx_buffer() {
+			let mut buffer = RxBuffer::new();
+			assert_eq!(buffer.pop(), None);
+			//going round the end of the buffer a few times keeps the bytes in order:
+			for round in 0..3 {
+				for i in 0..RX_BUFFER_LENGTH - 100 {
+					assert!(buffer.push((i + round) as u8));
+				}
+				for i in 0..RX_BUFFER_LENGTH - 100 {
+					assert_eq!(buffer.pop(), Some((i + round) as u8));
+				}
+				assert!(buffer.is_empty());
+			}
+			//once full, bytes are turned away rather than written over the oldest:
+			for i in 0..RX_BUFFER_LENGTH {
+				assert!(buffer.push(i as u8));
+			}
+			assert!(buffer.is_full());
+			assert!(!buffer.push(0xFF));
+			assert_eq!(buffer.len(), RX_BUFFER_LENGTH);
+			assert_eq!(buffer.pop(), Some(0));
+			assert!(buffer.push(0xFF));
+			assert_eq!((0..RX_BUFFER_LENGTH).map(|_| buffer.pop().unwrap()).last(), Some(0xFF));
+		}
+	}
+}
@@ -0,0 +1,315 @@
+pub mod views {
+	use crate::c;
+
+	//the most pieces of physical strip one logical strip can be made from:
+	pub const MAX_SLICES: usize = 8;
+
+	//a run of LEDs on one physical strip. start is counted the same way as a whole strip in
+	//a logical strip, so the physical strip's own reversed flag is still taken care of when sending.
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct StripSlice {
+		//the index of the physical strip in the strip array the logical strip was made with:
+		pub strip_index: usize,
+		pub start: usize,
+		pub led_count: usize,
+		//run through this slice backwards:
+		pub reversed: bool,
+	}
+
+	impl StripSlice {
+		pub const fn new(strip_index: usize, start: usize, led_count: usize, reversed: bool) -> Self {
+			StripSlice {
+				strip_index,
+				start,
+				led_count,
+				reversed,
+			}
+		}
+	}
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum ViewError {
+		TooManySlices,
+		//the slice at this index refers to a physical strip that doesn't exist
+		UnknownStrip(usize),
+		//the slice at this index runs past the end of its physical strip
+		SliceOutOfRange(usize),
+		//all the slices together have more LEDs than the logical strip can hold
+		TooManyLeds,
+	}
+
+	//the map from a logical strip's LED indexes to LEDs on the physical strips.
+	//the slices are laid end to end in order.
+	#[derive(Copy, Clone)]
+	pub struct ViewMap {
+		slices: [Option<StripSlice>; MAX_SLICES],
+		led_count: usize,
+	}
+
+	//this gets where a physical strip starts in a buffer of all the physical strips end to end:
+	fn strip_offset(strip_lengths: &[usize], strip_index: usize) -> usize {
+		strip_lengths[..strip_index].iter().sum()
+	}
+
+	impl ViewMap {
+		//strip_lengths are the LED counts of the physical strips, and max_leds is the most
+		//LEDs the logical strip can hold.
+		pub fn new(slices: &[StripSlice], strip_lengths: &[usize], max_leds: usize) -> Result<Self, ViewError> {
+			if slices.len() > MAX_SLICES {
+				return Err(ViewError::TooManySlices);
+			}
+			let mut map = ViewMap {
+				slices: [None; MAX_SLICES],
+				led_count: 0,
+			};
+			for (i, slice) in slices.iter().enumerate() {
+				let strip_length = match strip_lengths.get(slice.strip_index) {
+					Some(&strip_length) => strip_length,
+					None => return Err(ViewError::UnknownStrip(i)),
+				};
+				if slice.start + slice.led_count > strip_length {
+					return Err(ViewError::SliceOutOfRange(i));
+				}
+				map.slices[i] = Some(*slice);
+				map.led_count += slice.led_count;
+			}
+			if map.led_count > max_leds {
+				return Err(ViewError::TooManyLeds);
+			}
+			Ok(map)
+		}
+
+		//a map that covers every physical strip from start to end, in order:
+		pub fn whole_strips(strip_lengths: &[usize], max_leds: usize) -> Result<Self, ViewError> {
+			let mut slices = [StripSlice::new(0, 0, 0, false); MAX_SLICES];
+			if strip_lengths.len() > MAX_SLICES {
+				return Err(ViewError::TooManySlices);
+			}
+			for (i, &strip_length) in strip_lengths.iter().enumerate() {
+				slices[i] = StripSlice::new(i, 0, strip_length, false);
+			}
+			ViewMap::new(&slices[..strip_lengths.len()], strip_lengths, max_leds)
+		}
+
+		pub fn led_count(&self) -> usize {
+			self.led_count
+		}
+
+		pub fn slices(&self) -> impl Iterator<Item = &StripSlice> {
+			self.slices.iter().filter_map(|slot| slot.as_ref())
+		}
+
+		//this gets the range of logical indexes covered by the first slice on a physical strip:
+		pub fn strip_range(&self, strip_index: usize) -> Option<core::ops::Range<usize>> {
+			let mut start = 0;
+			for slice in self.slices() {
+				if slice.strip_index == strip_index {
+					return Some(start..start + slice.led_count);
+				}
+				start += slice.led_count;
+			}
+			None
+		}
+
+		//this calls draw with the index into a buffer of all the physical strips end to end for
+		//each LED of the logical strip, in logical order.
+		pub fn for_each_physical_index<F: FnMut(usize)>(&self, strip_lengths: &[usize], mut draw: F) {
+			for slice in self.slices() {
+				let offset = strip_offset(strip_lengths, slice.strip_index) + slice.start;
+				for i in 0..slice.led_count {
+					match slice.reversed {
+						true => draw(offset + slice.led_count - 1 - i),
+						false => draw(offset + i),
+					}
+				}
+			}
+		}
+	}
+
+	//where each physical strip is in a logical strip, for effects that stay on one strip at a time.
+	//ranges are in logical LED indexes.
+	#[derive(Copy, Clone, PartialEq, Debug, Default)]
+	pub struct StripRanges {
+		ranges: [(usize, usize); MAX_SLICES],
+		count: usize,
+	}
+
+	impl StripRanges {
+		pub const fn new() -> Self {
+			StripRanges {
+				ranges: [(0, 0); MAX_SLICES],
+				count: 0,
+			}
+		}
+
+		//adds a range to the end, returning false if there are already MAX_SLICES of them:
+		pub fn push(&mut self, range: core::ops::Range<usize>) -> bool {
+			match self.ranges.get_mut(self.count) {
+				Some(slot) => {
+					*slot = (range.start, range.end);
+					self.count += 1;
+					true
+				}
+				None => false,
+			}
+		}
+
+		pub fn len(&self) -> usize {
+			self.count
+		}
+
+		pub fn is_empty(&self) -> bool {
+			self.count == 0
+		}
+
+		pub fn get(&self, index: usize) -> Option<core::ops::Range<usize>> {
+			self.ranges[..self.count].get(index).map(|&(start, end)| start..end)
+		}
+	}
+
+	//the colors for all the physical strips end to end, put together from any number of logical
+	//strips. Where logical strips share LEDs, the highest priority wins, and on a tie the one
+	//drawn last wins. LEDs no logical strip covers stay off.
+	pub struct PhysicalFrame<const NUM_LEDS: usize> {
+		pub colors: [c::Color; NUM_LEDS],
+		priorities: [Option<u8>; NUM_LEDS],
+	}
+
+	impl<const NUM_LEDS: usize> Default for PhysicalFrame<NUM_LEDS> {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+
+	impl<const NUM_LEDS: usize> PhysicalFrame<NUM_LEDS> {
+		pub fn new() -> Self {
+			PhysicalFrame {
+				colors: [c::C_OFF; NUM_LEDS],
+				priorities: [None; NUM_LEDS],
+			}
+		}
+
+		pub fn clear(&mut self) {
+			self.colors = [c::C_OFF; NUM_LEDS];
+			self.priorities = [None; NUM_LEDS];
+		}
+
+		//this draws a logical strip's colors onto the physical LEDs it maps to:
+		pub fn draw_view(&mut self, map: &ViewMap, colors: &[c::Color], priority: u8, strip_lengths: &[usize]) {
+			let mut colors = colors.iter();
+			let frame_colors = &mut self.colors;
+			let frame_priorities = &mut self.priorities;
+			map.for_each_physical_index(strip_lengths, |physical_index| {
+				let color = match colors.next() {
+					Some(&color) => color,
+					None => return,
+				};
+				if physical_index >= NUM_LEDS {
+					return;
+				}
+				let wins = match frame_priorities[physical_index] {
+					Some(existing) => priority >= existing,
+					None => true,
+				};
+				if wins {
+					frame_colors[physical_index] = color;
+					frame_priorities[physical_index] = Some(priority);
+				}
+			});
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const STRIP_LENGTHS: [usize; 2] = [5, 4];
+		const RED: c::Color = c::Color { r: 200, g: 0, b: 0 };
+		const BLUE: c::Color = c::Color { r: 0, g: 0, b: 200 };
+
+		fn physical_indexes(map: &ViewMap) -> Vec<usize> {
+			let mut indexes = Vec::new();
+			map.for_each_physical_index(&STRIP_LENGTHS, |index| indexes.push(index));
+			indexes
+		}
+
+		//a map over part of the physical strips, as one logical strip:
+		fn view(slices: &[StripSlice]) -> ViewMap {
+			ViewMap::new(slices, &STRIP_LENGTHS, 9).unwrap()
+		}
+
+		#[test]
+		fn slices_map_in_order() {
+			let map = view(&[StripSlice::new(1, 1, 2, false), StripSlice::new(0, 1, 3, false)]);
+			assert_eq!(map.led_count(), 5);
+			assert_eq!(physical_indexes(&map), [6, 7, 1, 2, 3]);
+			assert_eq!(map.strip_range(0), Some(2..5));
+			assert_eq!(map.strip_range(1), Some(0..2));
+			let whole = ViewMap::whole_strips(&STRIP_LENGTHS, 9).unwrap();
+			assert_eq!(physical_indexes(&whole), (0..9).collect::<Vec<_>>());
+		}
+
+		#[test]
+		fn reversed_slices() {
+			let map = view(&[StripSlice::new(0, 1, 3, true), StripSlice::new(1, 0, 4, true)]);
+			assert_eq!(physical_indexes(&map), [3, 2, 1, 8, 7, 6, 5]);
+			//reversing is within the slice, so it still covers the same LEDs:
+			assert_eq!(map.strip_range(1), Some(3..7));
+		}
+
+		#[test]
+		fn bad_slices() {
+			let new = |slices: &[StripSlice], max_leds| ViewMap::new(slices, &STRIP_LENGTHS, max_leds).err();
+			let fine = StripSlice::new(0, 0, 5, false);
+			assert_eq!(new(&[fine, StripSlice::new(2, 0, 1, false)], 9), Some(ViewError::UnknownStrip(1)));
+			assert_eq!(new(&[fine, StripSlice::new(1, 2, 3, false)], 9), Some(ViewError::SliceOutOfRange(1)));
+			assert_eq!(new(&[fine, StripSlice::new(1, 0, 4, false)], 8), Some(ViewError::TooManyLeds));
+			assert_eq!(new(&[StripSlice::new(1, 0, 1, false); MAX_SLICES + 1], 9), Some(ViewError::TooManySlices));
+			assert_eq!(ViewMap::whole_strips(&[1; MAX_SLICES + 1], 100).err(), Some(ViewError::TooManySlices));
+			assert_eq!(new(&[StripSlice::new(1, 4, 0, false)], 9), None);
+		}
+
+		#[test]
+		fn higher_priorities_win_where_views_overlap() {
+			let mut frame = PhysicalFrame::<10>::default();
+			let low = view(&[StripSlice::new(0, 0, 5, false), StripSlice::new(1, 0, 1, false)]);
+			let high = view(&[StripSlice::new(0, 3, 2, true)]);
+			frame.draw_view(&high, &[BLUE; 2], 2, &STRIP_LENGTHS);
+			frame.draw_view(&low, &[RED; 6], 1, &STRIP_LENGTHS);
+			assert_eq!(frame.colors[..7], [RED, RED, RED, BLUE, BLUE, RED, c::C_OFF]);
+			//on a tie, the view drawn last wins:
+			frame.draw_view(&low, &[BLUE; 6], 2, &STRIP_LENGTHS);
+			assert_eq!(frame.colors[..7], [BLUE, BLUE, BLUE, BLUE, BLUE, BLUE, c::C_OFF]);
+			frame.draw_view(&high, &[RED; 2], 2, &STRIP_LENGTHS);
+			assert_eq!(frame.colors[3..5], [RED, RED]);
+			frame.clear();
+			assert_eq!(frame.colors, [c::C_OFF; 10]);
+			frame.draw_view(&low, &[BLUE; 6], 0, &STRIP_LENGTHS);
+			assert_eq!(frame.colors[..6], [BLUE; 6]);
+		}
+
+		#[test]
+		fn short_colors_and_small_frames() {
+			let mut frame = PhysicalFrame::<7>::new();
+			let whole = ViewMap::whole_strips(&STRIP_LENGTHS, 9).unwrap();
+			frame.draw_view(&whole, &[RED; 3], 0, &STRIP_LENGTHS);
+			assert_eq!(frame.colors, [RED, RED, RED, c::C_OFF, c::C_OFF, c::C_OFF, c::C_OFF]);
+			//LEDs past the end of the frame are left out:
+			frame.draw_view(&whole, &[BLUE; 9], 0, &STRIP_LENGTHS);
+			assert_eq!(frame.colors, [BLUE; 7]);
+		}
+
+		#[test]
+		fn strip_ranges() {
+			let mut ranges = StripRanges::new();
+			assert!(ranges.is_empty());
+			for i in 0..MAX_SLICES {
+				assert!(ranges.push(i * 10..i * 10 + 5));
+			}
+			assert!(!ranges.push(100..105));
+			assert_eq!(ranges.len(), MAX_SLICES);
+			assert_eq!(ranges.get(2), Some(20..25));
+			assert_eq!(ranges.get(MAX_SLICES), None);
+		}
+	}
+}
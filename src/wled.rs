@@ -0,0 +1,312 @@
+pub mod wled {
+	use crate::a;
+	use crate::c;
+	use crate::ctl;
+	use crate::js;
+	use core::fmt;
+
+	//a subset of the WLED JSON API, so the WLED phone app can control the lights. The network
+	//side answers GET /json/state, /json/info, /json/eff and /json/pal with what the write_*
+	//functions write, and hands the body of a POST to /json/state to parse_state. The lights
+	//are one WLED segment, effects are picked by their number in a::EFFECTS and palettes by
+	//their number in RAINBOW_ARRAY. A state update looks like:
+	//  {"on":true, "bri":128, "seg":[{"col":[[255,160,0],[0,0,0],[0,0,0]], "fx":10, "pal":14}]}
+	//"on" can also be "t" to toggle, and colors can also be hex strings like "FFA000".
+
+	//the WLED version the app is told this is. The app only uses features it knows the
+	//version has, and this one has everything in the subset:
+	pub const VERSION: &str = "0.14.0";
+	//the port WLED uses for syncing with other WLED devices, which the app looks for in the info:
+	pub const WLED_UDP_PORT: u16 = 21324;
+	//what a POST without "v":true is answered with:
+	pub const SUCCESS: &str = "{\"success\":true}";
+	//the most commands one state update turns into:
+	pub const MAX_COMMANDS: usize = 3;
+
+	//palette 0 is each effect's own default, like WLED's "Default" palette, in place of the
+	//RAINBOW_ARRAY palette that is all off. The chase uses its color, and the other effects
+	//all the colors of the rainbow:
+	pub const PALETTE_NAMES: [&str; c::NUM_RAINBOWS] = [
+		"Default",
+		"On",
+		"Red",
+		"Orange",
+		"Yellow",
+		"Yellow Green",
+		"Green",
+		"Green Blue",
+		"Sky Blue",
+		"Deep Blue",
+		"Blue",
+		"Blue Purple",
+		"Purple",
+		"Dark Purple",
+		"ROYGBIV",
+		"Double ROYGBIV",
+		"Red Yellow Blue",
+		"Orange Green Purple",
+		"Red Green Blue",
+		"Blue Yellow",
+		"Red Blue",
+		"Orange Blue",
+		"Blue White",
+		"Red White",
+		"Green White",
+		"Dark Red Pattern",
+		"Dark Yellow Pattern",
+		"Dark Green Pattern",
+		"Dark Sky Blue Pattern",
+		"Dark Blue Pattern",
+		"Dark Purple Pattern",
+		"White Pattern",
+	];
+
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub enum WledError {
+		Json(js::JsonError),
+		//the body is JSON, but not an object
+		NotAnObject,
+	}
+
+	//what the lights are doing now, for answering the app and filling in what an update leaves out:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct State {
+		pub on: bool,
+		pub brightness: u8,
+		pub effect: a::EffectParams,
+		pub led_count: usize,
+	}
+
+	//what the lights are, for /json/info:
+	#[derive(Copy, Clone, PartialEq, Debug)]
+	pub struct Info<'a> {
+		pub name: &'a str,
+		pub mac_address: [u8; 6],
+		pub ip_address: [u8; 4],
+		pub led_count: usize,
+		//frames are coming in from one of the lighting protocols
+		pub live: bool,
+		pub uptime_s: u32,
+	}
+
+	fn color(value: js::Value) -> Option<c::Color> {
+		match value {
+			//white, if it is there, is left out since the strips are RGB:
+			js::Value::Array(rgb) => Some(c::Color {
+				r: rgb.get(0)?.as_u8()?,
+				g: rgb.get(1)?.as_u8()?,
+				b: rgb.get(2)?.as_u8()?,
+			}),
+			js::Value::String(hex) if hex.len() == 6 || hex.len() == 8 => {
+				let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+				//eight digits are white first:
+				let first = hex.len() - 6;
+				Some(c::Color { r: channel(first)?, g: channel(first + 2)?, b: channel(first + 4)? })
+			}
+			_ => None,
+		}
+	}
+
+	//this turns a state update into the commands that carry it out, in the order to run them.
+	//fields that aren't in the subset, or have values that are out of range, are ignored, the
+	//same as WLED does.
+	pub fn parse_state(body: &str, current: &State) -> Result<[Option<ctl::Command<'static>>; MAX_COMMANDS], WledError> {
+		let update = js::parse(body).map_err(WledError::Json)?.as_object().ok_or(WledError::NotAnObject)?;
+		let mut commands = [None; MAX_COMMANDS];
+
+		//the lights are a single segment, so only the first one sent is used:
+		let segment = update.get("seg").and_then(|seg| match seg {
+			js::Value::Array(segments) => segments.get(0).and_then(|segment| segment.as_object()),
+			seg => seg.as_object(),
+		});
+		if let Some(segment) = segment {
+			let effect = segment.get("fx").and_then(|fx| fx.as_i64()).filter(|&fx| fx >= 0).and_then(|fx| a::EFFECTS.get(fx as usize).copied());
+			let palette = segment.get("pal").and_then(|pal| pal.as_i64()).filter(|&pal| pal >= 0 && pal < c::NUM_RAINBOWS as i64).map(|pal| pal as u8);
+			let color = segment.get("col").and_then(|col| col.as_array()).and_then(|col| col.get(0)).and_then(color);
+			if effect.is_some() || palette.is_some() || color.is_some() {
+				commands[0] = Some(ctl::Command::Effect {
+					effect: effect.unwrap_or(current.effect.effect),
+					options: ctl::EffectOptions { palette, color, ..ctl::EffectOptions::default() },
+				});
+			}
+		}
+
+		//WLED turns the lights off for a brightness of 0, and keeps the brightness they had:
+		let mut on = None;
+		match update.get("bri").and_then(|bri| bri.as_u8()) {
+			Some(0) => on = Some(false),
+			Some(brightness) => commands[1] = Some(ctl::Command::Brightness(brightness)),
+			None => {}
+		}
+		on = match update.get("on") {
+			Some(js::Value::Bool(value)) => Some(value),
+			Some(js::Value::String("t")) => Some(!current.on),
+			_ => on,
+		};
+		//picking an effect turns the lights on, so on and off go last:
+		commands[2] = on.map(|on| if on { ctl::Command::On } else { ctl::Command::Off });
+		Ok(commands)
+	}
+
+	fn write_color<W: fmt::Write>(out: &mut W, color: c::Color) -> fmt::Result {
+		write!(out, "[{},{},{}]", color.r, color.g, color.b)
+	}
+
+	//this writes the answer to /json/state:
+	pub fn write_state<W: fmt::Write>(out: &mut W, state: &State) -> fmt::Result {
+		write!(out, "{{\"on\":{},\"bri\":{},\"ps\":-1,\"pl\":-1,", state.on, state.brightness)?;
+		write!(out, "\"seg\":[{{\"id\":0,\"start\":0,\"stop\":{0},\"len\":{0},\"on\":true,\"bri\":255,\"col\":[", state.led_count)?;
+		write_color(out, state.effect.color)?;
+		out.write_str(",[0,0,0],[0,0,0]],")?;
+		write!(out, "\"fx\":{},\"pal\":{},\"sel\":true}}]}}", state.effect.effect.to_u8(), state.effect.palette)
+	}
+
+	//this writes the answer to /json/info:
+	pub fn write_info<W: fmt::Write>(out: &mut W, info: &Info) -> fmt::Result {
+		write!(out, "{{\"ver\":\"{}\",\"leds\":{{\"count\":{},\"rgbw\":false,\"wv\":false,\"fps\":30,\"maxseg\":1}},", VERSION, info.led_count)?;
+		out.write_str("\"name\":")?;
+		js::write_string(out, info.name)?;
+		write!(out, ",\"udpport\":{},\"live\":{},", WLED_UDP_PORT, info.live)?;
+		write!(out, "\"fxcount\":{},\"palcount\":{},\"arch\":\"esp32\",\"brand\":\"WLED\",\"product\":\"ws2811 office lights\",", a::EFFECTS.len(), c::NUM_RAINBOWS)?;
+		out.write_str("\"mac\":\"")?;
+		for byte in info.mac_address.iter() {
+			write!(out, "{:02x}", byte)?;
+		}
+		let ip = info.ip_address;
+		write!(out, "\",\"ip\":\"{}.{}.{}.{}\",\"uptime\":{}}}", ip[0], ip[1], ip[2], ip[3], info.uptime_s)
+	}
+
+	fn write_names<W: fmt::Write>(out: &mut W, names: impl Iterator<Item = &'static str>) -> fmt::Result {
+		out.write_char('[')?;
+		for (index, name) in names.enumerate() {
+			if index > 0 {
+				out.write_char(',')?;
+			}
+			js::write_string(out, name)?;
+		}
+		out.write_char(']')
+	}
+
+	//this writes the answer to /json/eff, the effect names by number:
+	pub fn write_effects<W: fmt::Write>(out: &mut W) -> fmt::Result {
+		write_names(out, a::EFFECTS.iter().map(|effect| effect.name()))
+	}
+
+	//this writes the answer to /json/pal, the palette names by number:
+	pub fn write_palettes<W: fmt::Write>(out: &mut W) -> fmt::Result {
+		write_names(out, PALETTE_NAMES.iter().copied())
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		const CURRENT: State = State {
+			on: true,
+			brightness: 200,
+			effect: a::EffectParams::solid(c::C_RED),
+			led_count: 169,
+		};
+
+		fn parse(body: &str) -> [Option<ctl::Command<'static>>; MAX_COMMANDS] {
+			parse_state(body, &CURRENT).unwrap()
+		}
+
+		fn effect(effect: a::Effect, palette: Option<u8>, color: Option<c::Color>) -> Option<ctl::Command<'static>> {
+			Some(ctl::Command::Effect { effect, options: ctl::EffectOptions { palette, color, ..ctl::EffectOptions::default() } })
+		}
+
+		#[test]
+		fn on_and_brightness() {
+			assert_eq!(parse("{\"on\":false}"), [None, None, Some(ctl::Command::Off)]);
+			assert_eq!(parse("{\"on\":true,\"bri\":128}"), [None, Some(ctl::Command::Brightness(128)), Some(ctl::Command::On)]);
+			//"t" toggles:
+			assert_eq!(parse("{\"on\":\"t\"}"), [None, None, Some(ctl::Command::Off)]);
+			let off = State { on: false, ..CURRENT };
+			assert_eq!(parse_state("{\"on\":\"t\"}", &off).unwrap(), [None, None, Some(ctl::Command::On)]);
+			//a brightness of 0 turns the lights off without changing the brightness, unless "on" says otherwise:
+			assert_eq!(parse("{\"bri\":0}"), [None, None, Some(ctl::Command::Off)]);
+			assert_eq!(parse("{\"bri\":0,\"on\":true}"), [None, None, Some(ctl::Command::On)]);
+			//numbers are clamped to fit, like WLED does:
+			assert_eq!(parse("{\"bri\":300}"), [None, Some(ctl::Command::Brightness(255)), None]);
+		}
+
+		#[test]
+		fn segment() {
+			let orange = c::Color { r: 255, g: 160, b: 0 };
+			assert_eq!(
+				parse("{\"seg\":[{\"col\":[[255,160,0],[0,0,0],[0,0,0]],\"fx\":10,\"pal\":14}]}")[0],
+				effect(a::Effect::Rainbow, Some(14), Some(orange))
+			);
+			//hex colors, with or without white in front, and white left off the end of an array:
+			assert_eq!(parse("{\"seg\":{\"col\":[\"FFA000\"]}}")[0], effect(a::Effect::Solid, None, Some(orange)));
+			assert_eq!(parse("{\"seg\":{\"col\":[\"10ffa000\"]}}")[0], effect(a::Effect::Solid, None, Some(orange)));
+			assert_eq!(parse("{\"seg\":{\"col\":[[255,160,0,16]]}}")[0], effect(a::Effect::Solid, None, Some(orange)));
+			assert_eq!(parse("{\"seg\":{\"col\":[\"FFA0\"]}}")[0], None);
+			//effects and palettes out of range are ignored, and the effect running now is kept:
+			let fx_count = a::EFFECTS.len();
+			assert_eq!(parse(&format!("{{\"seg\":[{{\"fx\":{},\"pal\":3}}]}}", fx_count))[0], effect(a::Effect::Solid, Some(3), None));
+			assert_eq!(parse("{\"seg\":[{\"fx\":-1}]}")[0], None);
+			assert_eq!(parse(&format!("{{\"seg\":[{{\"fx\":2,\"pal\":{}}}]}}", c::NUM_RAINBOWS))[0], effect(a::Effect::Aurora, None, None));
+			//only the first segment is used:
+			assert_eq!(parse("{\"seg\":[{\"fx\":1},{\"fx\":2}]}")[0], effect(a::Effect::Sunrise, None, None));
+		}
+
+		#[test]
+		fn bad_bodies() {
+			assert_eq!(parse_state("[1,2]", &CURRENT), Err(WledError::NotAnObject));
+			assert!(matches!(parse_state("{\"on\":", &CURRENT), Err(WledError::Json(_))));
+			//fields that aren't in the subset are ignored:
+			assert_eq!(parse("{\"v\":true,\"transition\":7,\"nl\":{\"on\":false}}"), [None; MAX_COMMANDS]);
+		}
+
+		#[test]
+		fn state() {
+			let mut out = String::new();
+			let state = State { on: false, effect: a::EffectParams { palette: 4, ..a::EffectParams::solid(c::Color { r: 1, g: 2, b: 3 }) }, ..CURRENT };
+			write_state(&mut out, &state).unwrap();
+			assert_eq!(
+				out,
+				"{\"on\":false,\"bri\":200,\"ps\":-1,\"pl\":-1,\"seg\":[{\"id\":0,\"start\":0,\"stop\":169,\"len\":169,\"on\":true,\
+				\"bri\":255,\"col\":[[1,2,3],[0,0,0],[0,0,0]],\"fx\":0,\"pal\":4,\"sel\":true}]}"
+			);
+			//what is written can be read back as an update that leaves everything as it was:
+			let commands = parse(&out);
+			assert_eq!(commands[0], effect(a::Effect::Solid, Some(4), Some(c::Color { r: 1, g: 2, b: 3 })));
+			assert_eq!(commands[2], Some(ctl::Command::Off));
+		}
+
+		#[test]
+		fn info_and_names() {
+			let info = Info {
+				name: "office \"lights\"",
+				mac_address: [0x24, 0x0A, 0xC4, 0x00, 0x01, 0xFF],
+				ip_address: [192, 168, 1, 40],
+				led_count: 169,
+				live: true,
+				uptime_s: 3600,
+			};
+			let mut out = String::new();
+			write_info(&mut out, &info).unwrap();
+			assert_eq!(
+				out,
+				format!(
+					"{{\"ver\":\"0.14.0\",\"leds\":{{\"count\":169,\"rgbw\":false,\"wv\":false,\"fps\":30,\"maxseg\":1}},\
+					\"name\":\"office \\\"lights\\\"\",\"udpport\":21324,\"live\":true,\"fxcount\":{},\"palcount\":32,\
+					\"arch\":\"esp32\",\"brand\":\"WLED\",\"product\":\"ws2811 office lights\",\"mac\":\"240ac40001ff\",\
+					\"ip\":\"192.168.1.40\",\"uptime\":3600}}",
+					a::EFFECTS.len()
+				)
+			);
+			assert!(js::parse(&out).is_ok());
+			out.clear();
+			write_effects(&mut out).unwrap();
+			assert!(out.starts_with("[\"solid\",\"sunrise\",\"aurora\","));
+			assert_eq!(js::parse(&out).unwrap().as_array().unwrap().iter().count(), a::EFFECTS.len());
+			out.clear();
+			write_palettes(&mut out).unwrap();
+			assert!(out.starts_with("[\"Default\",\"On\","));
+			assert_eq!(js::parse(&out).unwrap().as_array().unwrap().iter().count(), c::NUM_RAINBOWS);
+		}
+	}
+}